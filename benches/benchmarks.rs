@@ -6,7 +6,7 @@ fn minimax_benchmark(c: &mut Criterion) {
     let position =
         Position::from_fen("r1bqkbnr/pppp1ppp/2n5/1B2p3/4P3/5N2/PPPP1PPP/RNBQK2R b KQkq - 0 1");
     group.bench_function("minimax 3", |b| {
-        b.iter(|| Planner {}.offer_move(black_box(&position)))
+        b.iter(|| Planner::new().offer_move(black_box(&position)))
     });
 }
 
@@ -0,0 +1,247 @@
+use crate::elementary_mate_distance;
+use crate::DrawReason;
+use crate::EndgameClass;
+use crate::GameResult;
+use crate::PieceColor;
+use crate::Position;
+use crate::WinReason;
+
+// How an engine game came to an end.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Termination {
+    Checkmate,
+    Stalemate,
+    // neither side has the material left to mate
+    InsufficientMaterial,
+    // a clock ran out, which is a draw when the other side can't mate
+    TimeForfeit,
+    MoveLimit,
+    // a side kept seeing itself lost, see `ResignRule`
+    Resignation,
+    // both sides kept seeing the game as level, see `DrawRule`
+    AdjudicatedDraw,
+    // the rest of the game is known from the endgame tables
+    Tablebase,
+    // the variant's own goal was reached, or its own draw
+    Variant,
+}
+
+// A side loses once its own evaluation has been at least `score` against it
+// on each of its last `moves` moves, while the other side's agreed.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct ResignRule {
+    pub moves: usize,
+    pub score: isize,
+}
+
+// The game is drawn once both sides have scored it within `score` of level
+// for `moves` moves each, as long as `after_plies` have been played.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct DrawRule {
+    pub moves: usize,
+    pub score: isize,
+    pub after_plies: usize,
+}
+
+// When to call an engine game before it is over on the board. Scores are
+// the players' own evaluations of the positions they move from, and they
+// are only asked for them when one of the rules needs them.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct AdjudicationPolicy {
+    pub max_plies: Option<usize>,
+    pub resign: Option<ResignRule>,
+    pub draw: Option<DrawRule>,
+    // draws without mating material, and mates in the elementary endgames
+    pub tablebase: bool,
+}
+
+impl Default for AdjudicationPolicy {
+    fn default() -> AdjudicationPolicy {
+        AdjudicationPolicy {
+            max_plies: Some(300),
+            resign: None,
+            draw: None,
+            tablebase: false,
+        }
+    }
+}
+
+impl AdjudicationPolicy {
+    pub fn needs_scores(&self) -> bool {
+        self.resign.is_some() || self.draw.is_some()
+    }
+}
+
+// Keeps track of a game for its policy.
+pub(crate) struct Adjudicator<'a> {
+    policy: &'a AdjudicationPolicy,
+    // each move's score, from white's point of view
+    scores: Vec<isize>,
+}
+
+impl<'a> Adjudicator<'a> {
+    pub(crate) fn new(policy: &'a AdjudicationPolicy) -> Adjudicator<'a> {
+        Adjudicator {
+            policy,
+            scores: Vec::new(),
+        }
+    }
+
+    // `score` is the mover's evaluation of the position it moved from.
+    pub(crate) fn record(&mut self, mover: &PieceColor, score: isize) {
+        self.scores.push(match mover {
+            PieceColor::White => score,
+            PieceColor::Black => -score,
+        });
+    }
+
+    // Whether the game should end in `position`, after `plies` moves.
+    pub(crate) fn verdict(
+        &self,
+        position: &Position,
+        plies: usize,
+    ) -> Option<(GameResult, Termination)> {
+        if self.policy.tablebase {
+            if position.endgame_class() == EndgameClass::InsufficientMaterial {
+                return Some((
+                    GameResult::Draw(DrawReason::InsufficientMaterial),
+                    Termination::Tablebase,
+                ));
+            }
+            if elementary_mate_distance(position).is_some() {
+                let winner = if position.has_mating_material(PieceColor::White) {
+                    PieceColor::White
+                } else {
+                    PieceColor::Black
+                };
+                let result = GameResult::win_for(winner, WinReason::Adjudicated);
+                return Some((result, Termination::Tablebase));
+            }
+        }
+        if let Some(rule) = self.policy.resign {
+            if let Some(recent) = self.last_moves(rule.moves) {
+                if recent.iter().all(|score| *score >= rule.score) {
+                    return Some((
+                        GameResult::WhiteWin(WinReason::Resignation),
+                        Termination::Resignation,
+                    ));
+                }
+                if recent.iter().all(|score| *score <= -rule.score) {
+                    return Some((
+                        GameResult::BlackWin(WinReason::Resignation),
+                        Termination::Resignation,
+                    ));
+                }
+            }
+        }
+        if let Some(rule) = self.policy.draw {
+            if plies >= rule.after_plies
+                && self
+                    .last_moves(rule.moves)
+                    .is_some_and(|recent| recent.iter().all(|score| score.abs() <= rule.score))
+            {
+                return Some((
+                    GameResult::Draw(DrawReason::Adjudicated),
+                    Termination::AdjudicatedDraw,
+                ));
+            }
+        }
+        if self.policy.max_plies.is_some_and(|max| plies >= max) {
+            return Some((
+                GameResult::Draw(DrawReason::Adjudicated),
+                Termination::MoveLimit,
+            ));
+        }
+        None
+    }
+
+    // The scores of each side's last `moves` moves, once both have made that
+    // many.
+    fn last_moves(&self, moves: usize) -> Option<&[isize]> {
+        let plies = 2 * moves.max(1);
+        (self.scores.len() >= plies).then(|| &self.scores[self.scores.len() - plies..])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn resigns_only_once_both_sides_agree_for_long_enough() {
+        let policy = AdjudicationPolicy {
+            resign: Some(ResignRule {
+                moves: 2,
+                score: 500,
+            }),
+            ..AdjudicationPolicy::default()
+        };
+        let position = Position::initial();
+        let mut adjudicator = Adjudicator::new(&policy);
+        // white thinks it is winning, black that it is losing
+        for (mover, score) in [
+            (PieceColor::White, 600),
+            (PieceColor::Black, -700),
+            (PieceColor::White, 650),
+        ] {
+            adjudicator.record(&mover, score);
+        }
+        assert_eq!(adjudicator.verdict(&position, 3), None);
+        adjudicator.record(&PieceColor::Black, -100);
+        assert_eq!(adjudicator.verdict(&position, 4), None);
+        for _ in 0..2 {
+            assert_eq!(adjudicator.verdict(&position, 4), None);
+            adjudicator.record(&PieceColor::White, 900);
+            adjudicator.record(&PieceColor::Black, -900);
+        }
+        assert_eq!(
+            adjudicator.verdict(&position, 6),
+            Some((
+                GameResult::WhiteWin(WinReason::Resignation),
+                Termination::Resignation
+            ))
+        );
+    }
+
+    #[test]
+    fn draws_level_games_and_known_endgames() {
+        let policy = AdjudicationPolicy {
+            draw: Some(DrawRule {
+                moves: 1,
+                score: 10,
+                after_plies: 40,
+            }),
+            tablebase: true,
+            max_plies: None,
+            ..AdjudicationPolicy::default()
+        };
+        let mut adjudicator = Adjudicator::new(&policy);
+        adjudicator.record(&PieceColor::White, 5);
+        adjudicator.record(&PieceColor::Black, -3);
+        let position = Position::initial();
+        assert_eq!(adjudicator.verdict(&position, 2), None);
+        assert_eq!(
+            adjudicator.verdict(&position, 40),
+            Some((
+                GameResult::Draw(DrawReason::Adjudicated),
+                Termination::AdjudicatedDraw
+            ))
+        );
+        let fresh = Adjudicator::new(&policy);
+        assert_eq!(
+            fresh.verdict(&Position::from_fen("8/8/3k4/8/8/8/8/2NK4 w - - 0 1"), 0),
+            Some((
+                GameResult::Draw(DrawReason::InsufficientMaterial),
+                Termination::Tablebase
+            ))
+        );
+        assert_eq!(
+            fresh.verdict(&Position::from_fen("r7/8/3k4/8/8/8/8/3K4 w - - 0 1"), 0),
+            Some((
+                GameResult::BlackWin(WinReason::Adjudicated),
+                Termination::Tablebase
+            ))
+        );
+        assert_eq!(fresh.verdict(&position, 10_000), None);
+    }
+}
@@ -0,0 +1,266 @@
+use crate::engine::MATE_BOUND;
+use crate::ChessMove;
+use crate::Game;
+use crate::GameStatus;
+use crate::PieceColor;
+use crate::Planner;
+use crate::Player;
+use crate::Position;
+use crate::SearchLimits;
+
+// Evaluations are capped at this many centipawns either way before losses
+// are worked out, so that missing a mate or going from winning by a queen
+// to winning by two doesn't swamp the average.
+pub const ANALYSIS_SCORE_CAP: isize = 1000;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum MoveFlaw {
+    Inaccuracy,
+    Mistake,
+    Blunder,
+}
+
+// A move losing at least this many centipawns gets the flaw.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct LossThresholds {
+    pub inaccuracy: isize,
+    pub mistake: isize,
+    pub blunder: isize,
+}
+
+impl Default for LossThresholds {
+    fn default() -> LossThresholds {
+        LossThresholds {
+            inaccuracy: 50,
+            mistake: 100,
+            blunder: 300,
+        }
+    }
+}
+
+impl LossThresholds {
+    pub fn flaw(&self, centipawn_loss: isize) -> Option<MoveFlaw> {
+        if centipawn_loss >= self.blunder {
+            Some(MoveFlaw::Blunder)
+        } else if centipawn_loss >= self.mistake {
+            Some(MoveFlaw::Mistake)
+        } else if centipawn_loss >= self.inaccuracy {
+            Some(MoveFlaw::Inaccuracy)
+        } else {
+            None
+        }
+    }
+}
+
+// Scores are from white's point of view and capped at
+// `ANALYSIS_SCORE_CAP`.
+#[derive(Clone, PartialEq, Debug)]
+pub struct MoveAnalysis {
+    pub ply: usize,
+    pub mover: PieceColor,
+    pub played: ChessMove,
+    pub best_move: ChessMove,
+    pub score_before: isize,
+    pub score_after: isize,
+    pub centipawn_loss: isize,
+    pub flaw: Option<MoveFlaw>,
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct GameAnalysis {
+    pub moves: Vec<MoveAnalysis>,
+}
+
+impl GameAnalysis {
+    // Zero for a side that hasn't moved.
+    pub fn average_centipawn_loss(&self, color: &PieceColor) -> f64 {
+        let losses: Vec<isize> = self
+            .moves
+            .iter()
+            .filter(|analysis| analysis.mover == *color)
+            .map(|analysis| analysis.centipawn_loss)
+            .collect();
+        if losses.is_empty() {
+            0.0
+        } else {
+            losses.iter().sum::<isize>() as f64 / losses.len() as f64
+        }
+    }
+
+    pub fn count(&self, color: &PieceColor, flaw: MoveFlaw) -> usize {
+        self.moves
+            .iter()
+            .filter(|analysis| analysis.mover == *color && analysis.flaw == Some(flaw))
+            .count()
+    }
+}
+
+fn capped(score: isize) -> isize {
+    score.clamp(-ANALYSIS_SCORE_CAP, ANALYSIS_SCORE_CAP)
+}
+
+fn white_point_of_view(position: &Position, score: isize) -> isize {
+    match position.to_move {
+        PieceColor::White => score,
+        PieceColor::Black => -score,
+    }
+}
+
+// The position's score for the side to move, with the best move when there
+// is one to play.
+fn judge(
+    engine: &Planner,
+    position: &Position,
+    limits: &SearchLimits,
+) -> (isize, Option<ChessMove>) {
+    match GameStatus::of(position) {
+        GameStatus::Checkmate(_) => (-(MATE_BOUND + 1), None),
+        GameStatus::Ongoing { .. } => {
+            let result = engine.search_with_limits(position, limits);
+            (result.score, Some(result.best_move))
+        }
+        _ => (0, None),
+    }
+}
+
+// Searches every position of the game with `limits`, each move losing the
+// difference between the score before it and the score after it.
+pub fn analyze_game(game: &Game, engine: &Planner, limits: &SearchLimits) -> GameAnalysis {
+    analyze_game_with(game, engine, limits, &LossThresholds::default())
+}
+
+pub fn analyze_game_with(
+    game: &Game,
+    engine: &Planner,
+    limits: &SearchLimits,
+    thresholds: &LossThresholds,
+) -> GameAnalysis {
+    engine.new_game();
+    let positions: Vec<&Position> = game
+        .previous_positions
+        .iter()
+        .chain(std::iter::once(&game.current_position))
+        .collect();
+    let judged: Vec<(isize, Option<ChessMove>)> = positions
+        .iter()
+        .map(|position| judge(engine, position, limits))
+        .collect();
+    let moves = game
+        .history
+        .iter()
+        .enumerate()
+        .map(|(ply, played)| {
+            let position = positions[ply];
+            let (before, best) = &judged[ply];
+            let best_move = best.clone().unwrap_or_else(|| played.clone());
+            // both scores from the mover's side
+            let before = capped(*before);
+            let after = capped(-judged[ply + 1].0);
+            let centipawn_loss = if *played == best_move {
+                0
+            } else {
+                (before - after).max(0)
+            };
+            MoveAnalysis {
+                ply: ply + 1,
+                mover: position.to_move,
+                played: played.clone(),
+                best_move,
+                score_before: white_point_of_view(position, before),
+                score_after: white_point_of_view(position, after),
+                centipawn_loss,
+                flaw: thresholds.flaw(centipawn_loss),
+            }
+        })
+        .collect();
+    GameAnalysis { moves }
+}
+
+// One point for the starting position and one after each move, white's
+// score capped at `ANALYSIS_SCORE_CAP` so that the graph keeps its scale.
+// The evaluator is taken to score for the side to move, and finished
+// positions are scored by their result instead.
+// The game already holds every position it went through, so nothing is
+// replayed.
+pub fn eval_graph(game: &Game, evaluator: &dyn Player) -> Vec<(usize, isize)> {
+    game.previous_positions
+        .iter()
+        .chain(std::iter::once(&game.current_position))
+        .enumerate()
+        .map(|(ply, position)| {
+            let score = match GameStatus::of(position) {
+                GameStatus::Checkmate(_) => -ANALYSIS_SCORE_CAP,
+                GameStatus::Ongoing { .. } => capped(evaluator.evalutate(position)),
+                _ => 0,
+            };
+            (ply, white_point_of_view(position, score))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::GreedyMaterialPlayer;
+
+    use super::*;
+
+    fn game_of(sans: &[&str]) -> Game {
+        let mut game = Game::start();
+        for san in sans {
+            game.make_move(&ChessMove::from_san(san, &game.current_position).unwrap());
+        }
+        game
+    }
+
+    #[test]
+    fn losing_moves_are_flagged() {
+        // white leaves the queen where the knight takes it
+        let game = game_of(&["e4", "Nf6", "Qh5", "Nxh5"]);
+        let analysis = analyze_game(&game, &Planner::new(), &SearchLimits::depth(3));
+        assert_eq!(analysis.moves.len(), 4);
+        let blunder = &analysis.moves[2];
+        assert_eq!(blunder.mover, PieceColor::White);
+        assert_eq!(blunder.flaw, Some(MoveFlaw::Blunder));
+        assert!(blunder.score_after < -500);
+        let capture = &analysis.moves[3];
+        assert_eq!(capture.played, capture.best_move);
+        assert_eq!(capture.centipawn_loss, 0);
+        assert_eq!(analysis.count(&PieceColor::White, MoveFlaw::Blunder), 1);
+        assert_eq!(analysis.count(&PieceColor::Black, MoveFlaw::Blunder), 0);
+        assert!(
+            analysis.average_centipawn_loss(&PieceColor::White)
+                > analysis.average_centipawn_loss(&PieceColor::Black)
+        );
+    }
+
+    #[test]
+    fn graphs_follow_the_game_from_white_side() {
+        let game = game_of(&["e4", "d5", "exd5", "Qxd5", "Qh5", "Qxh5"]);
+        let graph = eval_graph(&game, &GreedyMaterialPlayer);
+        assert_eq!(graph.len(), 7);
+        assert_eq!(
+            graph.iter().map(|(ply, _)| *ply).collect::<Vec<_>>(),
+            (0..7).collect::<Vec<_>>()
+        );
+        // a pawn up after exd5, a queen down at the end
+        assert!(graph[3].1 > 50);
+        assert!(graph[6].1 < -500);
+        let mate = eval_graph(&game_of(&["f3", "e5", "g4", "Qh4#"]), &GreedyMaterialPlayer);
+        assert_eq!(mate.last(), Some(&(4, -ANALYSIS_SCORE_CAP)));
+    }
+
+    #[test]
+    fn mates_are_scored_at_the_cap() {
+        let game = game_of(&["f3", "e5", "g4", "Qh4#"]);
+        let analysis = analyze_game(&game, &Planner::new(), &SearchLimits::depth(2));
+        let mate = analysis.moves.last().unwrap();
+        assert_eq!(mate.score_after, -ANALYSIS_SCORE_CAP);
+        assert_eq!(mate.flaw, None);
+        assert_eq!(analysis.moves[2].flaw, Some(MoveFlaw::Blunder));
+        assert_eq!(LossThresholds::default().flaw(49), None);
+        assert_eq!(
+            LossThresholds::default().flaw(50),
+            Some(MoveFlaw::Inaccuracy)
+        );
+    }
+}
@@ -0,0 +1,121 @@
+use crate::bitboard::{piece_attacks, PieceBitboards};
+use crate::Bitboard;
+use crate::Coords;
+use crate::PieceColor;
+use crate::PieceKind;
+
+// Squares attacked from every occupied square, plus their union per side.
+// Kept per square so a move only has to recompute the pieces it touched
+// and the sliders whose rays went through a touched square.
+#[derive(Clone, PartialEq, Debug)]
+pub struct AttackMaps {
+    from_square: [Bitboard; 64],
+    by_color: [Bitboard; 2],
+}
+
+impl AttackMaps {
+    pub fn new(bitboards: &PieceBitboards) -> AttackMaps {
+        let mut attack_maps = AttackMaps {
+            from_square: [Bitboard::EMPTY; 64],
+            by_color: [Bitboard::EMPTY; 2],
+        };
+        let occupancy = bitboards.occupancy();
+        for square in occupancy.squares() {
+            attack_maps.recompute_square(bitboards, &square, occupancy);
+        }
+        attack_maps.recompute_colors(bitboards);
+        attack_maps
+    }
+
+    pub fn attacked_by(&self, color: &PieceColor) -> Bitboard {
+        self.by_color[color.index()]
+    }
+
+    pub fn attacks_from(&self, square: &Coords) -> Bitboard {
+        self.from_square[square.to_square_number()]
+    }
+
+    // `bitboards` must already reflect the board after the change.
+    pub fn update(&mut self, bitboards: &PieceBitboards, changed: Bitboard) {
+        let occupancy = bitboards.occupancy();
+        for square in changed.squares() {
+            self.recompute_square(bitboards, &square, occupancy);
+        }
+        let sliders = bitboards.kind(&PieceKind::Rook)
+            | bitboards.kind(&PieceKind::Bishop)
+            | bitboards.kind(&PieceKind::Queen);
+        for square in (sliders & !changed).squares() {
+            if !(self.attacks_from(&square) & changed).is_empty() {
+                self.recompute_square(bitboards, &square, occupancy);
+            }
+        }
+        self.recompute_colors(bitboards);
+    }
+
+    fn recompute_square(
+        &mut self,
+        bitboards: &PieceBitboards,
+        square: &Coords,
+        occupancy: Bitboard,
+    ) {
+        self.from_square[square.to_square_number()] = match bitboards.piece_at(square) {
+            None => Bitboard::EMPTY,
+            Some(piece) => piece_attacks(&piece, square, occupancy),
+        };
+    }
+
+    fn recompute_colors(&mut self, bitboards: &PieceBitboards) {
+        for color in PieceColor::both() {
+            self.by_color[color.index()] = bitboards
+                .color(&color)
+                .squares()
+                .fold(Bitboard::EMPTY, |attacked, square| {
+                    attacked | self.attacks_from(&square)
+                });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::Position;
+
+    use super::*;
+
+    #[test]
+    fn incremental_maps_match_fresh_maps() {
+        let mut position =
+            Position::from_fen("r3k2r/pp1n1ppp/2p5/3pP3/1b1P4/2N2N2/PPP2PPP/R3KB1R w KQkq d6 0 1");
+        for ply in 0..40 {
+            let moves = position.all_legal_moves();
+            if moves.is_empty() {
+                break;
+            }
+            position = position.after_move(&moves[(ply * 7) % moves.len()]);
+            let fresh = AttackMaps::new(&PieceBitboards::from_board(position.board()));
+            for color in PieceColor::both() {
+                assert_eq!(
+                    position.attacked_squares(&color),
+                    fresh.attacked_by(&color),
+                    "{:?}",
+                    position
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn slider_sees_through_vacated_square() {
+        let position = Position::from_fen("8/8/8/8/8/8/8/R2N4 w - - 0 1");
+        assert!(!position
+            .attacked_squares(&PieceColor::White)
+            .contains(&Coords::from_algebraic("e1")));
+        let after_knight_moves = position.after_move(&crate::ChessMove::RegularMove(crate::Move {
+            origin: Coords::from_algebraic("d1"),
+            destination: Coords::from_algebraic("e3"),
+        }));
+        assert!(after_knight_moves
+            .attacked_squares(&PieceColor::White)
+            .contains(&Coords::from_algebraic("e1")));
+    }
+}
@@ -0,0 +1,35 @@
+use std::env;
+use std::fs;
+use std::process;
+
+use libchessticot::{run_perft_suite, PerftCase, STANDARD_PERFT_SUITE};
+
+// libchessticot-perft [MAX_DEPTH] [SUITE_FILE]
+// Counts the standard suite when no file is given, every known depth when no
+// maximum is.
+fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+    let max_depth = match args.first() {
+        Some(depth) => depth.parse().unwrap_or_else(|_| {
+            eprintln!("not a depth: {depth}");
+            process::exit(2);
+        }),
+        None => usize::MAX,
+    };
+    let suite = match args.get(1) {
+        Some(path) => fs::read_to_string(path).unwrap_or_else(|error| {
+            eprintln!("{path}: {error}");
+            process::exit(2);
+        }),
+        None => String::from(STANDARD_PERFT_SUITE),
+    };
+    let cases = PerftCase::parse_suite(&suite).unwrap_or_else(|error| {
+        eprintln!("{error:?}");
+        process::exit(2);
+    });
+    let report = run_perft_suite(&cases, max_depth);
+    println!("{report}");
+    if !report.passed() {
+        process::exit(1);
+    }
+}
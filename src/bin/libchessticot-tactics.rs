@@ -0,0 +1,32 @@
+use std::env;
+use std::fs;
+use std::process;
+
+use libchessticot::{run_tactical_suite, Planner, TacticalCase};
+
+// libchessticot-tactics SUITE_FILE [DEPTH]
+// Runs the Planner over an EPD suite such as WAC or STS, at its default
+// depth when none is given.
+fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+    let Some(path) = args.first() else {
+        eprintln!("usage: libchessticot-tactics SUITE_FILE [DEPTH]");
+        process::exit(2);
+    };
+    let suite = fs::read_to_string(path).unwrap_or_else(|error| {
+        eprintln!("{path}: {error}");
+        process::exit(2);
+    });
+    let cases = TacticalCase::parse_suite(&suite).unwrap_or_else(|error| {
+        eprintln!("{error:?}");
+        process::exit(2);
+    });
+    let planner = match args.get(1) {
+        Some(depth) => Planner::new().with_depth(depth.parse().unwrap_or_else(|_| {
+            eprintln!("not a depth: {depth}");
+            process::exit(2);
+        })),
+        None => Planner::new(),
+    };
+    println!("{}", run_tactical_suite(&cases, &planner, None));
+}
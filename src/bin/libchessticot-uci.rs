@@ -0,0 +1,7 @@
+use std::io;
+
+use libchessticot::{Planner, UciEngine};
+
+fn main() {
+    UciEngine::new(Planner::new(), io::stdout()).run(io::stdin().lock());
+}
@@ -0,0 +1,522 @@
+use std::ops;
+use std::sync::LazyLock;
+
+use crate::Coords;
+use crate::Mailbox;
+use crate::Piece;
+use crate::PieceColor;
+use crate::PieceKind;
+
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug, Default)]
+pub struct Bitboard(pub u64);
+
+impl Bitboard {
+    pub const EMPTY: Bitboard = Bitboard(0);
+
+    pub fn from_square(square: &Coords) -> Bitboard {
+        Bitboard(1 << square.to_square_number())
+    }
+
+    pub fn contains(&self, square: &Coords) -> bool {
+        square.is_in_bounds() && self.0 & (1 << square.to_square_number()) != 0
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0 == 0
+    }
+
+    pub fn count(&self) -> u32 {
+        self.0.count_ones()
+    }
+
+    pub fn squares(&self) -> BitboardSquares {
+        BitboardSquares { remaining: self.0 }
+    }
+}
+
+impl ops::BitAnd for Bitboard {
+    type Output = Bitboard;
+    fn bitand(self, rhs: Bitboard) -> Bitboard {
+        Bitboard(self.0 & rhs.0)
+    }
+}
+
+impl ops::BitOr for Bitboard {
+    type Output = Bitboard;
+    fn bitor(self, rhs: Bitboard) -> Bitboard {
+        Bitboard(self.0 | rhs.0)
+    }
+}
+
+impl ops::BitXor for Bitboard {
+    type Output = Bitboard;
+    fn bitxor(self, rhs: Bitboard) -> Bitboard {
+        Bitboard(self.0 ^ rhs.0)
+    }
+}
+
+impl ops::Not for Bitboard {
+    type Output = Bitboard;
+    fn not(self) -> Bitboard {
+        Bitboard(!self.0)
+    }
+}
+
+impl ops::BitAndAssign for Bitboard {
+    fn bitand_assign(&mut self, rhs: Bitboard) {
+        self.0 &= rhs.0;
+    }
+}
+
+impl ops::BitOrAssign for Bitboard {
+    fn bitor_assign(&mut self, rhs: Bitboard) {
+        self.0 |= rhs.0;
+    }
+}
+
+impl ops::BitXorAssign for Bitboard {
+    fn bitxor_assign(&mut self, rhs: Bitboard) {
+        self.0 ^= rhs.0;
+    }
+}
+
+pub struct BitboardSquares {
+    remaining: u64,
+}
+
+impl Iterator for BitboardSquares {
+    type Item = Coords;
+    fn next(&mut self) -> Option<Coords> {
+        if self.remaining == 0 {
+            return None;
+        }
+        let index = self.remaining.trailing_zeros() as usize;
+        self.remaining &= self.remaining - 1;
+        Some(Coords::from_index(index))
+    }
+}
+
+// Occupancy split by color and by kind, built from a mailbox board.
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct PieceBitboards {
+    by_color: [Bitboard; 2],
+    by_kind: [Bitboard; 6],
+}
+
+impl PieceBitboards {
+    pub fn from_board(board: &Mailbox) -> PieceBitboards {
+        let mut bitboards = PieceBitboards {
+            by_color: [Bitboard::EMPTY; 2],
+            by_kind: [Bitboard::EMPTY; 6],
+        };
+        for (y, rank) in board.iter().enumerate() {
+            for (x, square_contents) in rank.iter().enumerate() {
+                if let Some(piece) = square_contents {
+                    let square = Bitboard(1 << (y * 8 + x));
+                    bitboards.by_color[piece.color.index()] |= square;
+                    bitboards.by_kind[piece.kind.index()] |= square;
+                }
+            }
+        }
+        bitboards
+    }
+
+    pub fn occupancy(&self) -> Bitboard {
+        self.by_color[0] | self.by_color[1]
+    }
+
+    pub fn color(&self, color: &PieceColor) -> Bitboard {
+        self.by_color[color.index()]
+    }
+
+    pub fn kind(&self, kind: &PieceKind) -> Bitboard {
+        self.by_kind[kind.index()]
+    }
+
+    pub fn pieces(&self, color: &PieceColor, kind: &PieceKind) -> Bitboard {
+        self.color(color) & self.kind(kind)
+    }
+
+    pub fn piece_at(&self, square: &Coords) -> Option<Piece> {
+        let color = PieceColor::both().find(|color| self.color(color).contains(square))?;
+        let kind = PieceKind::all().find(|kind| self.kind(kind).contains(square))?;
+        Some(Piece { kind: *kind, color })
+    }
+
+    pub fn set(&mut self, square: &Coords, contents: Option<Piece>) {
+        let mask = !Bitboard::from_square(square);
+        self.by_color
+            .iter_mut()
+            .for_each(|bitboard| *bitboard &= mask);
+        self.by_kind
+            .iter_mut()
+            .for_each(|bitboard| *bitboard &= mask);
+        if let Some(piece) = contents {
+            self.by_color[piece.color.index()] |= !mask;
+            self.by_kind[piece.kind.index()] |= !mask;
+        }
+    }
+
+    // The pieces of `by` attacking `square`, with sliders seeing through
+    // anything left out of `occupancy`.
+    pub fn attackers(&self, square: &Coords, by: &PieceColor, occupancy: Bitboard) -> Bitboard {
+        let straight = self.kind(&PieceKind::Rook) | self.kind(&PieceKind::Queen);
+        let diagonal = self.kind(&PieceKind::Bishop) | self.kind(&PieceKind::Queen);
+        (knight_attacks(square) & self.kind(&PieceKind::Knight)
+            | king_attacks(square) & self.kind(&PieceKind::King)
+            | pawn_attacks(square, &by.opposite()) & self.kind(&PieceKind::Pawn)
+            | rook_attacks(square, occupancy) & straight
+            | bishop_attacks(square, occupancy) & diagonal)
+            & self.color(by)
+    }
+}
+
+const KNIGHT_OFFSETS: [(isize, isize); 8] = [
+    (1, 2),
+    (-1, 2),
+    (2, 1),
+    (-2, 1),
+    (1, -2),
+    (-1, -2),
+    (-2, -1),
+    (2, -1),
+];
+
+const KING_OFFSETS: [(isize, isize); 8] = [
+    (1, 1),
+    (-1, -1),
+    (-1, 1),
+    (1, -1),
+    (0, 1),
+    (0, -1),
+    (-1, 0),
+    (1, 0),
+];
+
+// rank, file, diagonal and anti-diagonal, both ways along each
+const LINES: [[(isize, isize); 2]; 4] = [
+    [(1, 0), (-1, 0)],
+    [(0, 1), (0, -1)],
+    [(1, 1), (-1, -1)],
+    [(1, -1), (-1, 1)],
+];
+const RANK: usize = 0;
+const FILE: usize = 1;
+const DIAGONAL: usize = 2;
+const ANTI_DIAGONAL: usize = 3;
+
+const A_FILE: u64 = 0x0101_0101_0101_0101;
+// The a-file square of rank y times this lands on bit 57 + y, and no two
+// products meet, so ranks 1 to 6 end up as the top six bits.
+const FILE_MAGIC: u64 = 1 << 50 | 1 << 43 | 1 << 36 | 1 << 29 | 1 << 22 | 1 << 15;
+// The squares of a diagonal are all on different files, and this moves the
+// one on file x to bit 57 + x.
+const DIAGONAL_MAGIC: u64 = A_FILE << 1;
+
+const fn leaper_attacks(offsets: &[(isize, isize)]) -> [u64; 64] {
+    let mut table = [0; 64];
+    let mut square = 0;
+    while square < 64 {
+        let x = (square % 8) as isize;
+        let y = (square / 8) as isize;
+        let mut i = 0;
+        while i < offsets.len() {
+            let target_x = x + offsets[i].0;
+            let target_y = y + offsets[i].1;
+            if target_x >= 0 && target_x < 8 && target_y >= 0 && target_y < 8 {
+                table[square] |= 1 << (target_y * 8 + target_x);
+            }
+            i += 1;
+        }
+        square += 1;
+    }
+    table
+}
+
+static KNIGHT_ATTACKS: [u64; 64] = leaper_attacks(&KNIGHT_OFFSETS);
+static KING_ATTACKS: [u64; 64] = leaper_attacks(&KING_OFFSETS);
+// y=0 is rank 8, so y grows towards white's side and white pawns attack
+// towards lower y
+static WHITE_PAWN_ATTACKS: [u64; 64] = leaper_attacks(&[(1, -1), (-1, -1)]);
+static BLACK_PAWN_ATTACKS: [u64; 64] = leaper_attacks(&[(1, 1), (-1, 1)]);
+
+pub fn knight_attacks(square: &Coords) -> Bitboard {
+    Bitboard(KNIGHT_ATTACKS[square.to_square_number()])
+}
+
+pub fn king_attacks(square: &Coords) -> Bitboard {
+    Bitboard(KING_ATTACKS[square.to_square_number()])
+}
+
+pub fn pawn_attacks(square: &Coords, color: &PieceColor) -> Bitboard {
+    match color {
+        PieceColor::White => Bitboard(WHITE_PAWN_ATTACKS[square.to_square_number()]),
+        PieceColor::Black => Bitboard(BLACK_PAWN_ATTACKS[square.to_square_number()]),
+    }
+}
+
+// The empty squares `pawns` of `color` can step to, a single step or a
+// double one from their starting row. White's pawns move towards y = 0,
+// down the square numbers.
+pub fn pawn_pushes(pawns: Bitboard, color: &PieceColor, empty: Bitboard) -> Bitboard {
+    let step = |pawns: Bitboard| match color {
+        PieceColor::White => Bitboard(pawns.0 >> 8),
+        PieceColor::Black => Bitboard(pawns.0 << 8),
+    };
+    let starting_row = Bitboard(0xff << (8 * (color.homerow() + color.pawn_orientation())));
+    let single = step(pawns) & empty;
+    let double = step(step(pawns & starting_row) & empty) & empty;
+    single | double
+}
+
+// Walks the rays one square at a time. Only used to fill the line tables
+// and to check them.
+fn slider_attacks(square: &Coords, occupancy: Bitboard, directions: &[(isize, isize)]) -> Bitboard {
+    let mut attacks = Bitboard::EMPTY;
+    for (dx, dy) in directions {
+        let mut x = square.x + dx;
+        let mut y = square.y + dy;
+        while (0..8).contains(&x) && (0..8).contains(&y) {
+            let target = Bitboard(1 << (y * 8 + x));
+            attacks |= target;
+            if !(occupancy & target).is_empty() {
+                break;
+            }
+            x += dx;
+            y += dy;
+        }
+    }
+    attacks
+}
+
+// Kindergarten bitboards: what a slider attacks along a line only depends
+// on the six squares of that line short of its ends, which a shift or a
+// multiplication gathers into an index. The attacks for every index are
+// worked out once.
+struct LineTables {
+    // the squares of the line through each square
+    masks: [[u64; 64]; 4],
+    // by line, then square, then occupancy index
+    attacks: Vec<u64>,
+}
+
+impl LineTables {
+    fn new() -> LineTables {
+        let masks: [[u64; 64]; 4] = std::array::from_fn(|line| {
+            std::array::from_fn(|square| {
+                slider_attacks(&Coords::from_index(square), Bitboard::EMPTY, &LINES[line]).0
+                    | 1 << square
+            })
+        });
+        let mut attacks = vec![0; 4 * 64 * 64];
+        for (line, line_masks) in masks.iter().enumerate() {
+            for (square, mask) in line_masks.iter().enumerate() {
+                for index in 0..64 {
+                    // the squares whose bit is set in the index
+                    let occupancy = Bitboard(*mask)
+                        .squares()
+                        .filter(|on_line| {
+                            let along = match line {
+                                FILE => on_line.y,
+                                _ => on_line.x,
+                            };
+                            (1..=6).contains(&along) && index >> (along - 1) & 1 == 1
+                        })
+                        .fold(Bitboard::EMPTY, |occupancy, on_line| {
+                            occupancy | Bitboard::from_square(&on_line)
+                        });
+                    attacks[(line * 64 + square) * 64 + index] =
+                        slider_attacks(&Coords::from_index(square), occupancy, &LINES[line]).0;
+                }
+            }
+        }
+        LineTables { masks, attacks }
+    }
+
+    fn attacks(&self, line: usize, square: usize, occupancy: Bitboard) -> u64 {
+        let index = match line {
+            RANK => occupancy.0 >> (square / 8 * 8 + 1),
+            FILE => ((occupancy.0 >> (square % 8)) & A_FILE).wrapping_mul(FILE_MAGIC) >> 58,
+            _ => (occupancy.0 & self.masks[line][square]).wrapping_mul(DIAGONAL_MAGIC) >> 58,
+        };
+        self.attacks[(line * 64 + square) * 64 + (index & 63) as usize]
+    }
+}
+
+static LINE_TABLES: LazyLock<LineTables> = LazyLock::new(LineTables::new);
+
+// Every square of the file `x`, 0 being the a-file.
+pub fn file(x: isize) -> Bitboard {
+    Bitboard(A_FILE << x)
+}
+
+pub fn rook_attacks(square: &Coords, occupancy: Bitboard) -> Bitboard {
+    let square = square.to_square_number();
+    Bitboard(
+        LINE_TABLES.attacks(RANK, square, occupancy) | LINE_TABLES.attacks(FILE, square, occupancy),
+    )
+}
+
+pub fn bishop_attacks(square: &Coords, occupancy: Bitboard) -> Bitboard {
+    let square = square.to_square_number();
+    Bitboard(
+        LINE_TABLES.attacks(DIAGONAL, square, occupancy)
+            | LINE_TABLES.attacks(ANTI_DIAGONAL, square, occupancy),
+    )
+}
+
+pub fn queen_attacks(square: &Coords, occupancy: Bitboard) -> Bitboard {
+    rook_attacks(square, occupancy) | bishop_attacks(square, occupancy)
+}
+
+// The squares strictly between two squares sharing a rank, file or
+// diagonal, and none when they don't share one.
+pub fn between(a: &Coords, b: &Coords) -> Bitboard {
+    let (only_a, only_b) = (Bitboard::from_square(a), Bitboard::from_square(b));
+    if rook_attacks(a, Bitboard::EMPTY).contains(b) {
+        rook_attacks(a, only_b) & rook_attacks(b, only_a)
+    } else if bishop_attacks(a, Bitboard::EMPTY).contains(b) {
+        bishop_attacks(a, only_b) & bishop_attacks(b, only_a)
+    } else {
+        Bitboard::EMPTY
+    }
+}
+
+pub fn piece_attacks(piece: &Piece, square: &Coords, occupancy: Bitboard) -> Bitboard {
+    match piece.kind {
+        PieceKind::Pawn => pawn_attacks(square, &piece.color),
+        PieceKind::Knight => knight_attacks(square),
+        PieceKind::Bishop => bishop_attacks(square, occupancy),
+        PieceKind::Rook => rook_attacks(square, occupancy),
+        PieceKind::Queen => queen_attacks(square, occupancy),
+        PieceKind::King => king_attacks(square),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::Position;
+
+    use super::*;
+
+    #[test]
+    fn knight_in_corner_attacks_two_squares() {
+        let attacks = knight_attacks(&Coords::from_algebraic("a1"));
+        assert_eq!(attacks.count(), 2);
+        assert!(attacks.contains(&Coords::from_algebraic("b3")));
+        assert!(attacks.contains(&Coords::from_algebraic("c2")));
+    }
+
+    #[test]
+    fn white_pawn_attacks_forward_diagonals() {
+        let attacks = pawn_attacks(&Coords::from_algebraic("e4"), &PieceColor::White);
+        assert_eq!(attacks.count(), 2);
+        assert!(attacks.contains(&Coords::from_algebraic("d5")));
+        assert!(attacks.contains(&Coords::from_algebraic("f5")));
+    }
+
+    #[test]
+    fn rook_attacks_stop_at_blockers() {
+        let occupancy = Bitboard::from_square(&Coords::from_algebraic("a4"))
+            | Bitboard::from_square(&Coords::from_algebraic("c1"));
+        let attacks = rook_attacks(&Coords::from_algebraic("a1"), occupancy);
+        assert_eq!(attacks.count(), 5);
+        assert!(attacks.contains(&Coords::from_algebraic("a4")));
+        assert!(!attacks.contains(&Coords::from_algebraic("a5")));
+        assert!(attacks.contains(&Coords::from_algebraic("c1")));
+        assert!(!attacks.contains(&Coords::from_algebraic("d1")));
+    }
+
+    #[test]
+    fn between_only_covers_shared_lines() {
+        let squares = |a: &str, b: &str| {
+            between(&Coords::from_algebraic(a), &Coords::from_algebraic(b))
+                .squares()
+                .collect::<Vec<Coords>>()
+        };
+        assert_eq!(
+            squares("a1", "a4"),
+            vec![Coords::from_algebraic("a3"), Coords::from_algebraic("a2")]
+        );
+        assert_eq!(
+            squares("h8", "e5"),
+            vec![Coords::from_algebraic("g7"), Coords::from_algebraic("f6")]
+        );
+        assert!(squares("e4", "e5").is_empty());
+        assert!(squares("a1", "b3").is_empty());
+    }
+
+    #[test]
+    fn finds_attackers_through_what_occupancy_leaves_out() {
+        let position = Position::from_fen("4k3/8/8/8/1b2r3/8/3P4/4K3 w - - 0 1");
+        let bitboards = PieceBitboards::from_board(position.board());
+        let e1 = Coords::from_algebraic("e1");
+        assert_eq!(
+            bitboards
+                .attackers(&e1, &PieceColor::Black, bitboards.occupancy())
+                .squares()
+                .collect::<Vec<Coords>>(),
+            vec![Coords::from_algebraic("e4")]
+        );
+        let without_pawn =
+            bitboards.occupancy() & !Bitboard::from_square(&Coords::from_algebraic("d2"));
+        assert_eq!(
+            bitboards
+                .attackers(&e1, &PieceColor::Black, without_pawn)
+                .count(),
+            2
+        );
+        assert_eq!(
+            bitboards
+                .attackers(
+                    &Coords::from_algebraic("e3"),
+                    &PieceColor::White,
+                    bitboards.occupancy()
+                )
+                .squares()
+                .collect::<Vec<Coords>>(),
+            vec![Coords::from_algebraic("d2")]
+        );
+    }
+
+    #[test]
+    fn line_tables_match_walking_the_rays() {
+        let rook_directions = [LINES[RANK], LINES[FILE]].concat();
+        let bishop_directions = [LINES[DIAGONAL], LINES[ANTI_DIAGONAL]].concat();
+        let mut state: u64 = 0x9E37_79B9_7F4A_7C15;
+        let mut random = || {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            state
+        };
+        for square in 0..64 {
+            let square = Coords::from_index(square);
+            for _ in 0..100 {
+                // sparse boards as well as crowded ones
+                let occupancy = Bitboard(random() & random() & random());
+                let crowded = Bitboard(random() | random());
+                for occupancy in [occupancy, crowded, Bitboard::EMPTY] {
+                    assert_eq!(
+                        rook_attacks(&square, occupancy),
+                        slider_attacks(&square, occupancy, &rook_directions)
+                    );
+                    assert_eq!(
+                        bishop_attacks(&square, occupancy),
+                        slider_attacks(&square, occupancy, &bishop_directions)
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn iterates_squares() {
+        let bitboard = Bitboard::from_square(&Coords::from_algebraic("h1"))
+            | Bitboard::from_square(&Coords::from_algebraic("a8"));
+        assert_eq!(
+            bitboard.squares().collect::<Vec<Coords>>(),
+            vec![Coords::from_algebraic("a8"), Coords::from_algebraic("h1")]
+        );
+    }
+}
@@ -0,0 +1,419 @@
+use std::fmt::Display;
+
+use crate::all_squares;
+use crate::ChessMove;
+use crate::Coords;
+use crate::Piece;
+use crate::PieceColor;
+use crate::PieceKind;
+use crate::Player;
+use crate::Position;
+
+// Captured pieces waiting to be dropped, for one side of one board.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub struct Reserve {
+    // indexed by PieceKind::index
+    counts: [usize; 6],
+}
+
+impl Reserve {
+    pub fn count(&self, kind: PieceKind) -> usize {
+        self.counts[kind.index()]
+    }
+
+    pub fn add(&mut self, kind: PieceKind) {
+        self.counts[kind.index()] += 1;
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.counts.iter().all(|count| *count == 0)
+    }
+
+    pub fn len(&self) -> usize {
+        self.counts.iter().sum()
+    }
+
+    pub fn kinds(&self) -> impl Iterator<Item = PieceKind> + '_ {
+        PieceKind::all()
+            .copied()
+            .filter(|kind| self.count(*kind) > 0)
+    }
+
+    fn take(&mut self, kind: PieceKind) {
+        self.counts[kind.index()] -= 1;
+    }
+}
+
+#[derive(Clone, PartialEq, Eq, Hash, Debug)]
+pub enum BughouseMove {
+    Board(ChessMove),
+    // a piece from the reserve of the side to move put on an empty square
+    Drop(PieceKind, Coords),
+}
+
+// One of the two boards of a bughouse game, with both sides' reserves. The
+// boards aren't `Game`s: a `Game` only records `ChessMove`s, which have no
+// way to say a piece was dropped, so the boards keep no move history, draw
+// claims or PGN, and the match itself decides the result.
+#[derive(Clone, PartialEq, Debug)]
+pub struct BughouseBoard {
+    pub position: Position,
+    // indexed by PieceColor::index
+    reserves: [Reserve; 2],
+    // squares holding promoted pieces, which go back to being pawns once
+    // they are captured
+    promoted: u64,
+}
+
+impl Default for BughouseBoard {
+    fn default() -> BughouseBoard {
+        BughouseBoard::from_position(Position::initial())
+    }
+}
+
+impl BughouseBoard {
+    pub fn new() -> BughouseBoard {
+        BughouseBoard::default()
+    }
+
+    pub fn from_position(position: Position) -> BughouseBoard {
+        BughouseBoard {
+            position,
+            reserves: [Reserve::default(); 2],
+            promoted: 0,
+        }
+    }
+
+    pub fn reserve(&self, color: &PieceColor) -> &Reserve {
+        &self.reserves[color.index()]
+    }
+
+    // Puts a piece passed over from the other board in the reserve of its
+    // color.
+    pub fn give(&mut self, piece: Piece) {
+        self.reserves[piece.color.index()].add(piece.kind);
+    }
+
+    pub fn legal_drops(&self) -> Vec<BughouseMove> {
+        let to_move = self.position.to_move;
+        let mut drops = Vec::new();
+        for kind in self.reserve(&to_move).kinds() {
+            for square in all_squares() {
+                if self.position.piece_at(&square).is_some()
+                    || kind == PieceKind::Pawn && (square.y == 0 || square.y == 7)
+                {
+                    continue;
+                }
+                if !self.after_drop(kind, square).is_in_check(&to_move) {
+                    drops.push(BughouseMove::Drop(kind, square));
+                }
+            }
+        }
+        drops
+    }
+
+    pub fn legal_moves(&self) -> Vec<BughouseMove> {
+        self.position
+            .all_legal_moves()
+            .into_iter()
+            .map(BughouseMove::Board)
+            .chain(self.legal_drops())
+            .collect()
+    }
+
+    pub fn is_move_legal(&self, bughouse_move: &BughouseMove) -> bool {
+        match bughouse_move {
+            BughouseMove::Board(chess_move) => self.position.is_move_legal(chess_move),
+            BughouseMove::Drop(..) => self.legal_drops().contains(bughouse_move),
+        }
+    }
+
+    // Only mate when no piece in hand can block the check either.
+    pub fn is_checkmate(&self) -> bool {
+        self.position.is_in_check(&self.position.to_move)
+            && !self.position.has_legal_move()
+            && self.legal_drops().is_empty()
+    }
+
+    pub fn is_stalemate(&self) -> bool {
+        !self.position.is_in_check(&self.position.to_move) && self.legal_moves().is_empty()
+    }
+
+    // Plays a legal move and returns the piece it captured, ready to be
+    // passed to the partner on the other board.
+    pub fn make_move(&mut self, bughouse_move: &BughouseMove) -> Option<Piece> {
+        match bughouse_move {
+            BughouseMove::Drop(kind, square) => {
+                self.reserves[self.position.to_move.index()].take(*kind);
+                self.position = self.after_drop(*kind, *square);
+                None
+            }
+            BughouseMove::Board(chess_move) => {
+                let captured = self.captured(chess_move);
+                let (origin, destination) =
+                    chess_move.origin_and_destination(&self.position.to_move);
+                if self.promoted & bit(&origin) != 0 {
+                    self.promoted = self.promoted & !bit(&origin) | bit(&destination);
+                }
+                if let ChessMove::Promotion(..) = chess_move {
+                    self.promoted |= bit(&destination);
+                }
+                self.position = self.position.after_move(chess_move);
+                captured
+            }
+        }
+    }
+
+    // The piece taken by `chess_move` as it came off the board, and the
+    // promoted squares updated for its removal.
+    fn captured(&mut self, chess_move: &ChessMove) -> Option<Piece> {
+        let square = match chess_move {
+            ChessMove::RegularMove(movement) | ChessMove::Promotion(movement, _) => {
+                movement.destination
+            }
+            ChessMove::EnPassant(_, taken) => *taken,
+            ChessMove::PawnSkip(_) | ChessMove::CastleLeft | ChessMove::CastleRight => return None,
+        };
+        let mut piece = self.position.piece_at(&square)?;
+        if self.promoted & bit(&square) != 0 {
+            self.promoted &= !bit(&square);
+            piece.kind = PieceKind::Pawn;
+        }
+        Some(piece)
+    }
+
+    fn after_drop(&self, kind: PieceKind, square: Coords) -> Position {
+        let mut position = self.position.clone();
+        position.put_piece_at(
+            Piece {
+                kind,
+                color: position.to_move,
+            },
+            square,
+        );
+        position.to_move = position.to_move.opposite();
+        position.en_passant_on = None;
+        position
+    }
+}
+
+fn bit(square: &Coords) -> u64 {
+    1 << square.to_square_number()
+}
+
+pub trait BughousePlayer: Display {
+    fn offer_bughouse_move(&self, board: &BughouseBoard) -> BughouseMove;
+    fn new_game(&self) {}
+}
+
+// A regular player sat at a bughouse board. It makes the board moves the
+// wrapped player picks and only drops when nothing else gets it out of check.
+pub struct WithoutDrops(pub Box<dyn Player>);
+
+impl Display for WithoutDrops {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+impl BughousePlayer for WithoutDrops {
+    fn offer_bughouse_move(&self, board: &BughouseBoard) -> BughouseMove {
+        if !board.position.has_legal_move() {
+            board
+                .legal_drops()
+                .into_iter()
+                .next()
+                .expect("a board still in play has a legal move")
+        } else {
+            BughouseMove::Board(self.0.offer_move(&board.position))
+        }
+    }
+    fn new_game(&self) {
+        self.0.new_game();
+    }
+}
+
+// The first team plays white on board 0 and black on board 1, so partners
+// always play opposite colors and every capture can be dropped by the
+// partner as it is.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum BughouseResult {
+    FirstTeamWin,
+    SecondTeamWin,
+    // a stalemate on either board
+    Draw,
+    TimedOut,
+}
+
+// per board, as for engine games
+const MOVE_LIMIT: usize = 300;
+
+pub struct BughouseMatch {
+    pub boards: [BughouseBoard; 2],
+    // by board, then by PieceColor::index
+    players: [[Box<dyn BughousePlayer>; 2]; 2],
+    moves_played: usize,
+}
+
+impl BughouseMatch {
+    pub fn new(players: [[Box<dyn BughousePlayer>; 2]; 2]) -> BughouseMatch {
+        BughouseMatch {
+            boards: [BughouseBoard::new(), BughouseBoard::new()],
+            players,
+            moves_played: 0,
+        }
+    }
+
+    pub fn result(&self) -> Option<BughouseResult> {
+        for (index, board) in self.boards.iter().enumerate() {
+            if board.is_checkmate() {
+                let first_team_mated =
+                    (index == 0) == (board.position.to_move == PieceColor::White);
+                return Some(if first_team_mated {
+                    BughouseResult::SecondTeamWin
+                } else {
+                    BughouseResult::FirstTeamWin
+                });
+            }
+        }
+        if self.boards.iter().any(|board| board.is_stalemate()) {
+            Some(BughouseResult::Draw)
+        } else if self.moves_played >= 2 * MOVE_LIMIT {
+            Some(BughouseResult::TimedOut)
+        } else {
+            None
+        }
+    }
+
+    // The side to move on `board` plays, and whatever it captures goes to
+    // its partner on the other board.
+    pub fn play_move(&mut self, board: usize) {
+        let to_move = self.boards[board].position.to_move;
+        let offered = self.players[board][to_move.index()].offer_bughouse_move(&self.boards[board]);
+        if !self.boards[board].is_move_legal(&offered) {
+            panic!("engine offered illegal move");
+        }
+        if let Some(captured) = self.boards[board].make_move(&offered) {
+            self.boards[1 - board].give(captured);
+        }
+        self.moves_played += 1;
+    }
+
+    // Both boards move in turn until either one is decided.
+    pub fn play(&mut self) -> BughouseResult {
+        self.players
+            .iter()
+            .flatten()
+            .for_each(|player| player.new_game());
+        let mut board = 0;
+        loop {
+            if let Some(result) = self.result() {
+                return result;
+            }
+            self.play_move(board);
+            board = 1 - board;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::FirstMovePlayer;
+    use crate::Move;
+
+    use super::*;
+
+    fn square(algebraic: &str) -> Coords {
+        Coords::from_algebraic(algebraic)
+    }
+
+    #[test]
+    fn pawns_are_not_dropped_on_the_back_ranks() {
+        let mut board =
+            BughouseBoard::from_position(Position::from_fen("4k3/8/8/8/8/8/8/4K3 w - - 0 1"));
+        board.give(Piece {
+            kind: PieceKind::Pawn,
+            color: PieceColor::White,
+        });
+        board.give(Piece {
+            kind: PieceKind::Knight,
+            color: PieceColor::Black,
+        });
+        assert_eq!(board.legal_drops().len(), 62 - 14);
+        let drop = BughouseMove::Drop(PieceKind::Pawn, square("d7"));
+        assert!(board.is_move_legal(&drop));
+        assert!(!board.is_move_legal(&BughouseMove::Drop(PieceKind::Pawn, square("a8"))));
+        board.make_move(&drop);
+        assert!(board.reserve(&PieceColor::White).is_empty());
+        assert!(board.position.is_in_check(&PieceColor::Black));
+        assert_eq!(
+            board.reserve(&PieceColor::Black).count(PieceKind::Knight),
+            1
+        );
+    }
+
+    #[test]
+    fn a_drop_can_block_mate() {
+        let mut board =
+            BughouseBoard::from_position(Position::from_fen("6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1"));
+        let mate = BughouseMove::Board(ChessMove::RegularMove(Move {
+            origin: square("a1"),
+            destination: square("a8"),
+        }));
+        let mut without_reserve = board.clone();
+        without_reserve.make_move(&mate);
+        assert!(without_reserve.is_checkmate());
+        board.give(Piece {
+            kind: PieceKind::Bishop,
+            color: PieceColor::Black,
+        });
+        board.make_move(&mate);
+        assert!(!board.is_checkmate());
+        assert_eq!(board.legal_moves().len(), 5);
+    }
+
+    #[test]
+    fn captured_promotions_are_passed_on_as_pawns() {
+        let mut board =
+            BughouseBoard::from_position(Position::from_fen("k7/2P5/8/8/8/8/7K/2r5 w - - 0 1"));
+        let promotion = ChessMove::Promotion(
+            Move {
+                origin: square("c7"),
+                destination: square("c8"),
+            },
+            PieceKind::Queen,
+        );
+        assert_eq!(board.make_move(&BughouseMove::Board(promotion)), None);
+        let recapture = ChessMove::RegularMove(Move {
+            origin: square("c1"),
+            destination: square("c8"),
+        });
+        assert_eq!(
+            board.make_move(&BughouseMove::Board(recapture)),
+            Some(Piece {
+                kind: PieceKind::Pawn,
+                color: PieceColor::White,
+            })
+        );
+        assert_eq!(board.promoted, 0);
+    }
+
+    #[test]
+    fn captured_pieces_change_boards() {
+        let player =
+            || Box::new(WithoutDrops(Box::new(FirstMovePlayer))) as Box<dyn BughousePlayer>;
+        let mut game = BughouseMatch::new([[player(), player()], [player(), player()]]);
+        let result = game.play();
+        assert_eq!(game.result(), Some(result));
+        let pieces: usize = game
+            .boards
+            .iter()
+            .map(|board| {
+                PieceColor::both()
+                    .map(|color| board.position.piece_count(color) + board.reserve(&color).len())
+                    .sum::<usize>()
+            })
+            .sum();
+        assert_eq!(pieces, 64);
+    }
+}
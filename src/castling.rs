@@ -0,0 +1,244 @@
+use crate::piece_at;
+use crate::Coords;
+use crate::Mailbox;
+use crate::Piece;
+use crate::PieceColor;
+use crate::PieceKind;
+
+// The rook file, 0 being the a-file, each side may still castle with.
+// Standard chess always has the rooks on the a- and h-files, Chess960 can
+// start them anywhere on either side of the king. Such rights are kept so
+// that Chess960 FENs round-trip, but castling is only played with the king
+// on the e-file and the rooks on the a- and h-files; `Position::validate`
+// reports the others.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub struct CastlingRights {
+    pub white_king_side: Option<isize>,
+    pub white_queen_side: Option<isize>,
+    pub black_king_side: Option<isize>,
+    pub black_queen_side: Option<isize>,
+}
+
+impl CastlingRights {
+    pub fn none() -> CastlingRights {
+        CastlingRights {
+            white_king_side: None,
+            white_queen_side: None,
+            black_king_side: None,
+            black_queen_side: None,
+        }
+    }
+
+    pub fn standard() -> CastlingRights {
+        CastlingRights {
+            white_king_side: Some(7),
+            white_queen_side: Some(0),
+            black_king_side: Some(7),
+            black_queen_side: Some(0),
+        }
+    }
+
+    pub fn king_side(&self, color: &PieceColor) -> Option<isize> {
+        match color {
+            PieceColor::White => self.white_king_side,
+            PieceColor::Black => self.black_king_side,
+        }
+    }
+
+    pub fn queen_side(&self, color: &PieceColor) -> Option<isize> {
+        match color {
+            PieceColor::White => self.white_queen_side,
+            PieceColor::Black => self.black_queen_side,
+        }
+    }
+
+    pub fn set_king_side(&mut self, color: &PieceColor, rook_file: Option<isize>) {
+        match color {
+            PieceColor::White => self.white_king_side = rook_file,
+            PieceColor::Black => self.black_king_side = rook_file,
+        }
+    }
+
+    pub fn set_queen_side(&mut self, color: &PieceColor, rook_file: Option<isize>) {
+        match color {
+            PieceColor::White => self.white_queen_side = rook_file,
+            PieceColor::Black => self.black_queen_side = rook_file,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        *self == CastlingRights::none()
+    }
+
+    // The rights the other side would have on the same files.
+    pub fn swap_colors(&self) -> CastlingRights {
+        CastlingRights {
+            white_king_side: self.black_king_side,
+            white_queen_side: self.black_queen_side,
+            black_king_side: self.white_king_side,
+            black_queen_side: self.white_queen_side,
+        }
+    }
+
+    // Reads the castling field of a FEN, "KQkq", Shredder-FEN's rook files,
+    // "HAha", or X-FEN's mix of the two. A K or a Q stands for the outermost
+    // rook on that side of the king, or the h- or a-file when there is none.
+    pub fn from_fen_field(field: &str, board: &Mailbox) -> CastlingRights {
+        let mut rights = CastlingRights::none();
+        for letter in field.chars() {
+            let color = if letter.is_ascii_uppercase() {
+                PieceColor::White
+            } else {
+                PieceColor::Black
+            };
+            let king = king_file(board, &color);
+            match letter.to_ascii_lowercase() {
+                'k' => rights.set_king_side(
+                    &color,
+                    Some(outermost_rook(board, &color, true).unwrap_or(7)),
+                ),
+                'q' => rights.set_queen_side(
+                    &color,
+                    Some(outermost_rook(board, &color, false).unwrap_or(0)),
+                ),
+                file @ 'a'..='h' => {
+                    let file = file as isize - 'a' as isize;
+                    if file > king.unwrap_or(3) {
+                        rights.set_king_side(&color, Some(file));
+                    } else {
+                        rights.set_queen_side(&color, Some(file));
+                    }
+                }
+                _ => {}
+            }
+        }
+        rights
+    }
+
+    // X-FEN: K and Q wherever they aren't ambiguous, rook files otherwise.
+    // With `shredder` every right is given by its rook file.
+    pub fn to_fen_field(&self, board: &Mailbox, shredder: bool) -> String {
+        if self.is_empty() {
+            return String::from("-");
+        }
+        let mut field = String::new();
+        for color in [PieceColor::White, PieceColor::Black] {
+            for (king_side, rook_file, standard) in [
+                (true, self.king_side(&color), 'k'),
+                (false, self.queen_side(&color), 'q'),
+            ] {
+                let Some(rook_file) = rook_file else {
+                    continue;
+                };
+                let outermost = outermost_rook(board, &color, king_side).unwrap_or(if king_side {
+                    7
+                } else {
+                    0
+                });
+                let letter = if !shredder && outermost == rook_file {
+                    standard
+                } else {
+                    (b'a' + rook_file as u8) as char
+                };
+                field.push(match color {
+                    PieceColor::White => letter.to_ascii_uppercase(),
+                    PieceColor::Black => letter,
+                });
+            }
+        }
+        field
+    }
+}
+
+fn king_file(board: &Mailbox, color: &PieceColor) -> Option<isize> {
+    let king = Piece {
+        kind: PieceKind::King,
+        color: *color,
+    };
+    (0..8).find(|x| {
+        piece_at(
+            board,
+            &Coords {
+                x: *x,
+                y: color.homerow(),
+            },
+        ) == Some(king)
+    })
+}
+
+// The rook of `color` on its home row furthest from the king towards the
+// h-file, or towards the a-file.
+fn outermost_rook(board: &Mailbox, color: &PieceColor, king_side: bool) -> Option<isize> {
+    let rook = Piece {
+        kind: PieceKind::Rook,
+        color: *color,
+    };
+    let king = king_file(board, color)?;
+    let is_rook = |x: &isize| {
+        piece_at(
+            board,
+            &Coords {
+                x: *x,
+                y: color.homerow(),
+            },
+        ) == Some(rook)
+    };
+    if king_side {
+        (king + 1..8).rev().find(is_rook)
+    } else {
+        (0..king).find(is_rook)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::ChessMove;
+    use crate::Position;
+
+    use super::*;
+
+    #[test]
+    fn reads_every_way_of_writing_the_rights() {
+        for fen in [
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w HAha - 0 1",
+        ] {
+            assert_eq!(
+                Position::from_fen(fen).castling_rights(),
+                CastlingRights::standard()
+            );
+        }
+        let chess960 =
+            Position::from_fen("bqnbrkrn/pppppppp/8/8/8/8/PPPPPPPP/BQNBRKRN w GEge - 0 1");
+        assert_eq!(
+            chess960.castling_rights().king_side(&PieceColor::White),
+            Some(6)
+        );
+        assert_eq!(
+            chess960.castling_rights().queen_side(&PieceColor::Black),
+            Some(4)
+        );
+        assert_eq!(
+            chess960.to_fen(),
+            "bqnbrkrn/pppppppp/8/8/8/8/PPPPPPPP/BQNBRKRN w KQkq - 0 1"
+        );
+        assert_eq!(
+            chess960.to_shredder_fen(),
+            "bqnbrkrn/pppppppp/8/8/8/8/PPPPPPPP/BQNBRKRN w GEge - 0 1"
+        );
+        assert_eq!(Position::from_fen(&chess960.to_fen()), chess960);
+    }
+
+    #[test]
+    fn names_inner_rooks_by_their_file() {
+        let position = Position::from_fen("4k3/8/8/8/8/8/8/RK1R3R w DA - 0 1");
+        assert_eq!(position.to_fen(), "4k3/8/8/8/8/8/8/RK1R3R w DQ - 0 1");
+        assert_eq!(Position::from_fen(&position.to_fen()), position);
+        assert_eq!(
+            position.to_shredder_fen(),
+            "4k3/8/8/8/8/8/8/RK1R3R w DA - 0 1"
+        );
+        let moved = position.after_move(&ChessMove::from_uci_long("d1d2", &position));
+        assert_eq!(moved.castling_rights().king_side(&PieceColor::White), None);
+    }
+}
@@ -1,5 +1,11 @@
+use std::fmt::Display;
+use std::str::FromStr;
+
 use crate::Coords;
+use crate::Piece;
+use crate::PieceColor;
 use crate::PieceKind;
+use crate::Square;
 
 #[derive(PartialEq, Hash, Eq, Debug, Clone)]
 pub enum ChessMove {
@@ -11,6 +17,104 @@ pub enum ChessMove {
     Promotion(Move, PieceKind),
 }
 
+impl ChessMove {
+    pub fn movement(&self) -> Option<&Move> {
+        match self {
+            ChessMove::RegularMove(movement)
+            | ChessMove::PawnSkip(movement)
+            | ChessMove::EnPassant(movement, _)
+            | ChessMove::Promotion(movement, _) => Some(movement),
+            ChessMove::CastleLeft | ChessMove::CastleRight => None,
+        }
+    }
+
+    // Castling is given as the movement of the king of `mover`.
+    pub fn origin_and_destination(&self, mover: &PieceColor) -> (Coords, Coords) {
+        let row = mover.homerow();
+        match self {
+            ChessMove::CastleLeft => (Coords { x: 4, y: row }, Coords { x: 2, y: row }),
+            ChessMove::CastleRight => (Coords { x: 4, y: row }, Coords { x: 6, y: row }),
+            _ => {
+                let movement = self.movement().expect("only castling has no movement");
+                (movement.origin, movement.destination)
+            }
+        }
+    }
+}
+
+// Written without a position, so castling can't be given as the king's
+// move and comes out as "O-O"/"O-O-O", promotions get their lowercase piece
+// letter, "e7e8q".
+impl Display for ChessMove {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ChessMove::CastleLeft => write!(f, "O-O-O"),
+            ChessMove::CastleRight => write!(f, "O-O"),
+            ChessMove::Promotion(movement, kind) => {
+                write!(
+                    f,
+                    "{}{}{}",
+                    movement.origin.to_algebraic(),
+                    movement.destination.to_algebraic(),
+                    promotion_letter(kind)
+                )
+            }
+            ChessMove::RegularMove(movement)
+            | ChessMove::PawnSkip(movement)
+            | ChessMove::EnPassant(movement, _) => write!(
+                f,
+                "{}{}",
+                movement.origin.to_algebraic(),
+                movement.destination.to_algebraic()
+            ),
+        }
+    }
+}
+
+pub(crate) fn promotion_letter(kind: &PieceKind) -> char {
+    Piece {
+        kind: *kind,
+        color: PieceColor::Black,
+    }
+    .to_fen_char()
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct ParseMoveError(pub String);
+
+// The inverse of `Display`. Without a position, pawn double steps and en
+// passant captures come back as regular moves and castling given as the
+// king's move, "e1g1", as a king move; `from_uci_long` tells them apart.
+impl FromStr for ChessMove {
+    type Err = ParseMoveError;
+    fn from_str(text: &str) -> Result<ChessMove, ParseMoveError> {
+        match text {
+            "O-O" | "0-0" => return Ok(ChessMove::CastleRight),
+            "O-O-O" | "0-0-0" => return Ok(ChessMove::CastleLeft),
+            _ => {}
+        }
+        let malformed = || ParseMoveError(String::from(text));
+        let square = |at: usize| {
+            text.get(at..at + 2)
+                .and_then(Square::from_algebraic)
+                .map(Coords::from)
+                .ok_or_else(malformed)
+        };
+        let movement = Move {
+            origin: square(0)?,
+            destination: square(2)?,
+        };
+        match text.get(4..) {
+            Some("") => Ok(ChessMove::RegularMove(movement)),
+            Some(letter) => PieceKind::promoteable()
+                .find(|kind| letter.chars().eq([promotion_letter(kind)]))
+                .map(|kind| ChessMove::Promotion(movement, *kind))
+                .ok_or_else(malformed),
+            None => Err(malformed()),
+        }
+    }
+}
+
 #[derive(PartialEq, Debug, Eq, Hash, Clone)]
 pub struct Move {
     pub origin: Coords,
@@ -29,3 +133,37 @@ impl Move {
         self.origin.x.abs_diff(self.destination.x)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn moves_are_written_and_read_back() {
+        for text in ["e2e4", "e7e8q", "a2a1n", "O-O", "O-O-O"] {
+            let chess_move: ChessMove = text.parse().unwrap();
+            assert_eq!(chess_move.to_string(), text);
+        }
+        assert_eq!(
+            "g7g8r".parse(),
+            Ok(ChessMove::Promotion(
+                Move {
+                    origin: Coords::from_algebraic("g7"),
+                    destination: Coords::from_algebraic("g8"),
+                },
+                PieceKind::Rook
+            ))
+        );
+        assert_eq!("0-0".parse(), Ok(ChessMove::CastleRight));
+    }
+
+    #[test]
+    fn malformed_moves_are_refused() {
+        for text in ["", "e2", "e2e9", "i2e4", "e7e8k", "e7e8qq", "e2-e4"] {
+            assert_eq!(
+                text.parse::<ChessMove>(),
+                Err(ParseMoveError(String::from(text)))
+            );
+        }
+    }
+}
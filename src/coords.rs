@@ -11,6 +11,20 @@ impl Coords {
         self.x < 8 && self.x >= 0 && self.y < 8 && self.y >= 0
     }
 
+    // Squares are numbered from a8 along each rank to h1, the numbering the
+    // bitboards use for their bits.
+    pub fn to_square_number(&self) -> usize {
+        (self.y * 8 + self.x) as usize
+    }
+
+    pub const fn from_index(index: usize) -> Coords {
+        assert!(index < 64, "square numbers go from 0 to 63");
+        Coords {
+            x: (index % 8) as isize,
+            y: (index / 8) as isize,
+        }
+    }
+
     pub fn to_algebraic(&self) -> String {
         let file = match self.x {
             0 => "a",
@@ -25,7 +39,7 @@ impl Coords {
         };
         let rank = self.y.abs_diff(8).to_string();
         let mut algebraic = String::new();
-        algebraic.push_str(&file);
+        algebraic.push_str(file);
         algebraic.push_str(&rank);
         algebraic
     }
@@ -64,14 +78,19 @@ impl Coords {
         }
     }
 }
-pub fn all_squares() -> Vec<Coords> {
-    let mut squares = Vec::new();
-    for i in 0..8 {
-        for j in 0..8 {
-            squares.push(Coords { y: i, x: j });
-        }
+// In square number order.
+pub const ALL_SQUARES: [Coords; 64] = {
+    let mut squares = [Coords { x: 0, y: 0 }; 64];
+    let mut index = 0;
+    while index < 64 {
+        squares[index] = Coords::from_index(index);
+        index += 1;
     }
     squares
+};
+
+pub fn all_squares() -> [Coords; 64] {
+    ALL_SQUARES
 }
 
 impl ops::Add<Direction> for Coords {
@@ -100,40 +119,43 @@ impl ops::Mul<isize> for Direction {
     }
 }
 
-pub fn eight_degrees() -> Vec<Direction> {
-    let up_right = Direction { dy: 1, dx: 1 };
-    let down_left = Direction { dy: -1, dx: -1 };
-    let up_left = Direction { dy: 1, dx: -1 };
-    let down_right = Direction { dy: -1, dx: 1 };
-    let up = Direction { dx: 0, dy: 1 };
-    let down = Direction { dx: 0, dy: -1 };
-    let left = Direction { dx: -1, dy: 0 };
-    let right = Direction { dx: 1, dy: 0 };
-    vec![
-        up_right, down_left, up_left, down_right, up, down, left, right,
-    ]
+const UP: Direction = Direction { dx: 0, dy: 1 };
+const DOWN: Direction = Direction { dx: 0, dy: -1 };
+const LEFT: Direction = Direction { dx: -1, dy: 0 };
+const RIGHT: Direction = Direction { dx: 1, dy: 0 };
+const UP_RIGHT: Direction = Direction { dx: 1, dy: 1 };
+const DOWN_LEFT: Direction = Direction { dx: -1, dy: -1 };
+const UP_LEFT: Direction = Direction { dx: -1, dy: 1 };
+const DOWN_RIGHT: Direction = Direction { dx: 1, dy: -1 };
+
+pub const CARDS: [Direction; 4] = [UP, DOWN, LEFT, RIGHT];
+pub const INTER_CARDS: [Direction; 4] = [UP_RIGHT, DOWN_LEFT, UP_LEFT, DOWN_RIGHT];
+pub const EIGHT_DEGREES: [Direction; 8] = [
+    UP_RIGHT, DOWN_LEFT, UP_LEFT, DOWN_RIGHT, UP, DOWN, LEFT, RIGHT,
+];
+
+pub fn eight_degrees() -> [Direction; 8] {
+    EIGHT_DEGREES
 }
 
-pub fn inter_cards() -> Vec<Direction> {
-    let up_right = Direction { dy: 1, dx: 1 };
-    let down_left = Direction { dy: -1, dx: -1 };
-    let up_left = Direction { dy: 1, dx: -1 };
-    let down_right = Direction { dy: -1, dx: 1 };
-    vec![up_right, down_left, up_left, down_right]
+pub fn inter_cards() -> [Direction; 4] {
+    INTER_CARDS
 }
 
-pub fn cards() -> Vec<Direction> {
-    let up = Direction { dx: 0, dy: 1 };
-    let down = Direction { dx: 0, dy: -1 };
-    let left = Direction { dx: -1, dy: 0 };
-    let right = Direction { dx: 1, dy: 0 };
-    vec![up, down, left, right]
+pub fn cards() -> [Direction; 4] {
+    CARDS
 }
 
 #[cfg(test)]
 mod tests {
+    use crate::Bitboard;
     use crate::Coords;
 
+    use super::ALL_SQUARES;
+    use super::CARDS;
+    use super::EIGHT_DEGREES;
+    use super::INTER_CARDS;
+
     #[test]
     fn coord_from_algebraic() {
         assert_eq!(Coords { x: 4, y: 4 }, Coords::from_algebraic("e4"));
@@ -142,4 +164,28 @@ mod tests {
     fn coords_to_algebraic() {
         assert_eq!("e4", Coords { x: 4, y: 4 }.to_algebraic());
     }
+    #[test]
+    fn square_numbers_go_both_ways() {
+        for (index, square) in ALL_SQUARES.into_iter().enumerate() {
+            assert_eq!(Coords::from_index(index), square);
+            assert_eq!(square.to_square_number(), index);
+            assert_eq!(
+                Bitboard::from_square(&square).squares().next(),
+                Some(square)
+            );
+        }
+        assert_eq!(Coords::from_index(0).to_algebraic(), "a8");
+        assert_eq!(Coords::from_index(63).to_algebraic(), "h1");
+    }
+    #[test]
+    fn eight_degrees_are_the_cards_and_inter_cards() {
+        let steps = |directions: &[super::Direction]| -> Vec<(isize, isize)> {
+            let mut steps: Vec<_> = directions.iter().map(|dir| (dir.dx, dir.dy)).collect();
+            steps.sort();
+            steps
+        };
+        assert_eq!(steps(&EIGHT_DEGREES), steps(&[CARDS, INTER_CARDS].concat()));
+        assert!(CARDS.iter().all(|dir| dir.dx == 0 || dir.dy == 0));
+        assert!(INTER_CARDS.iter().all(|dir| dir.dx != 0 && dir.dy != 0));
+    }
 }
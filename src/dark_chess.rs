@@ -0,0 +1,141 @@
+use crate::all_squares;
+use crate::bitboard::pawn_pushes;
+use crate::Bitboard;
+use crate::ChessMove;
+use crate::GameResult;
+use crate::PieceColor;
+use crate::PieceKind;
+use crate::Position;
+use crate::RuleSet;
+use crate::WinReason;
+
+// The side whose king is still on the board once the other one has been
+// captured.
+fn winner(position: &Position) -> Option<PieceColor> {
+    let has_king = |color: PieceColor| position.king_location(&color).is_some();
+    match (has_king(PieceColor::White), has_king(PieceColor::Black)) {
+        (true, false) => Some(PieceColor::White),
+        (false, true) => Some(PieceColor::Black),
+        _ => None,
+    }
+}
+
+impl Position {
+    // The squares `color` sees in dark chess: those its pieces stand on or
+    // attack, along with the ones its pawns can step to.
+    pub fn visible_squares(&self, color: PieceColor) -> Bitboard {
+        let bitboards = self.bitboards();
+        bitboards.color(&color)
+            | self.attacked_squares(&color)
+            | pawn_pushes(
+                bitboards.pieces(&color, &PieceKind::Pawn),
+                &color,
+                !bitboards.occupancy(),
+            )
+    }
+}
+
+// Dark chess, or fog of war: each side only sees part of the board, there
+// is no check, and the game is won by capturing the king.
+pub struct DarkChessRules;
+
+impl DarkChessRules {
+    // The board as `color` is shown it: the other side's pieces on squares
+    // `color` can't see are left out, and so are its castling rights.
+    pub fn view(&self, position: &Position, color: PieceColor) -> Position {
+        let visible = position.visible_squares(color);
+        let mut view = position.clone();
+        for square in all_squares() {
+            if !visible.contains(&square) && view.piece_at(&square).is_some() {
+                view.take_piece_at(square);
+            }
+        }
+        view.set_can_castle_king_side(&color.opposite(), false);
+        view.set_can_castle_queen_side(&color.opposite(), false);
+        view
+    }
+}
+
+impl RuleSet for DarkChessRules {
+    fn name(&self) -> &str {
+        "Dark chess"
+    }
+    // Any move the pieces allow, whether or not it leaves the king attacked.
+    fn legal_moves(&self, position: &Position) -> Vec<ChessMove> {
+        if winner(position).is_some() {
+            return Vec::new();
+        }
+        position.all_possible_moves()
+    }
+    fn result(&self, position: &Position) -> Option<GameResult> {
+        winner(position).map(|winner| GameResult::win_for(winner, WinReason::Variant))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::Coords;
+    use crate::Move;
+
+    use super::*;
+
+    #[test]
+    fn pieces_see_where_they_attack_and_move() {
+        let position = Position::initial();
+        let visible = position.visible_squares(PieceColor::White);
+        assert_eq!(visible.count(), 32);
+        assert!(visible.contains(&Coords::from_algebraic("e4")));
+        assert!(!visible.contains(&Coords::from_algebraic("e5")));
+        let view = DarkChessRules.view(&position, PieceColor::White);
+        assert_eq!(view.piece_count(PieceColor::Black), 0);
+        assert_eq!(view.piece_count(PieceColor::White), 16);
+        assert!(!view.can_castle_king_side(&PieceColor::Black));
+    }
+
+    #[test]
+    fn blocked_pieces_stay_hidden() {
+        // neither side reaches the other, so each only sees its own pieces
+        let position = Position::from_fen("4kb2/4p3/8/8/8/8/8/R3K3 w - - 0 1");
+        let view = DarkChessRules.view(&position, PieceColor::White);
+        assert!(view.piece_at(&Coords::from_algebraic("e8")).is_none());
+        assert!(view.piece_at(&Coords::from_algebraic("f8")).is_none());
+        let view = DarkChessRules.view(&position, PieceColor::Black);
+        assert!(view.piece_at(&Coords::from_algebraic("a1")).is_none());
+        assert_eq!(view.piece_count(PieceColor::White), 0);
+    }
+
+    #[test]
+    fn pawns_see_the_squares_they_can_step_to() {
+        let position = Position::from_fen("4k3/8/8/8/8/2p5/P1P4P/4K3 w - - 0 1");
+        let visible = position.visible_squares(PieceColor::White);
+        for seen in ["a3", "a4", "b3", "h3", "h4"] {
+            assert!(visible.contains(&Coords::from_algebraic(seen)), "{}", seen);
+        }
+        // the c-pawn is blocked, so it sees neither step past the pawn
+        assert!(!visible.contains(&Coords::from_algebraic("c4")));
+        assert!(!visible.contains(&Coords::from_algebraic("a5")));
+    }
+
+    #[test]
+    fn capturing_the_king_wins() {
+        // white may leave its king in check
+        let position = Position::from_fen("4k3/8/8/8/8/8/8/R3K2q w - - 0 1");
+        let ignored = ChessMove::RegularMove(Move {
+            origin: Coords::from_algebraic("a1"),
+            destination: Coords::from_algebraic("a8"),
+        });
+        assert!(!position.all_legal_moves().contains(&ignored));
+        assert!(DarkChessRules.legal_moves(&position).contains(&ignored));
+        let position = position.after_move(&ignored);
+        assert_eq!(DarkChessRules.result(&position), None);
+        let king_taken = position.after_move(&ChessMove::RegularMove(Move {
+            origin: Coords::from_algebraic("h1"),
+            destination: Coords::from_algebraic("e1"),
+        }));
+        assert_eq!(
+            DarkChessRules.result(&king_taken),
+            Some(GameResult::BlackWin(WinReason::Variant))
+        );
+        assert!(DarkChessRules.legal_moves(&king_taken).is_empty());
+    }
+}
@@ -1,16 +1,44 @@
 #[cfg(feature = "rng")]
 use rand::prelude::IndexedRandom;
-use std::collections::HashMap;
+#[cfg(feature = "rng")]
+use rand::rngs::StdRng;
+#[cfg(feature = "rng")]
+use rand::{RngCore, SeedableRng};
 use std::fmt::Display;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::{Duration, Instant};
 
 use crate::all_squares;
-use crate::piece_at;
+use crate::bitboard::file;
+use crate::elementary_mate_distance;
+use crate::elementary_mate_move;
+use crate::piece_square::blend;
+use crate::piece_square::phase_weight;
+use crate::piece_square::DEFAULT_TABLES;
 use crate::player::Player;
+use crate::static_exchange;
+use crate::Bitboard;
+use crate::Bound;
 use crate::ChessMove;
+use crate::ClockState;
+use crate::Coords;
+use crate::EngineOption;
+use crate::EvalTrace;
+use crate::MoveList;
+use crate::OptionValue;
 use crate::Piece;
 use crate::PieceColor;
 use crate::PieceKind;
+use crate::PieceSquareTables;
 use crate::Position;
+use crate::SearchDeadline;
+use crate::SquareTerms;
+use crate::TableConfig;
+use crate::TableEntry;
+use crate::TableStats;
+use crate::TimeManager;
+use crate::TranspositionTable;
 
 pub struct FirstMovePlayer;
 
@@ -21,15 +49,64 @@ impl Display for FirstMovePlayer {
 }
 impl Player for FirstMovePlayer {
     fn offer_move(&self, position: &Position) -> ChessMove {
-        position.all_legal_moves().first().unwrap().clone()
+        position.legal_moves_iter().next().unwrap()
     }
     fn evalutate(&self, _position: &Position) -> isize {
         0
     }
 }
 
+// Where a random player draws from: the thread's generator, or one of its
+// own so that its games can be replayed.
+#[cfg(feature = "rng")]
+#[derive(Default)]
+struct MoveRng(Option<Mutex<Box<dyn RngCore + Send>>>);
+
+#[cfg(feature = "rng")]
+impl MoveRng {
+    fn seeded(seed: u64) -> MoveRng {
+        MoveRng::from_rng(StdRng::seed_from_u64(seed))
+    }
+
+    fn from_rng(rng: impl RngCore + Send + 'static) -> MoveRng {
+        MoveRng(Some(Mutex::new(Box::new(rng))))
+    }
+
+    fn choose(&self, moves: &[ChessMove]) -> Option<ChessMove> {
+        match &self.0 {
+            None => moves.choose(&mut rand::rng()).cloned(),
+            Some(rng) => moves
+                .choose(&mut *rng.lock().unwrap_or_else(|poisoned| poisoned.into_inner()))
+                .cloned(),
+        }
+    }
+}
+
+#[cfg(feature = "rng")]
+#[derive(Default)]
+pub struct RandomPlayer {
+    rng: MoveRng,
+}
+
 #[cfg(feature = "rng")]
-pub struct RandomPlayer;
+impl RandomPlayer {
+    pub fn new() -> RandomPlayer {
+        RandomPlayer::default()
+    }
+
+    // The same seed plays the same moves in the same positions.
+    pub fn seeded(seed: u64) -> RandomPlayer {
+        RandomPlayer {
+            rng: MoveRng::seeded(seed),
+        }
+    }
+
+    pub fn from_rng(rng: impl RngCore + Send + 'static) -> RandomPlayer {
+        RandomPlayer {
+            rng: MoveRng::from_rng(rng),
+        }
+    }
+}
 
 #[cfg(feature = "rng")]
 impl Display for RandomPlayer {
@@ -41,7 +118,7 @@ impl Display for RandomPlayer {
 #[cfg(feature = "rng")]
 impl Player for RandomPlayer {
     fn offer_move(&self, position: &Position) -> ChessMove {
-        pick_random_move(position)
+        pick_random_move(position, &self.rng)
     }
     fn evalutate(&self, _position: &Position) -> isize {
         0
@@ -49,16 +126,34 @@ impl Player for RandomPlayer {
 }
 
 #[cfg(feature = "rng")]
-fn pick_random_move(position: &Position) -> ChessMove {
-    position
-        .all_legal_moves()
-        .choose(&mut rand::rng())
-        .unwrap()
-        .clone()
+fn pick_random_move(position: &Position, rng: &MoveRng) -> ChessMove {
+    rng.choose(&position.all_legal_moves()).unwrap()
 }
 
 #[cfg(feature = "rng")]
-pub struct RandomCapturePrioPlayer;
+#[derive(Default)]
+pub struct RandomCapturePrioPlayer {
+    rng: MoveRng,
+}
+
+#[cfg(feature = "rng")]
+impl RandomCapturePrioPlayer {
+    pub fn new() -> RandomCapturePrioPlayer {
+        RandomCapturePrioPlayer::default()
+    }
+
+    pub fn seeded(seed: u64) -> RandomCapturePrioPlayer {
+        RandomCapturePrioPlayer {
+            rng: MoveRng::seeded(seed),
+        }
+    }
+
+    pub fn from_rng(rng: impl RngCore + Send + 'static) -> RandomCapturePrioPlayer {
+        RandomCapturePrioPlayer {
+            rng: MoveRng::from_rng(rng),
+        }
+    }
+}
 
 #[cfg(feature = "rng")]
 impl Display for RandomCapturePrioPlayer {
@@ -73,22 +168,11 @@ impl Player for RandomCapturePrioPlayer {
         let moves_with_capture: Vec<ChessMove> = position
             .all_legal_moves()
             .into_iter()
-            .filter(|chess_move| {
-                position
-                    .after_move(chess_move)
-                    .piece_count(position.to_move.opposite())
-                    < position.piece_count(position.to_move.opposite())
-            })
+            .filter(|chess_move| position.is_capture(chess_move))
             .collect();
-        if moves_with_capture.len() > 0 {
-            moves_with_capture
-                .choose(&mut rand::rng())
-                .unwrap()
-                .clone()
-                .clone()
-        } else {
-            pick_random_move(position)
-        }
+        self.rng
+            .choose(&moves_with_capture)
+            .unwrap_or_else(|| pick_random_move(position, &self.rng))
     }
     fn evalutate(&self, _position: &Position) -> isize {
         0
@@ -118,7 +202,7 @@ fn basic_evaluation(position: &Position) -> isize {
     }
     all_squares()
         .iter()
-        .map(|square| match piece_at(&position.board, square) {
+        .map(|square| match position.piece_at(square) {
             None => 0_isize,
             Some(piece) => evaluate_piece(
                 &piece,
@@ -137,41 +221,46 @@ impl Display for BasicEvaluationPlayer {
     }
 }
 
+// Moves keep the order in which they were generated so that ties are always
+// broken the same way.
 fn moves_with_evaluation(
     position: &Position,
-    evaluation: fn(&Position) -> isize,
-) -> HashMap<isize, Vec<ChessMove>> {
-    let all_moves = position.all_legal_moves();
-    let mut moves_by_evaluation = HashMap::new();
-    all_moves.iter().for_each(|chess_move| {
-        moves_by_evaluation
-            .entry(evaluation(&position.after_move(chess_move)))
-            .or_insert(Vec::new())
-            .push(chess_move.clone())
-    });
-    moves_by_evaluation
+    evaluation: impl Fn(&Position) -> isize,
+) -> Vec<(ChessMove, isize)> {
+    position
+        .all_legal_moves()
+        .into_iter()
+        .map(|chess_move| {
+            let evaluation = evaluation(&position.after_move(&chess_move));
+            (chess_move, evaluation)
+        })
+        .collect()
 }
 
-fn first_move_with_max_evaluation(
-    moves_by_evaluation: HashMap<isize, Vec<ChessMove>>,
-) -> ChessMove {
-    moves_by_evaluation
-        .get(moves_by_evaluation.keys().max().unwrap())
-        .unwrap()
-        .first()
+fn first_move_with_max_evaluation(moves_with_evaluation: Vec<(ChessMove, isize)>) -> ChessMove {
+    let max = moves_with_evaluation
+        .iter()
+        .map(|(_, evaluation)| *evaluation)
+        .max()
+        .unwrap();
+    moves_with_evaluation
+        .into_iter()
+        .find(|(_, evaluation)| *evaluation == max)
         .unwrap()
-        .clone()
+        .0
 }
 
-fn first_move_with_min_evaluation(
-    moves_by_evaluation: HashMap<isize, Vec<ChessMove>>,
-) -> ChessMove {
-    moves_by_evaluation
-        .get(moves_by_evaluation.keys().min().unwrap())
-        .unwrap()
-        .first()
+fn first_move_with_min_evaluation(moves_with_evaluation: Vec<(ChessMove, isize)>) -> ChessMove {
+    let min = moves_with_evaluation
+        .iter()
+        .map(|(_, evaluation)| *evaluation)
+        .min()
+        .unwrap();
+    moves_with_evaluation
+        .into_iter()
+        .find(|(_, evaluation)| *evaluation == min)
         .unwrap()
-        .clone()
+        .0
 }
 
 impl Player for BasicEvaluationPlayer {
@@ -183,14 +272,159 @@ impl Player for BasicEvaluationPlayer {
     }
 }
 
-pub struct BetterEvaluationPlayer {}
+// The positional terms of the better evaluation that can be switched off,
+// to measure what each of them is worth.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct EvalTerms {
+    // scale each piece's control by `mobility_weights` rather than counting
+    // every move the same
+    pub weighted_mobility: bool,
+    pub bishop_pair: bool,
+    pub rook_files: bool,
+}
+
+impl Default for EvalTerms {
+    fn default() -> EvalTerms {
+        EvalTerms {
+            weighted_mobility: true,
+            bishop_pair: true,
+            rook_files: true,
+        }
+    }
+}
+
+impl EvalTerms {
+    pub fn none() -> EvalTerms {
+        EvalTerms {
+            weighted_mobility: false,
+            bishop_pair: false,
+            rook_files: false,
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct EvalParams {
+    pub pawn_value: isize,
+    pub knight_value: isize,
+    pub bishop_value: isize,
+    pub rook_value: isize,
+    pub queen_value: isize,
+    pub king_value: isize,
+    pub control_value: isize,
+    pub attacked_own_piece_penalty: isize,
+    pub checkmate_bonus: isize,
+    pub terms: EvalTerms,
+    // how many times `control_value` a move of each piece is worth, indexed
+    // by PieceKind::index
+    pub mobility_weights: [isize; 6],
+    pub bishop_pair_bonus: isize,
+    // no pawns on the rook's file
+    pub rook_open_file_bonus: isize,
+    // only the other side's pawns on it
+    pub rook_semi_open_file_bonus: isize,
+}
+
+impl Default for EvalParams {
+    fn default() -> EvalParams {
+        EvalParams {
+            pawn_value: 100,
+            knight_value: 200,
+            bishop_value: 300,
+            rook_value: 500,
+            queen_value: 5000,
+            king_value: 10000,
+            control_value: 2,
+            attacked_own_piece_penalty: 5,
+            checkmate_bonus: 10000000,
+            terms: EvalTerms::default(),
+            // minor pieces gain the most from every extra square, the king
+            // is better off staying put
+            mobility_weights: [1, 2, 4, 3, 1, 0],
+            bishop_pair_bonus: 50,
+            rook_open_file_bonus: 25,
+            rook_semi_open_file_bonus: 10,
+        }
+    }
+}
+
+impl EvalParams {
+    pub fn mobility_weight(&self, kind: &PieceKind) -> isize {
+        if self.terms.weighted_mobility {
+            self.mobility_weights[kind.index()]
+        } else {
+            1
+        }
+    }
+
+    pub fn piece_value(&self, kind: &PieceKind) -> isize {
+        match kind {
+            PieceKind::King => self.king_value,
+            PieceKind::Pawn => self.pawn_value,
+            PieceKind::Rook => self.rook_value,
+            PieceKind::Bishop => self.bishop_value,
+            PieceKind::Knight => self.knight_value,
+            PieceKind::Queen => self.queen_value,
+        }
+    }
+
+    // The values worth tuning, in a fixed order. The king value and checkmate
+    // bonus only need to dominate everything else and are left out.
+    pub fn tunable_values(&self) -> Vec<isize> {
+        vec![
+            self.pawn_value,
+            self.knight_value,
+            self.bishop_value,
+            self.rook_value,
+            self.queen_value,
+            self.control_value,
+            self.attacked_own_piece_penalty,
+        ]
+    }
+
+    pub fn with_tunable_values(&self, values: &[isize]) -> EvalParams {
+        assert_eq!(values.len(), 7, "expected one value per tunable parameter");
+        EvalParams {
+            pawn_value: values[0],
+            knight_value: values[1],
+            bishop_value: values[2],
+            rook_value: values[3],
+            queen_value: values[4],
+            control_value: values[5],
+            attacked_own_piece_penalty: values[6],
+            ..*self
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct BetterEvaluationPlayer {
+    pub params: EvalParams,
+}
+
+impl BetterEvaluationPlayer {
+    pub fn with_params(params: EvalParams) -> BetterEvaluationPlayer {
+        BetterEvaluationPlayer { params }
+    }
+
+    // The evaluation of `position` from the side to move's point of view,
+    // split into what every square and term contributes.
+    pub fn trace(&self, position: &Position) -> EvalTrace {
+        better_evaluation_trace(position, &self.params)
+    }
+}
 
 impl Player for BetterEvaluationPlayer {
     fn offer_move(&self, position: &Position) -> ChessMove {
-        first_move_with_min_evaluation(moves_with_evaluation(position, better_evaluation))
+        if let Some(chess_move) = elementary_mate_move(position) {
+            return chess_move;
+        }
+        first_move_with_min_evaluation(moves_with_evaluation(position, |position| {
+            better_evaluation_with(position, &self.params)
+        }))
     }
     fn evalutate(&self, position: &Position) -> isize {
-        -better_evaluation(position)
+        -better_evaluation_with(position, &self.params)
     }
 }
 
@@ -200,109 +434,1224 @@ impl Display for BetterEvaluationPlayer {
     }
 }
 
-fn better_evaluation(position: &Position) -> isize {
-    fn piece_value(kind: &PieceKind) -> isize {
-        match kind {
-            PieceKind::King => 10000,
-            PieceKind::Pawn => 100,
-            PieceKind::Rook => 500,
-            PieceKind::Bishop => 300,
-            PieceKind::Knight => 200,
-            PieceKind::Queen => 5000,
-        }
-    }
-    fn evaluate_piece(
-        piece: &Piece,
-        is_attacked: bool,
-        to_move: &PieceColor,
-        controlled_squares: isize,
-    ) -> isize {
-        let value = piece_value(&piece.kind);
-        let control_value = 2;
-        let own_color_factor = if &piece.color == to_move { 1 } else { -1 };
-        let attacked_factor = if is_attacked {
-            if &piece.color == to_move {
-                -5
-            } else {
-                -piece_value(&piece.kind)
+// Material and where the pieces stand, which keeps knights off the rim and
+// the king at home until the endgame.
+#[derive(Default)]
+pub struct PieceSquarePlayer {
+    pub tables: PieceSquareTables,
+}
+
+impl PieceSquarePlayer {
+    fn evaluation(&self, position: &Position) -> isize {
+        let params = EvalParams::default();
+        if position.is_checkmate() {
+            return -params.checkmate_bonus;
+        }
+        MaterialBalance::of(position).relative_to(&position.to_move)
+            + self.tables.evaluate(position)
+    }
+}
+
+impl Player for PieceSquarePlayer {
+    fn offer_move(&self, position: &Position) -> ChessMove {
+        if let Some(chess_move) = elementary_mate_move(position) {
+            return chess_move;
+        }
+        first_move_with_min_evaluation(moves_with_evaluation(position, |position| {
+            self.evaluation(position)
+        }))
+    }
+    fn evalutate(&self, position: &Position) -> isize {
+        self.evaluation(position)
+    }
+}
+
+impl Display for PieceSquarePlayer {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Piece-square tables")
+    }
+}
+
+// Plays whichever move comes out of the exchange it starts with the most
+// material, mating when it can. Stronger than playing at random, and still
+// easy to beat.
+pub struct GreedyMaterialPlayer;
+
+impl Player for GreedyMaterialPlayer {
+    fn offer_move(&self, position: &Position) -> ChessMove {
+        if let Some(chess_move) = elementary_mate_move(position) {
+            return chess_move;
+        }
+        let moves = position.legal_move_list();
+        if let Some(mate) = moves
+            .iter()
+            .find(|chess_move| position.after_move(chess_move).is_checkmate())
+        {
+            return mate;
+        }
+        moves
+            .iter()
+            .rev()
+            .max_by_key(|chess_move| static_exchange(position, chess_move))
+            .expect("there should be at least one legal move")
+    }
+    fn evalutate(&self, position: &Position) -> isize {
+        MaterialBalance::of(position).relative_to(&position.to_move)
+    }
+}
+
+impl Display for GreedyMaterialPlayer {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Greedy material")
+    }
+}
+
+fn better_evaluation_with(position: &Position, params: &EvalParams) -> isize {
+    better_evaluation_trace(position, params).total()
+}
+
+// A rook's bonus for the pawns that aren't in its way along the file.
+fn rook_file_bonus(
+    position: &Position,
+    params: &EvalParams,
+    square: &Coords,
+    color: &PieceColor,
+) -> isize {
+    let bitboards = position.bitboards();
+    let on_file = file(square.x);
+    let own_pawns = bitboards.pieces(color, &PieceKind::Pawn) & on_file;
+    let other_pawns = bitboards.pieces(&color.opposite(), &PieceKind::Pawn) & on_file;
+    match (own_pawns.is_empty(), other_pawns.is_empty()) {
+        (true, true) => params.rook_open_file_bonus,
+        (true, false) => params.rook_semi_open_file_bonus,
+        _ => 0,
+    }
+}
+
+fn has_bishop_pair(position: &Position, color: &PieceColor) -> bool {
+    position
+        .bitboards()
+        .pieces(color, &PieceKind::Bishop)
+        .count()
+        >= 2
+}
+
+// What the piece on `square` adds to the evaluation, from the side to
+// move's point of view.
+fn square_terms(
+    position: &Position,
+    params: &EvalParams,
+    square: &Coords,
+    piece: &Piece,
+) -> SquareTerms {
+    let to_move = &position.to_move;
+    let value = params.piece_value(&piece.kind);
+    let own_color_factor = if &piece.color == to_move { 1 } else { -1 };
+    let attacked_factor = if position.is_attacked_by(&piece.color.opposite(), square) {
+        if &piece.color == to_move {
+            -params.attacked_own_piece_penalty
+        } else {
+            -value
+        }
+    } else {
+        0
+    };
+    let file = if params.terms.rook_files && piece.kind == PieceKind::Rook {
+        rook_file_bonus(position, params, square, &piece.color)
+    } else {
+        0
+    };
+    let controlled_squares: isize = position
+        .color_to_move(piece.color)
+        .legal_moves_from_origin(square)
+        .len()
+        .try_into()
+        .unwrap();
+    SquareTerms {
+        material: value * own_color_factor,
+        control: controlled_squares
+            * params.control_value
+            * params.mobility_weight(&piece.kind)
+            * own_color_factor,
+        attacked: attacked_factor * own_color_factor,
+        file: file * own_color_factor,
+    }
+}
+
+fn bishop_pair_term(position: &Position, params: &EvalParams) -> isize {
+    if !params.terms.bishop_pair {
+        return 0;
+    }
+    let to_move = position.to_move;
+    let pair = |color: &PieceColor| has_bishop_pair(position, color) as isize;
+    (pair(&to_move) - pair(&to_move.opposite())) * params.bishop_pair_bonus
+}
+
+fn piece_terms(position: &Position, params: &EvalParams) -> EvalTrace {
+    let mut trace = EvalTrace::new();
+    trace.bishop_pair = bishop_pair_term(position, params);
+    for (square, piece) in position
+        .pieces(PieceColor::White)
+        .chain(position.pieces(PieceColor::Black))
+    {
+        trace.set(&square, square_terms(position, params, &square, &piece));
+    }
+    trace
+}
+
+fn better_evaluation_trace(position: &Position, params: &EvalParams) -> EvalTrace {
+    let mut trace = piece_terms(position, params);
+    // the side to move is the one that has been mated
+    if position.is_checkmate() {
+        trace.checkmate = -params.checkmate_bonus;
+    }
+    trace
+}
+
+// White's material minus black's, kept up to date move by move during the
+// search rather than counted again at every leaf. Positions are copied on
+// every move, so taking a move back is just going back to the parent's.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+struct MaterialBalance(isize);
+
+impl MaterialBalance {
+    fn of(position: &Position) -> MaterialBalance {
+        let params = EvalParams::default();
+        MaterialBalance(
+            position
+                .pieces(PieceColor::White)
+                .chain(position.pieces(PieceColor::Black))
+                .map(|(_, piece)| match piece.color {
+                    PieceColor::White => params.piece_value(&piece.kind),
+                    PieceColor::Black => -params.piece_value(&piece.kind),
+                })
+                .sum(),
+        )
+    }
+
+    fn after_move(self, position: &Position, chess_move: &ChessMove) -> MaterialBalance {
+        let params = EvalParams::default();
+        let captured = match chess_move {
+            ChessMove::EnPassant(..) => Some(PieceKind::Pawn),
+            _ => {
+                let (_, destination) = chess_move.origin_and_destination(&position.to_move);
+                position.piece_at(&destination).map(|piece| piece.kind)
+            }
+        };
+        let mut gained = captured.map_or(0, |kind| params.piece_value(&kind));
+        if let ChessMove::Promotion(_, promoted_to) = chess_move {
+            gained += params.piece_value(promoted_to) - params.pawn_value;
+        }
+        match position.to_move {
+            PieceColor::White => MaterialBalance(self.0 + gained),
+            PieceColor::Black => MaterialBalance(self.0 - gained),
+        }
+    }
+
+    fn relative_to(self, color: &PieceColor) -> isize {
+        match color {
+            PieceColor::White => self.0,
+            PieceColor::Black => -self.0,
+        }
+    }
+}
+
+// What the search keeps up to date move by move, handed to the evaluation
+// at the leaves and used for the transposition table, so that none of it
+// is worked out again from the whole board at every node. Evaluations
+// given to `Planner::with_evaluation` can read the material and the
+// default piece-square tables' score off it.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Accumulated {
+    material: MaterialBalance,
+    // white's piece-square bonuses minus black's, in each half of the game
+    middlegame: isize,
+    endgame: isize,
+    // the piece weights `PieceSquareTables::phase` caps
+    phase: isize,
+    key: u64,
+}
+
+impl Accumulated {
+    pub fn of(position: &Position) -> Accumulated {
+        let mut accumulated = Accumulated {
+            material: MaterialBalance::of(position),
+            middlegame: 0,
+            endgame: 0,
+            phase: 0,
+            key: position.zobrist_key(),
+        };
+        for (square, piece) in position
+            .pieces(PieceColor::White)
+            .chain(position.pieces(PieceColor::Black))
+        {
+            accumulated.place(&piece, &square, 1);
+        }
+        accumulated
+    }
+
+    // `after` being the position `chess_move` leads to. Only the squares
+    // whose piece changed are looked at, which covers castling rooks and
+    // pawns taken en passant along with everything else.
+    pub fn after_move(
+        self,
+        position: &Position,
+        chess_move: &ChessMove,
+        after: &Position,
+    ) -> Accumulated {
+        let mut accumulated = Accumulated {
+            material: self.material.after_move(position, chess_move),
+            key: position.zobrist_key_after(chess_move, after, self.key),
+            ..self
+        };
+        let (before_boards, after_boards) = (position.bitboards(), after.bitboards());
+        let changed = [PieceColor::White, PieceColor::Black]
+            .iter()
+            .map(|color| before_boards.color(color) ^ after_boards.color(color))
+            .chain(PieceKind::all().map(|kind| before_boards.kind(kind) ^ after_boards.kind(kind)))
+            .fold(Bitboard::EMPTY, |changed, squares| changed | squares);
+        for square in changed.squares() {
+            if let Some(piece) = position.piece_at(&square) {
+                accumulated.place(&piece, &square, -1);
+            }
+            if let Some(piece) = after.piece_at(&square) {
+                accumulated.place(&piece, &square, 1);
+            }
+        }
+        accumulated
+    }
+
+    // Adds the piece's piece-square bonuses and phase weight, or takes them
+    // away when `sign` is -1.
+    fn place(&mut self, piece: &Piece, square: &Coords, sign: isize) {
+        let (middlegame, endgame) = DEFAULT_TABLES.white_relative(piece, square);
+        self.middlegame += sign * middlegame;
+        self.endgame += sign * endgame;
+        self.phase += sign * phase_weight(&piece.kind);
+    }
+
+    // `color`'s material minus the other side's.
+    pub fn material(&self, color: &PieceColor) -> isize {
+        self.material.relative_to(color)
+    }
+
+    // What `PieceSquareTables::default().evaluate` gives when `color` is to
+    // move.
+    pub fn piece_squares(&self, color: &PieceColor) -> isize {
+        let score = blend(self.middlegame, self.endgame, self.phase);
+        match color {
+            PieceColor::White => score,
+            PieceColor::Black => -score,
+        }
+    }
+
+    pub fn key(&self) -> u64 {
+        self.key
+    }
+}
+
+// How the search scores a quiet leaf for the side to move, there always
+// being a legal move to play.
+pub type LeafEvaluation = fn(position: &Position, accumulated: &Accumulated) -> isize;
+
+// `better_evaluation` with the material taken from what the search keeps,
+// so that only the terms depending on how the pieces stand are worked out.
+fn planner_leaf_evaluation(position: &Position, accumulated: &Accumulated) -> isize {
+    let params = EvalParams::default();
+    let placement: isize = position
+        .pieces(PieceColor::White)
+        .chain(position.pieces(PieceColor::Black))
+        .map(|(square, piece)| {
+            let terms = square_terms(position, &params, &square, &piece);
+            terms.total() - terms.material
+        })
+        .sum();
+    accumulated.material(&position.to_move) + placement + bishop_pair_term(position, &params)
+}
+
+// Deep enough that only the clock, the node count or a stop end the search.
+pub(crate) const MAX_SEARCH_DEPTH: usize = 64;
+
+// Scores beyond this are forced mates, shorter mates scoring higher.
+pub(crate) const MATE_SCORE: isize = 1_000_000_000;
+
+#[derive(Clone, Debug)]
+pub struct SearchLimits {
+    pub depth: usize,
+    // counted over the whole search, so results don't depend on hardware speed
+    pub nodes: Option<u64>,
+    pub time: Option<Duration>,
+    pub stop: Option<Arc<AtomicBool>>,
+    pub deadline: Option<SearchDeadline>,
+    // only these moves are searched at the root, the way MultiPV finds its
+    // later lines; a list without a legal move in it is ignored
+    pub root_moves: Option<Vec<ChessMove>>,
+}
+
+impl SearchLimits {
+    pub fn depth(depth: usize) -> SearchLimits {
+        SearchLimits {
+            depth,
+            nodes: None,
+            time: None,
+            stop: None,
+            deadline: None,
+            root_moves: None,
+        }
+    }
+
+    // Deepens for as long as `time` lasts.
+    pub fn movetime(time: Duration) -> SearchLimits {
+        SearchLimits::depth(MAX_SEARCH_DEPTH).time(time)
+    }
+
+    pub fn nodes(mut self, nodes: u64) -> SearchLimits {
+        self.nodes = Some(nodes);
+        self
+    }
+
+    pub fn time(mut self, time: Duration) -> SearchLimits {
+        self.time = Some(time);
+        self
+    }
+
+    pub fn stop_flag(mut self, stop: Arc<AtomicBool>) -> SearchLimits {
+        self.stop = Some(stop);
+        self
+    }
+
+    pub fn deadline(mut self, deadline: SearchDeadline) -> SearchLimits {
+        self.deadline = Some(deadline);
+        self
+    }
+
+    pub fn root_moves(mut self, moves: Vec<ChessMove>) -> SearchLimits {
+        self.root_moves = Some(moves);
+        self
+    }
+}
+
+// Shortcuts the Planner takes close to the leaves when the static evaluation
+// is so far below alpha that the moves left are unlikely to bring it back.
+// Margins are in centipawns per ply of depth left, and a depth of zero
+// turns the shortcut off.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct PruningParams {
+    // quiet moves that don't give check are skipped, once one move has been
+    // searched
+    pub futility_depth: usize,
+    pub futility_margin: isize,
+    // the node is only searched for captures, and only searched in full when
+    // they bring the score back above alpha. Off by default, as it misses
+    // the quiet mates a frontier node may still have.
+    pub razoring_depth: usize,
+    pub razoring_margin: isize,
+    // captures that lose material by static exchange are left out of the
+    // search for captures at the leaves
+    pub skip_losing_captures: bool,
+}
+
+impl Default for PruningParams {
+    fn default() -> PruningParams {
+        PruningParams {
+            futility_depth: 2,
+            futility_margin: 200,
+            razoring_depth: 0,
+            razoring_margin: 400,
+            skip_losing_captures: true,
+        }
+    }
+}
+
+impl PruningParams {
+    // Searches every move to its full depth, and every capture at the
+    // leaves.
+    pub fn none() -> PruningParams {
+        PruningParams {
+            futility_depth: 0,
+            razoring_depth: 0,
+            skip_losing_captures: false,
+            ..PruningParams::default()
+        }
+    }
+
+    // The search constants worth tuning, in a fixed order. Whether razoring
+    // is on is left as it is, as it misses quiet mates, and depths are kept
+    // from going below zero.
+    pub fn tunable_values(&self) -> Vec<isize> {
+        vec![
+            self.futility_depth as isize,
+            self.futility_margin,
+            self.razoring_margin,
+        ]
+    }
+
+    pub fn with_tunable_values(&self, values: &[isize]) -> PruningParams {
+        assert_eq!(values.len(), 3, "expected one value per tunable parameter");
+        PruningParams {
+            futility_depth: values[0].max(0) as usize,
+            futility_margin: values[1],
+            razoring_margin: values[2],
+            ..*self
+        }
+    }
+}
+
+// Scores past this bound are mates; they are stored in the transposition
+// table relative to the node so that they stay correct at any ply.
+pub(crate) const MATE_BOUND: isize = MATE_SCORE - 1000;
+
+fn score_to_table(score: isize, ply: isize) -> isize {
+    if score > MATE_BOUND {
+        score + ply
+    } else if score < -MATE_BOUND {
+        score - ply
+    } else {
+        score
+    }
+}
+
+fn score_from_table(score: isize, ply: isize) -> isize {
+    if score > MATE_BOUND {
+        score - ply
+    } else if score < -MATE_BOUND {
+        score + ply
+    } else {
+        score
+    }
+}
+
+const PLANNER_HASH_MEGABYTES: usize = 16;
+const PLANNER_MAX_HASH_MEGABYTES: usize = 1024;
+
+// Everything the Planner learns while searching that is still useful for the
+// next move of the same game.
+#[derive(Debug)]
+struct SearchMemory {
+    table: TranspositionTable,
+    // two quiet moves per ply that recently caused a beta cutoff
+    killers: Vec<[Option<ChessMove>; 2]>,
+    // indexed by origin * 64 + destination square
+    history: Vec<u32>,
+    // the principal variation still expected after the moves we predicted,
+    // along with the key of the position it starts from
+    expected_line: Option<(u64, Vec<ChessMove>)>,
+}
+
+impl SearchMemory {
+    fn new(table: TranspositionTable) -> SearchMemory {
+        SearchMemory {
+            table,
+            killers: Vec::new(),
+            history: vec![0; 64 * 64],
+            expected_line: None,
+        }
+    }
+
+    fn killers_at(&mut self, ply: isize) -> &mut [Option<ChessMove>; 2] {
+        let ply = ply as usize;
+        if self.killers.len() <= ply {
+            self.killers.resize(ply + 1, [None, None]);
+        }
+        &mut self.killers[ply]
+    }
+
+    fn record_cutoff(
+        &mut self,
+        position: &Position,
+        chess_move: &ChessMove,
+        depth: isize,
+        ply: isize,
+    ) {
+        if position.is_capture(chess_move) {
+            return;
+        }
+        let killers = self.killers_at(ply);
+        if killers[0].as_ref() != Some(chess_move) {
+            killers[1] = killers[0].take();
+            killers[0] = Some(chess_move.clone());
+        }
+        let index = history_index(position, chess_move);
+        self.history[index] = self.history[index].saturating_add((depth * depth) as u32);
+    }
+
+    // Best moves from the table, starting at `position`, for as long as they
+    // stay legal and don't loop.
+    fn principal_variation(&self, position: &Position, max_length: usize) -> Vec<ChessMove> {
+        let mut line = Vec::new();
+        let mut position = position.clone();
+        let mut seen = Vec::new();
+        while line.len() < max_length {
+            let key = position.zobrist_key();
+            if seen.contains(&key) {
+                break;
             }
+            seen.push(key);
+            let Some(chess_move) = self
+                .table
+                .probe(key)
+                .and_then(|entry| entry.best_move.clone())
+                .filter(|chess_move| position.is_move_legal(chess_move))
+            else {
+                break;
+            };
+            position = position.after_move(&chess_move);
+            line.push(chess_move);
+        }
+        line
+    }
+}
+
+fn history_index(position: &Position, chess_move: &ChessMove) -> usize {
+    let (origin, destination) = chess_move.origin_and_destination(&position.to_move);
+    origin.to_square_number() * 64 + destination.to_square_number()
+}
+
+// Best first: the move the table or the previous line suggests, then
+// captures of the most valuable pieces, killers, and quiet moves by history.
+// The sort is stable, so equal moves keep their generation order.
+fn order_moves(
+    position: &Position,
+    moves: &mut MoveList,
+    first: Option<&ChessMove>,
+    ply: isize,
+    memory: &mut SearchMemory,
+) {
+    let killers = memory.killers_at(ply).clone();
+    moves.sort_by_cached_key(|chess_move| {
+        let priority = if Some(chess_move) == first {
+            4_000_000
+        } else if position.is_capture(chess_move) {
+            let (_, destination) = chess_move.origin_and_destination(&position.to_move);
+            let victim = position
+                .piece_at(&destination)
+                .map_or(PieceKind::Pawn, |piece| piece.kind);
+            3_000_000 + EvalParams::default().piece_value(&victim)
+        } else if killers.contains(&Some(chess_move.clone())) {
+            2_000_000
         } else {
-            0
+            memory.history[history_index(position, chess_move)].min(1_000_000) as isize
         };
-        ((value + (controlled_squares * control_value)) + attacked_factor) * own_color_factor
+        -priority
+    });
+}
+
+struct SearchContext<'a> {
+    limits: &'a SearchLimits,
+    evaluate: LeafEvaluation,
+    pruning: PruningParams,
+    started: Instant,
+    nodes: u64,
+    aborted: bool,
+    memory: &'a mut SearchMemory,
+}
+
+impl SearchContext<'_> {
+    fn should_abort(&mut self) -> bool {
+        if !self.aborted {
+            self.aborted = self.limits.nodes.is_some_and(|nodes| self.nodes > nodes)
+                || self
+                    .limits
+                    .stop
+                    .as_ref()
+                    .is_some_and(|stop| stop.load(Ordering::Relaxed))
+                || self
+                    .limits
+                    .time
+                    .is_some_and(|time| self.started.elapsed() >= time)
+                || self
+                    .limits
+                    .deadline
+                    .as_ref()
+                    .is_some_and(|deadline| deadline.hard_passed());
+        }
+        self.aborted
+    }
+}
+
+// Follows captures and promotions from a leaf until the position is quiet,
+// so that it isn't scored halfway through an exchange. The side to move may
+// stand on its evaluation instead of capturing, unless it is in check, when
+// it has to try every move. Captures are tried best exchange first, and
+// those that lose material by static exchange, left out.
+fn quiescence(
+    position: &Position,
+    accumulated: Accumulated,
+    ply: isize,
+    mut alpha: isize,
+    beta: isize,
+    moves: &MoveList,
+    context: &mut SearchContext,
+) -> Option<isize> {
+    let in_check = position.is_in_check(&position.to_move);
+    let mut best = isize::MIN;
+    if !in_check {
+        best = (context.evaluate)(position, &accumulated);
+        if best >= beta {
+            return Some(best);
+        }
+        alpha = alpha.max(best);
     }
-    let score_from_all_squares = all_squares()
+    let mut candidates: Vec<(isize, ChessMove)> = moves
         .iter()
-        .map(|square| match piece_at(&position.board, square) {
-            None => 0_isize,
-            Some(piece) => evaluate_piece(
-                &piece,
-                position.is_attacked_by(&piece.color.opposite(), square),
-                &position.to_move,
-                position
-                    .color_to_move(piece.color)
-                    .legal_moves_from_origin(square)
-                    .len()
-                    .try_into()
-                    .unwrap(),
-            ),
+        .filter(|chess_move| {
+            in_check
+                || position.is_capture(chess_move)
+                || matches!(chess_move, ChessMove::Promotion(..))
         })
-        .reduce(|acc, e| acc + e)
-        .expect("all squares is never 0 length");
-    let score_from_checkmate = if position.is_checkmate() { 10000000 } else { 0 };
-    score_from_all_squares + score_from_checkmate
+        .map(|chess_move| (static_exchange(position, &chess_move), chess_move))
+        .filter(|(exchange, _)| in_check || *exchange >= 0 || !context.pruning.skip_losing_captures)
+        .collect();
+    candidates.sort_by_key(|(exchange, _)| -exchange);
+    for (_, chess_move) in candidates {
+        context.nodes += 1;
+        if context.should_abort() {
+            return None;
+        }
+        let after = position.after_move(&chess_move);
+        let replies = after.legal_move_list();
+        let eval = if replies.is_empty() {
+            if after.is_in_check(&after.to_move) {
+                MATE_SCORE - ply - 1
+            } else {
+                0
+            }
+        } else {
+            -quiescence(
+                &after,
+                accumulated.after_move(position, &chess_move, &after),
+                ply + 1,
+                -beta,
+                -alpha,
+                &replies,
+                context,
+            )?
+        };
+        best = best.max(eval);
+        if eval >= beta {
+            break;
+        }
+        alpha = alpha.max(eval);
+    }
+    Some(best)
 }
 
+// Returns None once the search has been told to stop, since the score of a
+// partly searched subtree can't be trusted.
 fn alpha_beta_negamax(
     position: &Position,
+    accumulated: Accumulated,
     depth: isize,
-    evaluate: fn(position: &Position) -> isize,
+    ply: isize,
     mut alpha: isize,
     beta: isize,
-) -> isize {
-    if depth == 0 || position.is_checkmate() || position.is_stalemate() {
-        return evaluate(position);
+    context: &mut SearchContext,
+) -> Option<isize> {
+    context.nodes += 1;
+    if context.should_abort() {
+        return None;
     }
+    let mut moves = position.legal_move_list();
+    if moves.is_empty() {
+        return Some(if position.is_in_check(&position.to_move) {
+            -(MATE_SCORE - ply)
+        } else {
+            0
+        });
+    }
+    if depth == 0 {
+        return quiescence(position, accumulated, ply, alpha, beta, &moves, context);
+    }
+    let key = accumulated.key;
+    let mut table_move = None;
+    if let Some(entry) = context.memory.table.probe(key) {
+        let score = score_from_table(entry.score, ply);
+        if entry.depth >= depth
+            && match entry.bound {
+                Bound::Exact => true,
+                Bound::Lower => score >= beta,
+                Bound::Upper => score <= alpha,
+            }
+        {
+            return Some(score);
+        }
+        table_move = entry.best_move.clone();
+    }
+    let pruning = context.pruning;
+    let prunable_depth = pruning.futility_depth.max(pruning.razoring_depth) as isize;
+    // only an alpha between the mates can be compared with a margin
+    let static_eval = (depth <= prunable_depth
+        && alpha.abs() < MATE_BOUND
+        && !position.is_in_check(&position.to_move))
+    .then(|| (context.evaluate)(position, &accumulated));
+    if let Some(static_eval) = static_eval {
+        if depth <= pruning.razoring_depth as isize
+            && static_eval + pruning.razoring_margin * depth <= alpha
+        {
+            let score = quiescence(position, accumulated, ply, alpha, beta, &moves, context)?;
+            if score <= alpha {
+                return Some(score);
+            }
+        }
+    }
+    let futile = static_eval.is_some_and(|static_eval| {
+        depth <= pruning.futility_depth as isize
+            && static_eval + pruning.futility_margin * depth <= alpha
+    });
+    let original_alpha = alpha;
     let mut best = isize::MIN;
-    for chess_move in position.all_legal_moves() {
+    let mut best_move = None;
+    order_moves(
+        position,
+        &mut moves,
+        table_move.as_ref(),
+        ply,
+        context.memory,
+    );
+    for chess_move in &moves {
+        let after = position.after_move(&chess_move);
+        if futile
+            && best_move.is_some()
+            && !position.is_capture(&chess_move)
+            && !matches!(chess_move, ChessMove::Promotion(..))
+            && !after.is_in_check(&after.to_move)
+        {
+            continue;
+        }
         let eval = -alpha_beta_negamax(
-            &position.after_move(&chess_move),
+            &after,
+            accumulated.after_move(position, &chess_move, &after),
             depth - 1,
-            evaluate,
+            ply + 1,
             -beta,
             -alpha,
-        );
+            context,
+        )?;
         if eval > best {
             best = eval;
+            best_move = Some(chess_move.clone());
             if eval > alpha {
                 alpha = eval;
             }
             if eval >= beta {
-                return best;
+                context
+                    .memory
+                    .record_cutoff(position, &chess_move, depth, ply);
+                break;
             }
         }
     }
-    best
+    let bound = if best >= beta {
+        Bound::Lower
+    } else if best > original_alpha {
+        Bound::Exact
+    } else {
+        Bound::Upper
+    };
+    context.memory.table.store(TableEntry {
+        key,
+        depth,
+        score: score_to_table(best, ply),
+        bound,
+        best_move,
+    });
+    Some(best)
 }
 
+const PLANNER_DEPTH: usize = 3;
 
-fn planner_evaluation(position: &Position) -> isize {
-    -alpha_beta_negamax(
+fn planner_evaluation(position: &Position, nodes: &mut u64) -> isize {
+    let limits = SearchLimits::depth(PLANNER_DEPTH - 1);
+    let mut memory = SearchMemory::new(TranspositionTable::new(1 << 10));
+    let mut context = SearchContext {
+        limits: &limits,
+        evaluate: planner_leaf_evaluation,
+        pruning: PruningParams::default(),
+        started: Instant::now(),
+        nodes: 0,
+        aborted: false,
+        memory: &mut memory,
+    };
+    let score = alpha_beta_negamax(
         position,
-        2,
-        better_evaluation,
+        Accumulated::of(position),
+        PLANNER_DEPTH as isize - 1,
+        1,
         isize::MIN + 1,
         isize::MAX - 1,
+        &mut context,
     )
+    .expect("searches without limits run to completion");
+    *nodes += context.nodes;
+    -score
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct SearchResult {
+    pub best_move: ChessMove,
+    pub score: isize,
+    // the line the search expects, starting with the best move
+    pub pv: Vec<ChessMove>,
+    pub depth: usize,
+    pub nodes: u64,
+}
+
+// Keeps its transposition table, killer and history tables and the expected
+// line between moves, so call `new_game` before reusing it for another game.
+#[derive(Debug)]
+pub struct Planner {
+    memory: Mutex<SearchMemory>,
+    depth: usize,
+    node_limit: Option<u64>,
+    pruning: PruningParams,
+    evaluate: LeafEvaluation,
+}
+
+impl Default for Planner {
+    fn default() -> Planner {
+        Planner::new()
+    }
+}
+
+impl Planner {
+    pub fn new() -> Planner {
+        Planner::with_hash_size(PLANNER_HASH_MEGABYTES)
+    }
+
+    pub fn with_hash_size(megabytes: usize) -> Planner {
+        Planner::with_table_config(&TableConfig {
+            megabytes,
+            ..TableConfig::default()
+        })
+    }
+
+    pub fn with_table_config(config: &TableConfig) -> Planner {
+        Planner {
+            memory: Mutex::new(SearchMemory::new(TranspositionTable::with_config(config))),
+            depth: PLANNER_DEPTH,
+            node_limit: None,
+            pruning: PruningParams::default(),
+            evaluate: planner_leaf_evaluation,
+        }
+    }
+
+    pub fn with_depth(mut self, depth: usize) -> Planner {
+        self.depth = depth;
+        self
+    }
+
+    // Caps every move at this many nodes. The iteration that hits the cap is
+    // dropped, so a given cap always gives the same move.
+    pub fn with_node_limit(mut self, nodes: u64) -> Planner {
+        self.node_limit = Some(nodes);
+        self
+    }
+
+    // `PruningParams::none()` gives the full-width search to measure the
+    // pruning against.
+    pub fn with_pruning(mut self, pruning: PruningParams) -> Planner {
+        self.pruning = pruning;
+        self
+    }
+
+    // Scores the leaves with `evaluate` instead of `BetterEvaluationPlayer`'s
+    // evaluation.
+    pub fn with_evaluation(mut self, evaluate: LeafEvaluation) -> Planner {
+        self.evaluate = evaluate;
+        self
+    }
+
+    pub fn limits(&self) -> SearchLimits {
+        let limits = SearchLimits::depth(self.depth);
+        match self.node_limit {
+            Some(nodes) => limits.nodes(nodes),
+            None => limits,
+        }
+    }
+
+    // Replaces the transposition table, so everything it held is lost. The
+    // buckets, replacement policy and aging stay as they were.
+    pub fn set_hash_size(&self, megabytes: usize) {
+        let mut memory = self.memory();
+        memory.table = TranspositionTable::with_config(&TableConfig {
+            megabytes,
+            bucket_size: memory.table.bucket_size(),
+            replacement: memory.table.replacement(),
+            aging: memory.table.aging(),
+        });
+    }
+
+    pub fn hash_entries(&self) -> usize {
+        self.memory().table.capacity()
+    }
+
+    pub fn table_stats(&self) -> TableStats {
+        self.memory().table.stats()
+    }
+
+    pub fn new_game(&self) {
+        let mut memory = self.memory();
+        memory.table.clear();
+        memory.killers.clear();
+        memory.history.iter_mut().for_each(|score| *score = 0);
+        memory.expected_line = None;
+    }
+
+    // The search is limited by depth and nodes only, so a fresh Planner always
+    // gives the same result and node count for a given position.
+    pub fn search(&self, position: &Position) -> SearchResult {
+        self.search_with_limits(position, &self.limits())
+    }
+
+    // Searches depth 1, 2, 3 and on until `time` is used up, keeping any node
+    // limit the Planner was given.
+    pub fn search_for(&self, position: &Position, time: Duration) -> SearchResult {
+        let limits = SearchLimits::movetime(time);
+        let limits = match self.node_limit {
+            Some(nodes) => limits.nodes(nodes),
+            None => limits,
+        };
+        self.search_with_limits(position, &limits)
+    }
+
+    // Deepens until the share of `clock` the time manager gives this move
+    // is used up.
+    pub fn search_on_clock(&self, position: &Position, clock: &ClockState) -> SearchResult {
+        let limits = self.clock_limits(position, clock, Instant::now());
+        self.search_with_limits(position, &limits)
+    }
+
+    // The limits of a search started at `started` that keeps to the side to
+    // move's share of `clock`.
+    fn clock_limits(
+        &self,
+        position: &Position,
+        clock: &ClockState,
+        started: Instant,
+    ) -> SearchLimits {
+        let deadline = SearchDeadline::new();
+        deadline.set(
+            started,
+            &TimeManager::default().allocate(clock, &position.to_move),
+        );
+        let limits = SearchLimits::depth(MAX_SEARCH_DEPTH).deadline(deadline);
+        match self.node_limit {
+            Some(nodes) => limits.nodes(nodes),
+            None => limits,
+        }
+    }
+
+    // Deepens one ply at a time. When the search is stopped or runs out of
+    // time partway through an iteration, that iteration is thrown away and the
+    // best move of the last completed one is returned.
+    pub fn search_with_limits(&self, position: &Position, limits: &SearchLimits) -> SearchResult {
+        let unrestricted;
+        let limits = match &limits.root_moves {
+            Some(root_moves)
+                if !position
+                    .all_legal_moves()
+                    .iter()
+                    .any(|chess_move| root_moves.contains(chess_move)) =>
+            {
+                unrestricted = SearchLimits {
+                    root_moves: None,
+                    ..limits.clone()
+                };
+                &unrestricted
+            }
+            _ => limits,
+        };
+        let mut memory = self.memory();
+        memory.table.new_search();
+        // a search kept to some of the moves isn't the line the game follows,
+        // so it leaves the expected line alone
+        let restricted = limits.root_moves.is_some();
+        if !restricted {
+            if let Some(result) = Planner::elementary_mate(position) {
+                memory.expected_line = None;
+                return result;
+            }
+        }
+        let mut moves = position.legal_move_list();
+        if let Some(root_moves) = &limits.root_moves {
+            moves.retain(|chess_move| root_moves.contains(chess_move));
+        }
+        let key = position.zobrist_key();
+        let mut first = if restricted {
+            None
+        } else {
+            memory
+                .expected_line
+                .take()
+                .filter(|(expected_key, _)| *expected_key == key)
+                .and_then(|(_, line)| line.first().cloned())
+        };
+        let mut context = SearchContext {
+            limits,
+            evaluate: self.evaluate,
+            pruning: self.pruning,
+            started: Instant::now(),
+            nodes: 0,
+            aborted: false,
+            memory: &mut memory,
+        };
+        let mut completed = SearchResult {
+            best_move: moves
+                .first()
+                .expect("searched position should have a legal move"),
+            score: 0,
+            pv: Vec::new(),
+            depth: 0,
+            nodes: 0,
+        };
+        for depth in 1..=limits.depth.max(1) {
+            let mut ordered = moves.clone();
+            order_moves(position, &mut ordered, first.as_ref(), 0, context.memory);
+            let Some((best_move, score)) =
+                Planner::search_root(position, &ordered, depth, &mut context)
+            else {
+                break;
+            };
+            first = Some(best_move.clone());
+            completed = SearchResult {
+                best_move,
+                score,
+                pv: Vec::new(),
+                depth,
+                nodes: context.nodes,
+            };
+            if limits
+                .deadline
+                .as_ref()
+                .is_some_and(|deadline| deadline.soft_passed())
+            {
+                break;
+            }
+        }
+        completed.nodes = context.nodes;
+        let mut pv = vec![completed.best_move.clone()];
+        pv.extend(memory.principal_variation(
+            &position.after_move(&completed.best_move),
+            completed.depth.saturating_sub(1),
+        ));
+        completed.pv = pv;
+        if !restricted {
+            memory.expected_line = Planner::expected_line(position, &completed.pv);
+        }
+        completed
+    }
+
+    // KQvK, KRvK and KBNvK are played from the mating tables rather than
+    // searched, the distance to mate standing in for the depth.
+    fn elementary_mate(position: &Position) -> Option<SearchResult> {
+        let best_move = elementary_mate_move(position)?;
+        let distance = elementary_mate_distance(position)?;
+        let score = MATE_SCORE - distance as isize;
+        Some(SearchResult {
+            pv: vec![best_move.clone()],
+            best_move,
+            score: if position.to_move == position.stronger_side() {
+                score
+            } else {
+                -score
+            },
+            depth: distance,
+            nodes: 0,
+        })
+    }
+
+    // What is left of the principal variation once our move and the reply
+    // we expect have been played.
+    fn expected_line(position: &Position, line: &[ChessMove]) -> Option<(u64, Vec<ChessMove>)> {
+        if line.len() < 3 {
+            return None;
+        }
+        let after_reply = position.after_move(&line[0]).after_move(&line[1]);
+        Some((after_reply.zobrist_key(), line[2..].to_vec()))
+    }
+
+    fn search_root(
+        position: &Position,
+        moves: &MoveList,
+        depth: usize,
+        context: &mut SearchContext,
+    ) -> Option<(ChessMove, isize)> {
+        let mut best: Option<(ChessMove, isize)> = None;
+        let accumulated = Accumulated::of(position);
+        for chess_move in moves {
+            let alpha = best.as_ref().map_or(isize::MIN + 1, |(_, score)| *score);
+            let after = position.after_move(&chess_move);
+            let score = -alpha_beta_negamax(
+                &after,
+                accumulated.after_move(position, &chess_move, &after),
+                depth as isize - 1,
+                1,
+                isize::MIN + 1,
+                -alpha,
+                context,
+            )?;
+            if best
+                .as_ref()
+                .is_none_or(|(_, best_score)| score > *best_score)
+            {
+                best = Some((chess_move, score));
+            }
+        }
+        // a search kept to some of the moves may have missed the best one
+        if let Some((best_move, score)) = best
+            .as_ref()
+            .filter(|_| context.limits.root_moves.is_none())
+        {
+            context.memory.table.store(TableEntry {
+                key: position.zobrist_key(),
+                depth: depth as isize,
+                score: *score,
+                bound: Bound::Exact,
+                best_move: Some(best_move.clone()),
+            });
+        }
+        best
+    }
+
+    fn memory(&self) -> MutexGuard<'_, SearchMemory> {
+        self.memory
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
 }
-pub struct Planner;
 
 impl Player for Planner {
     fn evalutate(&self, position: &Position) -> isize {
-        planner_evaluation(position)
+        planner_evaluation(position, &mut 0)
     }
     fn offer_move(&self, position: &Position) -> ChessMove {
-        first_move_with_max_evaluation(moves_with_evaluation(position, planner_evaluation))
+        self.search(position).best_move
+    }
+    fn offer_move_timed(&self, position: &Position, clock: &ClockState) -> ChessMove {
+        self.search_on_clock(position, clock).best_move
+    }
+    fn analyze(&self, position: &Position) -> SearchResult {
+        self.search(position)
+    }
+    fn new_game(&self) {
+        Planner::new_game(self);
+    }
+    fn options(&self) -> Vec<EngineOption> {
+        vec![
+            EngineOption::spin(
+                "Hash",
+                PLANNER_HASH_MEGABYTES as i64,
+                1,
+                PLANNER_MAX_HASH_MEGABYTES as i64,
+            ),
+            // the search runs on the calling thread only
+            EngineOption::spin("Threads", 1, 1, 1),
+        ]
+    }
+    fn set_option(&self, name: &str, value: &OptionValue) {
+        if let ("Hash", OptionValue::Spin(megabytes)) = (name, value) {
+            self.set_hash_size(*megabytes as usize);
+        }
     }
 }
 
@@ -316,6 +1665,7 @@ impl Display for Planner {
 mod tests {
     use crate::Coords;
     use crate::Move;
+    use crate::ReplacementPolicy;
 
     use super::*;
     #[test]
@@ -323,7 +1673,7 @@ mod tests {
         let position =
             Position::from_fen("rnb1kbnr/pppppppp/8/1N6/8/8/PPPPPPPP/R1BQKBNR w KQkq - 0 1");
         assert_eq!(
-            BetterEvaluationPlayer {}.offer_move(&position),
+            BetterEvaluationPlayer::default().offer_move(&position),
             ChessMove::RegularMove(Move {
                 origin: Coords { x: 1, y: 3 },
                 destination: Coords { x: 2, y: 1 }
@@ -331,12 +1681,119 @@ mod tests {
         );
     }
 
+    #[test]
+    fn trace_adds_up_to_the_evaluation() {
+        let player = BetterEvaluationPlayer::default();
+        let position =
+            Position::from_fen("rnb1kbnr/1ppppppp/p7/1N6/8/8/PPPPPPPP/R1BQKBNR b KQkq - 0 1");
+        let trace = player.trace(&position);
+        assert_eq!(
+            trace.total(),
+            better_evaluation_with(&position, &player.params)
+        );
+        // the knight on b5 is black's to take, so it counts for nothing
+        let knight = trace.at(&Coords::from_algebraic("b5"));
+        assert_eq!(knight.material, -player.params.knight_value);
+        assert_eq!(knight.attacked, player.params.knight_value);
+        assert_eq!(
+            trace.at(&Coords::from_algebraic("e4")),
+            SquareTerms::default()
+        );
+    }
+
+    #[test]
+    fn positional_terms_can_be_switched_off() {
+        // white has both bishops and a rook on the open e-file, black a rook
+        // on the semi-open c-file and one behind its own pawn
+        let position = Position::from_fen("r1r1k3/p7/8/8/8/8/2P5/2B1RBK1 w - - 0 1");
+        let player = BetterEvaluationPlayer::default();
+        let trace = player.trace(&position);
+        assert_eq!(trace.bishop_pair, player.params.bishop_pair_bonus);
+        assert_eq!(
+            trace.at(&Coords::from_algebraic("e1")).file,
+            player.params.rook_open_file_bonus
+        );
+        assert_eq!(
+            trace.at(&Coords::from_algebraic("c8")).file,
+            -player.params.rook_semi_open_file_bonus
+        );
+        assert_eq!(trace.at(&Coords::from_algebraic("a8")).file, 0);
+        let knight = Position::from_fen("4k3/8/8/8/4N3/8/8/4K3 w - - 0 1");
+        let e4 = Coords::from_algebraic("e4");
+        assert_eq!(
+            player.trace(&knight).at(&e4).control,
+            8 * player.params.control_value * 4
+        );
+
+        let plain = BetterEvaluationPlayer::with_params(EvalParams {
+            terms: EvalTerms::none(),
+            ..EvalParams::default()
+        });
+        let trace = plain.trace(&position);
+        assert_eq!(trace.bishop_pair, 0);
+        assert_eq!(trace.at(&Coords::from_algebraic("e1")).file, 0);
+        assert_eq!(
+            plain.trace(&knight).at(&e4).control,
+            8 * plain.params.control_value
+        );
+    }
+
+    #[test]
+    fn material_balance_follows_captures_and_promotions() {
+        let mut position = Position::from_fen("4k3/1P6/8/3pP3/8/8/8/4K3 w - d6 0 1");
+        let mut material = MaterialBalance::of(&position);
+        for text in ["exd6", "Kd7", "b8=N+", "Kxd6"] {
+            let chess_move = ChessMove::from_san(text, &position).unwrap();
+            material = material.after_move(&position, &chess_move);
+            position = position.after_move(&chess_move);
+            assert_eq!(material, MaterialBalance::of(&position), "after {}", text);
+        }
+        assert_eq!(
+            planner_leaf_evaluation(&position, &Accumulated::of(&position)),
+            better_evaluation_with(&position, &EvalParams::default())
+        );
+    }
+
+    #[test]
+    fn accumulated_piece_squares_follow_every_kind_of_move() {
+        let tables = PieceSquareTables::default();
+        let mut position = Position::from_fen("r3k2r/1P6/8/3pP3/8/8/8/R3K2R w KQkq d6 0 1");
+        let mut accumulated = Accumulated::of(&position);
+        for text in ["exd6", "O-O", "bxa8=Q", "Rxa8", "O-O-O"] {
+            let chess_move = ChessMove::from_san(text, &position).unwrap();
+            let after = position.after_move(&chess_move);
+            accumulated = accumulated.after_move(&position, &chess_move, &after);
+            position = after;
+            assert_eq!(accumulated, Accumulated::of(&position), "after {}", text);
+            assert_eq!(
+                accumulated.piece_squares(&position.to_move),
+                tables.evaluate(&position),
+                "after {}",
+                text
+            );
+        }
+        assert_eq!(accumulated.key(), position.zobrist_key());
+    }
+
+    #[test]
+    fn planners_can_score_leaves_from_the_accumulated_sums() {
+        fn lookup(position: &Position, accumulated: &Accumulated) -> isize {
+            accumulated.material(&position.to_move) + accumulated.piece_squares(&position.to_move)
+        }
+        let position = Position::from_fen("4k3/8/8/3q4/8/8/3R4/3K4 w - - 0 1");
+        let planner = Planner::new().with_depth(2).with_evaluation(lookup);
+        assert_eq!(
+            planner.offer_move(&position),
+            ChessMove::from_san("Rxd5", &position).unwrap()
+        );
+    }
+
     #[test]
     fn better_evaluation_doesnt_sac_knight_after_fork() {
         let position =
             Position::from_fen("Nnbk1bnr/pp1p1ppp/8/4p3/8/8/PPPPPPPP/R1BQKBNR w KQka - 0 1");
         assert_ne!(
-            BetterEvaluationPlayer {}.offer_move(&position),
+            BetterEvaluationPlayer::default().offer_move(&position),
             ChessMove::RegularMove(Move {
                 origin: Coords { x: 0, y: 0 },
                 destination: Coords { x: 2, y: 1 }
@@ -349,7 +1806,7 @@ mod tests {
         let position =
             Position::from_fen("Nnbk1bnr/pp1p1ppp/8/4p3/8/8/PPPPPPPP/R1BQKBNR w KQka - 0 1");
         assert_ne!(
-            Planner {}.offer_move(&position),
+            Planner::new().offer_move(&position),
             ChessMove::RegularMove(Move {
                 origin: Coords { x: 0, y: 0 },
                 destination: Coords { x: 2, y: 1 }
@@ -357,12 +1814,328 @@ mod tests {
         );
     }
 
+    #[test]
+    fn planner_search_is_deterministic() {
+        let position = Position::from_fen("4k3/3p4/8/8/8/8/3P4/R3K3 w Q - 0 1");
+        let first = Planner::new().search(&position);
+        let second = Planner::new().search(&position);
+        assert_eq!(first, second);
+        assert!(first.nodes > 0);
+        assert_eq!(first.depth, 3);
+    }
+
+    #[test]
+    fn stopped_search_still_returns_a_legal_move() {
+        let position = Position::initial();
+        let stop = Arc::new(AtomicBool::new(true));
+        let result =
+            Planner::new().search_with_limits(&position, &SearchLimits::depth(4).stop_flag(stop));
+        assert_eq!(result.depth, 0);
+        assert!(position.is_move_legal(&result.best_move));
+    }
+
+    #[test]
+    fn timed_out_search_keeps_last_completed_iteration() {
+        let position = Position::initial();
+        let result = Planner::new().search_with_limits(
+            &position,
+            &SearchLimits::depth(50).time(Duration::from_millis(50)),
+        );
+        assert!(result.depth < 50);
+        let completed =
+            Planner::new().search_with_limits(&position, &SearchLimits::depth(result.depth));
+        assert_eq!(result.best_move, completed.best_move);
+        assert_eq!(result.score, completed.score);
+    }
+
+    #[test]
+    fn movetime_search_deepens_until_the_time_is_up() {
+        let position = Position::initial();
+        let started = Instant::now();
+        let result = Planner::new().search_for(&position, Duration::from_millis(100));
+        assert!(started.elapsed() < Duration::from_secs(5));
+        assert!((1..MAX_SEARCH_DEPTH).contains(&result.depth));
+        assert!(position.is_move_legal(&result.best_move));
+    }
+
+    #[test]
+    fn greedy_player_wins_material_without_giving_any_back() {
+        let player = GreedyMaterialPlayer;
+        let hanging_queen = Position::from_fen("4k3/8/8/3q4/8/8/3R4/4K3 w - - 0 1");
+        assert_eq!(
+            player
+                .offer_move(&hanging_queen)
+                .to_uci_long(&hanging_queen),
+            "d2d5"
+        );
+        // the pawn is defended, so taking it would lose the queen
+        let defended_pawn = Position::from_fen("4k3/2p5/3p4/8/8/8/3Q4/4K3 w - - 0 1");
+        assert_ne!(
+            player
+                .offer_move(&defended_pawn)
+                .to_uci_long(&defended_pawn),
+            "d2d6"
+        );
+        assert_eq!(
+            player.evalutate(&defended_pawn),
+            -player.evalutate(&defended_pawn.color_to_move(PieceColor::Black))
+        );
+    }
+
+    #[test]
+    fn clocked_search_keeps_to_its_share_of_the_clock() {
+        let position = Position::initial();
+        let clock = ClockState {
+            white_remaining: Duration::from_millis(500),
+            black_remaining: Duration::from_secs(600),
+            ..ClockState::default()
+        };
+        let planner = Planner::new();
+        let started = Instant::now();
+        let limits = planner.clock_limits(&position, &clock, started);
+        let deadline = limits.deadline.expect("clocked searches have a deadline");
+        let budget = TimeManager::default().allocate(&clock, &PieceColor::White);
+        assert_eq!(deadline.soft_deadline(), Some(started + budget.optimum));
+        assert_eq!(deadline.hard_deadline(), Some(started + budget.maximum));
+        assert!(budget.maximum < Duration::from_millis(500));
+        assert!(position.is_move_legal(&planner.offer_move_timed(&position, &clock)));
+    }
+
+    #[test]
+    fn search_result_carries_a_playable_line() {
+        let position = Position::from_fen(
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+        );
+        let result = Planner::new().with_depth(3).search(&position);
+        assert_eq!(result.pv.first(), Some(&result.best_move));
+        assert!(result.pv.len() > 1 && result.pv.len() <= result.depth);
+        result.pv.iter().fold(position, |position, chess_move| {
+            assert!(position.is_move_legal(chess_move), "{:?}", chess_move);
+            position.after_move(chess_move)
+        });
+    }
+
+    #[cfg(feature = "rng")]
+    #[test]
+    fn seeded_random_players_replay_their_games() {
+        let play = |seed: u64| {
+            let (white, black) = (
+                RandomPlayer::seeded(seed),
+                RandomCapturePrioPlayer::seeded(seed),
+            );
+            let mut position = Position::initial();
+            let mut moves = Vec::new();
+            for ply in 0..40 {
+                if !position.has_legal_move() {
+                    break;
+                }
+                let player: &dyn Player = if ply % 2 == 0 { &white } else { &black };
+                let chess_move = player.offer_move(&position);
+                position = position.after_move(&chess_move);
+                moves.push(chess_move);
+            }
+            moves
+        };
+        assert_eq!(play(7), play(7));
+        assert_ne!(play(7), play(8));
+    }
+
+    #[test]
+    fn piece_square_player_develops_towards_the_centre() {
+        let position = Position::initial();
+        let chess_move = PieceSquarePlayer::default().offer_move(&position);
+        let (_, destination) = chess_move.origin_and_destination(&position.to_move);
+        assert!(["c3", "f3"].contains(&destination.to_algebraic().as_str()));
+    }
+
+    #[test]
+    fn planner_delivers_mate_in_one() {
+        let position = Position::from_fen("6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1");
+        let result = Planner::new().search(&position);
+        assert!(position.after_move(&result.best_move).is_checkmate());
+        assert_eq!(result.score, MATE_SCORE - 1);
+    }
+
+    #[test]
+    fn engines_play_elementary_mates_from_the_tables() {
+        let position = Position::from_fen("8/8/8/3k4/8/8/8/R3K3 w - - 0 1");
+        let distance = elementary_mate_distance(&position).unwrap();
+        let result = Planner::new().with_depth(1).search(&position);
+        assert_eq!(
+            Some(result.best_move.clone()),
+            elementary_mate_move(&position)
+        );
+        assert_eq!(result.score, MATE_SCORE - distance as isize);
+        assert_eq!(
+            BetterEvaluationPlayer::default().offer_move(&position),
+            result.best_move
+        );
+        let defending = Planner::new().search(&position.after_move(&result.best_move));
+        assert_eq!(defending.score, -(MATE_SCORE - distance as isize + 1));
+    }
+
+    #[test]
+    fn planner_reuses_what_it_learned_until_new_game() {
+        let position = Position::from_fen("4k3/p2p4/8/8/8/8/3P4/R3K3 w Q - 0 1");
+        let planner = Planner::new();
+        let cold = planner.search(&position);
+        let warm = planner.search(&position);
+        assert_eq!(warm.best_move, cold.best_move);
+        assert!(warm.nodes < cold.nodes);
+
+        planner.new_game();
+        assert_eq!(planner.search(&position), cold);
+    }
+
+    #[test]
+    fn planner_table_policy_survives_resizing() {
+        let planner = Planner::with_table_config(&TableConfig {
+            megabytes: 1,
+            bucket_size: 2,
+            replacement: ReplacementPolicy::TwoTier,
+            aging: true,
+        });
+        let position = Position::from_fen("4k3/3p4/8/8/8/8/3P4/R3K3 w Q - 0 1");
+        planner.search(&position);
+        let stats = planner.table_stats();
+        assert!(stats.used > 0);
+        assert_eq!(stats.current, stats.used);
+        planner.search(&position.after_move(&planner.search(&position).best_move));
+        assert!(planner.table_stats().current < planner.table_stats().used);
+        planner.set_hash_size(2);
+        assert_eq!(planner.memory().table.bucket_size(), 2);
+        assert_eq!(
+            planner.memory().table.replacement(),
+            ReplacementPolicy::TwoTier
+        );
+        assert_eq!(planner.table_stats().used, 0);
+    }
+
+    #[test]
+    fn planner_hash_size_is_configurable() {
+        let small = Planner::with_hash_size(1);
+        let large = Planner::with_hash_size(4);
+        assert!(large.hash_entries() > small.hash_entries());
+        small.set_hash_size(4);
+        assert_eq!(small.hash_entries(), large.hash_entries());
+        small.set_option("Hash", &OptionValue::Spin(1));
+        assert!(small.hash_entries() < large.hash_entries());
+
+        let position = Position::from_fen("4k3/3p4/8/8/8/8/3P4/R3K3 w Q - 0 1");
+        let tiny = Planner::with_hash_size(0);
+        assert_eq!(
+            tiny.search(&position).best_move,
+            large.search(&position).best_move
+        );
+    }
+
+    #[test]
+    fn planner_respects_depth_and_node_limits() {
+        let position = Position::initial();
+        assert_eq!(Planner::new().with_depth(1).search(&position).depth, 1);
+
+        let capped = Planner::new().with_depth(20).with_node_limit(2_000);
+        let result = capped.search(&position);
+        assert!(result.depth < 20);
+        assert!(result.nodes <= 2_001);
+        let again = Planner::new().with_depth(20).with_node_limit(2_000);
+        assert_eq!(again.search(&position), result);
+    }
+
+    #[test]
+    fn ties_go_to_the_first_generated_move() {
+        let moves = vec![
+            (ChessMove::CastleLeft, 1),
+            (ChessMove::CastleRight, 3),
+            (ChessMove::CastleLeft, 3),
+        ];
+        assert_eq!(
+            first_move_with_max_evaluation(moves.clone()),
+            ChessMove::CastleRight
+        );
+        assert_eq!(first_move_with_min_evaluation(moves), ChessMove::CastleLeft);
+    }
+
+    #[test]
+    fn pruning_searches_fewer_nodes_for_the_same_fork() {
+        let position =
+            Position::from_fen("rnb1kbnr/pppppppp/8/1N6/8/8/PPPPPPPP/R1BQKBNR w KQkq - 0 1");
+        let pruned = Planner::new().with_depth(4).search(&position);
+        let full = Planner::new()
+            .with_depth(4)
+            .with_pruning(PruningParams::none())
+            .search(&position);
+        assert_eq!(pruned.best_move, full.best_move);
+        assert!(pruned.nodes < full.nodes, "{} {}", pruned.nodes, full.nodes);
+        let razored = Planner::new()
+            .with_depth(4)
+            .with_pruning(PruningParams {
+                razoring_depth: 1,
+                ..PruningParams::default()
+            })
+            .search(&position);
+        assert!(razored.nodes < pruned.nodes);
+    }
+
+    #[test]
+    fn leaves_are_searched_for_captures() {
+        // the pawn on d5 is defended, so the queen taking it is lost
+        let position = Position::from_fen("4k3/8/4p3/3p4/8/8/8/3QK3 w - - 0 1");
+        let queen_takes = ChessMove::from_san("Qxd5", &position).unwrap();
+        assert_ne!(
+            Planner::new().with_depth(1).offer_move(&position),
+            queen_takes
+        );
+        let open = Position::from_fen(
+            "r1bqkb1r/pppp1ppp/2n2n2/4p3/2B1P3/5N2/PPPP1PPP/RNBQK2R w KQkq - 0 1",
+        );
+        let skipping = Planner::new().with_depth(3).search(&open);
+        let every_capture = Planner::new()
+            .with_depth(3)
+            .with_pruning(PruningParams {
+                skip_losing_captures: false,
+                ..PruningParams::default()
+            })
+            .search(&open);
+        assert!(skipping.nodes < every_capture.nodes);
+    }
+
+    #[test]
+    fn root_moves_without_a_legal_move_are_ignored() {
+        let position = Position::initial();
+        let planner = Planner::new();
+        let e4 = ChessMove::from_san("e4", &position).unwrap();
+        let restricted = planner.search_with_limits(
+            &position,
+            &SearchLimits::depth(2).root_moves(vec![e4.clone()]),
+        );
+        assert_eq!(restricted.best_move, e4);
+        let black_move = ChessMove::from_san("e5", &position.opposite_color_to_move()).unwrap();
+        for root_moves in [vec![], vec![black_move]] {
+            let searched = planner
+                .search_with_limits(&position, &SearchLimits::depth(2).root_moves(root_moves));
+            assert!(position.is_move_legal(&searched.best_move));
+        }
+    }
+
+    #[test]
+    fn deepest_search_fits_a_spawned_threads_stack() {
+        // the kings can only shuffle, so every iteration is quick
+        let position = Position::from_fen("k7/8/8/p1p1p1p1/P1P1P1P1/8/8/K7 w - - 0 1");
+        let result = std::thread::spawn(move || {
+            Planner::new().search_with_limits(&position, &SearchLimits::depth(MAX_SEARCH_DEPTH))
+        })
+        .join()
+        .unwrap();
+        assert_eq!(result.depth, MAX_SEARCH_DEPTH);
+    }
+
     #[test]
     fn planner_finds_king_rook_fork() {
         let position =
             Position::from_fen("rnb1kbnr/pppppppp/8/1N6/8/8/PPPPPPPP/R1BQKBNR w KQkq - 0 1");
         assert_eq!(
-            Planner {}.offer_move(&position),
+            Planner::new().offer_move(&position),
             ChessMove::RegularMove(Move {
                 origin: Coords { x: 1, y: 3 },
                 destination: Coords { x: 2, y: 1 }
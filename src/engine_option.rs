@@ -0,0 +1,246 @@
+// The kinds of setting UCI knows about, with what each starts out as.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum OptionKind {
+    Check {
+        default: bool,
+    },
+    Spin {
+        default: i64,
+        min: i64,
+        max: i64,
+    },
+    Combo {
+        default: String,
+        choices: Vec<String>,
+    },
+    // what UCI calls a string option
+    Text {
+        default: String,
+    },
+    Button,
+}
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum OptionValue {
+    Check(bool),
+    Spin(i64),
+    Combo(String),
+    Text(String),
+    Button,
+}
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum OptionError {
+    Unknown(String),
+    InvalidValue { name: String, value: String },
+}
+
+// A setting an engine lets the GUI change.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct EngineOption {
+    pub name: String,
+    pub kind: OptionKind,
+}
+
+impl EngineOption {
+    pub fn check(name: &str, default: bool) -> EngineOption {
+        EngineOption::new(name, OptionKind::Check { default })
+    }
+
+    pub fn spin(name: &str, default: i64, min: i64, max: i64) -> EngineOption {
+        EngineOption::new(name, OptionKind::Spin { default, min, max })
+    }
+
+    pub fn combo(name: &str, default: &str, choices: &[&str]) -> EngineOption {
+        EngineOption::new(
+            name,
+            OptionKind::Combo {
+                default: String::from(default),
+                choices: choices.iter().map(|choice| String::from(*choice)).collect(),
+            },
+        )
+    }
+
+    pub fn text(name: &str, default: &str) -> EngineOption {
+        EngineOption::new(
+            name,
+            OptionKind::Text {
+                default: String::from(default),
+            },
+        )
+    }
+
+    pub fn button(name: &str) -> EngineOption {
+        EngineOption::new(name, OptionKind::Button)
+    }
+
+    fn new(name: &str, kind: OptionKind) -> EngineOption {
+        EngineOption {
+            name: String::from(name),
+            kind,
+        }
+    }
+
+    pub fn default_value(&self) -> OptionValue {
+        match &self.kind {
+            OptionKind::Check { default } => OptionValue::Check(*default),
+            OptionKind::Spin { default, .. } => OptionValue::Spin(*default),
+            OptionKind::Combo { default, .. } => OptionValue::Combo(default.clone()),
+            OptionKind::Text { default } => OptionValue::Text(default.clone()),
+            OptionKind::Button => OptionValue::Button,
+        }
+    }
+
+    // The line declaring the option in answer to `uci`.
+    pub fn to_uci(&self) -> String {
+        let declaration = match &self.kind {
+            OptionKind::Check { default } => format!("check default {}", default),
+            OptionKind::Spin { default, min, max } => {
+                format!("spin default {} min {} max {}", default, min, max)
+            }
+            OptionKind::Combo { default, choices } => choices.iter().fold(
+                format!("combo default {}", default),
+                |declaration, choice| declaration + " var " + choice,
+            ),
+            OptionKind::Text { default } if default.is_empty() => {
+                String::from("string default <empty>")
+            }
+            OptionKind::Text { default } => format!("string default {}", default),
+            OptionKind::Button => String::from("button"),
+        };
+        format!("option name {} type {}", self.name, declaration)
+    }
+
+    // Reads the value part of a setoption command. Spins have to be within
+    // bounds and combos one of their choices, whose spelling is kept.
+    pub fn parse_value(&self, value: Option<&str>) -> Result<OptionValue, OptionError> {
+        let text = value.unwrap_or("");
+        let parsed = match &self.kind {
+            OptionKind::Check { .. } => match text {
+                "true" => Some(OptionValue::Check(true)),
+                "false" => Some(OptionValue::Check(false)),
+                _ => None,
+            },
+            OptionKind::Spin { min, max, .. } => text
+                .parse::<i64>()
+                .ok()
+                .filter(|number| (*min..=*max).contains(number))
+                .map(OptionValue::Spin),
+            OptionKind::Combo { choices, .. } => choices
+                .iter()
+                .find(|choice| choice.eq_ignore_ascii_case(text))
+                .map(|choice| OptionValue::Combo(choice.clone())),
+            OptionKind::Text { .. } if text == "<empty>" => Some(OptionValue::Text(String::new())),
+            OptionKind::Text { .. } => Some(OptionValue::Text(String::from(text))),
+            OptionKind::Button => Some(OptionValue::Button),
+        };
+        parsed.ok_or_else(|| OptionError::InvalidValue {
+            name: self.name.clone(),
+            value: String::from(text),
+        })
+    }
+}
+
+// The options a front-end offers, each with its current value. Names are
+// matched without regard to case, as UCI asks.
+#[derive(Clone, PartialEq, Eq, Debug, Default)]
+pub struct EngineOptions {
+    options: Vec<(EngineOption, OptionValue)>,
+}
+
+impl EngineOptions {
+    pub fn new() -> EngineOptions {
+        EngineOptions::default()
+    }
+
+    // A later declaration of the same name replaces the earlier one.
+    pub fn declare(&mut self, option: EngineOption) {
+        let value = option.default_value();
+        match self.position(&option.name) {
+            Some(index) => self.options[index] = (option, value),
+            None => self.options.push((option, value)),
+        }
+    }
+
+    pub fn declarations(&self) -> impl Iterator<Item = &EngineOption> {
+        self.options.iter().map(|(option, _)| option)
+    }
+
+    pub fn value(&self, name: &str) -> Option<&OptionValue> {
+        self.position(name).map(|index| &self.options[index].1)
+    }
+
+    // Checks and stores the value, returning the option it was for.
+    pub fn set(
+        &mut self,
+        name: &str,
+        value: Option<&str>,
+    ) -> Result<(&EngineOption, &OptionValue), OptionError> {
+        let index = self
+            .position(name)
+            .ok_or_else(|| OptionError::Unknown(String::from(name)))?;
+        let (option, current) = &mut self.options[index];
+        *current = option.parse_value(value)?;
+        Ok((option, current))
+    }
+
+    fn position(&self, name: &str) -> Option<usize> {
+        self.options
+            .iter()
+            .position(|(option, _)| option.name.eq_ignore_ascii_case(name))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn declares_options_the_uci_way() {
+        assert_eq!(
+            EngineOption::spin("Hash", 16, 1, 1024).to_uci(),
+            "option name Hash type spin default 16 min 1 max 1024"
+        );
+        assert_eq!(
+            EngineOption::combo("Style", "Normal", &["Solid", "Normal"]).to_uci(),
+            "option name Style type combo default Normal var Solid var Normal"
+        );
+        assert_eq!(
+            EngineOption::text("Book File", "").to_uci(),
+            "option name Book File type string default <empty>"
+        );
+        assert_eq!(
+            EngineOption::button("Clear Hash").to_uci(),
+            "option name Clear Hash type button"
+        );
+    }
+
+    #[test]
+    fn checks_values_before_storing_them() {
+        let mut options = EngineOptions::new();
+        options.declare(EngineOption::spin("Hash", 16, 1, 1024));
+        options.declare(EngineOption::combo("Style", "Normal", &["Solid", "Normal"]));
+        assert_eq!(options.value("hash"), Some(&OptionValue::Spin(16)));
+        let (option, value) = options.set("hash", Some("64")).unwrap();
+        assert_eq!(
+            (option.name.as_str(), value),
+            ("Hash", &OptionValue::Spin(64))
+        );
+        assert_eq!(
+            options.set("Hash", Some("0")),
+            Err(OptionError::InvalidValue {
+                name: String::from("Hash"),
+                value: String::from("0")
+            })
+        );
+        assert_eq!(options.value("Hash"), Some(&OptionValue::Spin(64)));
+        assert_eq!(
+            options.set("style", Some("solid")).unwrap().1,
+            &OptionValue::Combo(String::from("Solid"))
+        );
+        assert_eq!(
+            options.set("Ponder", Some("true")),
+            Err(OptionError::Unknown(String::from("Ponder")))
+        );
+    }
+}
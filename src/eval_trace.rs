@@ -0,0 +1,139 @@
+use crate::all_squares;
+use crate::Coords;
+
+// What the piece on one square adds to the evaluation, by term. Every term
+// is from the point of view of the side to move, so the other side's pieces
+// count against it.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub struct SquareTerms {
+    pub material: isize,
+    // squares the piece can move to
+    pub control: isize,
+    // the penalty for an attacked piece of the side to move, or the whole
+    // value of an attacked piece of the other side
+    pub attacked: isize,
+    // a rook's bonus for standing on an open or semi-open file
+    pub file: isize,
+}
+
+impl SquareTerms {
+    pub fn total(&self) -> isize {
+        self.material + self.control + self.attacked + self.file
+    }
+}
+
+// An evaluation taken apart square by square.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct EvalTrace {
+    squares: [SquareTerms; 64],
+    // positive when only the side to move has both bishops
+    pub bishop_pair: isize,
+    pub checkmate: isize,
+}
+
+impl Default for EvalTrace {
+    fn default() -> EvalTrace {
+        EvalTrace::new()
+    }
+}
+
+impl EvalTrace {
+    pub fn new() -> EvalTrace {
+        EvalTrace {
+            squares: [SquareTerms::default(); 64],
+            bishop_pair: 0,
+            checkmate: 0,
+        }
+    }
+
+    pub fn at(&self, square: &Coords) -> SquareTerms {
+        self.squares[square.to_square_number()]
+    }
+
+    pub fn set(&mut self, square: &Coords, terms: SquareTerms) {
+        self.squares[square.to_square_number()] = terms;
+    }
+
+    // The evaluation the trace was taken from.
+    pub fn total(&self) -> isize {
+        self.squares.iter().map(SquareTerms::total).sum::<isize>()
+            + self.bishop_pair
+            + self.checkmate
+    }
+
+    pub fn material(&self) -> isize {
+        self.squares.iter().map(|terms| terms.material).sum()
+    }
+
+    // Totals laid out rank 8 first, matching how boards are printed.
+    pub fn grid(&self) -> [[isize; 8]; 8] {
+        let mut grid = [[0; 8]; 8];
+        for square in all_squares() {
+            grid[square.y as usize][square.x as usize] = self.at(&square).total();
+        }
+        grid
+    }
+
+    // The grid as text, with ranks and files labelled and empty squares
+    // shown as dots.
+    pub fn render(&self) -> String {
+        let mut text = String::new();
+        for (y, row) in self.grid().iter().enumerate() {
+            text.push_str(&format!("{} ", 8 - y));
+            for total in row {
+                match total {
+                    0 => text.push_str(&format!("{:>7}", ".")),
+                    total => text.push_str(&format!("{:>7}", total)),
+                }
+            }
+            text.push('\n');
+        }
+        text.push_str("  ");
+        for file in 'a'..='h' {
+            text.push_str(&format!("{:>7}", file));
+        }
+        text.push('\n');
+        if self.bishop_pair != 0 {
+            text.push_str(&format!("bishop pair {}\n", self.bishop_pair));
+        }
+        if self.checkmate != 0 {
+            text.push_str(&format!("checkmate {}\n", self.checkmate));
+        }
+        text
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn renders_totals_on_a_board() {
+        let mut trace = EvalTrace::new();
+        trace.set(
+            &Coords::from_algebraic("e4"),
+            SquareTerms {
+                material: 100,
+                control: 20,
+                attacked: -5,
+                file: 0,
+            },
+        );
+        trace.set(
+            &Coords::from_algebraic("a8"),
+            SquareTerms {
+                material: -500,
+                ..SquareTerms::default()
+            },
+        );
+        assert_eq!(trace.total(), -385);
+        assert_eq!(trace.material(), -400);
+        assert_eq!(trace.grid()[4][4], 115);
+        let rendered = trace.render();
+        let lines: Vec<&str> = rendered.lines().collect();
+        assert_eq!(lines.len(), 9);
+        assert!(lines[0].starts_with("8    -500      ."));
+        assert!(lines[4].contains("    115"));
+        assert!(lines[8].ends_with("      h"));
+    }
+}
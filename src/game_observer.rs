@@ -0,0 +1,195 @@
+use std::fmt::Debug;
+
+use crate::ChessMove;
+use crate::Coords;
+use crate::GameResult;
+use crate::GameStatus;
+use crate::Piece;
+use crate::PieceColor;
+use crate::Position;
+
+// Told what happens in a `Game` it has been subscribed to. Every event is
+// optional, a move that captures with check is reported as the move, then
+// the capture, then the check.
+pub trait GameObserver {
+    fn move_played(&mut self, _before: &Position, _chess_move: &ChessMove, _after: &Position) {}
+    // the captured piece and the square it was taken on, which for en
+    // passant isn't where the capturing pawn lands
+    fn capture(&mut self, _captured: &Piece, _square: &Coords) {}
+    // the side in check, mated sides included
+    fn check(&mut self, _color: &PieceColor) {}
+    // the piece the pawn became
+    fn promotion(&mut self, _piece: &Piece, _square: &Coords) {}
+    fn game_over(&mut self, _result: &GameResult) {}
+}
+
+#[derive(Default)]
+pub(crate) struct Observers(Vec<Box<dyn GameObserver + Send + Sync>>);
+
+impl Observers {
+    pub(crate) fn subscribe(&mut self, observer: Box<dyn GameObserver + Send + Sync>) {
+        self.0.push(observer);
+    }
+
+    pub(crate) fn moved(
+        &mut self,
+        before: &Position,
+        chess_move: &ChessMove,
+        after: &Position,
+        status: &GameStatus,
+    ) {
+        if self.0.is_empty() {
+            return;
+        }
+        let captured = before.captured_piece(chess_move).map(|piece| {
+            let square = match chess_move {
+                ChessMove::EnPassant(_, taken) => *taken,
+                _ => chess_move.origin_and_destination(&before.to_move).1,
+            };
+            (piece, square)
+        });
+        let promoted = match chess_move {
+            ChessMove::Promotion(movement, kind) => Some((
+                Piece {
+                    kind: *kind,
+                    color: before.to_move,
+                },
+                movement.destination,
+            )),
+            _ => None,
+        };
+        let check = matches!(
+            status,
+            GameStatus::Ongoing { check: true } | GameStatus::Checkmate(_)
+        );
+        for observer in &mut self.0 {
+            observer.move_played(before, chess_move, after);
+            if let Some((piece, square)) = &captured {
+                observer.capture(piece, square);
+            }
+            if let Some((piece, square)) = &promoted {
+                observer.promotion(piece, square);
+            }
+            if check {
+                observer.check(&after.to_move);
+            }
+        }
+        if let Some(result) = status.result() {
+            self.ended(&result);
+        }
+    }
+
+    pub(crate) fn ended(&mut self, result: &GameResult) {
+        for observer in &mut self.0 {
+            observer.game_over(result);
+        }
+    }
+}
+
+impl Debug for Observers {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} observers", self.0.len())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use crate::Game;
+    use crate::PieceKind;
+    use crate::WinReason;
+
+    use super::*;
+
+    #[derive(Default, Clone)]
+    struct Recorder(Arc<Mutex<Vec<String>>>);
+
+    impl Recorder {
+        fn note(&self, event: String) {
+            self.0.lock().unwrap().push(event);
+        }
+
+        fn events(&self) -> Vec<String> {
+            std::mem::take(&mut self.0.lock().unwrap())
+        }
+    }
+
+    impl GameObserver for Recorder {
+        fn move_played(&mut self, before: &Position, chess_move: &ChessMove, _after: &Position) {
+            self.note(chess_move.to_san(before));
+        }
+        fn capture(&mut self, captured: &Piece, square: &Coords) {
+            self.note(format!(
+                "took {:?} on {}",
+                captured.kind,
+                square.to_algebraic()
+            ));
+        }
+        fn check(&mut self, color: &PieceColor) {
+            self.note(format!("{:?} in check", color));
+        }
+        fn promotion(&mut self, piece: &Piece, _square: &Coords) {
+            self.note(format!("promoted to {:?}", piece.kind));
+        }
+        fn game_over(&mut self, result: &GameResult) {
+            self.note(format!("{:?}", result));
+        }
+    }
+
+    fn play(game: &mut Game, san: &str) {
+        game.make_move(&ChessMove::from_san(san, &game.current_position).unwrap());
+    }
+
+    #[test]
+    fn observers_hear_each_part_of_a_move() {
+        let recorder = Recorder::default();
+        let mut game =
+            Game::from_starting_position(Position::from_fen("r3k3/1P6/8/8/8/8/8/4K3 w - - 0 1"));
+        game.subscribe(recorder.clone());
+        play(&mut game, "bxa8=Q+");
+        assert_eq!(
+            recorder.events(),
+            vec![
+                "bxa8=Q+",
+                "took Rook on a8",
+                "promoted to Queen",
+                "Black in check"
+            ]
+        );
+        play(&mut game, "Ke7");
+        game.resign(PieceColor::Black);
+        assert_eq!(
+            recorder.events(),
+            vec![
+                String::from("Ke7"),
+                format!("{:?}", GameResult::WhiteWin(WinReason::Resignation))
+            ]
+        );
+        assert_eq!(
+            game.current_position
+                .piece_at(&Coords::from_algebraic("a8")),
+            Some(Piece {
+                kind: PieceKind::Queen,
+                color: PieceColor::White
+            })
+        );
+    }
+
+    #[test]
+    fn mates_end_the_game_by_themselves() {
+        let recorder = Recorder::default();
+        let mut game = Game::start();
+        game.subscribe(recorder.clone());
+        for san in ["f3", "e5", "g4", "Qh4#"] {
+            play(&mut game, san);
+        }
+        assert_eq!(
+            recorder.events()[4..],
+            [
+                String::from("White in check"),
+                format!("{:?}", GameResult::BlackWin(WinReason::Checkmate))
+            ]
+        );
+    }
+}
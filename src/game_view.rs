@@ -0,0 +1,121 @@
+use std::collections::HashMap;
+
+use crate::ChessMove;
+use crate::Coords;
+use crate::DrawReason;
+use crate::Game;
+use crate::GameResult;
+use crate::PieceColor;
+use crate::Position;
+use crate::WinReason;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct CastlingAvailability {
+    pub king_side: bool,
+    pub queen_side: bool,
+}
+
+// Everything a board UI needs to draw a frame, computed in one pass.
+#[derive(Clone, PartialEq, Debug)]
+pub struct GameView {
+    pub to_move: PieceColor,
+    pub check_square: Option<Coords>,
+    pub last_move_squares: Option<(Coords, Coords)>,
+    pub legal_destinations: HashMap<Coords, Vec<Coords>>,
+    pub white_castling: CastlingAvailability,
+    pub black_castling: CastlingAvailability,
+    pub result: Option<GameResult>,
+}
+
+impl GameView {
+    pub fn new(position: &Position, last_move: Option<&ChessMove>) -> GameView {
+        let mut legal_destinations: HashMap<Coords, Vec<Coords>> = HashMap::new();
+        for chess_move in position.all_legal_moves() {
+            let (origin, destination) = chess_move.origin_and_destination(&position.to_move);
+            let destinations = legal_destinations.entry(origin).or_default();
+            if !destinations.contains(&destination) {
+                destinations.push(destination);
+            }
+        }
+        let in_check = position.is_in_check(&position.to_move);
+        let result = if !legal_destinations.is_empty() {
+            None
+        } else if in_check {
+            Some(GameResult::win_for(
+                position.to_move.opposite(),
+                WinReason::Checkmate,
+            ))
+        } else {
+            Some(GameResult::Draw(DrawReason::Stalemate))
+        };
+        let castling = |color: PieceColor| CastlingAvailability {
+            king_side: position.can_castle_king_side(&color),
+            queen_side: position.can_castle_queen_side(&color),
+        };
+        GameView {
+            to_move: position.to_move,
+            check_square: if in_check {
+                position.king_location(&position.to_move)
+            } else {
+                None
+            },
+            last_move_squares: last_move
+                .map(|chess_move| chess_move.origin_and_destination(&position.to_move.opposite())),
+            legal_destinations,
+            white_castling: castling(PieceColor::White),
+            black_castling: castling(PieceColor::Black),
+            result,
+        }
+    }
+
+    pub fn destinations_from(&self, origin: &Coords) -> &[Coords] {
+        self.legal_destinations
+            .get(origin)
+            .map_or(&[], |destinations| destinations)
+    }
+}
+
+impl Game {
+    pub fn view(&self) -> GameView {
+        GameView::new(&self.current_position, self.last_move.as_ref())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn summarizes_position_after_check() {
+        let mut game = Game::start();
+        for san in ["e4", "f5", "Qh5+"] {
+            let chess_move = ChessMove::from_san(san, &game.current_position).unwrap();
+            game.make_move(&chess_move);
+        }
+        let view = game.view();
+        assert_eq!(view.to_move, PieceColor::Black);
+        assert_eq!(view.check_square, Some(Coords::from_algebraic("e8")));
+        assert_eq!(
+            view.last_move_squares,
+            Some((Coords::from_algebraic("d1"), Coords::from_algebraic("h5")))
+        );
+        assert_eq!(
+            view.destinations_from(&Coords::from_algebraic("g7")),
+            &[Coords::from_algebraic("g6")]
+        );
+        assert_eq!(view.legal_destinations.len(), 1);
+        assert!(view.black_castling.king_side && view.white_castling.queen_side);
+        assert_eq!(view.result, None);
+    }
+
+    #[test]
+    fn reports_checkmate_result() {
+        let view = GameView::new(&Position::from_fen("k7/1Q6/1K6/8/8/8/8/8 b - - 0 1"), None);
+        assert_eq!(
+            view.result,
+            Some(GameResult::WhiteWin(WinReason::Checkmate))
+        );
+        assert_eq!(view.check_square, Some(Coords::from_algebraic("a8")));
+        assert!(view.legal_destinations.is_empty());
+    }
+}
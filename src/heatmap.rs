@@ -0,0 +1,209 @@
+use crate::all_squares;
+use crate::ChessMove;
+use crate::Coords;
+use crate::Piece;
+use crate::PieceKind;
+use crate::Position;
+use crate::VariationTree;
+
+// Per-square statistics gathered over any number of games. Occupancy counts
+// every position of a game, including the starting one, so dividing by
+// `positions` gives the share of time a square held a given piece.
+#[derive(Clone, PartialEq, Debug)]
+pub struct Heatmaps {
+    occupancy: [[u64; 64]; 12],
+    captures: [u64; 64],
+    destinations: [[u64; 64]; 6],
+    games: usize,
+    positions: u64,
+}
+
+impl Default for Heatmaps {
+    fn default() -> Heatmaps {
+        Heatmaps::new()
+    }
+}
+
+fn piece_index(piece: &Piece) -> usize {
+    piece.color.index() * 6 + piece.kind.index()
+}
+
+impl Heatmaps {
+    pub fn new() -> Heatmaps {
+        Heatmaps {
+            occupancy: [[0; 64]; 12],
+            captures: [0; 64],
+            destinations: [[0; 64]; 6],
+            games: 0,
+            positions: 0,
+        }
+    }
+
+    pub fn from_games<'a>(
+        games: impl IntoIterator<Item = (&'a Position, &'a [ChessMove])>,
+    ) -> Heatmaps {
+        let mut heatmaps = Heatmaps::new();
+        for (start, moves) in games {
+            heatmaps.add_game(start, moves);
+        }
+        heatmaps
+    }
+
+    pub fn games(&self) -> usize {
+        self.games
+    }
+
+    pub fn positions(&self) -> u64 {
+        self.positions
+    }
+
+    // Moves are applied from `start` until the first illegal one.
+    pub fn add_game(&mut self, start: &Position, moves: &[ChessMove]) {
+        self.games += 1;
+        let mut position = start.clone();
+        self.add_position(&position);
+        for chess_move in moves {
+            if !position.is_move_legal(chess_move) {
+                break;
+            }
+            self.add_move(&position, chess_move);
+            position = position.after_move(chess_move);
+            self.add_position(&position);
+        }
+    }
+
+    pub fn add_mainline(&mut self, tree: &VariationTree) {
+        let start = tree.position(tree.root()).expect("root exists");
+        let moves = tree.moves_to(*tree.mainline().last().unwrap_or(&tree.root()));
+        self.add_game(start, &moves);
+    }
+
+    fn add_position(&mut self, position: &Position) {
+        self.positions += 1;
+        for square in all_squares() {
+            if let Some(piece) = position.piece_at(&square) {
+                self.occupancy[piece_index(&piece)][square.to_square_number()] += 1;
+            }
+        }
+    }
+
+    fn add_move(&mut self, position: &Position, chess_move: &ChessMove) {
+        let (origin, destination) = chess_move.origin_and_destination(&position.to_move);
+        let kind = position
+            .piece_at(&origin)
+            .map_or(PieceKind::Pawn, |piece| piece.kind);
+        self.destinations[kind.index()][destination.to_square_number()] += 1;
+        let captured_on = match chess_move {
+            ChessMove::EnPassant(_, taken) => Some(*taken),
+            _ if position.piece_at(&destination).is_some() => Some(destination),
+            _ => None,
+        };
+        if let Some(square) = captured_on {
+            self.captures[square.to_square_number()] += 1;
+        }
+    }
+
+    pub fn occupancy(&self, piece: &Piece, square: &Coords) -> u64 {
+        self.occupancy[piece_index(piece)][square.to_square_number()]
+    }
+
+    pub fn total_occupancy(&self, square: &Coords) -> u64 {
+        self.occupancy
+            .iter()
+            .map(|counts| counts[square.to_square_number()])
+            .sum()
+    }
+
+    pub fn occupancy_share(&self, piece: &Piece, square: &Coords) -> f64 {
+        if self.positions == 0 {
+            0.0
+        } else {
+            self.occupancy(piece, square) as f64 / self.positions as f64
+        }
+    }
+
+    pub fn captures(&self, square: &Coords) -> u64 {
+        self.captures[square.to_square_number()]
+    }
+
+    pub fn destinations(&self, kind: &PieceKind, square: &Coords) -> u64 {
+        self.destinations[kind.index()][square.to_square_number()]
+    }
+
+    // Counts laid out rank 8 first, matching how boards are printed.
+    pub fn destination_grid(&self, kind: &PieceKind) -> [[u64; 8]; 8] {
+        let mut grid = [[0; 8]; 8];
+        for square in all_squares() {
+            grid[square.y as usize][square.x as usize] = self.destinations(kind, &square);
+        }
+        grid
+    }
+
+    pub fn merge(&mut self, other: &Heatmaps) {
+        for (counts, other_counts) in self.occupancy.iter_mut().zip(&other.occupancy) {
+            for (count, other_count) in counts.iter_mut().zip(other_counts) {
+                *count += other_count;
+            }
+        }
+        for (count, other_count) in self.captures.iter_mut().zip(&other.captures) {
+            *count += other_count;
+        }
+        for (counts, other_counts) in self.destinations.iter_mut().zip(&other.destinations) {
+            for (count, other_count) in counts.iter_mut().zip(other_counts) {
+                *count += other_count;
+            }
+        }
+        self.games += other.games;
+        self.positions += other.positions;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::PieceColor;
+
+    use super::*;
+
+    #[test]
+    fn counts_occupancy_captures_and_destinations() {
+        let tree = VariationTree::from_pgn("1. e4 d5 2. exd5 Qxd5 *").unwrap();
+        let mut heatmaps = Heatmaps::new();
+        heatmaps.add_mainline(&tree);
+        heatmaps.add_mainline(&tree);
+
+        assert_eq!(heatmaps.games(), 2);
+        assert_eq!(heatmaps.positions(), 10);
+        let white_pawn = Piece {
+            kind: PieceKind::Pawn,
+            color: PieceColor::White,
+        };
+        assert_eq!(
+            heatmaps.occupancy(&white_pawn, &Coords::from_algebraic("e2")),
+            2
+        );
+        assert_eq!(
+            heatmaps.occupancy(&white_pawn, &Coords::from_algebraic("e4")),
+            4
+        );
+        assert_eq!(heatmaps.captures(&Coords::from_algebraic("d5")), 4);
+        assert_eq!(
+            heatmaps.destinations(&PieceKind::Queen, &Coords::from_algebraic("d5")),
+            2
+        );
+        assert_eq!(heatmaps.destination_grid(&PieceKind::Pawn)[4][4], 2);
+        assert_eq!(heatmaps.total_occupancy(&Coords::from_algebraic("d5")), 6);
+    }
+
+    #[test]
+    fn merged_heatmaps_match_combined_games() {
+        let start = Position::initial();
+        let first = [ChessMove::from_san("Nf3", &start).unwrap()];
+        let second = [ChessMove::from_san("e4", &start).unwrap()];
+        let mut merged = Heatmaps::from_games([(&start, &first[..])]);
+        merged.merge(&Heatmaps::from_games([(&start, &second[..])]));
+        assert_eq!(
+            merged,
+            Heatmaps::from_games([(&start, &first[..]), (&start, &second[..])])
+        );
+    }
+}
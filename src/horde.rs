@@ -0,0 +1,98 @@
+use crate::ChessMove;
+use crate::Coords;
+use crate::DrawReason;
+use crate::GameResult;
+use crate::Move;
+use crate::PieceColor;
+use crate::PieceKind;
+use crate::Position;
+use crate::RuleSet;
+use crate::WinReason;
+
+// Horde: white has thirty-six pawns and no king against black's usual army.
+// Black wins by taking every white piece, white by mating black, and the
+// pawns on white's first rank can step two squares like those on its second.
+pub struct HordeRules;
+
+impl RuleSet for HordeRules {
+    fn name(&self) -> &str {
+        "Horde"
+    }
+    fn starting_position(&self) -> Position {
+        Position::from_fen(
+            "rnbqkbnr/pppppppp/8/1PP2PP1/PPPPPPPP/PPPPPPPP/PPPPPPPP/PPPPPPPP w kq - 0 1",
+        )
+    }
+    fn legal_moves(&self, position: &Position) -> Vec<ChessMove> {
+        if self.result(position).is_some() {
+            return Vec::new();
+        }
+        let mut moves = position.all_legal_moves();
+        if position.to_move == PieceColor::White {
+            let row = PieceColor::White.homerow();
+            for x in 0..8 {
+                let origin = Coords { x, y: row };
+                let ahead_one = Coords { x, y: row - 1 };
+                let ahead_two = Coords { x, y: row - 2 };
+                if position.piece_at(&origin).is_some_and(|piece| {
+                    piece.kind == PieceKind::Pawn && piece.color == position.to_move
+                }) && position.is_empty(&ahead_one)
+                    && position.is_empty(&ahead_two)
+                {
+                    moves.push(ChessMove::PawnSkip(Move {
+                        origin,
+                        destination: ahead_two,
+                    }));
+                }
+            }
+        }
+        moves
+    }
+    fn result(&self, position: &Position) -> Option<GameResult> {
+        if position.pieces(PieceColor::White).next().is_none() {
+            return Some(GameResult::BlackWin(WinReason::Variant));
+        }
+        // a pawn that could skip could also step, so the skips don't count
+        if position.has_legal_move() {
+            None
+        } else if position.is_in_check(&position.to_move) {
+            Some(GameResult::win_for(
+                position.to_move.opposite(),
+                WinReason::Checkmate,
+            ))
+        } else {
+            Some(GameResult::Draw(DrawReason::Stalemate))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn first_rank_pawns_can_skip() {
+        assert!(HordeRules.result(&HordeRules.starting_position()).is_none());
+        let position = Position::from_fen("4k3/8/8/8/8/8/8/P7 w - - 0 1");
+        let moves: Vec<String> = HordeRules
+            .legal_moves(&position)
+            .iter()
+            .map(|chess_move| chess_move.to_uci_long(&position))
+            .collect();
+        assert_eq!(moves, ["a1a2", "a1a3"]);
+        let skipped = position.after_move(&HordeRules.legal_moves(&position)[1]);
+        assert_eq!(skipped.en_passant_on, Some(Coords::from_algebraic("a2")));
+    }
+
+    #[test]
+    fn black_wins_by_taking_the_last_white_piece() {
+        let position = Position::from_fen("4k3/8/8/8/8/8/Pr6/8 b - - 0 1");
+        assert_eq!(HordeRules.result(&position), None);
+        let after = position.after_move(&ChessMove::from_uci_long("b2a2", &position));
+        assert_eq!(
+            HordeRules.result(&after),
+            Some(GameResult::BlackWin(WinReason::Variant))
+        );
+        assert!(HordeRules.legal_moves(&after).is_empty());
+    }
+}
@@ -1,118 +1,728 @@
+mod adjudication;
+mod analysis;
+mod attack_map;
+mod bitboard;
 mod board_manip;
+mod bughouse;
+mod castling;
 mod chess_move;
 mod coords;
+mod dark_chess;
 mod engine;
+mod engine_option;
+mod eval_trace;
+mod game_observer;
+mod game_view;
+mod heatmap;
+mod horde;
+#[cfg(feature = "lichess")]
+mod lichess;
+mod long_algebraic;
+mod material;
+mod mating;
+mod move_gen;
+mod move_list;
+mod move_text;
+mod opening_book;
+mod perft;
+mod pgn;
 mod piece;
+mod piece_square;
 mod player;
 mod position;
+mod position_builder;
+#[cfg(feature = "python")]
+mod python;
+mod racing_kings;
+mod reference_movegen;
+mod rule_set;
+mod san;
+mod search_control;
+mod see;
+mod self_play;
+mod sprt;
+mod square;
+mod symmetry;
+mod tactical_suite;
+mod time_manager;
+mod tournament;
+mod transposition;
+mod tuning;
+mod uci;
+mod uci_engine_player;
 mod uci_long;
+mod variation_tree;
+mod zobrist;
 
 use core::panic;
-
-pub use crate::board_manip::{move_piece, piece_at, put_piece_at, take_piece_at};
-pub use crate::chess_move::{ChessMove, Move};
-pub use crate::coords::{all_squares, cards, eight_degrees, inter_cards, Coords, Direction};
-pub use crate::engine::{BasicEvaluationPlayer, BetterEvaluationPlayer, FirstMovePlayer, Planner};
+use std::time::Instant;
+
+use crate::adjudication::Adjudicator;
+use crate::game_observer::Observers;
+use crate::pgn::tokenize;
+
+pub use crate::adjudication::{AdjudicationPolicy, DrawRule, ResignRule, Termination};
+pub use crate::analysis::{
+    analyze_game, analyze_game_with, eval_graph, GameAnalysis, LossThresholds, MoveAnalysis,
+    MoveFlaw, ANALYSIS_SCORE_CAP,
+};
+pub use crate::bitboard::Bitboard;
+pub use crate::board_manip::{move_piece, piece_at, put_piece_at, take_piece_at, Mailbox};
+pub use crate::bughouse::{
+    BughouseBoard, BughouseMatch, BughouseMove, BughousePlayer, BughouseResult, Reserve,
+    WithoutDrops,
+};
+pub use crate::castling::CastlingRights;
+pub use crate::chess_move::{ChessMove, Move, ParseMoveError};
+pub use crate::coords::{
+    all_squares, cards, eight_degrees, inter_cards, Coords, Direction, ALL_SQUARES, CARDS,
+    EIGHT_DEGREES, INTER_CARDS,
+};
+pub use crate::dark_chess::DarkChessRules;
+pub use crate::engine::{
+    Accumulated, BasicEvaluationPlayer, BetterEvaluationPlayer, EvalParams, EvalTerms,
+    FirstMovePlayer, GreedyMaterialPlayer, LeafEvaluation, PieceSquarePlayer, Planner,
+    PruningParams, SearchLimits, SearchResult,
+};
 #[cfg(feature = "rng")]
 pub use crate::engine::{RandomCapturePrioPlayer, RandomPlayer};
+pub use crate::engine_option::{EngineOption, EngineOptions, OptionError, OptionKind, OptionValue};
+pub use crate::eval_trace::{EvalTrace, SquareTerms};
+pub use crate::game_observer::GameObserver;
+pub use crate::game_view::{CastlingAvailability, GameView};
+pub use crate::heatmap::Heatmaps;
+pub use crate::horde::HordeRules;
+#[cfg(feature = "lichess")]
+pub use crate::lichess::{LichessBot, LichessError, LichessEvent, LichessGame, LICHESS_URL};
+pub use crate::material::{EndgameClass, MaterialCount};
+pub use crate::mating::{elementary_mate_distance, elementary_mate_move};
+pub use crate::move_gen::{MoveGen, Pin};
+pub use crate::move_list::{MoveList, MoveListIntoIter, MoveListIter, MAX_MOVES};
+pub use crate::opening_book::{BookError, BookMove, BookPlayer, OpeningBook};
+pub use crate::perft::{
+    perft, perft_divide, run_perft_suite, PerftCase, PerftOutcome, PerftReport, PerftSuiteError,
+    STANDARD_PERFT_SUITE,
+};
+pub use crate::pgn::{pgn_date_from_unix_days, PgnError, PgnTags, SevenTagRoster};
 pub use crate::piece::{Piece, PieceColor, PieceKind};
-pub use crate::player::Player;
-pub use crate::position::Position;
+pub use crate::piece_square::PieceSquareTables;
+pub use crate::player::{AskMove, CallbackPlayer, EnginePlayer, Player, StatefulPlayer};
+pub use crate::position::{FenError, Position};
+pub use crate::position_builder::{PositionBuilder, PositionError};
+pub use crate::racing_kings::RacingKingsRules;
+pub use crate::reference_movegen::{movegen_mismatches, MovegenMismatch, ReferencePosition};
+pub use crate::rule_set::{RuleSet, StandardRules};
+pub use crate::san::{SanError, SanLanguage, SanNotation};
+pub use crate::search_control::{ControlError, EngineState, SearchControl, SearchDeadline};
+pub use crate::see::{exchange_value, static_exchange};
+pub use crate::self_play::{PlayerConfig, ReplayError, SelfPlay, SelfPlayGame};
+pub use crate::sprt::{run_sprt, Sprt, SprtConfig, SprtVerdict};
+pub use crate::square::Square;
+pub use crate::symmetry::{assert_eval_symmetric, assert_evals_symmetric, eval_asymmetry};
+pub use crate::tactical_suite::{
+    run_tactical_suite, TacticalCase, TacticalOutcome, TacticalReport, TacticalSuiteError,
+};
+pub use crate::time_manager::{ClockState, TimeBudget, TimeManager};
+pub use crate::tournament::{Standing, Standings, Tournament, TournamentFormat, TournamentGame};
+pub use crate::transposition::{
+    Bound, ReplacementPolicy, TableConfig, TableEntry, TableStats, TranspositionTable,
+};
+pub use crate::tuning::{play_match, spsa, tune_eval_params, tune_pruning_params, SpsaConfig};
+pub use crate::uci::{GoOptions, UciCommand, UciEngine};
+pub use crate::uci_engine_player::UciEnginePlayer;
+pub use crate::uci_long::InferMoveError;
+pub use crate::variation_tree::{NodeId, VariationTree};
+
+// Positions, games and the built-in players keep no Rc, RefCell or
+// thread-local state, so they can be shared between worker threads as they
+// are. This fails to compile if one of them stops being Send + Sync.
+const _: () = {
+    const fn assert_send_sync<T: Send + Sync>() {}
+    assert_send_sync::<Position>();
+    assert_send_sync::<Game>();
+    assert_send_sync::<ChessMove>();
+    assert_send_sync::<VariationTree>();
+    assert_send_sync::<FirstMovePlayer>();
+    assert_send_sync::<BasicEvaluationPlayer>();
+    assert_send_sync::<BetterEvaluationPlayer>();
+    assert_send_sync::<PieceSquarePlayer>();
+    assert_send_sync::<GreedyMaterialPlayer>();
+    assert_send_sync::<Planner>();
+    assert_send_sync::<SearchControl>();
+    assert_send_sync::<UciEnginePlayer>();
+    #[cfg(feature = "rng")]
+    assert_send_sync::<RandomPlayer>();
+    #[cfg(feature = "rng")]
+    assert_send_sync::<RandomCapturePrioPlayer>();
+};
+
+// Where a game stands, worked out once per move.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum GameStatus {
+    Ongoing { check: bool },
+    // the side that was mated
+    Checkmate(PieceColor),
+    Stalemate,
+    Draw(DrawReason),
+    // the side that resigned
+    Resigned(PieceColor),
+    // the side that reached the variant's own goal, see `RuleSet::status`
+    VariantWin(PieceColor),
+}
+
+impl GameStatus {
+    // Only draws no one has to claim end the game by themselves: a position
+    // in which neither side can ever mate.
+    pub fn of(position: &Position) -> GameStatus {
+        let check = position.is_in_check(&position.to_move);
+        if !position.has_legal_move() {
+            if check {
+                GameStatus::Checkmate(position.to_move)
+            } else {
+                GameStatus::Stalemate
+            }
+        } else if position.endgame_class() == EndgameClass::InsufficientMaterial {
+            GameStatus::Draw(DrawReason::InsufficientMaterial)
+        } else {
+            GameStatus::Ongoing { check }
+        }
+    }
+
+    pub fn is_over(&self) -> bool {
+        !matches!(self, GameStatus::Ongoing { .. })
+    }
+
+    // None while the game goes on.
+    pub fn result(&self) -> Option<GameResult> {
+        match self {
+            GameStatus::Ongoing { .. } => None,
+            GameStatus::Checkmate(mated) => {
+                Some(GameResult::win_for(mated.opposite(), WinReason::Checkmate))
+            }
+            GameStatus::Stalemate => Some(GameResult::Draw(DrawReason::Stalemate)),
+            GameStatus::Draw(reason) => Some(GameResult::Draw(*reason)),
+            GameStatus::Resigned(loser) => Some(GameResult::win_for(
+                loser.opposite(),
+                WinReason::Resignation,
+            )),
+            GameStatus::VariantWin(winner) => {
+                Some(GameResult::win_for(*winner, WinReason::Variant))
+            }
+        }
+    }
+}
+
+fn moves_a_pawn(position: &Position, chess_move: &ChessMove) -> bool {
+    let (origin, _) = chess_move.origin_and_destination(&position.to_move);
+    position
+        .piece_at(&origin)
+        .is_some_and(|piece| piece.kind == PieceKind::Pawn)
+}
 
 #[derive(Debug)]
 pub struct Game {
     pub current_position: Position,
-    pub checkmated: Option<PieceColor>,
-    pub stalemate: bool,
+    status: GameStatus,
+    pub last_move: Option<ChessMove>,
+    starting_position: Position,
+    history: Vec<ChessMove>,
+    // the position before each move in `history`
+    previous_positions: Vec<Position>,
+    // the side whose draw offer is waiting on an answer
+    draw_offer: Option<PieceColor>,
+    observers: Observers,
+    pub tags: PgnTags,
+    rules: &'static dyn RuleSet,
 }
 
 impl Game {
     pub fn start() -> Game {
-        let mut board = Vec::new();
-        for i in 0..8 {
-            let mut row = Vec::new();
-            for j in 0..8 {
-                row.push(Piece::from_initial_position(j, i));
-            }
-            board.push(row);
-        }
         Game {
             current_position: Position::initial(),
-            checkmated: None,
-            stalemate: false,
+            status: GameStatus::Ongoing { check: false },
+            last_move: None,
+            starting_position: Position::initial(),
+            history: Vec::new(),
+            previous_positions: Vec::new(),
+            draw_offer: None,
+            observers: Observers::default(),
+            tags: PgnTags::default(),
+            rules: &StandardRules,
         }
     }
 
     pub fn empty() -> Game {
         Game {
             current_position: Position::empty_board(),
-            checkmated: None,
-            stalemate: false,
+            // the board is for setting pieces up on, not a finished game
+            status: GameStatus::Ongoing { check: false },
+            last_move: None,
+            starting_position: Position::empty_board(),
+            history: Vec::new(),
+            previous_positions: Vec::new(),
+            draw_offer: None,
+            observers: Observers::default(),
+            tags: PgnTags::default(),
+            rules: &StandardRules,
         }
     }
     pub fn make_move(&mut self, chess_move: &ChessMove) {
-        if self.current_position.is_move_legal(chess_move) {
-            self.current_position = self.current_position.after_move(chess_move);
-            if self.current_position.is_checkmate() {
-                self.checkmated = Some(self.current_position.to_move.clone());
+        if !self.status.is_over() && self.rules.is_move_legal(&self.current_position, chess_move) {
+            // moving instead of answering an offer declines it
+            if self.draw_offer != Some(self.current_position.to_move) {
+                self.draw_offer = None;
             }
-            self.stalemate = self.current_position.is_stalemate()
+            let next = self.current_position.after_move(chess_move);
+            self.previous_positions
+                .push(std::mem::replace(&mut self.current_position, next));
+            self.status = self.rules.status(&self.current_position);
+            self.last_move = Some(chess_move.clone());
+            self.history.push(chess_move.clone());
+            let before = self
+                .previous_positions
+                .last()
+                .expect("a move was just made");
+            self.observers
+                .moved(before, chess_move, &self.current_position, &self.status);
         }
     }
 
+    // Told about every move made from now on, and how the game ends.
+    pub fn subscribe(&mut self, observer: impl GameObserver + Send + Sync + 'static) {
+        self.observers.subscribe(Box::new(observer));
+    }
+
     pub fn from_starting_position(starting_position: Position) -> Game {
-        let checkmated = starting_position.checkmated();
-        let stalemate = starting_position.is_stalemate();
+        Game::from_position_with_rules(starting_position, &StandardRules)
+    }
+
+    // A game of the variant `rules` describe, from its own starting position.
+    pub fn with_rules(rules: &'static dyn RuleSet) -> Game {
+        Game::from_position_with_rules(rules.starting_position(), rules)
+    }
+
+    pub fn from_position_with_rules(
+        starting_position: Position,
+        rules: &'static dyn RuleSet,
+    ) -> Game {
         Game {
-            current_position: starting_position,
-            checkmated,
-            stalemate,
+            current_position: starting_position.clone(),
+            status: rules.status(&starting_position),
+            last_move: None,
+            starting_position,
+            history: Vec::new(),
+            previous_positions: Vec::new(),
+            draw_offer: None,
+            observers: Observers::default(),
+            tags: PgnTags::default(),
+            rules,
+        }
+    }
+
+    pub fn rules(&self) -> &'static dyn RuleSet {
+        self.rules
+    }
+
+    pub fn status(&self) -> GameStatus {
+        self.status
+    }
+
+    pub fn history(&self) -> &[ChessMove] {
+        &self.history
+    }
+
+    // Takes back the last move, returning it, or None at the start of the
+    // game.
+    pub fn undo_move(&mut self) -> Option<ChessMove> {
+        let chess_move = self.history.pop()?;
+        self.current_position = self
+            .previous_positions
+            .pop()
+            .expect("every move has the position before it");
+        self.status = self.rules.status(&self.current_position);
+        self.last_move = self.history.last().cloned();
+        self.draw_offer = None;
+        Some(chess_move)
+    }
+
+    pub fn resign(&mut self, color: PieceColor) {
+        if !self.status.is_over() {
+            self.status = GameStatus::Resigned(color);
+            self.draw_offer = None;
+            self.end();
+        }
+    }
+
+    // The offer stands until the other side accepts it or moves.
+    pub fn offer_draw(&mut self, color: PieceColor) {
+        if !self.status.is_over() {
+            self.draw_offer = Some(color);
+        }
+    }
+
+    pub fn draw_offer(&self) -> Option<PieceColor> {
+        self.draw_offer
+    }
+
+    // Whether there was an offer from the other side for `color` to accept.
+    // A side can't accept its own offer, which is left standing.
+    pub fn accept_draw(&mut self, color: PieceColor) -> bool {
+        if self.draw_offer != Some(color.opposite()) || self.status.is_over() {
+            return false;
+        }
+        self.draw_offer = None;
+        self.status = GameStatus::Draw(DrawReason::Agreement);
+        self.end();
+        true
+    }
+
+    // Plies since the last capture or pawn move. The count starts from the
+    // beginning of the game, whatever the starting position's FEN said.
+    pub fn halfmove_clock(&self) -> usize {
+        self.history
+            .iter()
+            .zip(&self.previous_positions)
+            .rev()
+            .take_while(|(chess_move, before)| {
+                !before.is_capture(chess_move) && !moves_a_pawn(before, chess_move)
+            })
+            .count()
+    }
+
+    // How many times the current position has come up, counting this one.
+    pub fn repetitions(&self) -> usize {
+        let key = self.current_position.zobrist_key();
+        1 + self
+            .previous_positions
+            .iter()
+            .filter(|position| position.zobrist_key() == key)
+            .count()
+    }
+
+    // The draw the side to move could claim, threefold repetition before the
+    // fifty-move rule.
+    pub fn claimable_draw(&self) -> Option<DrawReason> {
+        if self.status.is_over() {
+            None
+        } else if self.repetitions() >= 3 {
+            Some(DrawReason::Repetition)
+        } else if self.halfmove_clock() >= 100 {
+            Some(DrawReason::FiftyMove)
+        } else {
+            None
+        }
+    }
+
+    // Ends the game in a draw if one can be claimed, returning why.
+    pub fn claim_draw(&mut self) -> Option<DrawReason> {
+        let reason = self.claimable_draw()?;
+        self.status = GameStatus::Draw(reason);
+        self.draw_offer = None;
+        self.end();
+        Some(reason)
+    }
+
+    fn end(&mut self) {
+        if let Some(result) = self.status.result() {
+            self.observers.ended(&result);
+        }
+    }
+
+    // The moves played so far as numbered SAN, "1. e4 e5 2. Nf3", without
+    // tags or a result.
+    pub fn movetext(&self) -> String {
+        self.moves().movetext()
+    }
+
+    fn moves(&self) -> VariationTree {
+        let mut moves = VariationTree::new(self.starting_position.clone());
+        let mut current = moves.root();
+        for chess_move in &self.history {
+            current = moves
+                .add_checked_move(current, chess_move)
+                .expect("the game only keeps legal moves");
+        }
+        moves.set_result(self.status.result());
+        moves
+    }
+
+    // The game with its tags, Result, Termination and FEN being written
+    // from the game itself.
+    pub fn to_pgn(&self) -> String {
+        self.moves().to_pgn_with_tags(&self.tags)
+    }
+
+    // Replays the main line of the first game in `pgn`, variations being
+    // left out. A decisive result the board doesn't show is taken as a
+    // resignation, the only way a `Game` has of ending otherwise.
+    pub fn from_pgn(pgn: &str) -> Result<Game, PgnError> {
+        let tree = VariationTree::from_pgn(pgn)?;
+        let mut game = Game::from_starting_position(
+            tree.position(tree.root())
+                .expect("the root always exists")
+                .clone(),
+        );
+        for chess_move in tree.moves_to(*tree.mainline().last().unwrap_or(&tree.root())) {
+            game.make_move(&chess_move);
+        }
+        if !game.status.is_over() {
+            game.status = match tree.result() {
+                Some(GameResult::Draw(reason)) => GameStatus::Draw(*reason),
+                Some(result) => result.winner().map_or(game.status, |winner| {
+                    GameStatus::Resigned(winner.opposite())
+                }),
+                None => game.status,
+            };
         }
+        game.tags = PgnTags::from_tokens(&tokenize(pgn)?);
+        Ok(game)
     }
 }
 
-#[derive(Debug)]
-pub enum GameResult {
-    WhiteWin,
-    BlackWin,
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum WinReason {
+    Checkmate,
+    TimeForfeit,
+    Resignation,
+    // called by an arbiter or an adjudication rule
+    Adjudicated,
+    // a variant's own goal, like wiping out the horde or racing a king home
+    Variant,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum DrawReason {
     Stalemate,
-    TimedOut,
+    FiftyMove,
+    Repetition,
+    InsufficientMaterial,
+    // by agreement, or for a reason the record doesn't give
+    Agreement,
+    // called by an arbiter or an adjudication rule, games stopped at the
+    // move limit included
+    Adjudicated,
+    // a variant's own way of drawing, like both kings reaching home
+    Variant,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum GameResult {
+    WhiteWin(WinReason),
+    BlackWin(WinReason),
+    Draw(DrawReason),
+}
+
+impl GameResult {
+    pub fn win_for(winner: PieceColor, reason: WinReason) -> GameResult {
+        match winner {
+            PieceColor::White => GameResult::WhiteWin(reason),
+            PieceColor::Black => GameResult::BlackWin(reason),
+        }
+    }
+
+    pub fn winner(&self) -> Option<PieceColor> {
+        match self {
+            GameResult::WhiteWin(_) => Some(PieceColor::White),
+            GameResult::BlackWin(_) => Some(PieceColor::Black),
+            GameResult::Draw(_) => None,
+        }
+    }
+
+    pub fn is_draw(&self) -> bool {
+        matches!(self, GameResult::Draw(_))
+    }
+
+    // 1.0 for a win, 0.5 for a draw and 0.0 for a loss.
+    pub fn points_for(&self, color: &PieceColor) -> f64 {
+        match self.winner() {
+            Some(winner) if winner == *color => 1.0,
+            Some(_) => 0.0,
+            None => 0.5,
+        }
+    }
 }
 
 pub fn play_engine_game(
     white_player: Box<dyn Player>,
     black_player: Box<dyn Player>,
 ) -> GameResult {
-    let mut game = Game::start();
-    let mut turn_counter = 0;
+    play_recorded_engine_game(white_player, black_player).result
+}
+
+pub struct EngineGame {
+    pub result: GameResult,
+    pub moves: VariationTree,
+    pub tags: SevenTagRoster,
+    // the side that ran out of time, only ever set in clocked games
+    pub flagged: Option<PieceColor>,
+    pub termination: Termination,
+}
+
+impl EngineGame {
+    pub fn to_pgn(&self) -> String {
+        self.moves.to_pgn_with_roster(&self.tags)
+    }
+}
+
+pub fn play_recorded_engine_game(
+    white_player: Box<dyn Player>,
+    black_player: Box<dyn Player>,
+) -> EngineGame {
+    play_game(
+        &*white_player,
+        &*black_player,
+        &StandardRules,
+        Position::initial(),
+        None,
+        &AdjudicationPolicy::default(),
+    )
+}
+
+// Like `play_recorded_engine_game`, but each player's thinking time comes
+// off its side of `clock`. A player who runs out loses, unless the opponent
+// has no mating material left, in which case the game is drawn.
+pub fn play_clocked_engine_game(
+    white_player: Box<dyn Player>,
+    black_player: Box<dyn Player>,
+    clock: ClockState,
+) -> EngineGame {
+    play_game(
+        &*white_player,
+        &*black_player,
+        &StandardRules,
+        Position::initial(),
+        Some(clock),
+        &AdjudicationPolicy::default(),
+    )
+}
+
+// Like `play_recorded_engine_game`, but the game may be called early, or
+// played on for longer, as `policy` says.
+pub fn play_adjudicated_engine_game(
+    white_player: Box<dyn Player>,
+    black_player: Box<dyn Player>,
+    policy: &AdjudicationPolicy,
+) -> EngineGame {
+    play_game(
+        &*white_player,
+        &*black_player,
+        &StandardRules,
+        Position::initial(),
+        None,
+        policy,
+    )
+}
 
-    while game.checkmated.is_none() && !game.current_position.is_stalemate() && turn_counter < 300 {
-        let offered_move = match game.current_position.to_move {
-            PieceColor::White => white_player.offer_move(&game.current_position),
-            PieceColor::Black => black_player.offer_move(&game.current_position),
+// Like `play_recorded_engine_game`, but played by the variant's `rules`
+// from its own starting position. The players have to offer moves the
+// variant allows.
+pub fn play_variant_engine_game(
+    white_player: Box<dyn Player>,
+    black_player: Box<dyn Player>,
+    rules: &'static dyn RuleSet,
+) -> EngineGame {
+    play_game(
+        &*white_player,
+        &*black_player,
+        rules,
+        rules.starting_position(),
+        None,
+        &AdjudicationPolicy::default(),
+    )
+}
+
+// Players are told a new game starts, so the same ones can play a series.
+pub(crate) fn play_game(
+    white_player: &dyn Player,
+    black_player: &dyn Player,
+    rules: &'static dyn RuleSet,
+    starting_position: Position,
+    mut clock: Option<ClockState>,
+    policy: &AdjudicationPolicy,
+) -> EngineGame {
+    white_player.new_game();
+    black_player.new_game();
+    let mut game = Game::from_position_with_rules(starting_position, rules);
+    let mut moves = VariationTree::new(game.current_position.clone());
+    let mut current = moves.root();
+    let mut plies = 0;
+    let mut flagged = None;
+    let mut adjudicator = Adjudicator::new(policy);
+    let mut adjudicated = adjudicator.verdict(&game.current_position, plies);
+
+    while !game.status().is_over() && adjudicated.is_none() {
+        let to_move = game.current_position.to_move;
+        let thinking = Instant::now();
+        let player = match to_move {
+            PieceColor::White => &white_player,
+            PieceColor::Black => &black_player,
+        };
+        let offered_move = match &clock {
+            Some(clock) => player.offer_move_timed(&game.current_position, clock),
+            None => player.offer_move(&game.current_position),
         };
-        if !game.current_position.is_move_legal(&offered_move) {
+        if let Some(clock) = clock.as_mut() {
+            if !clock.spend(&to_move, thinking.elapsed()) {
+                flagged = Some(to_move);
+                break;
+            }
+        }
+        if !rules.is_move_legal(&game.current_position, &offered_move) {
             panic!("engine offered illegal move");
-        } else {
-            game.make_move(&offered_move);
-            turn_counter += 1;
         }
+        if policy.needs_scores() {
+            adjudicator.record(&to_move, player.evalutate(&game.current_position));
+        }
+        white_player.move_played(&game.current_position, &offered_move);
+        black_player.move_played(&game.current_position, &offered_move);
+        game.make_move(&offered_move);
+        current = moves
+            .add_checked_move(current, &offered_move)
+            .expect("legal moves can be added to the game record");
+        plies += 1;
+        adjudicated = adjudicator.verdict(&game.current_position, plies);
     }
-    if let Some(color) = game.checkmated {
-        match color {
-            PieceColor::White => GameResult::BlackWin,
-            PieceColor::Black => GameResult::WhiteWin,
+    let (result, termination) = if let Some(color) = flagged {
+        if !game.current_position.has_mating_material(color.opposite()) {
+            (
+                GameResult::Draw(DrawReason::InsufficientMaterial),
+                Termination::TimeForfeit,
+            )
+        } else {
+            (
+                GameResult::win_for(color.opposite(), WinReason::TimeForfeit),
+                Termination::TimeForfeit,
+            )
         }
-    } else if game.current_position.is_stalemate() {
-        GameResult::Stalemate
+    } else if let Some(result) = game.status().result() {
+        let termination = match game.status() {
+            GameStatus::Checkmate(_) => Termination::Checkmate,
+            GameStatus::Stalemate => Termination::Stalemate,
+            GameStatus::Draw(DrawReason::InsufficientMaterial) => Termination::InsufficientMaterial,
+            _ => Termination::Variant,
+        };
+        (result, termination)
     } else {
-        GameResult::TimedOut
+        adjudicated.expect("the game only stops early when adjudicated")
+    };
+    moves.set_result(Some(result));
+    EngineGame {
+        result,
+        moves,
+        tags: SevenTagRoster::for_players(white_player, black_player).event("Engine game"),
+        flagged,
+        termination,
     }
 }
 
 #[cfg(test)]
 mod tests {
     use core::panic;
+    use std::cell::{Cell, RefCell};
+    use std::fmt::Display;
+    use std::rc::Rc;
+    use std::thread;
+    use std::time::Duration;
     use std::{collections::HashSet, hash::RandomState};
 
     use super::*;
@@ -234,10 +844,13 @@ mod tests {
     #[test]
     fn rook_middle_board() {
         let mut position = Position::empty_board();
-        position.board[4][4] = Some(Piece {
-            kind: PieceKind::Rook,
-            color: PieceColor::White,
-        });
+        position.put_piece_at(
+            Piece {
+                kind: PieceKind::Rook,
+                color: PieceColor::White,
+            },
+            Coords { y: 4, x: 4 },
+        );
         let rook_location = Coords { y: 4, x: 4 };
         let mut legal_moves = vec![];
 
@@ -271,26 +884,41 @@ mod tests {
     #[test]
     fn rook_middle_board_boxed_in_opposite_color() {
         let mut position = Position::empty_board();
-        position.board[4][4] = Some(Piece {
-            kind: PieceKind::Rook,
-            color: PieceColor::White,
-        });
-        position.board[5][4] = Some(Piece {
-            kind: PieceKind::Rook,
-            color: PieceColor::Black,
-        });
-        position.board[3][4] = Some(Piece {
-            kind: PieceKind::Rook,
-            color: PieceColor::Black,
-        });
-        position.board[4][5] = Some(Piece {
-            kind: PieceKind::Rook,
-            color: PieceColor::Black,
-        });
-        position.board[4][3] = Some(Piece {
-            kind: PieceKind::Rook,
-            color: PieceColor::Black,
-        });
+        position.put_piece_at(
+            Piece {
+                kind: PieceKind::Rook,
+                color: PieceColor::White,
+            },
+            Coords { y: 4, x: 4 },
+        );
+        position.put_piece_at(
+            Piece {
+                kind: PieceKind::Rook,
+                color: PieceColor::Black,
+            },
+            Coords { y: 5, x: 4 },
+        );
+        position.put_piece_at(
+            Piece {
+                kind: PieceKind::Rook,
+                color: PieceColor::Black,
+            },
+            Coords { y: 3, x: 4 },
+        );
+        position.put_piece_at(
+            Piece {
+                kind: PieceKind::Rook,
+                color: PieceColor::Black,
+            },
+            Coords { y: 4, x: 5 },
+        );
+        position.put_piece_at(
+            Piece {
+                kind: PieceKind::Rook,
+                color: PieceColor::Black,
+            },
+            Coords { y: 4, x: 3 },
+        );
         let rook_location = Coords { y: 4, x: 4 };
         let up = Coords { y: 5, x: 4 };
         let down = Coords { y: 3, x: 4 };
@@ -317,34 +945,51 @@ mod tests {
         ];
 
         assert_eq!(
-            position.legal_moves_from_origin(&rook_location),
-            legal_moves
+            HashSet::<ChessMove, RandomState>::from_iter(
+                position.legal_moves_from_origin(&rook_location)
+            ),
+            HashSet::from_iter(legal_moves)
         );
     }
 
     #[test]
     fn rook_middle_board_boxed_in_own_color() {
         let mut position = Position::empty_board();
-        position.board[4][4] = Some(Piece {
-            kind: PieceKind::Rook,
-            color: PieceColor::White,
-        });
-        position.board[5][4] = Some(Piece {
-            kind: PieceKind::Rook,
-            color: PieceColor::White,
-        });
-        position.board[3][4] = Some(Piece {
-            kind: PieceKind::Rook,
-            color: PieceColor::White,
-        });
-        position.board[4][5] = Some(Piece {
-            kind: PieceKind::Rook,
-            color: PieceColor::White,
-        });
-        position.board[4][3] = Some(Piece {
-            kind: PieceKind::Rook,
-            color: PieceColor::White,
-        });
+        position.put_piece_at(
+            Piece {
+                kind: PieceKind::Rook,
+                color: PieceColor::White,
+            },
+            Coords { y: 4, x: 4 },
+        );
+        position.put_piece_at(
+            Piece {
+                kind: PieceKind::Rook,
+                color: PieceColor::White,
+            },
+            Coords { y: 5, x: 4 },
+        );
+        position.put_piece_at(
+            Piece {
+                kind: PieceKind::Rook,
+                color: PieceColor::White,
+            },
+            Coords { y: 3, x: 4 },
+        );
+        position.put_piece_at(
+            Piece {
+                kind: PieceKind::Rook,
+                color: PieceColor::White,
+            },
+            Coords { y: 4, x: 5 },
+        );
+        position.put_piece_at(
+            Piece {
+                kind: PieceKind::Rook,
+                color: PieceColor::White,
+            },
+            Coords { y: 4, x: 3 },
+        );
         let rook_location = Coords { y: 4, x: 4 };
 
         let legal_moves = vec![];
@@ -358,14 +1003,17 @@ mod tests {
     #[test]
     fn knight_middle_board() {
         let mut position = Position::empty_board();
-        position.board[3][3] = Some(Piece {
-            kind: PieceKind::Knight,
-            color: PieceColor::White,
-        });
+        position.put_piece_at(
+            Piece {
+                kind: PieceKind::Knight,
+                color: PieceColor::White,
+            },
+            Coords { y: 3, x: 3 },
+        );
         let knight_location = Coords { y: 3, x: 3 };
 
         let legal_moves: HashSet<ChessMove, RandomState> = HashSet::from_iter(
-            vec![
+            [
                 ChessMove::RegularMove(Move {
                     origin: knight_location,
                     destination: Coords { y: 5, x: 4 },
@@ -419,14 +1067,17 @@ mod tests {
     #[test]
     fn knight_corner() {
         let mut position = Position::empty_board();
-        position.board[0][0] = Some(Piece {
-            kind: PieceKind::Knight,
-            color: PieceColor::White,
-        });
+        position.put_piece_at(
+            Piece {
+                kind: PieceKind::Knight,
+                color: PieceColor::White,
+            },
+            Coords { y: 0, x: 0 },
+        );
         let knight_location = Coords { y: 0, x: 0 };
 
         let legal_moves: HashSet<ChessMove, RandomState> = HashSet::from_iter(
-            vec![
+            [
                 ChessMove::RegularMove(Move {
                     origin: knight_location,
                     destination: Coords { y: 2, x: 1 },
@@ -456,18 +1107,27 @@ mod tests {
     #[test]
     fn knight_corner_blocked() {
         let mut position = Position::empty_board();
-        position.board[0][0] = Some(Piece {
-            kind: PieceKind::Knight,
-            color: PieceColor::White,
-        });
-        position.board[1][2] = Some(Piece {
-            kind: PieceKind::Knight,
-            color: PieceColor::White,
-        });
-        position.board[2][1] = Some(Piece {
-            kind: PieceKind::Knight,
-            color: PieceColor::White,
-        });
+        position.put_piece_at(
+            Piece {
+                kind: PieceKind::Knight,
+                color: PieceColor::White,
+            },
+            Coords { y: 0, x: 0 },
+        );
+        position.put_piece_at(
+            Piece {
+                kind: PieceKind::Knight,
+                color: PieceColor::White,
+            },
+            Coords { y: 1, x: 2 },
+        );
+        position.put_piece_at(
+            Piece {
+                kind: PieceKind::Knight,
+                color: PieceColor::White,
+            },
+            Coords { y: 2, x: 1 },
+        );
         let knight_location = Coords { y: 0, x: 0 };
 
         assert_eq!(position.legal_moves_from_origin(&knight_location).len(), 0)
@@ -476,10 +1136,13 @@ mod tests {
     #[test]
     fn bishob_middle_board() {
         let mut position = Position::empty_board();
-        position.board[3][3] = Some(Piece {
-            kind: PieceKind::Bishop,
-            color: PieceColor::White,
-        });
+        position.put_piece_at(
+            Piece {
+                kind: PieceKind::Bishop,
+                color: PieceColor::White,
+            },
+            Coords { y: 3, x: 3 },
+        );
         let bishop_location = Coords { y: 3, x: 3 };
         let mut legal_moves = vec![];
 
@@ -518,42 +1181,45 @@ mod tests {
     #[test]
     fn king_middle_board() {
         let mut position = Position::empty_board();
-        position.board[3][3] = Some(Piece {
-            kind: PieceKind::King,
-            color: PieceColor::White,
-        });
+        position.put_piece_at(
+            Piece {
+                kind: PieceKind::King,
+                color: PieceColor::White,
+            },
+            Coords { y: 3, x: 3 },
+        );
         let king_location = Coords { y: 3, x: 3 };
         let legal_moves = HashSet::from([
             ChessMove::RegularMove(Move {
-                origin: king_location.clone(),
+                origin: king_location,
                 destination: Coords { y: 3, x: 4 },
             }),
             ChessMove::RegularMove(Move {
-                origin: king_location.clone(),
+                origin: king_location,
                 destination: Coords { y: 3, x: 2 },
             }),
             ChessMove::RegularMove(Move {
-                origin: king_location.clone(),
+                origin: king_location,
                 destination: Coords { y: 2, x: 3 },
             }),
             ChessMove::RegularMove(Move {
-                origin: king_location.clone(),
+                origin: king_location,
                 destination: Coords { y: 4, x: 3 },
             }),
             ChessMove::RegularMove(Move {
-                origin: king_location.clone(),
+                origin: king_location,
                 destination: Coords { y: 4, x: 4 },
             }),
             ChessMove::RegularMove(Move {
-                origin: king_location.clone(),
+                origin: king_location,
                 destination: Coords { y: 2, x: 2 },
             }),
             ChessMove::RegularMove(Move {
-                origin: king_location.clone(),
+                origin: king_location,
                 destination: Coords { y: 4, x: 2 },
             }),
             ChessMove::RegularMove(Move {
-                origin: king_location.clone(),
+                origin: king_location,
                 destination: Coords { y: 2, x: 4 },
             }),
         ]);
@@ -574,10 +1240,13 @@ mod tests {
     #[test]
     fn cannot_move_out_of_turn() {
         let mut position = Position::empty_board();
-        position.board[3][3] = Some(Piece {
-            kind: PieceKind::King,
-            color: PieceColor::Black,
-        });
+        position.put_piece_at(
+            Piece {
+                kind: PieceKind::King,
+                color: PieceColor::Black,
+            },
+            Coords { y: 3, x: 3 },
+        );
         let king_location = Coords { y: 3, x: 3 };
         assert_eq!(position.legal_moves_from_origin(&king_location).len(), 0);
     }
@@ -586,14 +1255,20 @@ mod tests {
     fn cannot_move_into_check() {
         let mut position = Position::empty_board();
 
-        position.board[0][0] = Some(Piece {
-            kind: PieceKind::King,
-            color: PieceColor::White,
-        });
-        position.board[2][2] = Some(Piece {
-            kind: PieceKind::Knight,
-            color: PieceColor::Black,
-        });
+        position.put_piece_at(
+            Piece {
+                kind: PieceKind::King,
+                color: PieceColor::White,
+            },
+            Coords { y: 0, x: 0 },
+        );
+        position.put_piece_at(
+            Piece {
+                kind: PieceKind::Knight,
+                color: PieceColor::Black,
+            },
+            Coords { y: 2, x: 2 },
+        );
         let king_location = Coords { y: 0, x: 0 };
         assert!(!position.is_move_legal(&ChessMove::RegularMove(Move {
             origin: king_location,
@@ -605,18 +1280,27 @@ mod tests {
     fn detects_checkmate() {
         let mut position = Position::empty_board();
 
-        position.board[0][0] = Some(Piece {
-            kind: PieceKind::King,
-            color: PieceColor::White,
-        });
-        position.board[1][1] = Some(Piece {
-            kind: PieceKind::Queen,
-            color: PieceColor::Black,
-        });
-        position.board[2][2] = Some(Piece {
-            kind: PieceKind::Queen,
-            color: PieceColor::Black,
-        });
+        position.put_piece_at(
+            Piece {
+                kind: PieceKind::King,
+                color: PieceColor::White,
+            },
+            Coords { y: 0, x: 0 },
+        );
+        position.put_piece_at(
+            Piece {
+                kind: PieceKind::Queen,
+                color: PieceColor::Black,
+            },
+            Coords { y: 1, x: 1 },
+        );
+        position.put_piece_at(
+            Piece {
+                kind: PieceKind::Queen,
+                color: PieceColor::Black,
+            },
+            Coords { y: 2, x: 2 },
+        );
         assert!(position.is_checkmate());
     }
 
@@ -634,38 +1318,42 @@ mod tests {
         let position = Position::from_fen("8/8/8/8/8/8/8/4K2R w KQ - 0 1");
 
         let after_castle_right = position.after_move(&ChessMove::CastleRight);
-        assert!(
-            piece_at(&after_castle_right.board, &Coords { y: 7, x: 6 }).is_some_and(|piece| piece
+        assert!(after_castle_right
+            .piece_at(&Coords { y: 7, x: 6 })
+            .is_some_and(|piece| piece
                 == Piece {
                     kind: PieceKind::King,
                     color: PieceColor::White
-                })
-        );
-        assert!(
-            piece_at(&after_castle_right.board, &Coords { y: 7, x: 5 }).is_some_and(|piece| piece
+                }));
+        assert!(after_castle_right
+            .piece_at(&Coords { y: 7, x: 5 })
+            .is_some_and(|piece| piece
                 == Piece {
                     kind: PieceKind::Rook,
                     color: PieceColor::White
-                })
-        );
+                }));
     }
 
     #[test]
     fn make_move() {
         let mut game = Game::empty();
 
-        game.current_position.board[0][0] = Some(Piece {
-            kind: PieceKind::King,
-            color: PieceColor::White,
-        });
+        game.current_position.put_piece_at(
+            Piece {
+                kind: PieceKind::King,
+                color: PieceColor::White,
+            },
+            Coords { y: 0, x: 0 },
+        );
         let king_location = Coords { x: 0, y: 0 };
         game.make_move(&ChessMove::RegularMove(Move {
             origin: king_location,
             destination: Coords { x: 0, y: 1 },
         }));
-        assert!(piece_at(&game.current_position.board, &king_location).is_none());
+        assert!(game.current_position.piece_at(&king_location).is_none());
         assert_eq!(
-            piece_at(&game.current_position.board, &Coords { x: 0, y: 1 })
+            game.current_position
+                .piece_at(&Coords { x: 0, y: 1 })
                 .unwrap()
                 .kind,
             PieceKind::King
@@ -709,7 +1397,7 @@ mod tests {
         }));
         assert!(game.current_position.to_move == PieceColor::Black);
 
-        assert!(game.checkmated == Some(PieceColor::Black));
+        assert_eq!(game.status(), GameStatus::Checkmate(PieceColor::Black));
     }
 
     #[test]
@@ -734,4 +1422,471 @@ mod tests {
                 _ => panic!("expected only promotions, found {:?}", chess_move),
             });
     }
+
+    #[test]
+    fn status_follows_the_game() {
+        let mut game =
+            Game::from_starting_position(Position::from_fen("3k4/3r4/8/8/8/8/8/3RK3 w - - 0 1"));
+        assert_eq!(game.status(), GameStatus::Ongoing { check: false });
+        game.make_move(&ChessMove::from_san("Rxd7+", &game.current_position).unwrap());
+        assert_eq!(game.status(), GameStatus::Ongoing { check: true });
+        game.make_move(&ChessMove::from_san("Kxd7", &game.current_position).unwrap());
+        assert_eq!(
+            game.status(),
+            GameStatus::Draw(DrawReason::InsufficientMaterial)
+        );
+        assert_eq!(
+            game.status().result(),
+            Some(GameResult::Draw(DrawReason::InsufficientMaterial))
+        );
+    }
+
+    #[test]
+    fn games_end_by_resignation_and_agreement() {
+        let mut game = Game::start();
+        game.offer_draw(PieceColor::White);
+        game.make_move(&ChessMove::from_san("e4", &game.current_position).unwrap());
+        assert_eq!(game.draw_offer(), Some(PieceColor::White));
+        // black moves on instead of accepting
+        game.make_move(&ChessMove::from_san("e5", &game.current_position).unwrap());
+        assert_eq!(game.draw_offer(), None);
+        assert!(!game.accept_draw(PieceColor::Black));
+        game.offer_draw(PieceColor::White);
+        assert!(!game.accept_draw(PieceColor::White));
+        assert_eq!(game.draw_offer(), Some(PieceColor::White));
+        assert!(game.status().result().is_none());
+        assert!(game.accept_draw(PieceColor::Black));
+        assert_eq!(
+            game.status().result(),
+            Some(GameResult::Draw(DrawReason::Agreement))
+        );
+        let mut game = Game::start();
+        game.resign(PieceColor::White);
+        assert_eq!(
+            game.status().result(),
+            Some(GameResult::BlackWin(WinReason::Resignation))
+        );
+        game.make_move(&ChessMove::from_san("e4", &game.current_position).unwrap());
+        assert!(game.history().is_empty());
+    }
+
+    #[test]
+    fn drawn_games_take_no_more_moves() {
+        let mut game =
+            Game::from_starting_position(Position::from_fen("8/8/3k4/8/8/3K4/8/8 w - - 0 1"));
+        assert_eq!(
+            game.status(),
+            GameStatus::Draw(DrawReason::InsufficientMaterial)
+        );
+        game.make_move(&ChessMove::from_uci_long("d3e3", &game.current_position));
+        assert!(game.history().is_empty());
+    }
+
+    #[test]
+    fn bishops_on_one_square_color_are_a_dead_draw() {
+        assert_eq!(
+            GameStatus::of(&Position::from_fen("8/8/3k4/4b3/8/8/8/2BK4 w - - 0 1")),
+            GameStatus::Draw(DrawReason::InsufficientMaterial)
+        );
+        assert_eq!(
+            GameStatus::of(&Position::from_fen("8/8/3k4/3b4/8/8/8/2BK4 w - - 0 1")),
+            GameStatus::Ongoing { check: false }
+        );
+    }
+
+    #[test]
+    fn draws_can_be_claimed_by_repetition_or_fifty_moves() {
+        let mut game = Game::start();
+        assert_eq!(game.claim_draw(), None);
+        for _ in 0..2 {
+            for san in ["Nf3", "Nf6", "Ng1", "Ng8"] {
+                game.make_move(&ChessMove::from_san(san, &game.current_position).unwrap());
+            }
+        }
+        assert_eq!(game.repetitions(), 3);
+        assert_eq!(game.halfmove_clock(), 8);
+        assert_eq!(game.claim_draw(), Some(DrawReason::Repetition));
+        assert_eq!(game.status(), GameStatus::Draw(DrawReason::Repetition));
+
+        let mut game =
+            Game::from_starting_position(Position::from_fen("rn2k3/8/8/8/8/8/4P3/RN2K3 w - - 0 1"));
+        game.make_move(&ChessMove::from_san("e4", &game.current_position).unwrap());
+        assert_eq!(game.halfmove_clock(), 0);
+        // the pieces wander about without taking or repeating anything
+        while game.halfmove_clock() < 99 {
+            assert_eq!(game.claimable_draw(), None);
+            let position = game.current_position.clone();
+            let wander = position
+                .all_legal_moves()
+                .into_iter()
+                .rfind(|chess_move| {
+                    let next = position.after_move(chess_move);
+                    !position.is_capture(chess_move)
+                        && !moves_a_pawn(&position, chess_move)
+                        && !GameStatus::of(&next).is_over()
+                        && game.previous_positions.iter().all(|seen| *seen != next)
+                })
+                .expect("there is somewhere new to go");
+            game.make_move(&wander);
+        }
+        assert_eq!(game.claimable_draw(), None);
+        let position = game.current_position.clone();
+        let last = position
+            .all_legal_moves()
+            .into_iter()
+            .find(|chess_move| {
+                !position.is_capture(chess_move) && !moves_a_pawn(&position, chess_move)
+            })
+            .unwrap();
+        game.make_move(&last);
+        assert_eq!(game.claim_draw(), Some(DrawReason::FiftyMove));
+    }
+
+    #[test]
+    fn undo_takes_back_moves_in_order() {
+        let mut game = Game::start();
+        assert_eq!(game.undo_move(), None);
+        for san in ["f3", "e5", "g4", "Qh4#"] {
+            game.make_move(&ChessMove::from_san(san, &game.current_position).unwrap());
+        }
+        assert_eq!(game.history().len(), 4);
+        assert_eq!(game.status(), GameStatus::Checkmate(PieceColor::White));
+        let mate = game.undo_move().unwrap();
+        assert_eq!(mate.to_san(&game.current_position), "Qh4#");
+        assert_eq!(game.status(), GameStatus::Ongoing { check: false });
+        assert_eq!(game.last_move, game.history().last().cloned());
+        assert_eq!(game.movetext(), "1. f3 e5 2. g4");
+        while game.undo_move().is_some() {}
+        assert_eq!(game.current_position, Position::initial());
+        assert_eq!(game.last_move, None);
+    }
+
+    #[test]
+    fn game_movetext_numbers_san_moves() {
+        let mut game = Game::start();
+        assert_eq!(game.movetext(), "");
+        for san in ["e4", "e5", "Nf3", "Nc6", "Bb5"] {
+            game.make_move(&ChessMove::from_san(san, &game.current_position).unwrap());
+        }
+        assert_eq!(game.movetext(), "1. e4 e5 2. Nf3 Nc6 3. Bb5");
+
+        let mut from_black =
+            Game::from_starting_position(Position::from_fen("4k3/8/8/8/8/8/4P3/4K3 b - - 0 1"));
+        for san in ["Kd7", "e4", "Ke6"] {
+            from_black.make_move(&ChessMove::from_san(san, &from_black.current_position).unwrap());
+        }
+        assert_eq!(from_black.movetext(), "1... Kd7 2. e4 Ke6");
+    }
+
+    #[test]
+    fn games_keep_their_tags_through_pgn() {
+        let pgn = "[Event \"Club [rapid] \\\"final\\\"\"]\n[Site \"?\"]\n[Date \"2024.05.06\"]\n\
+                   [Round \"2\"]\n[White \"Ann\"]\n[Black \"Bo\"]\n[Result \"0-1\"]\n\
+                   [Annotator \"Cy\"]\n[Termination \"normal\"]\n\n1. e4 e5 2. Nf3 0-1";
+        let game = Game::from_pgn(pgn).unwrap();
+        assert_eq!(game.tags.roster.event, "Club [rapid] \"final\"");
+        assert_eq!(game.tags.get("White"), Some("Ann"));
+        assert_eq!(game.tags.get("Annotator"), Some("Cy"));
+        assert_eq!(game.tags.get("Result"), None);
+        assert_eq!(game.status(), GameStatus::Resigned(PieceColor::White));
+        assert_eq!(game.history().len(), 3);
+        assert_eq!(game.to_pgn(), pgn);
+
+        let mut game = Game::start();
+        assert!(game.tags.set("Opening", "King's pawn"));
+        assert!(!game.tags.set("Result", "1-0"));
+        game.make_move(&ChessMove::from_san("e4", &game.current_position).unwrap());
+        assert!(game
+            .to_pgn()
+            .ends_with("[Opening \"King's pawn\"]\n\n1. e4 *"));
+        assert_eq!(
+            game.tags.remove("Opening"),
+            Some(String::from("King's pawn"))
+        );
+    }
+
+    #[test]
+    fn records_engine_games_with_tags() {
+        let game = play_recorded_engine_game(Box::new(FirstMovePlayer), Box::new(FirstMovePlayer));
+        let pgn = game.to_pgn();
+        assert!(pgn.starts_with("[Event \"Engine game\"]\n[Site \"?\"]\n[Date \""));
+        assert!(pgn.contains("[White \"First available move\"]\n[Black \"First available move\"]"));
+        let replayed = VariationTree::from_pgn(&pgn).unwrap();
+        assert_eq!(replayed.mainline().len(), game.moves.mainline().len());
+        assert_eq!(replayed.result(), Some(&game.result));
+    }
+
+    struct CountsNewGames(Rc<Cell<usize>>);
+
+    impl Display for CountsNewGames {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            write!(f, "Counts new games")
+        }
+    }
+
+    impl Player for CountsNewGames {
+        fn offer_move(&self, position: &Position) -> ChessMove {
+            FirstMovePlayer.offer_move(position)
+        }
+        fn evalutate(&self, position: &Position) -> isize {
+            FirstMovePlayer.evalutate(position)
+        }
+        fn new_game(&self) {
+            self.0.set(self.0.get() + 1);
+        }
+    }
+
+    #[test]
+    fn engine_games_tell_players_a_new_game_starts() {
+        let white = Rc::new(Cell::new(0));
+        let black = Rc::new(Cell::new(0));
+        play_engine_game(
+            Box::new(CountsNewGames(white.clone())),
+            Box::new(CountsNewGames(black.clone())),
+        );
+        assert_eq!((white.get(), black.get()), (1, 1));
+    }
+
+    struct CountsMoves(Rc<Cell<usize>>);
+
+    impl Display for CountsMoves {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            write!(f, "Counts moves")
+        }
+    }
+
+    impl Player for CountsMoves {
+        fn offer_move(&self, position: &Position) -> ChessMove {
+            FirstMovePlayer.offer_move(position)
+        }
+        fn evalutate(&self, position: &Position) -> isize {
+            FirstMovePlayer.evalutate(position)
+        }
+        fn move_played(&self, _position: &Position, _chess_move: &ChessMove) {
+            self.0.set(self.0.get() + 1);
+        }
+    }
+
+    #[test]
+    fn engine_games_tell_both_players_every_move() {
+        let white = Rc::new(Cell::new(0));
+        let black = Rc::new(Cell::new(0));
+        let game = play_recorded_engine_game(
+            Box::new(CountsMoves(white.clone())),
+            Box::new(CountsMoves(black.clone())),
+        );
+        let plies = game.moves.mainline().len();
+        assert!(plies > 0);
+        assert_eq!((white.get(), black.get()), (plies, plies));
+    }
+
+    struct SlowPlayer;
+
+    impl Display for SlowPlayer {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            write!(f, "Slow")
+        }
+    }
+
+    impl Player for SlowPlayer {
+        fn offer_move(&self, position: &Position) -> ChessMove {
+            thread::sleep(Duration::from_millis(5));
+            FirstMovePlayer.offer_move(position)
+        }
+        fn evalutate(&self, position: &Position) -> isize {
+            FirstMovePlayer.evalutate(position)
+        }
+    }
+
+    fn clock(white: Duration, black: Duration) -> ClockState {
+        ClockState {
+            white_remaining: white,
+            black_remaining: black,
+            ..ClockState::default()
+        }
+    }
+
+    // what is left on its own clock whenever it is asked for a move
+    struct WatchesTheClock(Rc<RefCell<Vec<Duration>>>);
+
+    impl Display for WatchesTheClock {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            write!(f, "Watches the clock")
+        }
+    }
+
+    impl Player for WatchesTheClock {
+        fn offer_move(&self, _position: &Position) -> ChessMove {
+            panic!("clocked games ask for timed moves")
+        }
+        fn evalutate(&self, position: &Position) -> isize {
+            FirstMovePlayer.evalutate(position)
+        }
+        fn offer_move_timed(&self, position: &Position, clock: &ClockState) -> ChessMove {
+            self.0.borrow_mut().push(clock.remaining(&position.to_move));
+            thread::sleep(Duration::from_millis(1));
+            FirstMovePlayer.offer_move(position)
+        }
+    }
+
+    #[test]
+    fn clocked_games_show_players_the_clock() {
+        let seen = Rc::new(RefCell::new(Vec::new()));
+        play_clocked_engine_game(
+            Box::new(WatchesTheClock(seen.clone())),
+            Box::new(FirstMovePlayer),
+            clock(Duration::from_secs(60), Duration::from_secs(60)),
+        );
+        let seen = seen.borrow();
+        assert!(seen.len() > 1);
+        assert_eq!(seen[0], Duration::from_secs(60));
+        assert!(seen.windows(2).all(|pair| pair[1] < pair[0]));
+    }
+
+    #[test]
+    fn results_know_who_won_and_why() {
+        let resigned = GameResult::win_for(PieceColor::Black, WinReason::Resignation);
+        assert_eq!(resigned, GameResult::BlackWin(WinReason::Resignation));
+        assert_eq!(resigned.winner(), Some(PieceColor::Black));
+        assert!(!resigned.is_draw());
+        assert_eq!(resigned.points_for(&PieceColor::White), 0.0);
+        let repeated = GameResult::Draw(DrawReason::Repetition);
+        assert_eq!(repeated.winner(), None);
+        assert!(repeated.is_draw());
+        assert_eq!(repeated.points_for(&PieceColor::Black), 0.5);
+    }
+
+    #[test]
+    fn games_stop_at_the_move_limit_they_are_given() {
+        let game = play_recorded_engine_game(Box::new(FirstMovePlayer), Box::new(FirstMovePlayer));
+        assert_eq!(game.termination, Termination::MoveLimit);
+        assert_eq!(game.moves.mainline().len(), 300);
+        let game = play_adjudicated_engine_game(
+            Box::new(FirstMovePlayer),
+            Box::new(FirstMovePlayer),
+            &AdjudicationPolicy {
+                max_plies: Some(10),
+                ..AdjudicationPolicy::default()
+            },
+        );
+        assert_eq!(
+            (game.result, game.termination, game.moves.mainline().len()),
+            (
+                GameResult::Draw(DrawReason::Adjudicated),
+                Termination::MoveLimit,
+                10
+            )
+        );
+    }
+
+    #[test]
+    fn running_out_of_time_loses() {
+        let game = play_clocked_engine_game(
+            Box::new(FirstMovePlayer),
+            Box::new(SlowPlayer),
+            clock(Duration::from_secs(60), Duration::from_millis(12)),
+        );
+        assert_eq!(game.flagged, Some(PieceColor::Black));
+        assert_eq!(game.result, GameResult::WhiteWin(WinReason::TimeForfeit));
+    }
+
+    #[test]
+    fn a_flag_only_loses_against_mating_material() {
+        let game = play_game(
+            &SlowPlayer,
+            &FirstMovePlayer,
+            &StandardRules,
+            Position::from_fen("8/8/3k4/8/8/8/8/2QK4 w - - 0 1"),
+            Some(clock(Duration::from_millis(1), Duration::from_secs(60))),
+            &AdjudicationPolicy::default(),
+        );
+        assert_eq!(game.flagged, Some(PieceColor::White));
+        assert_eq!(
+            game.result,
+            GameResult::Draw(DrawReason::InsufficientMaterial)
+        );
+        let game = play_game(
+            &FirstMovePlayer,
+            &SlowPlayer,
+            &StandardRules,
+            Position::from_fen("8/8/3k4/8/8/8/8/2QK4 w - - 0 1"),
+            Some(clock(Duration::from_secs(60), Duration::from_millis(1))),
+            &AdjudicationPolicy::default(),
+        );
+        assert_eq!(game.flagged, Some(PieceColor::Black));
+        assert_eq!(game.result, GameResult::WhiteWin(WinReason::TimeForfeit));
+    }
+
+    #[test]
+    fn engines_and_positions_are_shared_between_threads() {
+        let planner = Planner::new().with_depth(1);
+        let position = Position::initial();
+        let moves: Vec<ChessMove> = thread::scope(|scope| {
+            let searches: Vec<_> = (0..2)
+                .map(|_| scope.spawn(|| planner.offer_move(&position)))
+                .collect();
+            searches
+                .into_iter()
+                .map(|search| search.join().unwrap())
+                .collect()
+        });
+        assert!(moves
+            .iter()
+            .all(|chess_move| position.is_move_legal(chess_move)));
+    }
+
+    #[test]
+    fn unclocked_games_never_flag() {
+        let game = play_recorded_engine_game(Box::new(FirstMovePlayer), Box::new(FirstMovePlayer));
+        assert_eq!(game.flagged, None);
+    }
+
+    #[test]
+    fn games_keep_to_their_rules() {
+        let mut game = Game::from_position_with_rules(
+            Position::from_fen("4k3/8/8/8/8/8/8/P7 w - - 0 1"),
+            &HordeRules,
+        );
+        game.make_move(&ChessMove::from_uci_long("a1a3", &game.current_position));
+        assert_eq!(game.history().len(), 1);
+        assert_eq!(game.movetext(), "1. a3");
+        let mut game = Game::from_position_with_rules(
+            Position::from_fen("8/K7/8/8/8/8/8/7k w - - 0 1"),
+            &RacingKingsRules,
+        );
+        game.make_move(&ChessMove::from_uci_long("a7a8", &game.current_position));
+        assert_eq!(game.status(), GameStatus::VariantWin(PieceColor::White));
+        assert_eq!(
+            game.status().result(),
+            Some(GameResult::WhiteWin(WinReason::Variant))
+        );
+        assert_eq!(
+            game.undo_move().map(|_| game.status()),
+            Some(GameStatus::Ongoing { check: false })
+        );
+        let standard = Game::from_starting_position(Position::initial());
+        assert_eq!(standard.rules().name(), "Standard");
+    }
+
+    #[test]
+    fn variant_games_end_by_the_variant() {
+        let capturing = |position: &Position| {
+            let moves = HordeRules.legal_moves(position);
+            moves
+                .iter()
+                .find(|chess_move| position.is_capture(chess_move))
+                .unwrap_or(&moves[0])
+                .clone()
+        };
+        let game = play_game(
+            &CallbackPlayer::new("Horde", capturing),
+            &CallbackPlayer::new("Army", capturing),
+            &HordeRules,
+            Position::from_fen("4k3/8/8/8/8/8/Pr6/8 b - - 0 1"),
+            None,
+            &AdjudicationPolicy::default(),
+        );
+        assert_eq!(game.result, GameResult::BlackWin(WinReason::Variant));
+        assert_eq!(game.termination, Termination::Variant);
+    }
 }
@@ -0,0 +1,429 @@
+use std::io::BufRead;
+use std::io::BufReader;
+use std::time::Duration;
+
+use serde_json::Value;
+
+use crate::uci::legal_move;
+use crate::ClockState;
+use crate::GameStatus;
+use crate::PieceColor;
+use crate::Player;
+use crate::Position;
+
+pub const LICHESS_URL: &str = "https://lichess.org";
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum LichessError {
+    // the request failed or Lichess answered with an error status
+    Http(String),
+    // a line Lichess sent that couldn't be made sense of
+    Malformed(String),
+    // a move Lichess reported that isn't legal where it was played
+    IllegalMove(String),
+}
+
+impl From<ureq::Error> for LichessError {
+    fn from(error: ureq::Error) -> LichessError {
+        LichessError::Http(error.to_string())
+    }
+}
+
+impl From<std::io::Error> for LichessError {
+    fn from(error: std::io::Error) -> LichessError {
+        LichessError::Http(error.to_string())
+    }
+}
+
+// What the event stream of the bot's account tells it about.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum LichessEvent {
+    // `variant` is Lichess's key for it, "standard" or "fromPosition" for
+    // the games the bot can play
+    Challenge {
+        id: String,
+        challenger: String,
+        variant: String,
+    },
+    GameStart(String),
+    GameFinish(String),
+    // challenges taken back or declined, and anything newer
+    Other,
+}
+
+impl LichessEvent {
+    // None for the empty lines Lichess keeps the stream alive with.
+    pub fn parse(line: &str) -> Result<Option<LichessEvent>, LichessError> {
+        if line.trim().is_empty() {
+            return Ok(None);
+        }
+        let event = parse_json(line)?;
+        let text = |value: &Value| value.as_str().unwrap_or_default().to_string();
+        Ok(Some(match event["type"].as_str() {
+            Some("challenge") => {
+                let challenge = &event["challenge"];
+                LichessEvent::Challenge {
+                    id: text(&challenge["id"]),
+                    challenger: text(&challenge["challenger"]["id"]),
+                    variant: text(&challenge["variant"]["key"]),
+                }
+            }
+            Some("gameStart") => LichessEvent::GameStart(text(&event["game"]["gameId"])),
+            Some("gameFinish") => LichessEvent::GameFinish(text(&event["game"]["gameId"])),
+            _ => LichessEvent::Other,
+        }))
+    }
+}
+
+fn parse_json(line: &str) -> Result<Value, LichessError> {
+    serde_json::from_str(line).map_err(|_| LichessError::Malformed(line.to_string()))
+}
+
+fn playable_variant(variant: &str) -> bool {
+    matches!(variant, "standard" | "fromPosition")
+}
+
+// One game as the bot's game stream describes it. Lichess sends every move
+// so far with each update, so the position is always replayed from the
+// start.
+#[derive(Clone, PartialEq, Debug)]
+pub struct LichessGame {
+    pub id: String,
+    pub color: PieceColor,
+    pub initial_position: Position,
+    pub position: Position,
+    // moves in UCI notation, as Lichess sends and takes them
+    pub moves: Vec<String>,
+    // None in games without a clock
+    pub clock: Option<ClockState>,
+    // "started" while the game goes on
+    pub status: String,
+    // how many moves had been played when the bot last replied, so that
+    // lines which don't move the game on don't get the move posted again
+    replied_at: Option<usize>,
+}
+
+impl LichessGame {
+    // From the first line of a game stream, `account` being the bot's own
+    // user id.
+    pub fn from_game_full(line: &str, account: &str) -> Result<LichessGame, LichessError> {
+        let full = parse_json(line)?;
+        if full["type"] != "gameFull" {
+            return Err(LichessError::Malformed(line.to_string()));
+        }
+        let is_account = |player: &Value| {
+            player["id"]
+                .as_str()
+                .is_some_and(|id| id.eq_ignore_ascii_case(account))
+        };
+        let color = if is_account(&full["white"]) {
+            PieceColor::White
+        } else if is_account(&full["black"]) {
+            PieceColor::Black
+        } else {
+            return Err(LichessError::Malformed(line.to_string()));
+        };
+        let initial_position = match full["initialFen"].as_str() {
+            None | Some("startpos") => Position::initial(),
+            Some(fen) => Position::try_from_fen(fen)
+                .map_err(|_| LichessError::Malformed(line.to_string()))?,
+        };
+        let mut game = LichessGame {
+            id: full["id"].as_str().unwrap_or_default().to_string(),
+            color,
+            position: initial_position.clone(),
+            initial_position,
+            moves: Vec::new(),
+            clock: None,
+            status: String::new(),
+            replied_at: None,
+        };
+        game.apply_state(&full["state"], None)?;
+        Ok(game)
+    }
+
+    // Takes in a later line of the game stream, telling `player` about the
+    // moves it brings. Chat and the opponent leaving are ignored.
+    pub fn update(&mut self, line: &str, player: &dyn Player) -> Result<(), LichessError> {
+        if line.trim().is_empty() {
+            return Ok(());
+        }
+        let state = parse_json(line)?;
+        if state["type"] == "gameState" {
+            self.apply_state(&state, Some(player))?;
+        }
+        Ok(())
+    }
+
+    fn apply_state(
+        &mut self,
+        state: &Value,
+        player: Option<&dyn Player>,
+    ) -> Result<(), LichessError> {
+        let moves: Vec<String> = state["moves"]
+            .as_str()
+            .unwrap_or_default()
+            .split_whitespace()
+            .map(String::from)
+            .collect();
+        let mut position = self.initial_position.clone();
+        for (played, uci) in moves.iter().enumerate() {
+            let chess_move =
+                legal_move(uci, &position).ok_or_else(|| LichessError::IllegalMove(uci.clone()))?;
+            if played >= self.moves.len() {
+                if let Some(player) = player {
+                    player.move_played(&position, &chess_move);
+                }
+            }
+            position = position.after_move(&chess_move);
+        }
+        let millis = |key: &str| state[key].as_u64().map(Duration::from_millis);
+        self.clock = match (millis("wtime"), millis("btime")) {
+            (Some(white_remaining), Some(black_remaining)) => Some(ClockState {
+                white_remaining,
+                black_remaining,
+                white_increment: millis("winc").unwrap_or_default(),
+                black_increment: millis("binc").unwrap_or_default(),
+                moves_to_go: None,
+            }),
+            _ => None,
+        };
+        self.status = state["status"].as_str().unwrap_or("started").to_string();
+        // a takeback can bring the bot's turn back round
+        if self.replied_at.is_some_and(|ply| moves.len() < ply) {
+            self.replied_at = None;
+        }
+        self.position = position;
+        self.moves = moves;
+        Ok(())
+    }
+
+    pub fn is_over(&self) -> bool {
+        self.status != "started" || GameStatus::of(&self.position).is_over()
+    }
+
+    // The move `player` wants posted, when it is the bot's turn and it
+    // hasn't already replied in this position.
+    pub fn reply(&mut self, player: &dyn Player) -> Option<String> {
+        if self.is_over()
+            || self.position.to_move != self.color
+            || self.replied_at.is_some_and(|ply| self.moves.len() <= ply)
+        {
+            return None;
+        }
+        self.replied_at = Some(self.moves.len());
+        let chess_move = match &self.clock {
+            Some(clock) => player.offer_move_timed(&self.position, clock),
+            None => player.offer_move(&self.position),
+        };
+        Some(chess_move.to_uci_long(&self.position))
+    }
+}
+
+// Plays `player` on Lichess through the Bot API, one game at a time.
+// Challenges to standard games, from the usual start or from a position,
+// are accepted and every other variant is declined.
+pub struct LichessBot {
+    player: Box<dyn Player>,
+    token: String,
+    base_url: String,
+    agent: ureq::Agent,
+    // the bot's user id, which tells it which color it has
+    pub account: String,
+}
+
+impl LichessBot {
+    // `token` is an API token of a bot account with the bot:play scope.
+    pub fn new(token: &str, player: Box<dyn Player>) -> Result<LichessBot, LichessError> {
+        LichessBot::with_base_url(token, player, LICHESS_URL)
+    }
+
+    pub fn with_base_url(
+        token: &str,
+        player: Box<dyn Player>,
+        base_url: &str,
+    ) -> Result<LichessBot, LichessError> {
+        let mut bot = LichessBot {
+            player,
+            token: token.to_string(),
+            base_url: base_url.trim_end_matches('/').to_string(),
+            agent: ureq::Agent::new(),
+            account: String::new(),
+        };
+        let account = parse_json(&bot.get("/api/account")?.into_string()?)?;
+        bot.account = account["id"]
+            .as_str()
+            .ok_or_else(|| LichessError::Malformed(account.to_string()))?
+            .to_string();
+        Ok(bot)
+    }
+
+    fn get(&self, path: &str) -> Result<ureq::Response, LichessError> {
+        Ok(self
+            .agent
+            .get(&format!("{}{}", self.base_url, path))
+            .set("Authorization", &format!("Bearer {}", self.token))
+            .call()?)
+    }
+
+    fn post(&self, path: &str, form: &[(&str, &str)]) -> Result<(), LichessError> {
+        self.agent
+            .post(&format!("{}{}", self.base_url, path))
+            .set("Authorization", &format!("Bearer {}", self.token))
+            .send_form(form)?;
+        Ok(())
+    }
+
+    // Answers challenges and plays the games they start until the event
+    // stream closes.
+    pub fn run(&self) -> Result<(), LichessError> {
+        let events = BufReader::new(self.get("/api/stream/event")?.into_reader());
+        for line in events.lines() {
+            match LichessEvent::parse(&line?)? {
+                Some(LichessEvent::Challenge { id, variant, .. }) => {
+                    if playable_variant(&variant) {
+                        self.post(&format!("/api/challenge/{id}/accept"), &[])?;
+                    } else {
+                        self.post(
+                            &format!("/api/challenge/{id}/decline"),
+                            &[("reason", "variant")],
+                        )?;
+                    }
+                }
+                Some(LichessEvent::GameStart(id)) => self.play_game(&id)?,
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+
+    // Follows the game's stream, posting the player's moves, until it is
+    // over.
+    pub fn play_game(&self, id: &str) -> Result<(), LichessError> {
+        let stream = BufReader::new(
+            self.get(&format!("/api/bot/game/stream/{id}"))?
+                .into_reader(),
+        );
+        let mut lines = stream.lines();
+        let first = loop {
+            match lines.next() {
+                Some(line) if line.as_ref().is_ok_and(|line| line.trim().is_empty()) => continue,
+                Some(line) => break line?,
+                None => return Ok(()),
+            }
+        };
+        let mut game = LichessGame::from_game_full(&first, &self.account)?;
+        self.player.new_game();
+        loop {
+            if let Some(reply) = game.reply(self.player.as_ref()) {
+                self.post(&format!("/api/bot/game/{id}/move/{reply}"), &[])?;
+            }
+            if game.is_over() {
+                return Ok(());
+            }
+            match lines.next() {
+                Some(line) => game.update(&line?, self.player.as_ref())?,
+                None => return Ok(()),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{FirstMovePlayer, Planner};
+
+    use super::*;
+
+    #[test]
+    fn reads_the_event_stream() {
+        assert_eq!(LichessEvent::parse(""), Ok(None));
+        assert_eq!(
+            LichessEvent::parse(
+                r#"{"type":"challenge","challenge":{"id":"7pGLxJ4Q","challenger":{"id":"someone"},"variant":{"key":"atomic"}}}"#
+            ),
+            Ok(Some(LichessEvent::Challenge {
+                id: String::from("7pGLxJ4Q"),
+                challenger: String::from("someone"),
+                variant: String::from("atomic"),
+            }))
+        );
+        assert_eq!(
+            LichessEvent::parse(r#"{"type":"gameStart","game":{"gameId":"abcd1234"}}"#),
+            Ok(Some(LichessEvent::GameStart(String::from("abcd1234"))))
+        );
+        assert!(LichessEvent::parse("{").is_err());
+        assert!(!playable_variant("atomic"));
+    }
+
+    #[test]
+    fn replies_only_on_its_own_turn() {
+        let player = FirstMovePlayer;
+        let mut game = LichessGame::from_game_full(
+            r#"{"type":"gameFull","id":"abcd1234","white":{"id":"someone"},"black":{"id":"ChessticotBot"},"initialFen":"startpos","state":{"type":"gameState","moves":"","wtime":60000,"btime":60000,"winc":1000,"binc":1000,"status":"started"}}"#,
+            "chessticotbot",
+        )
+        .unwrap();
+        assert_eq!(game.color, PieceColor::Black);
+        assert_eq!(game.reply(&player), None);
+        game.update(
+            r#"{"type":"gameState","moves":"e2e4","wtime":59000,"btime":60000,"winc":1000,"binc":1000,"status":"started"}"#,
+            &player,
+        )
+        .unwrap();
+        let reply = game.reply(&player).unwrap();
+        assert!(legal_move(&reply, &game.position).is_some());
+        // neither a keep-alive nor an offer before the move comes back
+        // gets it posted twice
+        game.update("\n", &player).unwrap();
+        assert_eq!(game.reply(&player), None);
+        game.update(
+            r#"{"type":"gameState","moves":"e2e4","wtime":59000,"btime":60000,"winc":1000,"binc":1000,"status":"started","wdraw":true}"#,
+            &player,
+        )
+        .unwrap();
+        assert_eq!(game.reply(&player), None);
+        assert_eq!(game.clock.unwrap().white_remaining, Duration::from_secs(59));
+        assert_eq!(
+            game.update(
+                r#"{"type":"gameState","moves":"e2e5","status":"started"}"#,
+                &player
+            ),
+            Err(LichessError::IllegalMove(String::from("e2e5")))
+        );
+        game.update(
+            r#"{"type":"gameState","moves":"e2e4","status":"resign"}"#,
+            &player,
+        )
+        .unwrap();
+        assert!(game.is_over());
+        assert_eq!(game.reply(&player), None);
+        assert!(matches!(
+            LichessGame::from_game_full(
+                r#"{"type":"gameFull","id":"abcd1234","white":{"id":"chessticotbot"},"black":{"id":"someone"},"initialFen":"9/8/8/8/8/8/8/8 w - - 0 1","state":{"type":"gameState","moves":"","status":"started"}}"#,
+                "chessticotbot",
+            ),
+            Err(LichessError::Malformed(_))
+        ));
+    }
+
+    #[test]
+    fn promotions_come_and_go_with_their_piece() {
+        let player = Planner::new().with_depth(2);
+        let mut game = LichessGame::from_game_full(
+            r#"{"type":"gameFull","id":"abcd1234","white":{"id":"chessticotbot"},"black":{"id":"someone"},"initialFen":"6k1/1P6/8/8/8/4K3/p7/8 b - - 0 1","state":{"type":"gameState","moves":"","status":"started"}}"#,
+            "chessticotbot",
+        )
+        .unwrap();
+        game.update(
+            r#"{"type":"gameState","moves":"a2a1q","status":"started"}"#,
+            &player,
+        )
+        .unwrap();
+        assert_eq!(
+            game.position,
+            Position::from_fen("6k1/1P6/8/8/8/4K3/8/q7 w - - 0 2")
+        );
+        assert_eq!(game.reply(&player), Some(String::from("b7b8q")));
+    }
+}
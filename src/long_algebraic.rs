@@ -0,0 +1,144 @@
+use crate::san::{check_suffix, is_algebraic_square, kind_from_letter, piece_letter, SanNotation};
+use crate::ChessMove;
+use crate::Coords;
+use crate::PieceKind;
+use crate::Position;
+use crate::SanError;
+
+impl ChessMove {
+    // Like SAN but always spelling out the origin square, as in `Ng1-f3`,
+    // `e4xd5+` or `e7-e8=Q#`.
+    pub fn to_long_algebraic(&self, position: &Position) -> String {
+        let notation = SanNotation::default();
+        let mut long = match self {
+            ChessMove::CastleRight => String::from("O-O"),
+            ChessMove::CastleLeft => String::from("O-O-O"),
+            _ => {
+                let movement = self.movement().expect("only castling has no movement");
+                let kind = position
+                    .piece_at(&movement.origin)
+                    .map_or(PieceKind::Pawn, |piece| piece.kind);
+                let is_capture = position.is_capture(self);
+                let mut long = String::from(piece_letter(&kind, &notation));
+                long.push_str(&movement.origin.to_algebraic());
+                long.push(if is_capture { 'x' } else { '-' });
+                long.push_str(&movement.destination.to_algebraic());
+                if let ChessMove::Promotion(_, promoted_to) = self {
+                    long.push('=');
+                    long.push_str(piece_letter(promoted_to, &notation));
+                }
+                long
+            }
+        };
+        long.push_str(check_suffix(self, position));
+        long
+    }
+
+    pub fn from_long_algebraic(long: &str, position: &Position) -> Result<ChessMove, SanError> {
+        let malformed = || SanError::Malformed(long.to_string());
+        let illegal = || SanError::Illegal(long.to_string());
+        let trimmed = long.trim_end_matches(['+', '#', '!', '?']);
+        let castle = match trimmed {
+            "O-O" | "0-0" => Some(ChessMove::CastleRight),
+            "O-O-O" | "0-0-0" => Some(ChessMove::CastleLeft),
+            _ => None,
+        };
+        if let Some(castle) = castle {
+            return if position.is_move_legal(&castle) {
+                Ok(castle)
+            } else {
+                Err(illegal())
+            };
+        }
+
+        let notation = SanNotation::default();
+        let kind = trimmed
+            .chars()
+            .next()
+            .and_then(|letter| kind_from_letter(letter, &notation));
+        let rest = if kind.is_some() {
+            &trimmed[1..]
+        } else {
+            trimmed
+        };
+        if !rest.is_ascii() || rest.len() < 5 {
+            return Err(malformed());
+        }
+        let (origin, separator, destination, promotion) =
+            (&rest[..2], &rest[2..3], &rest[3..5], &rest[5..]);
+        if !is_algebraic_square(origin) || !is_algebraic_square(destination) {
+            return Err(malformed());
+        }
+        let promotion = match promotion.strip_prefix('=').unwrap_or(promotion) {
+            "" => None,
+            letter => match letter
+                .chars()
+                .next()
+                .and_then(|c| kind_from_letter(c, &notation))
+            {
+                Some(target) if letter.len() == 1 => Some(target),
+                _ => return Err(malformed()),
+            },
+        };
+
+        let origin = Coords::from_algebraic(origin);
+        let destination = Coords::from_algebraic(destination);
+        let moved_kind = position.piece_at(&origin).map(|piece| piece.kind);
+        if moved_kind.is_none() || moved_kind != Some(kind.unwrap_or(PieceKind::Pawn)) {
+            return Err(illegal());
+        }
+        let chess_move =
+            ChessMove::infer(origin, destination, promotion, position).map_err(|_| illegal())?;
+        let separator_matches = match separator {
+            "x" => position.is_capture(&chess_move),
+            "-" => !position.is_capture(&chess_move),
+            _ => return Err(malformed()),
+        };
+        if separator_matches && position.is_move_legal(&chess_move) {
+            Ok(chess_move)
+        } else {
+            Err(illegal())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn formats_long_algebraic() {
+        let position = Position::initial();
+        let knight_move = ChessMove::from_san("Nf3", &position).unwrap();
+        assert_eq!(knight_move.to_long_algebraic(&position), "Ng1-f3");
+
+        let position = Position::from_fen("4k3/8/8/3q4/4P3/8/8/4K3 w - - 0 1");
+        let capture = ChessMove::from_san("exd5", &position).unwrap();
+        assert_eq!(capture.to_long_algebraic(&position), "e4xd5");
+
+        let position = Position::from_fen("k7/4P3/1K6/8/8/8/8/8 w - - 0 1");
+        let promotion = ChessMove::from_san("e8=Q", &position).unwrap();
+        assert_eq!(promotion.to_long_algebraic(&position), "e7-e8=Q#");
+    }
+
+    #[test]
+    fn parses_long_algebraic() {
+        let position = Position::from_fen("k7/4P3/1K6/8/8/8/8/7R w - - 0 1");
+        for long in ["e7-e8=Q#", "Rh1-h8#", "Kb6-c6"] {
+            let chess_move = ChessMove::from_long_algebraic(long, &position).unwrap();
+            assert_eq!(chess_move.to_long_algebraic(&position), long);
+        }
+        assert_eq!(
+            ChessMove::from_long_algebraic("Rh1xh8", &position),
+            Err(SanError::Illegal(String::from("Rh1xh8")))
+        );
+        assert_eq!(
+            ChessMove::from_long_algebraic("Nh1-h8", &position),
+            Err(SanError::Illegal(String::from("Nh1-h8")))
+        );
+        assert_eq!(
+            ChessMove::from_long_algebraic("h1h8", &position),
+            Err(SanError::Malformed(String::from("h1h8")))
+        );
+    }
+}
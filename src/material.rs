@@ -0,0 +1,376 @@
+use crate::PieceColor;
+use crate::PieceKind;
+use crate::Position;
+
+// Signature order, strongest piece first.
+const SIGNATURE_ORDER: [(PieceKind, char); 6] = [
+    (PieceKind::King, 'K'),
+    (PieceKind::Queen, 'Q'),
+    (PieceKind::Rook, 'R'),
+    (PieceKind::Bishop, 'B'),
+    (PieceKind::Knight, 'N'),
+    (PieceKind::Pawn, 'P'),
+];
+
+// Traditional point values, with the king counting for nothing.
+fn points(kind: &PieceKind) -> usize {
+    match kind {
+        PieceKind::Pawn => 1,
+        PieceKind::Knight | PieceKind::Bishop => 3,
+        PieceKind::Rook => 5,
+        PieceKind::Queen => 9,
+        PieceKind::King => 0,
+    }
+}
+
+// Past this many points of pieces for either side it's not an endgame yet.
+const ENDGAME_PIECE_POINTS: usize = 13;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum EndgameClass {
+    // neither side can ever mate: KvK, KNvK, and kings with bishops that
+    // all stand on squares of one color, KBvK and KBvKB among them
+    InsufficientMaterial,
+    KPvK,
+    KQvK,
+    KRvK,
+    KBNvK,
+    // kings and pawns only
+    Pawn,
+    // besides kings and pawns, only pieces of one kind: rooks, queens, or
+    // minor pieces
+    Rook,
+    Queen,
+    MinorPiece,
+    // an endgame with any other combination of pieces
+    Mixed,
+    NotEndgame,
+}
+
+// How many pieces of each kind one side has.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct MaterialCount {
+    // indexed by PieceKind::index
+    counts: [usize; 6],
+}
+
+impl MaterialCount {
+    pub fn count(&self, kind: PieceKind) -> usize {
+        self.counts[kind.index()]
+    }
+
+    // Pawns and pieces at their traditional values, the king counting for
+    // nothing.
+    pub fn points(&self) -> usize {
+        self.piece_points() + self.count(PieceKind::Pawn)
+    }
+
+    fn piece_points(&self) -> usize {
+        PieceKind::all()
+            .filter(|kind| **kind != PieceKind::Pawn)
+            .map(|kind| points(kind) * self.count(*kind))
+            .sum()
+    }
+
+    fn pieces(&self) -> usize {
+        self.count(PieceKind::Knight)
+            + self.count(PieceKind::Bishop)
+            + self.count(PieceKind::Rook)
+            + self.count(PieceKind::Queen)
+    }
+
+    fn is_bare(&self) -> bool {
+        self.pieces() == 0 && self.count(PieceKind::Pawn) == 0
+    }
+
+    fn only(&self, kinds: &[PieceKind]) -> bool {
+        [
+            PieceKind::Knight,
+            PieceKind::Bishop,
+            PieceKind::Rook,
+            PieceKind::Queen,
+        ]
+        .iter()
+        .all(|kind| kinds.contains(kind) || self.count(*kind) == 0)
+    }
+
+    // total points first, then the piece counts in signature order
+    fn strength(&self) -> (usize, Vec<usize>) {
+        (
+            self.points(),
+            SIGNATURE_ORDER
+                .iter()
+                .map(|(kind, _)| self.count(*kind))
+                .collect(),
+        )
+    }
+
+    fn signature(&self) -> String {
+        SIGNATURE_ORDER
+            .iter()
+            .flat_map(|(kind, letter)| std::iter::repeat_n(*letter, self.count(*kind)))
+            .collect()
+    }
+}
+
+impl Position {
+    pub fn material(&self, color: PieceColor) -> MaterialCount {
+        let mut counts = [0; 6];
+        for kind in PieceKind::all() {
+            counts[kind.index()] = self.bitboards().pieces(&color, kind).count() as usize;
+        }
+        MaterialCount { counts }
+    }
+
+    // Every piece count of both sides packed into one number, white's in
+    // the low half, for keying tables by material without building a
+    // signature. Pawns get seven bits, enough for all of Horde's, and the
+    // other kinds five. A count too big for its field, which takes more
+    // than thirty promotions to one kind, is kept at the field's largest
+    // value, so only such positions can share a key.
+    pub fn material_key(&self) -> u64 {
+        let mut key = 0;
+        for color in [PieceColor::White, PieceColor::Black] {
+            let material = self.material(color);
+            for kind in PieceKind::all() {
+                let (shift, bits) = match kind {
+                    PieceKind::Pawn => (0, 7),
+                    _ => (7 + 5 * (kind.index() - 1), 5),
+                };
+                let count = material.count(*kind).min((1 << bits) - 1) as u64;
+                key |= count << (32 * color.index() + shift);
+            }
+        }
+        key
+    }
+
+    // The side with more material, or white when material is level.
+    pub fn stronger_side(&self) -> PieceColor {
+        let white = self.material(PieceColor::White);
+        let black = self.material(PieceColor::Black);
+        if black.strength() > white.strength() {
+            PieceColor::Black
+        } else {
+            PieceColor::White
+        }
+    }
+
+    // Both sides' pieces like "KRPvKR", the stronger side first, so that a
+    // position and its color-swapped mirror share a signature.
+    pub fn material_signature(&self) -> String {
+        let strong = self.stronger_side();
+        format!(
+            "{}v{}",
+            self.material(strong).signature(),
+            self.material(strong.opposite()).signature()
+        )
+    }
+
+    // Piece counts white has over black, strongest piece first, leaving out
+    // the kinds both have as many of. A knight for a pawn is
+    // [(Knight, 1), (Pawn, -1)].
+    pub fn material_imbalance(&self) -> Vec<(PieceKind, i8)> {
+        let white = self.material(PieceColor::White);
+        let black = self.material(PieceColor::Black);
+        SIGNATURE_ORDER
+            .iter()
+            .map(|(kind, _)| (*kind, white.count(*kind) as i8 - black.count(*kind) as i8))
+            .filter(|(_, difference)| *difference != 0)
+            .collect()
+    }
+
+    // Whether `color` could still mate with the other side's help, which
+    // decides a time forfeit. A lone king never can, nor can a king and a
+    // single minor piece against a lone king.
+    pub fn has_mating_material(&self, color: PieceColor) -> bool {
+        let side = self.material(color);
+        let lone_minor = side.count(PieceKind::Pawn) == 0
+            && side.pieces() == 1
+            && side.count(PieceKind::Knight) + side.count(PieceKind::Bishop) == 1;
+        !(side.is_bare() || lone_minor && self.material(color.opposite()).is_bare())
+    }
+
+    pub fn endgame_class(&self) -> EndgameClass {
+        let strong_side = self.stronger_side();
+        let strong = self.material(strong_side);
+        let weak = self.material(strong_side.opposite());
+        if strong.piece_points() > ENDGAME_PIECE_POINTS
+            || weak.piece_points() > ENDGAME_PIECE_POINTS
+        {
+            return EndgameClass::NotEndgame;
+        }
+        let only_bishops = |side: &MaterialCount| {
+            side.count(PieceKind::Pawn) == 0 && side.pieces() == side.count(PieceKind::Bishop)
+        };
+        if only_bishops(&strong) && only_bishops(&weak) {
+            let mut bishop_square_colors = self
+                .bitboards()
+                .kind(&PieceKind::Bishop)
+                .squares()
+                .map(|square| (square.x + square.y) % 2);
+            let first = bishop_square_colors.next();
+            if bishop_square_colors.all(|color| Some(color) == first) {
+                return EndgameClass::InsufficientMaterial;
+            }
+        }
+        let strong_pawns = strong.count(PieceKind::Pawn);
+        if weak.is_bare() {
+            let minors = strong.count(PieceKind::Knight) + strong.count(PieceKind::Bishop);
+            if strong_pawns == 0 && strong.pieces() == minors && minors <= 1 {
+                return EndgameClass::InsufficientMaterial;
+            }
+            if strong_pawns == 0 && strong.pieces() == 1 && strong.count(PieceKind::Queen) == 1 {
+                return EndgameClass::KQvK;
+            }
+            if strong_pawns == 0 && strong.pieces() == 1 && strong.count(PieceKind::Rook) == 1 {
+                return EndgameClass::KRvK;
+            }
+            if strong_pawns == 0
+                && strong.pieces() == 2
+                && strong.count(PieceKind::Knight) == 1
+                && strong.count(PieceKind::Bishop) == 1
+            {
+                return EndgameClass::KBNvK;
+            }
+            if strong_pawns == 1 && strong.pieces() == 0 {
+                return EndgameClass::KPvK;
+            }
+        }
+        let sides = [strong, weak];
+        if sides.iter().all(|side| side.pieces() == 0) {
+            EndgameClass::Pawn
+        } else if sides.iter().all(|side| side.only(&[PieceKind::Rook])) {
+            EndgameClass::Rook
+        } else if sides.iter().all(|side| side.only(&[PieceKind::Queen])) {
+            EndgameClass::Queen
+        } else if sides
+            .iter()
+            .all(|side| side.only(&[PieceKind::Knight, PieceKind::Bishop]))
+        {
+            EndgameClass::MinorPiece
+        } else {
+            EndgameClass::Mixed
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{HordeRules, RuleSet};
+
+    use super::*;
+
+    #[test]
+    fn signature_lists_the_stronger_side_first() {
+        assert_eq!(
+            Position::initial().material_signature(),
+            "KQRRBBNNPPPPPPPPvKQRRBBNNPPPPPPPP"
+        );
+        let position = Position::from_fen("8/8/3k4/3r4/8/3R4/3P4/3K4 b - - 0 1");
+        assert_eq!(position.material_signature(), "KRPvKR");
+        assert_eq!(position.mirror().material_signature(), "KRPvKR");
+        assert_eq!(position.mirror().stronger_side(), PieceColor::Black);
+    }
+
+    #[test]
+    fn counts_each_side_by_kind() {
+        let position = Position::from_fen("8/8/3k4/3r4/8/3R4/3P4/3K4 b - - 0 1");
+        let white = position.material(PieceColor::White);
+        assert_eq!(white.count(PieceKind::Rook), 1);
+        assert_eq!(white.count(PieceKind::Pawn), 1);
+        assert_eq!(white.count(PieceKind::Queen), 0);
+        assert_eq!(white.points(), 6);
+        assert_eq!(Position::initial().material(PieceColor::Black).points(), 39);
+        assert_ne!(position.material_key(), position.mirror().material_key());
+        assert_eq!(
+            position.material_key(),
+            Position::from_fen("3k4/8/8/r7/8/8/P7/R3K3 w - - 0 1").material_key()
+        );
+    }
+
+    #[test]
+    fn material_keys_tell_horde_pawn_counts_apart() {
+        let horde = HordeRules.starting_position();
+        assert_eq!(horde.material(PieceColor::White).count(PieceKind::Pawn), 36);
+        // twenty pawns fewer is what a four-bit count would have wrapped to
+        let fewer =
+            Position::from_fen("rnbqkbnr/pppppppp/8/1PP2PP1/PPPPPPPP/PPPPPPPP/8/8 w kq - 0 1");
+        assert_eq!(fewer.material(PieceColor::White).count(PieceKind::Pawn), 20);
+        assert_ne!(horde.material_key(), fewer.material_key());
+        assert_ne!(horde.material_key(), Position::initial().material_key());
+    }
+
+    #[test]
+    fn imbalance_counts_pieces_white_is_up() {
+        assert!(Position::initial().material_imbalance().is_empty());
+        let knight_for_pawn = Position::from_fen("4k3/pp6/8/8/8/8/1P6/1N2K3 w - - 0 1");
+        assert_eq!(
+            knight_for_pawn.material_imbalance(),
+            vec![(PieceKind::Knight, 1), (PieceKind::Pawn, -1)]
+        );
+        let exchange = Position::from_fen("r3k3/8/8/8/8/8/8/1B2K3 w - - 0 1");
+        assert_eq!(
+            exchange.material_imbalance(),
+            vec![(PieceKind::Rook, -1), (PieceKind::Bishop, 1)]
+        );
+    }
+
+    #[test]
+    fn mating_material_needs_more_than_a_lone_minor() {
+        let cases = [
+            ("8/8/3k4/8/8/8/8/3K4 w - - 0 1", false),
+            ("8/8/3k4/8/8/8/8/2NK4 w - - 0 1", false),
+            ("8/7p/3k4/8/8/8/8/2NK4 w - - 0 1", true),
+            ("8/8/3k4/8/8/8/8/1NNK4 w - - 0 1", true),
+            ("8/8/3k4/8/8/8/3P4/3K4 w - - 0 1", true),
+        ];
+        for (fen, white_can_mate) in cases {
+            let position = Position::from_fen(fen);
+            assert_eq!(
+                position.has_mating_material(PieceColor::White),
+                white_can_mate,
+                "{}",
+                fen
+            );
+        }
+        assert!(Position::from_fen("8/7p/3k4/8/8/8/8/2NK4 w - - 0 1")
+            .has_mating_material(PieceColor::Black));
+    }
+
+    #[test]
+    fn classifies_elementary_endgames() {
+        let cases = [
+            (
+                "8/8/3k4/8/8/8/8/3K4 w - - 0 1",
+                EndgameClass::InsufficientMaterial,
+            ),
+            (
+                "8/8/3k4/8/8/8/8/2NK4 w - - 0 1",
+                EndgameClass::InsufficientMaterial,
+            ),
+            (
+                "8/8/3k4/4b3/8/8/8/2BK4 w - - 0 1",
+                EndgameClass::InsufficientMaterial,
+            ),
+            ("8/8/3k4/3b4/8/8/8/2BK4 w - - 0 1", EndgameClass::MinorPiece),
+            ("8/8/3k4/8/8/8/3P4/3K4 w - - 0 1", EndgameClass::KPvK),
+            ("8/8/3k4/8/8/8/8/2QK4 b - - 0 1", EndgameClass::KQvK),
+            ("8/2r5/3k4/8/8/8/8/3K4 w - - 0 1", EndgameClass::KRvK),
+            ("8/8/3k4/8/8/8/8/1BNK4 w - - 0 1", EndgameClass::KBNvK),
+            ("8/5p2/3k4/8/8/8/3P4/3K4 w - - 0 1", EndgameClass::Pawn),
+            ("8/8/3k4/3r4/8/3R4/3P4/3K4 w - - 0 1", EndgameClass::Rook),
+            (
+                "8/8/1n1k4/8/8/8/3P4/2BK4 w - - 0 1",
+                EndgameClass::MinorPiece,
+            ),
+            ("8/8/1n1k4/8/8/8/3P4/2QK4 w - - 0 1", EndgameClass::Mixed),
+            (
+                "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+                EndgameClass::NotEndgame,
+            ),
+        ];
+        for (fen, class) in cases {
+            assert_eq!(Position::from_fen(fen).endgame_class(), class, "{}", fen);
+        }
+    }
+}
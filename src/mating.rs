@@ -0,0 +1,523 @@
+use std::sync::{LazyLock, OnceLock};
+
+use crate::all_squares;
+use crate::ChessMove;
+use crate::EndgameClass;
+use crate::PieceKind;
+use crate::Position;
+
+// Distance to mate for every KQvK, KRvK and KBNvK position, worked out
+// backwards from the mates the first time an ending comes up. Knowing the
+// exact distance means the stronger side can always pick a move that gets
+// closer, which search and evaluation alone can't guarantee for KBNvK.
+
+// stored as plies to mate plus one, so that zero can mean not lost
+const UNKNOWN: u8 = 0;
+const INVALID: u8 = u8::MAX;
+
+const STRONG_TO_MOVE: usize = 0;
+const WEAK_TO_MOVE: usize = 1;
+
+// slots in a layout, the strong side's other pieces follow
+const WEAK_KING: usize = 0;
+const STRONG_KING: usize = 1;
+
+const KING_STEPS: [(i8, i8); 8] = [
+    (-1, -1),
+    (0, -1),
+    (1, -1),
+    (-1, 0),
+    (1, 0),
+    (-1, 1),
+    (0, 1),
+    (1, 1),
+];
+const KNIGHT_JUMPS: [(i8, i8); 8] = [
+    (1, 2),
+    (2, 1),
+    (2, -1),
+    (1, -2),
+    (-1, -2),
+    (-2, -1),
+    (-2, 1),
+    (-1, 2),
+];
+// the rook's directions first, then the bishop's
+const QUEEN_DIRECTIONS: [(i8, i8); 8] = [
+    (0, -1),
+    (-1, 0),
+    (1, 0),
+    (0, 1),
+    (-1, -1),
+    (1, -1),
+    (-1, 1),
+    (1, 1),
+];
+
+fn step(square: usize, (dx, dy): (i8, i8)) -> Option<usize> {
+    let x = (square % 8) as i8 + dx;
+    let y = (square / 8) as i8 + dy;
+    ((0..8).contains(&x) && (0..8).contains(&y)).then(|| y as usize * 8 + x as usize)
+}
+
+fn kings_touch(a: usize, b: usize) -> bool {
+    (a % 8).abs_diff(b % 8) <= 1 && (a / 8).abs_diff(b / 8) <= 1
+}
+
+// a1 is dark, and a1 is x = 0, y = 7 here
+fn is_dark(square: usize) -> bool {
+    (square % 8 + square / 8) % 2 == 1
+}
+
+fn squares_in_reach(square: usize, steps: &[(i8, i8)]) -> u64 {
+    steps
+        .iter()
+        .filter_map(|direction| step(square, *direction))
+        .fold(0, |reach, target| reach | 1 << target)
+}
+
+static KING_REACH: LazyLock<[u64; 64]> =
+    LazyLock::new(|| std::array::from_fn(|square| squares_in_reach(square, &KING_STEPS)));
+static KNIGHT_REACH: LazyLock<[u64; 64]> =
+    LazyLock::new(|| std::array::from_fn(|square| squares_in_reach(square, &KNIGHT_JUMPS)));
+
+// Every square from a square to the edge of the board, by direction.
+static RAYS: LazyLock<[[u64; 64]; 8]> = LazyLock::new(|| {
+    std::array::from_fn(|direction| {
+        std::array::from_fn(|from| {
+            let mut ray = 0;
+            let mut square = from;
+            while let Some(next) = step(square, QUEEN_DIRECTIONS[direction]) {
+                ray |= 1 << next;
+                square = next;
+            }
+            ray
+        })
+    })
+});
+
+// Squares a piece on `from` attacks, sliders stopping at the first square
+// in `occupied`.
+fn piece_reach(kind: &PieceKind, from: usize, occupied: u64) -> u64 {
+    let directions = match kind {
+        PieceKind::King => return KING_REACH[from],
+        PieceKind::Knight => return KNIGHT_REACH[from],
+        PieceKind::Queen => 0..8,
+        PieceKind::Rook => 0..4,
+        PieceKind::Bishop => 4..8,
+        PieceKind::Pawn => 0..0,
+    };
+    directions.fold(0, |reach, direction| {
+        let ray = RAYS[direction][from];
+        let blockers = ray & occupied;
+        if blockers == 0 {
+            return reach | ray;
+        }
+        let (dx, dy) = QUEEN_DIRECTIONS[direction];
+        // the nearest blocker has the lowest square number on rays going
+        // towards higher numbers
+        let nearest = if dy > 0 || dy == 0 && dx > 0 {
+            blockers.trailing_zeros()
+        } else {
+            63 - blockers.leading_zeros()
+        };
+        reach | ray & !RAYS[direction][nearest as usize]
+    })
+}
+
+// Square numbers by slot: the weak king, the strong king, then the strong
+// side's other pieces in the order of `kinds`.
+type Layout = [usize; 4];
+
+// The strong pieces' squares and what they attack, shared by every weak
+// king square.
+#[derive(Clone, Copy)]
+struct Placement {
+    occupied: u64,
+    attacks: u64,
+    undefended: u64,
+}
+
+struct Tablebase {
+    kinds: Vec<PieceKind>,
+    side_size: usize,
+    distances: Vec<u8>,
+}
+
+impl Tablebase {
+    fn new(kinds: &[PieceKind]) -> Tablebase {
+        let mut table = Tablebase {
+            kinds: kinds.to_vec(),
+            side_size: 0,
+            distances: Vec::new(),
+        };
+        table.side_size = (0..table.slots())
+            .map(|slot| table.dimension(slot))
+            .product();
+        table
+    }
+
+    fn slots(&self) -> usize {
+        2 + self.kinds.len()
+    }
+
+    fn kind(&self, slot: usize) -> PieceKind {
+        match slot {
+            WEAK_KING | STRONG_KING => PieceKind::King,
+            _ => self.kinds[slot - 2],
+        }
+    }
+
+    // Positions with a light squared bishop are mirrored before lookup, so
+    // the bishop only needs the 32 dark squares.
+    fn dimension(&self, slot: usize) -> usize {
+        if self.kind(slot) == PieceKind::Bishop {
+            32
+        } else {
+            64
+        }
+    }
+
+    fn compact(&self, slot: usize, square: usize) -> usize {
+        if self.kind(slot) == PieceKind::Bishop {
+            square / 8 * 4 + square % 8 / 2
+        } else {
+            square
+        }
+    }
+
+    // How far apart the indexes of two layouts are that only differ by one
+    // compact square in `slot`.
+    fn stride(&self, slot: usize) -> usize {
+        (slot + 1..self.slots())
+            .map(|later| self.dimension(later))
+            .product()
+    }
+
+    fn index(&self, to_move: usize, layout: &Layout) -> usize {
+        let mut index = to_move;
+        for (slot, square) in layout.iter().enumerate().take(self.slots()) {
+            index = index * self.dimension(slot) + self.compact(slot, *square);
+        }
+        index
+    }
+
+    fn layout(&self, index: usize) -> (usize, Layout) {
+        let mut layout = [0; 4];
+        let mut rest = index;
+        for slot in (0..self.slots()).rev() {
+            let compact = rest % self.dimension(slot);
+            rest /= self.dimension(slot);
+            layout[slot] = if self.kind(slot) == PieceKind::Bishop {
+                let y = compact / 4;
+                y * 8 + compact % 4 * 2 + (y + 1) % 2
+            } else {
+                compact
+            };
+        }
+        (rest, layout)
+    }
+
+    // Squares attacked by the strong side, leaving out the piece on slot
+    // `captured`. The weak king never blocks: it is either the one being
+    // attacked or has just stepped off the line.
+    fn attacks(&self, layout: &Layout, captured: Option<usize>) -> u64 {
+        let attackers = (STRONG_KING..self.slots()).filter(|slot| Some(*slot) != captured);
+        let occupied = attackers
+            .clone()
+            .fold(0, |occupied, slot| occupied | 1 << layout[slot]);
+        attackers.fold(0, |attacks, slot| {
+            attacks | piece_reach(&self.kind(slot), layout[slot], occupied)
+        })
+    }
+
+    fn strong_squares(&self, layout: &Layout) -> u64 {
+        (STRONG_KING..self.slots()).fold(0, |occupied, slot| occupied | 1 << layout[slot])
+    }
+
+    // Strong pieces the weak king could take if it stood next to them.
+    fn undefended(&self, layout: &Layout) -> u64 {
+        (STRONG_KING + 1..self.slots())
+            .filter(|slot| self.attacks(layout, Some(*slot)) & 1 << layout[*slot] == 0)
+            .fold(0, |undefended, slot| undefended | 1 << layout[slot])
+    }
+
+    // Index without the side to move and the weak king, which make up the
+    // most significant part of every index.
+    fn strong_part(&self, index: usize) -> usize {
+        index % (self.side_size / 64)
+    }
+
+    fn with_weak_king(&self, to_move: usize, weak_king: usize, strong_part: usize) -> usize {
+        (to_move * 64 + weak_king) * (self.side_size / 64) + strong_part
+    }
+
+    fn generate(kinds: &[PieceKind]) -> Tablebase {
+        let mut table = Tablebase::new(kinds);
+        let strong_size = table.side_size / 64;
+        table.distances = vec![INVALID; 2 * table.side_size];
+
+        // Every weak king square is tried against each placement of the
+        // strong pieces, finding the legal positions and the mates.
+        let mut lost = Vec::new();
+        let mut placements = vec![None; strong_size];
+        for (strong_part, placement) in placements.iter_mut().enumerate() {
+            let (_, layout) = table.layout(strong_part);
+            let occupied = table.strong_squares(&layout);
+            if occupied.count_ones() as usize != table.slots() - 1 {
+                continue;
+            }
+            let attacks = table.attacks(&layout, None);
+            let undefended = table.undefended(&layout);
+            *placement = Some(Placement {
+                occupied,
+                attacks,
+                undefended,
+            });
+            for weak_king in 0..64 {
+                if occupied & 1 << weak_king != 0 || kings_touch(weak_king, layout[STRONG_KING]) {
+                    continue;
+                }
+                if attacks & 1 << weak_king == 0 {
+                    let strong_index = table.with_weak_king(STRONG_TO_MOVE, weak_king, strong_part);
+                    table.distances[strong_index] = UNKNOWN;
+                }
+                let weak_index = table.with_weak_king(WEAK_TO_MOVE, weak_king, strong_part);
+                let escapes = KING_REACH[weak_king] & (!attacks & !occupied | undefended);
+                table.distances[weak_index] = if attacks & 1 << weak_king != 0 && escapes == 0 {
+                    lost.push(weak_index);
+                    1
+                } else {
+                    UNKNOWN
+                };
+            }
+        }
+
+        // `lost` holds the weak-to-move positions mated in `plies`. The strong
+        // moves into them are wins one ply further out, and the weak king
+        // moves that can only lead into such wins are losses after that.
+        let kinds: Vec<PieceKind> = (0..table.slots()).map(|slot| table.kind(slot)).collect();
+        let strides: Vec<usize> = (0..table.slots()).map(|slot| table.stride(slot)).collect();
+        let mut plies = 0;
+        while !lost.is_empty() {
+            let mut won = Vec::new();
+            for index in lost {
+                let (_, layout) = table.layout(index);
+                let occupied = table.strong_squares(&layout) | 1 << layout[WEAK_KING];
+                for (slot, kind) in kinds.iter().enumerate().skip(STRONG_KING) {
+                    // the same layout with the strong side to move, less the
+                    // moved piece
+                    let base =
+                        index - table.side_size - table.compact(slot, layout[slot]) * strides[slot];
+                    let mut origins = piece_reach(kind, layout[slot], occupied) & !occupied;
+                    while origins != 0 {
+                        let origin = origins.trailing_zeros() as usize;
+                        origins &= origins - 1;
+                        let before = base + table.compact(slot, origin) * strides[slot];
+                        if table.distances[before] == UNKNOWN {
+                            table.distances[before] = plies + 2;
+                            won.push(before);
+                        }
+                    }
+                }
+            }
+            lost = Vec::new();
+            for index in won {
+                let strong_part = table.strong_part(index);
+                let weak_king = index / strong_size % 64;
+                let Placement {
+                    occupied,
+                    attacks,
+                    undefended,
+                } = placements[strong_part].expect("won positions have a legal placement");
+                let mut origins = KING_REACH[weak_king] & !occupied;
+                while origins != 0 {
+                    let origin = origins.trailing_zeros() as usize;
+                    origins &= origins - 1;
+                    let before = table.with_weak_king(WEAK_TO_MOVE, origin, strong_part);
+                    if table.distances[before] != UNKNOWN || KING_REACH[origin] & undefended != 0 {
+                        continue;
+                    }
+                    let mut targets = KING_REACH[origin] & !attacks & !occupied;
+                    let mut all_lose = true;
+                    while targets != 0 && all_lose {
+                        let target = targets.trailing_zeros() as usize;
+                        targets &= targets - 1;
+                        all_lose = table.distances
+                            [table.with_weak_king(STRONG_TO_MOVE, target, strong_part)]
+                            != UNKNOWN;
+                    }
+                    if all_lose {
+                        table.distances[before] = plies + 3;
+                        lost.push(before);
+                    }
+                }
+            }
+            plies += 2;
+        }
+        table
+    }
+
+    // Plies to mate, None when the weak side isn't lost or the position
+    // doesn't belong to this ending.
+    fn distance(&self, position: &Position) -> Option<usize> {
+        let strong = position.stronger_side();
+        let mut layout = [0; 4];
+        let mut filled = [false; 4];
+        for square in all_squares() {
+            let Some(piece) = position.piece_at(&square) else {
+                continue;
+            };
+            let slot = match (piece.kind, piece.color == strong) {
+                (PieceKind::King, true) => STRONG_KING,
+                (PieceKind::King, false) => WEAK_KING,
+                (kind, true) => {
+                    (2..self.slots()).find(|slot| !filled[*slot] && self.kind(*slot) == kind)?
+                }
+                (_, false) => return None,
+            };
+            if filled[slot] {
+                return None;
+            }
+            filled[slot] = true;
+            layout[slot] = square.to_square_number();
+        }
+        if filled[..self.slots()].contains(&false) {
+            return None;
+        }
+        let bishop_on_light = (2..self.slots())
+            .any(|slot| self.kind(slot) == PieceKind::Bishop && !is_dark(layout[slot]));
+        if bishop_on_light {
+            for square in layout.iter_mut() {
+                *square = *square / 8 * 8 + 7 - *square % 8;
+            }
+        }
+        let to_move = if position.to_move == strong {
+            STRONG_TO_MOVE
+        } else {
+            WEAK_TO_MOVE
+        };
+        match self.distances[self.index(to_move, &layout)] {
+            UNKNOWN | INVALID => None,
+            stored => Some(stored as usize - 1),
+        }
+    }
+}
+
+fn tablebase(class: EndgameClass) -> Option<&'static Tablebase> {
+    static QUEEN: OnceLock<Tablebase> = OnceLock::new();
+    static ROOK: OnceLock<Tablebase> = OnceLock::new();
+    static BISHOP_AND_KNIGHT: OnceLock<Tablebase> = OnceLock::new();
+    match class {
+        EndgameClass::KQvK => Some(QUEEN.get_or_init(|| Tablebase::generate(&[PieceKind::Queen]))),
+        EndgameClass::KRvK => Some(ROOK.get_or_init(|| Tablebase::generate(&[PieceKind::Rook]))),
+        EndgameClass::KBNvK => Some(
+            BISHOP_AND_KNIGHT
+                .get_or_init(|| Tablebase::generate(&[PieceKind::Bishop, PieceKind::Knight])),
+        ),
+        _ => None,
+    }
+}
+
+// Plies until mate with best play from both sides, for KQvK, KRvK and KBNvK
+// positions the stronger side wins.
+pub fn elementary_mate_distance(position: &Position) -> Option<usize> {
+    tablebase(position.endgame_class())?.distance(position)
+}
+
+// The fastest mate for the stronger side, or the longest defence for the
+// lone king, which takes a hanging piece whenever it can. None outside of
+// KQvK, KRvK and KBNvK, or when the stronger side can't win.
+pub fn elementary_mate_move(position: &Position) -> Option<ChessMove> {
+    let class = position.endgame_class();
+    let table = tablebase(class)?;
+    table.distance(position)?;
+    let moves = position.all_legal_moves().into_iter().map(|chess_move| {
+        let after = position.after_move(&chess_move);
+        let distance = (after.endgame_class() == class)
+            .then(|| table.distance(&after))
+            .flatten();
+        (distance, chess_move)
+    });
+    if position.to_move == position.stronger_side() {
+        moves
+            .filter_map(|(distance, chess_move)| Some((distance?, chess_move)))
+            .min_by_key(|(distance, _)| *distance)
+            .map(|(_, chess_move)| chess_move)
+    } else {
+        moves
+            .max_by_key(|(distance, _)| distance.unwrap_or(usize::MAX))
+            .map(|(_, chess_move)| chess_move)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::Coords;
+
+    use super::*;
+
+    fn play_out(fen: &str) -> (Position, usize) {
+        let mut position = Position::from_fen(fen);
+        let mut plies = 0;
+        while let Some(chess_move) = elementary_mate_move(&position) {
+            position = position.after_move(&chess_move);
+            plies += 1;
+        }
+        (position, plies)
+    }
+
+    #[test]
+    fn knows_mate_distances() {
+        let distance = |fen| elementary_mate_distance(&Position::from_fen(fen));
+        assert_eq!(distance("k7/8/1K6/8/8/8/7Q/8 w - - 0 1"), Some(1));
+        assert_eq!(distance("k7/1Q6/1K6/8/8/8/8/8 b - - 0 1"), Some(0));
+        // stalemate
+        assert_eq!(distance("k7/2Q5/1K6/8/8/8/8/8 b - - 0 1"), None);
+        // the rook hangs
+        assert_eq!(distance("8/8/8/3k4/3R4/8/8/7K b - - 0 1"), None);
+        assert_eq!(
+            distance("8/8/8/3k4/8/8/8/R3K3 w - - 0 1"),
+            distance("8/8/8/3K4/8/8/8/r3k3 b - - 0 1")
+        );
+    }
+
+    #[test]
+    fn queen_and_rook_mate_in_time() {
+        for fen in [
+            "8/8/8/3k4/8/8/8/Q3K3 w - - 0 1",
+            "8/8/8/3k4/8/8/8/R3K3 w - - 0 1",
+            "7K/8/8/8/8/8/8/k5r1 b - - 0 1",
+        ] {
+            let distance = elementary_mate_distance(&Position::from_fen(fen)).unwrap();
+            let (position, plies) = play_out(fen);
+            assert!(position.is_checkmate(), "{} ends in {:?}", fen, position);
+            assert_eq!(plies, distance);
+            assert!(plies <= 100);
+        }
+    }
+
+    #[test]
+    fn bishop_and_knight_mate_within_fifty_moves() {
+        for fen in [
+            "8/8/8/3k4/8/8/8/1NB1K3 w - - 0 1",
+            "8/8/8/4k3/8/8/8/1N2KB2 b - - 0 1",
+        ] {
+            let distance = elementary_mate_distance(&Position::from_fen(fen)).unwrap();
+            let (position, plies) = play_out(fen);
+            assert!(position.is_checkmate(), "{} ends in {:?}", fen, position);
+            assert_eq!(plies, distance);
+            assert!(plies <= 100);
+        }
+    }
+
+    #[test]
+    fn layouts_round_trip_through_indexes() {
+        let table = Tablebase::new(&[PieceKind::Bishop, PieceKind::Knight]);
+        let layout = ["h8", "e1", "c1", "b7"]
+            .map(|square| Coords::from_algebraic(square).to_square_number());
+        let index = table.index(WEAK_TO_MOVE, &layout);
+        assert_eq!(table.layout(index), (WEAK_TO_MOVE, layout));
+        assert!(index < 2 * table.side_size);
+    }
+}
@@ -0,0 +1,299 @@
+use crate::bitboard::between;
+use crate::Bitboard;
+use crate::ChessMove;
+use crate::Coords;
+use crate::MoveList;
+use crate::PieceColor;
+use crate::PieceKind;
+use crate::Position;
+
+// A piece that can't leave the line between its king and an enemy slider.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Pin {
+    pub pinned: Coords,
+    pub pinner: Coords,
+    // the squares between the king and the pinner, and the pinner's own,
+    // which are all the pinned piece may still move to
+    pub ray: Bitboard,
+}
+
+pub(crate) fn pins(position: &Position, color: &PieceColor) -> Vec<Pin> {
+    let Some(king) = position.king_location(color) else {
+        return Vec::new();
+    };
+    let bitboards = position.bitboards();
+    let occupancy = bitboards.occupancy();
+    let enemy = color.opposite();
+    // enemy sliders that would reach the king if our own pieces weren't there
+    let sliders = bitboards.kind(&PieceKind::Rook)
+        | bitboards.kind(&PieceKind::Bishop)
+        | bitboards.kind(&PieceKind::Queen);
+    let snipers = bitboards.attackers(&king, &enemy, bitboards.color(&enemy)) & sliders;
+    snipers
+        .squares()
+        .filter_map(|pinner| {
+            let line = between(&king, &pinner);
+            let blockers = line & occupancy;
+            if blockers.count() == 1 && !(blockers & bitboards.color(color)).is_empty() {
+                Some(Pin {
+                    pinned: blockers.squares().next()?,
+                    pinner,
+                    ray: line | Bitboard::from_square(&pinner),
+                })
+            } else {
+                None
+            }
+        })
+        .collect()
+}
+
+// What the side to move has to respect to keep its king safe, worked out
+// once per position so that most moves can be judged without playing them.
+pub(crate) struct KingSafety {
+    king: Option<Coords>,
+    // where the other pieces may go: anywhere out of check, onto the checker
+    // or in its way when in check, and nowhere in double check
+    targets: Bitboard,
+    pinned: Bitboard,
+    pins: Vec<Pin>,
+}
+
+impl KingSafety {
+    pub(crate) fn of(position: &Position) -> KingSafety {
+        let color = position.to_move;
+        let Some(king) = position.king_location(&color) else {
+            return KingSafety {
+                king: None,
+                targets: !Bitboard::EMPTY,
+                pinned: Bitboard::EMPTY,
+                pins: Vec::new(),
+            };
+        };
+        let bitboards = position.bitboards();
+        let occupancy = bitboards.occupancy();
+        let enemy = color.opposite();
+        let checkers = bitboards.attackers(&king, &enemy, occupancy);
+        let targets = match checkers.count() {
+            0 => !Bitboard::EMPTY,
+            1 => checkers.squares().fold(checkers, |targets, checker| {
+                targets | between(&king, &checker)
+            }),
+            _ => Bitboard::EMPTY,
+        };
+        let pins = pins(position, &color);
+        let pinned = pins.iter().fold(Bitboard::EMPTY, |pinned, pin| {
+            pinned | Bitboard::from_square(&pin.pinned)
+        });
+        KingSafety {
+            king: Some(king),
+            targets,
+            pinned,
+            pins,
+        }
+    }
+
+    // Whether a pseudo-legal move keeps the king out of check.
+    pub(crate) fn allows(&self, position: &Position, chess_move: &ChessMove) -> bool {
+        let Some(king) = self.king else {
+            return true;
+        };
+        let movement = match chess_move {
+            ChessMove::RegularMove(movement)
+            | ChessMove::PawnSkip(movement)
+            | ChessMove::Promotion(movement, _) => movement,
+            // these move more than one piece, and are rare enough to just play out
+            _ => return !position.opens_own_king(chess_move),
+        };
+        if movement.origin == king {
+            let bitboards = position.bitboards();
+            // the king no longer shields the squares behind it
+            let occupancy = bitboards.occupancy() & !Bitboard::from_square(&king);
+            return bitboards
+                .attackers(
+                    &movement.destination,
+                    &position.to_move.opposite(),
+                    occupancy,
+                )
+                .is_empty();
+        }
+        self.targets.contains(&movement.destination)
+            && (!self.pinned.contains(&movement.origin)
+                || self.pins.iter().any(|pin| {
+                    pin.pinned == movement.origin && pin.ray.contains(&movement.destination)
+                }))
+    }
+}
+
+// Legal moves in the order `all_legal_moves` gives them, generated one
+// origin square at a time and checked for legality only once they are asked
+// for, so that stopping early skips the rest of the work.
+pub struct MoveGen<'a> {
+    position: &'a Position,
+    safety: KingSafety,
+    next_square: usize,
+    // the moves of the last origin square, from `next_pending` on yet to be checked
+    pending: MoveList,
+    next_pending: usize,
+}
+
+impl Iterator for MoveGen<'_> {
+    type Item = ChessMove;
+
+    fn next(&mut self) -> Option<ChessMove> {
+        loop {
+            while let Some(chess_move) = self.pending.get(self.next_pending) {
+                self.next_pending += 1;
+                if self.safety.allows(self.position, &chess_move) {
+                    return Some(chess_move);
+                }
+            }
+            if self.next_square == 64 {
+                return None;
+            }
+            let origin = Coords {
+                y: (self.next_square / 8) as isize,
+                x: (self.next_square % 8) as isize,
+            };
+            self.next_square += 1;
+            self.pending.clear();
+            self.next_pending = 0;
+            self.position
+                .push_pseudo_legal_moves_from(&origin, &mut self.pending);
+        }
+    }
+}
+
+impl Position {
+    pub fn legal_moves_iter(&self) -> MoveGen<'_> {
+        MoveGen {
+            position: self,
+            safety: KingSafety::of(self),
+            next_square: 0,
+            pending: MoveList::new(),
+            next_pending: 0,
+        }
+    }
+
+    // All the legal moves, in the same order, without allocating.
+    pub fn legal_move_list(&self) -> MoveList {
+        let safety = KingSafety::of(self);
+        let mut moves = MoveList::new();
+        let mut from_origin = MoveList::new();
+        // own pieces come up in square order, as they do for `all_legal_moves`
+        for origin in self.bitboards().color(&self.to_move).squares() {
+            from_origin.clear();
+            self.push_pseudo_legal_moves_from(&origin, &mut from_origin);
+            moves.extend(
+                from_origin
+                    .iter()
+                    .filter(|chess_move| safety.allows(self, chess_move)),
+            );
+        }
+        moves
+    }
+
+    // Stops at the first legal move, which is all mate and stalemate need.
+    pub fn has_legal_move(&self) -> bool {
+        self.legal_moves_iter().next().is_some()
+    }
+
+    pub fn count_legal_moves(&self) -> usize {
+        self.legal_moves_iter().count()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn yields_the_legal_moves_in_order() {
+        for fen in [
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+            "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1",
+            // checkmated, so there is nothing to yield
+            "6Rk/5Q2/8/8/8/8/8/6K1 b - - 0 1",
+        ] {
+            let position = Position::from_fen(fen);
+            assert_eq!(
+                position.legal_moves_iter().collect::<Vec<ChessMove>>(),
+                position.all_legal_moves(),
+                "{}",
+                fen
+            );
+        }
+        for fen in [
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+            "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1",
+        ] {
+            let position = Position::from_fen(fen);
+            assert_eq!(
+                position.legal_move_list().to_vec(),
+                position.all_legal_moves()
+            );
+        }
+        let first = Position::initial().legal_moves_iter().next();
+        assert_eq!(
+            first,
+            Position::initial().all_legal_moves().first().cloned()
+        );
+    }
+
+    #[test]
+    fn counts_legal_moves_without_listing_them() {
+        assert_eq!(Position::initial().count_legal_moves(), 20);
+        assert!(Position::initial().has_legal_move());
+        let kiwipete = Position::from_fen(
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+        );
+        assert_eq!(kiwipete.count_legal_moves(), 48);
+        let stalemate = Position::from_fen("7k/5Q2/6K1/8/8/8/8/8 b - - 0 1");
+        assert!(!stalemate.has_legal_move());
+        assert_eq!(stalemate.count_legal_moves(), 0);
+        assert!(stalemate.is_stalemate());
+    }
+
+    #[test]
+    fn pinned_pieces_stay_on_their_line() {
+        // the bishop on d2 is pinned by the one on b4 and the rook on e2 by the one on e8
+        let position = Position::from_fen("4r1k1/8/8/8/1b6/8/3BR3/4K3 w - - 0 1");
+        for chess_move in position.all_legal_moves() {
+            let (origin, destination) = chess_move.origin_and_destination(&position.to_move);
+            if origin == Coords::from_algebraic("d2") {
+                assert!(["c3", "b4"].contains(&destination.to_algebraic().as_str()));
+            }
+            if origin == Coords::from_algebraic("e2") {
+                assert_eq!(destination.x, 4);
+            }
+        }
+        assert_eq!(
+            position
+                .legal_moves_from_origin(&Coords::from_algebraic("d2"))
+                .len(),
+            2
+        );
+        assert_eq!(
+            position
+                .legal_moves_from_origin(&Coords::from_algebraic("e2"))
+                .len(),
+            6
+        );
+    }
+
+    #[test]
+    fn only_the_king_moves_out_of_double_check() {
+        let position = Position::from_fen("4k3/8/8/8/8/7n/8/Q3r1K1 w - - 0 1");
+        assert!(position
+            .all_legal_moves()
+            .iter()
+            .all(
+                |chess_move| chess_move.origin_and_destination(&position.to_move).0
+                    == Coords::from_algebraic("g1")
+            ));
+        assert_eq!(
+            position.movable_pieces(),
+            vec![Coords::from_algebraic("g1")]
+        );
+    }
+}
@@ -0,0 +1,361 @@
+use std::fmt::Debug;
+
+use crate::ChessMove;
+use crate::Coords;
+use crate::Move;
+use crate::PieceKind;
+
+// No position has more than 218 legal moves, so this leaves room to spare.
+pub const MAX_MOVES: usize = 256;
+
+// A move in four bytes, origin, destination and the pawn taken en passant
+// by square number, with what kind of move it is in the top bits. A list
+// holds these rather than `ChessMove`s, which take fourteen times the room,
+// so that every ply of a deep search can keep one on the stack.
+#[derive(Clone, Copy, PartialEq, Eq, Default)]
+struct PackedMove(u32);
+
+const REGULAR: u32 = 0;
+const PAWN_SKIP: u32 = 1;
+const EN_PASSANT: u32 = 2;
+const CASTLE_LEFT: u32 = 3;
+const CASTLE_RIGHT: u32 = 4;
+// then one for each piece a pawn can promote to
+const PROMOTION: u32 = 5;
+
+impl PackedMove {
+    fn pack(chess_move: &ChessMove) -> PackedMove {
+        let squares = |movement: &Move, taken: Option<&Coords>| {
+            movement.origin.to_square_number() as u32
+                | (movement.destination.to_square_number() as u32) << 6
+                | taken.map_or(0, |taken| taken.to_square_number() as u32) << 12
+        };
+        PackedMove(match chess_move {
+            ChessMove::RegularMove(movement) => squares(movement, None) | REGULAR << 18,
+            ChessMove::PawnSkip(movement) => squares(movement, None) | PAWN_SKIP << 18,
+            ChessMove::EnPassant(movement, taken) => {
+                squares(movement, Some(taken)) | EN_PASSANT << 18
+            }
+            ChessMove::CastleLeft => CASTLE_LEFT << 18,
+            ChessMove::CastleRight => CASTLE_RIGHT << 18,
+            ChessMove::Promotion(movement, kind) => {
+                squares(movement, None) | (PROMOTION + kind.index() as u32) << 18
+            }
+        })
+    }
+
+    fn unpack(self) -> ChessMove {
+        let square = |shift: u32| Coords::from_index((self.0 >> shift & 63) as usize);
+        let movement = Move {
+            origin: square(0),
+            destination: square(6),
+        };
+        match self.0 >> 18 {
+            REGULAR => ChessMove::RegularMove(movement),
+            PAWN_SKIP => ChessMove::PawnSkip(movement),
+            EN_PASSANT => ChessMove::EnPassant(movement, square(12)),
+            CASTLE_LEFT => ChessMove::CastleLeft,
+            CASTLE_RIGHT => ChessMove::CastleRight,
+            kind => ChessMove::Promotion(
+                movement,
+                *PieceKind::all()
+                    .nth((kind - PROMOTION) as usize)
+                    .expect("packed from a piece kind"),
+            ),
+        }
+    }
+}
+
+// A list of moves that lives on the stack, so that generating the moves of
+// a search node doesn't allocate. Moves are handed out by value, since the
+// list only keeps them packed.
+#[derive(Clone)]
+pub struct MoveList {
+    moves: [PackedMove; MAX_MOVES],
+    len: usize,
+}
+
+impl Default for MoveList {
+    fn default() -> MoveList {
+        MoveList::new()
+    }
+}
+
+impl MoveList {
+    pub fn new() -> MoveList {
+        MoveList {
+            moves: [PackedMove::default(); MAX_MOVES],
+            len: 0,
+        }
+    }
+
+    // Panics once the list is full, which legal moves never fill.
+    pub fn push(&mut self, chess_move: ChessMove) {
+        assert!(
+            self.len < MAX_MOVES,
+            "a move list holds at most {} moves",
+            MAX_MOVES
+        );
+        self.moves[self.len] = PackedMove::pack(&chess_move);
+        self.len += 1;
+    }
+
+    pub fn clear(&mut self) {
+        self.len = 0;
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn get(&self, index: usize) -> Option<ChessMove> {
+        self.moves[..self.len]
+            .get(index)
+            .map(|packed| packed.unpack())
+    }
+
+    pub fn first(&self) -> Option<ChessMove> {
+        self.get(0)
+    }
+
+    pub fn contains(&self, chess_move: &ChessMove) -> bool {
+        self.iter().any(|listed| listed == *chess_move)
+    }
+
+    pub fn iter(&self) -> MoveListIter<'_> {
+        MoveListIter {
+            packed: self.moves[..self.len].iter(),
+        }
+    }
+
+    pub fn to_vec(&self) -> Vec<ChessMove> {
+        self.iter().collect()
+    }
+
+    // Keeps the moves `keep` accepts, in the order they were in.
+    pub fn retain(&mut self, mut keep: impl FnMut(&ChessMove) -> bool) {
+        let mut kept = 0;
+        for index in 0..self.len {
+            if keep(&self.moves[index].unpack()) {
+                self.moves.swap(kept, index);
+                kept += 1;
+            }
+        }
+        self.len = kept;
+    }
+
+    // A stable sort on keys worked out once per move, like the slice method
+    // of the same name but without its allocation. It's an insertion sort,
+    // which is quick at the length of a move list.
+    pub fn sort_by_cached_key<K: Ord + Copy + Default>(
+        &mut self,
+        mut key: impl FnMut(&ChessMove) -> K,
+    ) {
+        let mut keys = [K::default(); MAX_MOVES];
+        for (slot, chess_move) in keys.iter_mut().zip(self.iter()) {
+            *slot = key(&chess_move);
+        }
+        for index in 1..self.len {
+            let mut slot = index;
+            while slot > 0 && keys[slot - 1] > keys[slot] {
+                keys.swap(slot - 1, slot);
+                self.moves.swap(slot - 1, slot);
+                slot -= 1;
+            }
+        }
+    }
+}
+
+impl Debug for MoveList {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_list().entries(self.iter()).finish()
+    }
+}
+
+impl PartialEq for MoveList {
+    fn eq(&self, other: &MoveList) -> bool {
+        self.moves[..self.len] == other.moves[..other.len]
+    }
+}
+
+impl Eq for MoveList {}
+
+impl Extend<ChessMove> for MoveList {
+    fn extend<I: IntoIterator<Item = ChessMove>>(&mut self, moves: I) {
+        moves
+            .into_iter()
+            .for_each(|chess_move| self.push(chess_move));
+    }
+}
+
+impl FromIterator<ChessMove> for MoveList {
+    fn from_iter<I: IntoIterator<Item = ChessMove>>(moves: I) -> MoveList {
+        let mut list = MoveList::new();
+        list.extend(moves);
+        list
+    }
+}
+
+pub struct MoveListIter<'a> {
+    packed: std::slice::Iter<'a, PackedMove>,
+}
+
+impl Iterator for MoveListIter<'_> {
+    type Item = ChessMove;
+
+    fn next(&mut self) -> Option<ChessMove> {
+        self.packed.next().map(|packed| packed.unpack())
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.packed.size_hint()
+    }
+}
+
+impl DoubleEndedIterator for MoveListIter<'_> {
+    fn next_back(&mut self) -> Option<ChessMove> {
+        self.packed.next_back().map(|packed| packed.unpack())
+    }
+}
+
+impl ExactSizeIterator for MoveListIter<'_> {}
+
+pub struct MoveListIntoIter {
+    list: MoveList,
+    next: usize,
+}
+
+impl Iterator for MoveListIntoIter {
+    type Item = ChessMove;
+
+    fn next(&mut self) -> Option<ChessMove> {
+        let chess_move = self.list.get(self.next)?;
+        self.next += 1;
+        Some(chess_move)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let remaining = self.list.len - self.next;
+        (remaining, Some(remaining))
+    }
+}
+
+impl ExactSizeIterator for MoveListIntoIter {}
+
+impl IntoIterator for MoveList {
+    type Item = ChessMove;
+    type IntoIter = MoveListIntoIter;
+
+    fn into_iter(self) -> MoveListIntoIter {
+        MoveListIntoIter {
+            list: self,
+            next: 0,
+        }
+    }
+}
+
+impl<'a> IntoIterator for &'a MoveList {
+    type Item = ChessMove;
+    type IntoIter = MoveListIter<'a>;
+
+    fn into_iter(self) -> MoveListIter<'a> {
+        self.iter()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::PieceColor;
+
+    use super::*;
+
+    fn forward(from: &str, to: &str) -> ChessMove {
+        ChessMove::RegularMove(Move {
+            origin: Coords::from_algebraic(from),
+            destination: Coords::from_algebraic(to),
+        })
+    }
+
+    #[test]
+    fn behaves_like_the_moves_pushed() {
+        let mut list = MoveList::new();
+        assert!(list.is_empty());
+        list.push(forward("e2", "e4"));
+        list.extend([forward("d2", "d4"), ChessMove::CastleRight]);
+        assert_eq!(list.len(), 3);
+        assert_eq!(list.get(1), Some(forward("d2", "d4")));
+        assert!(list.contains(&ChessMove::CastleRight));
+        list.retain(|chess_move| *chess_move != forward("d2", "d4"));
+        assert_eq!(
+            list.clone().into_iter().collect::<Vec<ChessMove>>(),
+            vec![forward("e2", "e4"), ChessMove::CastleRight]
+        );
+        list.clear();
+        assert_eq!(list, MoveList::new());
+    }
+
+    #[test]
+    fn every_kind_of_move_comes_back_out() {
+        let promotions = PieceKind::promoteable().map(|kind| {
+            ChessMove::Promotion(
+                Move {
+                    origin: Coords::from_algebraic("b7"),
+                    destination: Coords::from_algebraic("a8"),
+                },
+                *kind,
+            )
+        });
+        let moves: Vec<ChessMove> = [
+            forward("h1", "a8"),
+            ChessMove::PawnSkip(Move {
+                origin: Coords::from_algebraic("e2"),
+                destination: Coords::from_algebraic("e4"),
+            }),
+            ChessMove::EnPassant(
+                Move {
+                    origin: Coords::from_algebraic("e5"),
+                    destination: Coords::from_algebraic("d6"),
+                },
+                Coords::from_algebraic("d5"),
+            ),
+            ChessMove::CastleLeft,
+            ChessMove::CastleRight,
+        ]
+        .into_iter()
+        .chain(promotions)
+        .collect();
+        let list: MoveList = moves.iter().cloned().collect();
+        assert_eq!(list.to_vec(), moves);
+        assert_eq!(list.iter().next_back(), moves.last().cloned());
+    }
+
+    #[test]
+    fn sorting_keeps_ties_in_order() {
+        let mut list: MoveList = [
+            forward("a2", "a3"),
+            forward("b2", "b3"),
+            forward("c2", "c3"),
+            forward("d2", "d3"),
+        ]
+        .into_iter()
+        .collect();
+        // b and d first, then a and c, each pair as it was
+        list.sort_by_cached_key(|chess_move| {
+            let (origin, _) = chess_move.origin_and_destination(&PieceColor::White);
+            origin.x % 2 == 0
+        });
+        assert_eq!(
+            list.to_vec(),
+            vec![
+                forward("b2", "b3"),
+                forward("d2", "d3"),
+                forward("a2", "a3"),
+                forward("c2", "c3"),
+            ]
+        );
+    }
+}
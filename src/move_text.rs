@@ -0,0 +1,185 @@
+use crate::san::is_algebraic_square;
+use crate::ChessMove;
+use crate::Coords;
+use crate::PieceKind;
+use crate::Position;
+use crate::SanError;
+
+fn promotion_from_letter(letter: char) -> Option<PieceKind> {
+    match letter {
+        'q' => Some(PieceKind::Queen),
+        'r' => Some(PieceKind::Rook),
+        'b' => Some(PieceKind::Bishop),
+        'n' => Some(PieceKind::Knight),
+        _ => None,
+    }
+}
+
+// Reads `e2e4`, `E2E4`, `e2-e4`, `e2 e4`, `Ng1-f3` and `e7e8q` style input
+fn from_coordinates(text: &str, position: &Position) -> Option<ChessMove> {
+    let mut squares: String = text
+        .chars()
+        .filter(|c| !c.is_whitespace() && !"-x=".contains(*c))
+        .collect();
+    if !squares.is_ascii() {
+        return None;
+    }
+    if squares.len() == 5 || squares.len() == 6 {
+        let first = squares.chars().next()?;
+        if "KQRBN".contains(first) && !is_algebraic_square(&squares[..2].to_ascii_lowercase()) {
+            squares.remove(0);
+        }
+    }
+    let squares = squares.to_ascii_lowercase();
+    if !(4..=5).contains(&squares.len()) {
+        return None;
+    }
+    if !is_algebraic_square(&squares[..2]) || !is_algebraic_square(&squares[2..4]) {
+        return None;
+    }
+    let promotion = match squares.chars().nth(4) {
+        None => None,
+        Some(letter) => Some(promotion_from_letter(letter)?),
+    };
+    ChessMove::infer(
+        Coords::from_algebraic(&squares[..2]),
+        Coords::from_algebraic(&squares[2..4]),
+        promotion,
+        position,
+    )
+    .ok()
+}
+
+impl ChessMove {
+    // Forgiving parsing for moves typed by people: coordinates with or without
+    // separators in any case, SAN with a lowercase piece letter, and castling
+    // written with letter O or zero. Only legal moves are returned.
+    pub fn from_text(text: &str, position: &Position) -> Result<ChessMove, SanError> {
+        let trimmed = text.trim().trim_end_matches(['+', '#', '!', '?']);
+        let castle: String = trimmed
+            .chars()
+            .filter(|c| !c.is_whitespace() && *c != '-')
+            .collect::<String>()
+            .to_ascii_uppercase()
+            .replace('0', "O");
+        let castle = match castle.as_str() {
+            "OO" => Some(ChessMove::CastleRight),
+            "OOO" => Some(ChessMove::CastleLeft),
+            _ => None,
+        };
+        let candidate = castle.or_else(|| from_coordinates(trimmed, position));
+        if let Some(chess_move) = candidate {
+            return if position.is_move_legal(&chess_move) {
+                Ok(chess_move)
+            } else {
+                Err(SanError::Illegal(text.to_string()))
+            };
+        }
+
+        let san: String = trimmed.chars().filter(|c| !c.is_whitespace()).collect();
+        let capitalized: String = san
+            .chars()
+            .enumerate()
+            .map(|(index, c)| {
+                if index == 0 {
+                    c.to_ascii_uppercase()
+                } else {
+                    c
+                }
+            })
+            .collect();
+        let first_error = match ChessMove::from_san(&san, position) {
+            Ok(chess_move) => return Ok(chess_move),
+            Err(error) => error,
+        };
+        if capitalized != san && capitalized.starts_with(['K', 'Q', 'R', 'B', 'N']) {
+            if let Ok(chess_move) = ChessMove::from_san(&capitalized, position) {
+                return Ok(chess_move);
+            }
+        }
+        Err(match first_error {
+            SanError::Malformed(_) => SanError::Malformed(text.to_string()),
+            SanError::Illegal(_) => SanError::Illegal(text.to_string()),
+            SanError::Ambiguous(_) => SanError::Ambiguous(text.to_string()),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn accepts_coordinate_variants() {
+        let position = Position::initial();
+        let e4 = ChessMove::from_san("e4", &position).unwrap();
+        for text in ["e2e4", "E2E4", "e2-e4", "e2 e4", " e2e4+ "] {
+            assert_eq!(
+                ChessMove::from_text(text, &position),
+                Ok(e4.clone()),
+                "{}",
+                text
+            );
+        }
+        let knight_move = ChessMove::from_san("Nf3", &position).unwrap();
+        for text in ["Ng1-f3", "g1f3", "nf3", "Nf3"] {
+            assert_eq!(
+                ChessMove::from_text(text, &position),
+                Ok(knight_move.clone()),
+                "{}",
+                text
+            );
+        }
+        let b4 = ChessMove::from_san("b4", &position).unwrap();
+        for text in ["B2B4", "B2-B4", "b2B4"] {
+            assert_eq!(
+                ChessMove::from_text(text, &position),
+                Ok(b4.clone()),
+                "{}",
+                text
+            );
+        }
+        let promotion = Position::from_fen("4k3/1P6/8/8/8/8/8/4K3 w - - 0 1");
+        assert_eq!(
+            ChessMove::from_text("B7B8Q", &promotion),
+            Ok(ChessMove::from_san("b8=Q+", &promotion).unwrap())
+        );
+        for text in ["Né1f3", "Ké2e4"] {
+            assert!(ChessMove::from_text(text, &position).is_err(), "{}", text);
+        }
+    }
+
+    #[test]
+    fn resolves_captures_castling_and_promotions() {
+        let position = Position::from_fen("4k3/1P6/8/3p4/4P3/8/8/4K2R w K - 0 1");
+        assert_eq!(
+            ChessMove::from_text("exd5", &position),
+            ChessMove::from_san("exd5", &position)
+        );
+        assert_eq!(
+            ChessMove::from_text("e4xd5", &position),
+            ChessMove::from_san("exd5", &position)
+        );
+        assert_eq!(
+            ChessMove::from_text("0-0", &position),
+            Ok(ChessMove::CastleRight)
+        );
+        assert_eq!(
+            ChessMove::from_text("o-o", &position),
+            Ok(ChessMove::CastleRight)
+        );
+        assert_eq!(
+            ChessMove::from_text("b7b8Q", &position),
+            ChessMove::from_san("b8=Q", &position)
+        );
+        assert_eq!(
+            ChessMove::from_text("e2e4", &position),
+            Err(SanError::Illegal(String::from("e2e4")))
+        );
+        assert!(ChessMove::from_text("\u{2658}f3", &position).is_err());
+        assert_eq!(
+            ChessMove::from_text("hello", &position),
+            Err(SanError::Malformed(String::from("hello")))
+        );
+    }
+}
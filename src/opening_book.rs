@@ -0,0 +1,392 @@
+use std::collections::HashMap;
+use std::fmt::Display;
+
+use crate::ChessMove;
+use crate::ClockState;
+use crate::Coords;
+use crate::GameResult;
+use crate::Move;
+use crate::PgnError;
+use crate::PieceKind;
+use crate::Player;
+use crate::Position;
+use crate::VariationTree;
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum BookError {
+    Pgn(PgnError),
+    // a line of the text format that couldn't be read back
+    Malformed(String),
+}
+
+impl From<PgnError> for BookError {
+    fn from(error: PgnError) -> BookError {
+        BookError::Pgn(error)
+    }
+}
+
+// How a move played from a book position turned out, counted from the side
+// that played it.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct BookMove {
+    pub chess_move: ChessMove,
+    pub wins: u32,
+    pub draws: u32,
+    pub losses: u32,
+    // games without a result count towards how often the move was played only
+    pub unfinished: u32,
+}
+
+impl BookMove {
+    pub fn games(&self) -> u32 {
+        self.wins + self.draws + self.losses + self.unfinished
+    }
+
+    // Points per finished game for the side that played the move, a draw
+    // counting half. None when no game with the move was finished.
+    pub fn score(&self) -> Option<f64> {
+        let finished = self.wins + self.draws + self.losses;
+        (finished > 0).then(|| (self.wins as f64 + self.draws as f64 / 2.0) / finished as f64)
+    }
+}
+
+// The moves a set of games played from each position of their openings,
+// looked up by the position's Zobrist key so that transpositions meet.
+#[derive(Clone, PartialEq, Eq, Debug, Default)]
+pub struct OpeningBook {
+    positions: HashMap<u64, Vec<BookMove>>,
+}
+
+impl OpeningBook {
+    pub fn new() -> OpeningBook {
+        OpeningBook::default()
+    }
+
+    // Reads every game of a PGN file, keeping the first `plies` moves of
+    // each one's main line.
+    pub fn from_pgn(corpus: &str, plies: usize) -> Result<OpeningBook, BookError> {
+        let mut book = OpeningBook::new();
+        for game in split_games(corpus) {
+            book.add_game(&VariationTree::from_pgn(game)?, plies);
+        }
+        Ok(book)
+    }
+
+    // Variations are left out: only the moves that were played count.
+    pub fn add_game(&mut self, game: &VariationTree, plies: usize) {
+        let result = game.result().copied();
+        let mut position = game.position(game.root()).expect("the root exists").clone();
+        for node in game.mainline().into_iter().take(plies) {
+            let chess_move = game.chess_move(node).expect("mainline nodes have moves");
+            self.record(&position, chess_move, result);
+            position = position.after_move(chess_move);
+        }
+    }
+
+    fn record(&mut self, position: &Position, chess_move: &ChessMove, result: Option<GameResult>) {
+        let moves = self.positions.entry(position.zobrist_key()).or_default();
+        let index = match moves
+            .iter()
+            .position(|book_move| book_move.chess_move == *chess_move)
+        {
+            Some(index) => index,
+            None => {
+                moves.push(BookMove {
+                    chess_move: chess_move.clone(),
+                    wins: 0,
+                    draws: 0,
+                    losses: 0,
+                    unfinished: 0,
+                });
+                moves.len() - 1
+            }
+        };
+        let book_move = &mut moves[index];
+        match result.map(|result| result.winner()) {
+            None => book_move.unfinished += 1,
+            Some(Some(winner)) if winner == position.to_move => book_move.wins += 1,
+            Some(Some(_)) => book_move.losses += 1,
+            Some(None) => book_move.draws += 1,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.positions.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.positions.is_empty()
+    }
+
+    // Most played first, ties kept in the order the moves were first seen.
+    pub fn moves_for_key(&self, key: u64) -> Vec<&BookMove> {
+        let mut moves: Vec<&BookMove> = self
+            .positions
+            .get(&key)
+            .map(|moves| moves.iter().collect())
+            .unwrap_or_default();
+        moves.sort_by_key(|book_move| std::cmp::Reverse(book_move.games()));
+        moves
+    }
+
+    // Only the moves that are legal here, in case another position shares
+    // the key.
+    pub fn moves(&self, position: &Position) -> Vec<&BookMove> {
+        self.moves_for_key(position.zobrist_key())
+            .into_iter()
+            .filter(|book_move| position.is_move_legal(&book_move.chess_move))
+            .collect()
+    }
+
+    pub fn most_played(&self, position: &Position) -> Option<ChessMove> {
+        self.moves(position)
+            .first()
+            .map(|book_move| book_move.chess_move.clone())
+    }
+
+    // One line per move: the position key in hex, the move, then its wins,
+    // draws, losses and unfinished games. Positions come out sorted by key
+    // so the same book always gives the same text.
+    pub fn to_text(&self) -> String {
+        let mut keys: Vec<&u64> = self.positions.keys().collect();
+        keys.sort();
+        let mut text = String::new();
+        for key in keys {
+            for book_move in &self.positions[key] {
+                text.push_str(&format!(
+                    "{:016x} {} {} {} {} {}\n",
+                    key,
+                    encode_move(&book_move.chess_move),
+                    book_move.wins,
+                    book_move.draws,
+                    book_move.losses,
+                    book_move.unfinished
+                ));
+            }
+        }
+        text
+    }
+
+    pub fn from_text(text: &str) -> Result<OpeningBook, BookError> {
+        let mut book = OpeningBook::new();
+        for line in text.lines().filter(|line| !line.trim().is_empty()) {
+            let malformed = || BookError::Malformed(String::from(line));
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            let [key, chess_move, wins, draws, losses, unfinished] = fields[..] else {
+                return Err(malformed());
+            };
+            let count = |field: &str| field.parse::<u32>().map_err(|_| malformed());
+            let key = u64::from_str_radix(key, 16).map_err(|_| malformed())?;
+            book.positions.entry(key).or_default().push(BookMove {
+                chess_move: decode_move(chess_move).ok_or_else(malformed)?,
+                wins: count(wins)?,
+                draws: count(draws)?,
+                losses: count(losses)?,
+                unfinished: count(unfinished)?,
+            });
+        }
+        Ok(book)
+    }
+}
+
+// Games in a PGN file start at the first tag after some movetext.
+fn split_games(corpus: &str) -> Vec<&str> {
+    let mut games = Vec::new();
+    let mut start = 0;
+    let mut in_movetext = false;
+    let mut offset = 0;
+    for line in corpus.split_inclusive('\n') {
+        let trimmed = line.trim();
+        if trimmed.starts_with('[') && in_movetext {
+            games.push(&corpus[start..offset]);
+            start = offset;
+            in_movetext = false;
+        } else if !trimmed.is_empty() && !trimmed.starts_with('[') {
+            in_movetext = true;
+        }
+        offset += line.len();
+    }
+    if !corpus[start..].trim().is_empty() {
+        games.push(&corpus[start..]);
+    }
+    games
+}
+
+// Moves are written without their position, so unlike UCI notation each
+// kind of move has its own spelling: e2e4, e2e4+ for a double pawn step,
+// e5d6x for en passant, e7e8q for a promotion and O-O or O-O-O.
+fn encode_move(chess_move: &ChessMove) -> String {
+    let squares =
+        |movement: &Move| movement.origin.to_algebraic() + &movement.destination.to_algebraic();
+    match chess_move {
+        ChessMove::RegularMove(movement) => squares(movement),
+        ChessMove::PawnSkip(movement) => squares(movement) + "+",
+        ChessMove::EnPassant(movement, _) => squares(movement) + "x",
+        ChessMove::Promotion(movement, kind) => squares(movement) + promotion_letter(kind),
+        ChessMove::CastleRight => String::from("O-O"),
+        ChessMove::CastleLeft => String::from("O-O-O"),
+    }
+}
+
+fn decode_move(text: &str) -> Option<ChessMove> {
+    match text {
+        "O-O" => return Some(ChessMove::CastleRight),
+        "O-O-O" => return Some(ChessMove::CastleLeft),
+        _ => {}
+    }
+    let square = |range: std::ops::Range<usize>| {
+        let name = text.get(range)?;
+        let mut characters = name.chars();
+        let (file, rank) = (characters.next()?, characters.next()?);
+        (('a'..='h').contains(&file) && ('1'..='8').contains(&rank))
+            .then(|| Coords::from_algebraic(name))
+    };
+    let movement = Move {
+        origin: square(0..2)?,
+        destination: square(2..4)?,
+    };
+    match text.get(4..)? {
+        "" => Some(ChessMove::RegularMove(movement)),
+        "+" => Some(ChessMove::PawnSkip(movement)),
+        "x" => {
+            // the captured pawn is beside the origin, level with it
+            let captured = Coords {
+                x: movement.destination.x,
+                y: movement.origin.y,
+            };
+            Some(ChessMove::EnPassant(movement, captured))
+        }
+        letter => PieceKind::promoteable()
+            .find(|kind| promotion_letter(kind) == letter)
+            .map(|kind| ChessMove::Promotion(movement, *kind)),
+    }
+}
+
+fn promotion_letter(kind: &PieceKind) -> &'static str {
+    match kind {
+        PieceKind::Rook => "r",
+        PieceKind::Knight => "n",
+        PieceKind::Bishop => "b",
+        _ => "q",
+    }
+}
+
+// Plays the book's most played move while the game is still in it, and
+// leaves the rest to another player.
+pub struct BookPlayer {
+    pub book: OpeningBook,
+    pub fallback: Box<dyn Player + Send + Sync>,
+}
+
+impl BookPlayer {
+    pub fn new(book: OpeningBook, fallback: Box<dyn Player + Send + Sync>) -> BookPlayer {
+        BookPlayer { book, fallback }
+    }
+}
+
+impl Player for BookPlayer {
+    fn offer_move(&self, position: &Position) -> ChessMove {
+        self.book
+            .most_played(position)
+            .unwrap_or_else(|| self.fallback.offer_move(position))
+    }
+    fn evalutate(&self, position: &Position) -> isize {
+        self.fallback.evalutate(position)
+    }
+    fn offer_move_timed(&self, position: &Position, clock: &ClockState) -> ChessMove {
+        self.book
+            .most_played(position)
+            .unwrap_or_else(|| self.fallback.offer_move_timed(position, clock))
+    }
+    fn new_game(&self) {
+        self.fallback.new_game();
+    }
+    fn move_played(&self, position: &Position, chess_move: &ChessMove) {
+        self.fallback.move_played(position, chess_move);
+    }
+}
+
+impl Display for BookPlayer {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} with a book", self.fallback)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::FirstMovePlayer;
+
+    use super::*;
+
+    const CORPUS: &str = "[Event \"One\"]
+[Result \"1-0\"]
+
+1. e4 e5 2. Nf3 Nc6 3. Bb5 1-0
+
+[Event \"Two\"]
+[Result \"1/2-1/2\"]
+
+1. e4 c5 2. Nf3 1/2-1/2
+
+[Event \"Three\"]
+[Result \"0-1\"]
+
+1. Nf3 e5 2. e4 (2. d4) Nc6 3. Bb5 0-1
+";
+
+    fn san(text: &str, position: &Position) -> ChessMove {
+        ChessMove::from_san(text, position).unwrap()
+    }
+
+    #[test]
+    fn counts_moves_and_results_from_the_mover_side() {
+        let book = OpeningBook::from_pgn(CORPUS, 5).unwrap();
+        let initial = Position::initial();
+        let moves = book.moves(&initial);
+        assert_eq!(moves.len(), 2);
+        assert_eq!(moves[0].chess_move, san("e4", &initial));
+        assert_eq!((moves[0].wins, moves[0].draws, moves[0].losses), (1, 1, 0));
+        assert_eq!(moves[0].score(), Some(0.75));
+        assert_eq!((moves[1].wins, moves[1].losses), (0, 1));
+        // the first and third games reach the same position in different orders
+        let four_knights =
+            Position::from_fen("r1bqkbnr/pppp1ppp/2n5/4p3/4P3/5N2/PPPP1PPP/RNBQKB1R w KQkq - 2 3");
+        let replies = book.moves(&four_knights);
+        assert_eq!(replies.len(), 1);
+        assert_eq!(replies[0].games(), 2);
+        assert_eq!((replies[0].wins, replies[0].losses), (1, 1));
+    }
+
+    #[test]
+    fn survives_a_round_trip_through_text() {
+        let mut book = OpeningBook::from_pgn(CORPUS, 10).unwrap();
+        let tree = VariationTree::from_pgn("1. e4 e6 2. e5 d5 3. exd6 c5 4. d7+ Ke7 5. dxc8=N+ *")
+            .unwrap();
+        book.add_game(&tree, 20);
+        book.add_game(
+            &VariationTree::from_pgn("[FEN \"r3k3/8/8/8/8/8/8/4K2R w Kq - 0 1\"] 1. O-O O-O-O *")
+                .unwrap(),
+            2,
+        );
+        let text = book.to_text();
+        assert_eq!(OpeningBook::from_text(&text), Ok(book.clone()));
+        assert_eq!(OpeningBook::from_text(&text).unwrap().to_text(), text);
+        assert_eq!(
+            OpeningBook::from_text("00ff e2e9 1 0 0 0"),
+            Err(BookError::Malformed(String::from("00ff e2e9 1 0 0 0")))
+        );
+    }
+
+    #[test]
+    fn book_player_leaves_the_book_for_its_fallback() {
+        let book = OpeningBook::from_pgn(CORPUS, 2).unwrap();
+        let player = BookPlayer::new(book, Box::new(FirstMovePlayer));
+        let initial = Position::initial();
+        assert_eq!(player.offer_move(&initial), san("e4", &initial));
+        let out_of_book = initial.after_move(&san("d4", &initial));
+        assert_eq!(
+            player.offer_move(&out_of_book),
+            FirstMovePlayer.offer_move(&out_of_book)
+        );
+    }
+}
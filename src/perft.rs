@@ -0,0 +1,251 @@
+use std::fmt::Display;
+
+use crate::ChessMove;
+use crate::Position;
+
+// The usual perft positions with their leaf counts, in the EPD-style format
+// `PerftCase::parse_suite` reads: the position, then ";D<depth> <nodes>"
+// for each depth that is known. The first five are from the Chess
+// Programming Wiki, the others each try one rule that move generators tend
+// to get wrong.
+pub const STANDARD_PERFT_SUITE: &str = "\
+rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1 ;D1 20 ;D2 400 ;D3 8902 ;D4 197281 ;D5 4865609
+r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1 ;D1 48 ;D2 2039 ;D3 97862 ;D4 4085603
+8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1 ;D1 14 ;D2 191 ;D3 2812 ;D4 43238 ;D5 674624
+r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1 ;D1 6 ;D2 264 ;D3 9467 ;D4 422333
+rnbq1k1r/pp1Pbppp/2p5/8/2B5/8/PPP1NnPP/RNBQK2R w KQ - 1 8 ;D1 44 ;D2 1486 ;D3 62379 ;D4 2103487
+# en passant that would leave the king in check
+3k4/3p4/8/K1P4r/8/8/8/8 b - - 0 1 ;D6 1134888
+8/8/4k3/8/2p5/8/B2P2K1/8 w - - 0 1 ;D6 1015133
+# en passant that gives check
+8/8/1k6/2b5/2pP4/8/5K2/8 b - d3 0 1 ;D6 1440467
+# castling that gives check
+5k2/8/8/8/8/8/8/4K2R w K - 0 1 ;D6 661072
+3k4/8/8/8/8/8/8/R3K3 w Q - 0 1 ;D6 803711
+# castling rights lost when rooks are taken, and castling through attacks
+r3k2r/1b4bq/8/8/8/8/7B/R3K2R w KQkq - 0 1 ;D4 1274206
+r3k2r/8/3Q4/8/8/5q2/8/R3K2R b KQkq - 0 1 ;D4 1720476
+# promotions out of check, giving check, and under-promotions
+2K2r2/4P3/8/8/8/8/8/3k4 w - - 0 1 ;D6 3821001
+4k3/1P6/8/8/8/8/K7/8 w - - 0 1 ;D6 217342
+8/P1k5/K7/8/8/8/8/8 w - - 0 1 ;D6 92683
+# discovered check
+8/8/1P2K3/8/2n5/1q6/8/5k2 b - - 0 1 ;D5 1004658
+# stalemates and mates
+K1k5/8/P7/8/8/8/8/8 w - - 0 1 ;D6 2217
+8/k1P5/8/1K6/8/8/8/8 w - - 0 1 ;D7 567584
+8/8/2k5/5q2/5n2/8/5K2/8 b - - 0 1 ;D4 23527
+";
+
+// The number of move sequences `depth` plies long from `position`.
+pub fn perft(position: &Position, depth: usize) -> u64 {
+    match depth {
+        0 => 1,
+        1 => position.all_legal_moves().len() as u64,
+        _ => position
+            .all_legal_moves()
+            .iter()
+            .map(|chess_move| perft(&position.after_move(chess_move), depth - 1))
+            .sum(),
+    }
+}
+
+// The perft count under each legal move, for finding which one a wrong
+// total comes from.
+pub fn perft_divide(position: &Position, depth: usize) -> Vec<(ChessMove, u64)> {
+    position
+        .all_legal_moves()
+        .into_iter()
+        .map(|chess_move| {
+            let nodes = perft(&position.after_move(&chess_move), depth.saturating_sub(1));
+            (chess_move, nodes)
+        })
+        .collect()
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum PerftSuiteError {
+    // a line that isn't a position followed by depths and counts
+    Malformed(String),
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct PerftCase {
+    pub position: Position,
+    // by depth, shallowest first
+    pub expected: Vec<(usize, u64)>,
+}
+
+impl PerftCase {
+    // Reads "<FEN> ;D1 20 ;D2 400 ...", one position a line. The move
+    // counters can be left out as EPD does, and blank lines and lines
+    // starting with '#' are skipped.
+    pub fn parse_suite(text: &str) -> Result<Vec<PerftCase>, PerftSuiteError> {
+        text.lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+            .map(PerftCase::parse)
+            .collect()
+    }
+
+    pub fn parse(line: &str) -> Result<PerftCase, PerftSuiteError> {
+        let malformed = || PerftSuiteError::Malformed(String::from(line));
+        let mut fields = line.split(';');
+        let fen = fields.next().unwrap_or_default().trim();
+        let fen = match fen.split_whitespace().count() {
+            4 => format!("{fen} 0 1"),
+            6 => fen.split_whitespace().collect::<Vec<_>>().join(" "),
+            _ => return Err(malformed()),
+        };
+        let position = Position::try_from_fen(&fen).map_err(|_| malformed())?;
+        let mut expected = fields
+            .map(|field| {
+                let (depth, nodes) = field.trim().split_once(' ').ok_or_else(malformed)?;
+                let depth = depth
+                    .strip_prefix('D')
+                    .and_then(|depth| depth.parse().ok())
+                    .ok_or_else(malformed)?;
+                let nodes = nodes.trim().parse().map_err(|_| malformed())?;
+                Ok((depth, nodes))
+            })
+            .collect::<Result<Vec<(usize, u64)>, PerftSuiteError>>()?;
+        if expected.is_empty() {
+            return Err(malformed());
+        }
+        expected.sort();
+        Ok(PerftCase { position, expected })
+    }
+}
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct PerftOutcome {
+    pub fen: String,
+    pub depth: usize,
+    pub expected: u64,
+    pub nodes: u64,
+}
+
+impl PerftOutcome {
+    pub fn passed(&self) -> bool {
+        self.nodes == self.expected
+    }
+}
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct PerftReport {
+    pub outcomes: Vec<PerftOutcome>,
+}
+
+impl PerftReport {
+    pub fn passed(&self) -> bool {
+        self.outcomes.iter().all(PerftOutcome::passed)
+    }
+
+    pub fn failures(&self) -> Vec<&PerftOutcome> {
+        self.outcomes
+            .iter()
+            .filter(|outcome| !outcome.passed())
+            .collect()
+    }
+}
+
+// One line per depth counted, then the tally.
+impl Display for PerftReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for outcome in &self.outcomes {
+            if outcome.passed() {
+                writeln!(
+                    f,
+                    "ok   D{} {} {}",
+                    outcome.depth, outcome.nodes, outcome.fen
+                )?;
+            } else {
+                writeln!(
+                    f,
+                    "FAIL D{} expected {}, got {} {}",
+                    outcome.depth, outcome.expected, outcome.nodes, outcome.fen
+                )?;
+            }
+        }
+        write!(
+            f,
+            "{} of {} passed",
+            self.outcomes.len() - self.failures().len(),
+            self.outcomes.len()
+        )
+    }
+}
+
+// Counts every case at each of its depths up to `max_depth`, deeper ones
+// being left out so that a quick run stays quick.
+pub fn run_perft_suite(cases: &[PerftCase], max_depth: usize) -> PerftReport {
+    let outcomes = cases
+        .iter()
+        .flat_map(|case| {
+            case.expected
+                .iter()
+                .filter(|(depth, _)| *depth <= max_depth)
+                .map(|(depth, expected)| PerftOutcome {
+                    fen: case.position.to_fen(),
+                    depth: *depth,
+                    expected: *expected,
+                    nodes: perft(&case.position, *depth),
+                })
+        })
+        .collect();
+    PerftReport { outcomes }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_suites_with_and_without_move_counters() {
+        let cases = PerftCase::parse_suite(
+            "# a comment\n\n8/8/8/8/8/8/8/K6k w - - ;D2 9 ;D1 3\n\
+             8/8/8/8/8/8/8/K6k b - - 3 40 ;D1 3\n",
+        )
+        .unwrap();
+        assert_eq!(cases.len(), 2);
+        assert_eq!(cases[0].expected, vec![(1, 3), (2, 9)]);
+        assert_eq!(cases[1].position.to_move, crate::PieceColor::Black);
+        for line in [
+            "8/8/8/8/8/8/8/K6k w - -",
+            "8/8/8/8/8/8/8/K6k w - - ;D1",
+            "8/8/8/8/8/8/8/K6k w - - 0 1 2 ;D1 3",
+            "9/8/8/8/8/8/8/8 w - - ;D1 3",
+        ] {
+            assert_eq!(
+                PerftCase::parse(line),
+                Err(PerftSuiteError::Malformed(String::from(line)))
+            );
+        }
+    }
+
+    #[test]
+    fn standard_suite_passes_at_shallow_depths() {
+        let cases = PerftCase::parse_suite(STANDARD_PERFT_SUITE).unwrap();
+        assert_eq!(cases.len(), 19);
+        let report = run_perft_suite(&cases, 2);
+        assert_eq!(report.outcomes.len(), 10);
+        assert!(report.passed(), "{report}");
+        let start = Position::initial();
+        let divided = perft_divide(&start, 3);
+        assert_eq!(divided.len(), 20);
+        assert_eq!(
+            divided.iter().map(|(_, nodes)| nodes).sum::<u64>(),
+            perft(&start, 3)
+        );
+    }
+
+    #[test]
+    fn wrong_counts_are_reported() {
+        let cases = PerftCase::parse_suite("8/8/8/8/8/8/8/K6k w - - ;D1 3 ;D2 10").unwrap();
+        let report = run_perft_suite(&cases, 2);
+        assert!(!report.passed());
+        assert_eq!(report.failures().len(), 1);
+        assert_eq!(report.failures()[0].nodes, 9);
+        assert!(report.to_string().ends_with("1 of 2 passed"));
+    }
+}
@@ -0,0 +1,459 @@
+use std::fmt::Display;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::san::SanError;
+use crate::DrawReason;
+use crate::EndgameClass;
+use crate::GameResult;
+use crate::PieceColor;
+use crate::Position;
+use crate::WinReason;
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum PgnError {
+    Malformed(String),
+    InvalidMove(SanError),
+    UnbalancedVariation,
+}
+
+impl From<SanError> for PgnError {
+    fn from(error: SanError) -> PgnError {
+        PgnError::InvalidMove(error)
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum PgnToken {
+    Tag(String, String),
+    Move(String),
+    Comment(String),
+    Nag(u8),
+    VariationStart,
+    VariationEnd,
+    Result(String),
+}
+
+const RESULTS: [&str; 4] = ["1-0", "0-1", "1/2-1/2", "*"];
+
+pub fn tokenize(pgn: &str) -> Result<Vec<PgnToken>, PgnError> {
+    let mut tokens = vec![];
+    let mut characters = pgn.chars().peekable();
+
+    while let Some(character) = characters.next() {
+        match character {
+            c if c.is_whitespace() => {}
+            '(' => tokens.push(PgnToken::VariationStart),
+            ')' => tokens.push(PgnToken::VariationEnd),
+            '{' => {
+                let comment: String = characters.by_ref().take_while(|c| *c != '}').collect();
+                tokens.push(PgnToken::Comment(comment.trim().to_string()));
+            }
+            ';' => {
+                let comment: String = characters.by_ref().take_while(|c| *c != '\n').collect();
+                tokens.push(PgnToken::Comment(comment.trim().to_string()));
+            }
+            '[' => {
+                // a ] inside the quoted value doesn't close the tag
+                let mut tag = String::new();
+                let mut quoted = false;
+                while let Some(next) = characters.next() {
+                    match next {
+                        ']' if !quoted => break,
+                        '"' => quoted = !quoted,
+                        '\\' if quoted => {
+                            tag.push(next);
+                            if let Some(escaped) = characters.next() {
+                                tag.push(escaped);
+                            }
+                            continue;
+                        }
+                        _ => {}
+                    }
+                    tag.push(next);
+                }
+                tokens.push(parse_tag(&tag)?);
+            }
+            '$' => {
+                let mut digits = String::new();
+                while let Some(digit) = characters.next_if(|c| c.is_ascii_digit()) {
+                    digits.push(digit);
+                }
+                let nag = digits
+                    .parse()
+                    .map_err(|_| PgnError::Malformed(format!("${}", digits)))?;
+                tokens.push(PgnToken::Nag(nag));
+            }
+            _ => {
+                let mut word = String::from(character);
+                while let Some(next) =
+                    characters.next_if(|c| !c.is_whitespace() && !"(){};[$".contains(*c))
+                {
+                    word.push(next);
+                }
+                if RESULTS.contains(&word.as_str()) {
+                    tokens.push(PgnToken::Result(word));
+                } else {
+                    let san = strip_move_number(&word);
+                    if !san.is_empty() {
+                        tokens.push(PgnToken::Move(san.to_string()));
+                    }
+                }
+            }
+        }
+    }
+    Ok(tokens)
+}
+
+fn strip_move_number(word: &str) -> &str {
+    let without_number = word.trim_start_matches(|c: char| c.is_ascii_digit());
+    if without_number.starts_with('.') {
+        without_number.trim_start_matches('.')
+    } else {
+        word
+    }
+}
+
+fn parse_tag(tag: &str) -> Result<PgnToken, PgnError> {
+    let malformed = || PgnError::Malformed(format!("[{}]", tag));
+    let (name, value) = tag
+        .trim()
+        .split_once(char::is_whitespace)
+        .ok_or_else(malformed)?;
+    let value = value
+        .trim()
+        .strip_prefix('"')
+        .and_then(|value| value.strip_suffix('"'))
+        .ok_or_else(malformed)?;
+    Ok(PgnToken::Tag(name.to_string(), unescape_tag_value(value)))
+}
+
+fn unescape_tag_value(value: &str) -> String {
+    let mut unescaped = String::new();
+    let mut characters = value.chars();
+    while let Some(character) = characters.next() {
+        match character {
+            '\\' => unescaped.extend(characters.next()),
+            _ => unescaped.push(character),
+        }
+    }
+    unescaped
+}
+
+pub fn result_token(result: Option<&GameResult>) -> &'static str {
+    match result {
+        None => "*",
+        Some(GameResult::WhiteWin(_)) => "1-0",
+        Some(GameResult::BlackWin(_)) => "0-1",
+        Some(GameResult::Draw(_)) => "1/2-1/2",
+    }
+}
+
+pub fn termination_tag(result: Option<&GameResult>) -> &'static str {
+    match result {
+        None => "unterminated",
+        Some(GameResult::WhiteWin(WinReason::TimeForfeit))
+        | Some(GameResult::BlackWin(WinReason::TimeForfeit)) => "time forfeit",
+        Some(GameResult::WhiteWin(WinReason::Adjudicated))
+        | Some(GameResult::BlackWin(WinReason::Adjudicated))
+        | Some(GameResult::Draw(DrawReason::Adjudicated)) => "adjudication",
+        Some(_) => "normal",
+    }
+}
+
+// The reason is read off the Termination tag and the final position: a
+// decisive game that didn't end in mate was resigned, and a draw that wasn't
+// a stalemate or a dead position was agreed.
+pub fn result_from_tokens(
+    token: &str,
+    termination: Option<&str>,
+    final_position: &Position,
+) -> Option<GameResult> {
+    let winner = match token {
+        "1-0" => PieceColor::White,
+        "0-1" => PieceColor::Black,
+        "1/2-1/2" => {
+            return Some(GameResult::Draw(match termination {
+                Some("adjudication") => DrawReason::Adjudicated,
+                _ if final_position.is_stalemate() => DrawReason::Stalemate,
+                _ if final_position.endgame_class() == EndgameClass::InsufficientMaterial => {
+                    DrawReason::InsufficientMaterial
+                }
+                _ => DrawReason::Agreement,
+            }))
+        }
+        _ => return None,
+    };
+    let reason = match termination {
+        Some("time forfeit") => WinReason::TimeForfeit,
+        Some("adjudication") => WinReason::Adjudicated,
+        _ if final_position.is_checkmate() => WinReason::Checkmate,
+        _ => WinReason::Resignation,
+    };
+    Some(GameResult::win_for(winner, reason))
+}
+
+// The Event, Site, Date, Round, White and Black tags every exported game
+// carries. The Result tag completing the roster comes from the game itself.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct SevenTagRoster {
+    pub event: String,
+    pub site: String,
+    pub date: String,
+    pub round: String,
+    pub white: String,
+    pub black: String,
+}
+
+impl Default for SevenTagRoster {
+    fn default() -> SevenTagRoster {
+        SevenTagRoster {
+            event: String::from("?"),
+            site: String::from("?"),
+            date: String::from("????.??.??"),
+            round: String::from("?"),
+            white: String::from("?"),
+            black: String::from("?"),
+        }
+    }
+}
+
+impl SevenTagRoster {
+    pub fn for_players(white: &dyn Display, black: &dyn Display) -> SevenTagRoster {
+        SevenTagRoster {
+            date: pgn_date_today(),
+            white: white.to_string(),
+            black: black.to_string(),
+            ..SevenTagRoster::default()
+        }
+    }
+
+    pub fn event(mut self, event: &str) -> SevenTagRoster {
+        self.event = event.to_string();
+        self
+    }
+
+    pub fn site(mut self, site: &str) -> SevenTagRoster {
+        self.site = site.to_string();
+        self
+    }
+
+    pub fn date(mut self, date: &str) -> SevenTagRoster {
+        self.date = date.to_string();
+        self
+    }
+
+    pub fn round(mut self, round: &str) -> SevenTagRoster {
+        self.round = round.to_string();
+        self
+    }
+
+    pub fn white(mut self, white: &str) -> SevenTagRoster {
+        self.white = white.to_string();
+        self
+    }
+
+    pub fn black(mut self, black: &str) -> SevenTagRoster {
+        self.black = black.to_string();
+        self
+    }
+
+    pub fn to_tags(&self, result: Option<&GameResult>) -> String {
+        [
+            ("Event", self.event.as_str()),
+            ("Site", &self.site),
+            ("Date", &self.date),
+            ("Round", &self.round),
+            ("White", &self.white),
+            ("Black", &self.black),
+            ("Result", result_token(result)),
+        ]
+        .iter()
+        .map(|(name, value)| write_tag(name, value))
+        .collect()
+    }
+}
+
+// Tags the game itself decides, written out from its result and starting
+// position rather than kept.
+const GAME_TAGS: [&str; 4] = ["Result", "Termination", "SetUp", "FEN"];
+
+// Every tag pair of a game: the seven tag roster and, after it, any other
+// tags in the order they were read or set.
+#[derive(Clone, PartialEq, Eq, Debug, Default)]
+pub struct PgnTags {
+    pub roster: SevenTagRoster,
+    pub others: Vec<(String, String)>,
+}
+
+impl PgnTags {
+    pub fn get(&self, name: &str) -> Option<&str> {
+        match name {
+            "Event" => Some(&self.roster.event),
+            "Site" => Some(&self.roster.site),
+            "Date" => Some(&self.roster.date),
+            "Round" => Some(&self.roster.round),
+            "White" => Some(&self.roster.white),
+            "Black" => Some(&self.roster.black),
+            _ => self
+                .others
+                .iter()
+                .find(|(other, _)| other == name)
+                .map(|(_, value)| value.as_str()),
+        }
+    }
+
+    // Whether the tag could be set, which the ones the game decides can't.
+    pub fn set(&mut self, name: &str, value: &str) -> bool {
+        let value = value.to_string();
+        match name {
+            _ if GAME_TAGS.contains(&name) => return false,
+            "Event" => self.roster.event = value,
+            "Site" => self.roster.site = value,
+            "Date" => self.roster.date = value,
+            "Round" => self.roster.round = value,
+            "White" => self.roster.white = value,
+            "Black" => self.roster.black = value,
+            _ => match self.others.iter_mut().find(|(other, _)| other == name) {
+                Some((_, existing)) => *existing = value,
+                None => self.others.push((name.to_string(), value)),
+            },
+        }
+        true
+    }
+
+    // Only tags outside the roster can be removed.
+    pub fn remove(&mut self, name: &str) -> Option<String> {
+        let index = self.others.iter().position(|(other, _)| other == name)?;
+        Some(self.others.remove(index).1)
+    }
+
+    pub fn from_tokens(tokens: &[PgnToken]) -> PgnTags {
+        let mut tags = PgnTags::default();
+        for token in tokens {
+            if let PgnToken::Tag(name, value) = token {
+                tags.set(name, value);
+            }
+        }
+        tags
+    }
+
+    pub fn to_tags(&self, result: Option<&GameResult>) -> String {
+        let mut tags = self.roster.to_tags(result);
+        for (name, value) in &self.others {
+            tags.push_str(&write_tag(name, value));
+        }
+        tags
+    }
+}
+
+impl From<SevenTagRoster> for PgnTags {
+    fn from(roster: SevenTagRoster) -> PgnTags {
+        PgnTags {
+            roster,
+            others: Vec::new(),
+        }
+    }
+}
+
+// YYYY.MM.DD for a day counted from 1970-01-01, using the proleptic
+// Gregorian calendar like PGN dates do.
+pub fn pgn_date_from_unix_days(days: i64) -> String {
+    let shifted = days + 719468;
+    let era = shifted.div_euclid(146097);
+    let day_of_era = shifted.rem_euclid(146097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+    let month = if month_index < 10 {
+        month_index + 3
+    } else {
+        month_index - 9
+    };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    format!("{:04}.{:02}.{:02}", year, month, day)
+}
+
+fn pgn_date_today() -> String {
+    match SystemTime::now().duration_since(UNIX_EPOCH) {
+        Ok(elapsed) => pgn_date_from_unix_days((elapsed.as_secs() / 86400) as i64),
+        Err(_) => SevenTagRoster::default().date,
+    }
+}
+
+pub fn escape_tag_value(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+pub fn write_tag(name: &str, value: &str) -> String {
+    format!("[{} \"{}\"]\n", name, escape_tag_value(value))
+}
+
+// joins movetext tokens, keeping parentheses tight around variations but
+// leaving those inside comments alone
+pub fn join_movetext(tokens: &[String]) -> String {
+    let mut movetext = String::new();
+    for (index, token) in tokens.iter().enumerate() {
+        if index > 0 && token != ")" && tokens[index - 1] != "(" {
+            movetext.push(' ');
+        }
+        movetext.push_str(token);
+    }
+    movetext
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tokenizes_movetext() {
+        assert_eq!(
+            tokenize(
+                "[Event \"Casual \\\"game\\\"\"]\n1. e4 {best by test} e5 (1...c5 $1) 2.Nf3 1-0"
+            )
+            .unwrap(),
+            vec![
+                PgnToken::Tag(String::from("Event"), String::from("Casual \"game\"")),
+                PgnToken::Move(String::from("e4")),
+                PgnToken::Comment(String::from("best by test")),
+                PgnToken::Move(String::from("e5")),
+                PgnToken::VariationStart,
+                PgnToken::Move(String::from("c5")),
+                PgnToken::Nag(1),
+                PgnToken::VariationEnd,
+                PgnToken::Move(String::from("Nf3")),
+                PgnToken::Result(String::from("1-0")),
+            ]
+        );
+    }
+
+    #[test]
+    fn escapes_tag_values() {
+        assert_eq!(
+            write_tag("Event", "\"quoted\" \\ slash"),
+            "[Event \"\\\"quoted\\\" \\\\ slash\"]\n"
+        );
+    }
+
+    #[test]
+    fn formats_pgn_dates() {
+        assert_eq!(pgn_date_from_unix_days(0), "1970.01.01");
+        assert_eq!(pgn_date_from_unix_days(11016), "2000.02.29");
+        assert_eq!(pgn_date_from_unix_days(20740), "2026.10.14");
+    }
+
+    #[test]
+    fn writes_seven_tag_roster() {
+        let roster = SevenTagRoster::for_players(&"Planner", &"First available move")
+            .event("Gauntlet")
+            .date("2024.01.02")
+            .round("3");
+        assert_eq!(
+            roster.to_tags(Some(&GameResult::WhiteWin(WinReason::Resignation))),
+            "[Event \"Gauntlet\"]\n[Site \"?\"]\n[Date \"2024.01.02\"]\n[Round \"3\"]\n\
+             [White \"Planner\"]\n[Black \"First available move\"]\n[Result \"1-0\"]\n"
+        );
+    }
+}
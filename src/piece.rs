@@ -25,16 +25,30 @@ impl Piece {
             },
             _ => None,
         };
-        if kind.is_none() || color.is_none() {
-            None
-        } else {
-            Some(Piece {
-                kind: kind.unwrap(),
-                color: color.unwrap(),
-            })
+        match (kind, color) {
+            (Some(kind), Some(color)) => Some(Piece { kind, color }),
+            _ => None,
         }
     }
 
+    pub fn from_fen_char(character: char) -> Option<Piece> {
+        let kind = match character.to_ascii_lowercase() {
+            'p' => PieceKind::Pawn,
+            'r' => PieceKind::Rook,
+            'n' => PieceKind::Knight,
+            'b' => PieceKind::Bishop,
+            'q' => PieceKind::Queen,
+            'k' => PieceKind::King,
+            _ => return None,
+        };
+        let color = if character.is_ascii_uppercase() {
+            PieceColor::White
+        } else {
+            PieceColor::Black
+        };
+        Some(Piece { kind, color })
+    }
+
     pub fn to_fen_char(&self) -> char {
         match self.kind {
             PieceKind::Pawn => match self.color {
@@ -76,6 +90,29 @@ pub enum PieceKind {
 }
 
 impl PieceKind {
+    pub fn index(&self) -> usize {
+        match self {
+            PieceKind::Pawn => 0,
+            PieceKind::Rook => 1,
+            PieceKind::Knight => 2,
+            PieceKind::Bishop => 3,
+            PieceKind::Queen => 4,
+            PieceKind::King => 5,
+        }
+    }
+
+    pub fn all() -> std::slice::Iter<'static, PieceKind> {
+        [
+            PieceKind::Pawn,
+            PieceKind::Rook,
+            PieceKind::Knight,
+            PieceKind::Bishop,
+            PieceKind::Queen,
+            PieceKind::King,
+        ]
+        .iter()
+    }
+
     pub fn promoteable() -> std::slice::Iter<'static, PieceKind> {
         [
             PieceKind::Rook,
@@ -94,6 +131,13 @@ pub enum PieceColor {
 }
 
 impl PieceColor {
+    pub fn index(&self) -> usize {
+        match self {
+            PieceColor::White => 0,
+            PieceColor::Black => 1,
+        }
+    }
+
     pub fn opposite(&self) -> PieceColor {
         match self {
             PieceColor::White => PieceColor::Black,
@@ -0,0 +1,250 @@
+use crate::Coords;
+use crate::Piece;
+use crate::PieceColor;
+use crate::PieceKind;
+use crate::Position;
+
+// Tables are written the way boards are printed, rank 8 first, as white
+// sees them; black reads them upside down.
+type Table = [isize; 64];
+
+#[rustfmt::skip]
+const PAWN_MIDDLEGAME: Table = [
+     0,   0,   0,   0,   0,   0,   0,   0,
+    50,  50,  50,  50,  50,  50,  50,  50,
+    10,  10,  20,  30,  30,  20,  10,  10,
+     5,   5,  10,  25,  25,  10,   5,   5,
+     0,   0,   0,  20,  20,   0,   0,   0,
+     5,  -5, -10,   0,   0, -10,  -5,   5,
+     5,  10,  10, -20, -20,  10,  10,   5,
+     0,   0,   0,   0,   0,   0,   0,   0,
+];
+
+// once the pieces are off, pawns are worth more the closer they are to
+// promoting
+#[rustfmt::skip]
+const PAWN_ENDGAME: Table = [
+     0,   0,   0,   0,   0,   0,   0,   0,
+    80,  80,  80,  80,  80,  80,  80,  80,
+    50,  50,  50,  50,  50,  50,  50,  50,
+    30,  30,  30,  30,  30,  30,  30,  30,
+    20,  20,  20,  20,  20,  20,  20,  20,
+    10,  10,  10,  10,  10,  10,  10,  10,
+     0,   0,   0,   0,   0,   0,   0,   0,
+     0,   0,   0,   0,   0,   0,   0,   0,
+];
+
+#[rustfmt::skip]
+const KNIGHT: Table = [
+   -50, -40, -30, -30, -30, -30, -40, -50,
+   -40, -20,   0,   0,   0,   0, -20, -40,
+   -30,   0,  10,  15,  15,  10,   0, -30,
+   -30,   5,  15,  20,  20,  15,   5, -30,
+   -30,   0,  15,  20,  20,  15,   0, -30,
+   -30,   5,  10,  15,  15,  10,   5, -30,
+   -40, -20,   0,   5,   5,   0, -20, -40,
+   -50, -40, -30, -30, -30, -30, -40, -50,
+];
+
+#[rustfmt::skip]
+const BISHOP: Table = [
+   -20, -10, -10, -10, -10, -10, -10, -20,
+   -10,   0,   0,   0,   0,   0,   0, -10,
+   -10,   0,   5,  10,  10,   5,   0, -10,
+   -10,   5,   5,  10,  10,   5,   5, -10,
+   -10,   0,  10,  10,  10,  10,   0, -10,
+   -10,  10,  10,  10,  10,  10,  10, -10,
+   -10,   5,   0,   0,   0,   0,   5, -10,
+   -20, -10, -10, -10, -10, -10, -10, -20,
+];
+
+#[rustfmt::skip]
+const ROOK: Table = [
+     0,   0,   0,   0,   0,   0,   0,   0,
+     5,  10,  10,  10,  10,  10,  10,   5,
+    -5,   0,   0,   0,   0,   0,   0,  -5,
+    -5,   0,   0,   0,   0,   0,   0,  -5,
+    -5,   0,   0,   0,   0,   0,   0,  -5,
+    -5,   0,   0,   0,   0,   0,   0,  -5,
+    -5,   0,   0,   0,   0,   0,   0,  -5,
+     0,   0,   0,   5,   5,   0,   0,   0,
+];
+
+#[rustfmt::skip]
+const QUEEN: Table = [
+   -20, -10, -10,  -5,  -5, -10, -10, -20,
+   -10,   0,   0,   0,   0,   0,   0, -10,
+   -10,   0,   5,   5,   5,   5,   0, -10,
+    -5,   0,   5,   5,   5,   5,   0,  -5,
+     0,   0,   5,   5,   5,   5,   0,  -5,
+   -10,   5,   5,   5,   5,   5,   0, -10,
+   -10,   0,   5,   0,   0,   0,   0, -10,
+   -20, -10, -10,  -5,  -5, -10, -10, -20,
+];
+
+// tucked away behind its pawns while there are pieces to attack it
+#[rustfmt::skip]
+const KING_MIDDLEGAME: Table = [
+   -30, -40, -40, -50, -50, -40, -40, -30,
+   -30, -40, -40, -50, -50, -40, -40, -30,
+   -30, -40, -40, -50, -50, -40, -40, -30,
+   -30, -40, -40, -50, -50, -40, -40, -30,
+   -20, -30, -30, -40, -40, -30, -30, -20,
+   -10, -20, -20, -20, -20, -20, -20, -10,
+    20,  20,   0,   0,   0,   0,  20,  20,
+    20,  30,  10,   0,   0,  10,  30,  20,
+];
+
+// in the centre once it has become a fighting piece
+#[rustfmt::skip]
+const KING_ENDGAME: Table = [
+   -50, -40, -30, -20, -20, -30, -40, -50,
+   -30, -20, -10,   0,   0, -10, -20, -30,
+   -30, -10,  20,  30,  30,  20, -10, -30,
+   -30, -10,  30,  40,  40,  30, -10, -30,
+   -30, -10,  30,  40,  40,  30, -10, -30,
+   -30, -10,  20,  30,  30,  20, -10, -30,
+   -30, -30,   0,   0,   0,   0, -30, -30,
+   -50, -30, -30, -30, -30, -30, -30, -50,
+];
+
+// What the pieces other than pawns and kings count towards the middlegame,
+// all of them on the board making a phase of 24.
+const FULL_PHASE: isize = 24;
+
+pub(crate) fn phase_weight(kind: &PieceKind) -> isize {
+    match kind {
+        PieceKind::Knight | PieceKind::Bishop => 1,
+        PieceKind::Rook => 2,
+        PieceKind::Queen => 4,
+        PieceKind::Pawn | PieceKind::King => 0,
+    }
+}
+
+// A bonus or penalty for every piece on every square, one set of tables for
+// the middlegame and one for the endgame, blended by how much material is
+// left. Both are indexed by PieceKind::index.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct PieceSquareTables {
+    pub middlegame: [Table; 6],
+    pub endgame: [Table; 6],
+}
+
+// the tables `PieceSquareTables::default` gives, for the search to read
+// without copying them
+pub(crate) const DEFAULT_TABLES: PieceSquareTables = PieceSquareTables {
+    middlegame: [
+        PAWN_MIDDLEGAME,
+        ROOK,
+        KNIGHT,
+        BISHOP,
+        QUEEN,
+        KING_MIDDLEGAME,
+    ],
+    endgame: [PAWN_ENDGAME, ROOK, KNIGHT, BISHOP, QUEEN, KING_ENDGAME],
+};
+
+impl Default for PieceSquareTables {
+    fn default() -> PieceSquareTables {
+        DEFAULT_TABLES
+    }
+}
+
+// Middlegame and endgame scores mixed by `phase`, the sum of the piece
+// weights before it is capped.
+pub(crate) fn blend(middlegame: isize, endgame: isize, phase: isize) -> isize {
+    let phase = phase.min(FULL_PHASE);
+    (middlegame * phase + endgame * (FULL_PHASE - phase)) / FULL_PHASE
+}
+
+impl PieceSquareTables {
+    // From 24 with every piece still on the board down to 0 with only kings
+    // and pawns. Promoted pieces can take it past 24, which counts as 24.
+    pub fn phase(position: &Position) -> isize {
+        position
+            .pieces(PieceColor::White)
+            .chain(position.pieces(PieceColor::Black))
+            .map(|(_, piece)| phase_weight(&piece.kind))
+            .sum::<isize>()
+            .min(FULL_PHASE)
+    }
+
+    // How well placed the pieces are, from the side to move's point of view.
+    pub fn evaluate(&self, position: &Position) -> isize {
+        let (mut middlegame, mut endgame) = (0, 0);
+        for (square, piece) in position
+            .pieces(PieceColor::White)
+            .chain(position.pieces(PieceColor::Black))
+        {
+            let (piece_middlegame, piece_endgame) = self.white_relative(&piece, &square);
+            middlegame += piece_middlegame;
+            endgame += piece_endgame;
+        }
+        let score = blend(middlegame, endgame, PieceSquareTables::phase(position));
+        match position.to_move {
+            PieceColor::White => score,
+            PieceColor::Black => -score,
+        }
+    }
+
+    // What `piece` on `square` is worth in the middlegame and the endgame,
+    // black's pieces counting against white.
+    pub(crate) fn white_relative(&self, piece: &Piece, square: &Coords) -> (isize, isize) {
+        let (rank, sign) = match piece.color {
+            PieceColor::White => (square.y, 1),
+            PieceColor::Black => (7 - square.y, -1),
+        };
+        let index = (rank * 8 + square.x) as usize;
+        (
+            sign * self.middlegame[piece.kind.index()][index],
+            sign * self.endgame[piece.kind.index()][index],
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::assert_evals_symmetric;
+
+    use super::*;
+
+    #[test]
+    fn knights_prefer_the_centre() {
+        let tables = PieceSquareTables::default();
+        let centre = Position::from_fen("4k3/8/8/8/4N3/8/8/4K3 w - - 0 1");
+        let rim = Position::from_fen("4k3/8/8/8/7N/8/8/4K3 w - - 0 1");
+        assert!(tables.evaluate(&centre) > tables.evaluate(&rim));
+        assert_eq!(
+            tables.evaluate(&centre),
+            -tables.evaluate(&centre.color_to_move(PieceColor::Black))
+        );
+    }
+
+    #[test]
+    fn kings_come_out_once_the_pieces_are_gone() {
+        let tables = PieceSquareTables::default();
+        assert_eq!(PieceSquareTables::phase(&Position::initial()), 24);
+        let sheltered = Position::from_fen("r2qk2r/8/8/8/8/8/8/R2Q2KR w - - 0 1");
+        let wandering = Position::from_fen("r2qk2r/8/8/8/4K3/8/8/R2Q3R w - - 0 1");
+        assert!(tables.evaluate(&sheltered) > tables.evaluate(&wandering));
+        let endgame_sheltered = Position::from_fen("4k3/8/8/8/8/8/8/6K1 w - - 0 1");
+        let endgame_central = Position::from_fen("4k3/8/8/8/4K3/8/8/8 w - - 0 1");
+        assert_eq!(PieceSquareTables::phase(&endgame_central), 0);
+        assert!(tables.evaluate(&endgame_central) > tables.evaluate(&endgame_sheltered));
+    }
+
+    #[test]
+    fn placement_is_symmetric() {
+        let tables = PieceSquareTables::default();
+        assert_evals_symmetric(
+            |position| tables.evaluate(position),
+            &[
+                Position::initial(),
+                Position::from_fen(
+                    "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+                ),
+                Position::from_fen("8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1"),
+            ],
+        );
+    }
+}
@@ -1,8 +1,270 @@
 use std::fmt::Display;
+use std::sync::mpsc::Receiver;
+use std::sync::Mutex;
 
-use crate::{ChessMove, Position};
+use crate::{
+    ChessMove, ClockState, EngineOption, OptionValue, Position, RuleSet, SearchResult,
+    StandardRules,
+};
 
 pub trait Player: Display {
     fn offer_move(&self, position: &Position) -> ChessMove;
     fn evalutate(&self, position: &Position) -> isize;
+    // Asked instead of `offer_move` in games played on a clock, so that
+    // players can think for as long as their remaining time allows.
+    fn offer_move_timed(&self, position: &Position, _clock: &ClockState) -> ChessMove {
+        self.offer_move(position)
+    }
+    // The move along with the line and score behind it. Players that don't
+    // search give their move as a line of one, scored by their evaluation
+    // of the position.
+    fn analyze(&self, position: &Position) -> SearchResult {
+        let best_move = self.offer_move(position);
+        SearchResult {
+            pv: vec![best_move.clone()],
+            best_move,
+            score: self.evalutate(position),
+            depth: 0,
+            nodes: 0,
+        }
+    }
+    // Called before the first move of every game, so that players can drop
+    // what they remembered about the previous one.
+    fn new_game(&self) {}
+    // Called for both players after every move of a game, with the position
+    // it was played from, so that players can follow along.
+    fn move_played(&self, _position: &Position, _chess_move: &ChessMove) {}
+    // Settings a GUI may change, declared to it over UCI.
+    fn options(&self) -> Vec<EngineOption> {
+        Vec::new()
+    }
+    // Only called with one of the declared options and a value it accepts.
+    fn set_option(&self, _name: &str, _value: &OptionValue) {}
+}
+
+// A player free to change itself as the game goes on, for engines that keep
+// tables, killers or book lines from one move to the next. Every `Player`
+// is one already; `StatefulPlayer` turns one back into a `Player`.
+pub trait EnginePlayer: Display {
+    fn offer_move(&mut self, position: &Position) -> ChessMove;
+    fn evaluate(&mut self, position: &Position) -> isize;
+    fn offer_move_timed(&mut self, position: &Position, _clock: &ClockState) -> ChessMove {
+        self.offer_move(position)
+    }
+    fn new_game(&mut self) {}
+    // The position the move was played from, for both sides' moves.
+    fn move_played(&mut self, _position: &Position, _chess_move: &ChessMove) {}
+}
+
+impl<P: Player + ?Sized> EnginePlayer for P {
+    fn offer_move(&mut self, position: &Position) -> ChessMove {
+        Player::offer_move(self, position)
+    }
+    fn evaluate(&mut self, position: &Position) -> isize {
+        Player::evalutate(self, position)
+    }
+    fn offer_move_timed(&mut self, position: &Position, clock: &ClockState) -> ChessMove {
+        Player::offer_move_timed(self, position, clock)
+    }
+    fn new_game(&mut self) {
+        Player::new_game(self);
+    }
+    fn move_played(&mut self, position: &Position, chess_move: &ChessMove) {
+        Player::move_played(self, position, chess_move);
+    }
+}
+
+// Lets an `EnginePlayer` take part wherever a `Player` is expected, games,
+// matches and UCI alike, by handing it out one call at a time.
+pub struct StatefulPlayer<E> {
+    engine: Mutex<E>,
+}
+
+impl<E: EnginePlayer> StatefulPlayer<E> {
+    pub fn new(engine: E) -> StatefulPlayer<E> {
+        StatefulPlayer {
+            engine: Mutex::new(engine),
+        }
+    }
+
+    pub fn into_inner(self) -> E {
+        self.engine.into_inner().expect("engine lock poisoned")
+    }
+
+    fn engine(&self) -> std::sync::MutexGuard<'_, E> {
+        self.engine.lock().expect("engine lock poisoned")
+    }
+}
+
+impl<E: EnginePlayer> Display for StatefulPlayer<E> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.engine().fmt(f)
+    }
+}
+
+impl<E: EnginePlayer> Player for StatefulPlayer<E> {
+    fn offer_move(&self, position: &Position) -> ChessMove {
+        self.engine().offer_move(position)
+    }
+    fn evalutate(&self, position: &Position) -> isize {
+        self.engine().evaluate(position)
+    }
+    fn offer_move_timed(&self, position: &Position, clock: &ClockState) -> ChessMove {
+        self.engine().offer_move_timed(position, clock)
+    }
+    fn new_game(&self) {
+        self.engine().new_game();
+    }
+    fn move_played(&self, position: &Position, chess_move: &ChessMove) {
+        self.engine().move_played(position, chess_move);
+    }
+}
+
+// A player whose moves come from somewhere else, a person at a terminal or
+// a web page, by way of `ask`. Moves that aren't legal in the position are
+// thrown away and `ask` is called again.
+pub struct CallbackPlayer<F> {
+    name: String,
+    ask: F,
+    rules: &'static dyn RuleSet,
+}
+
+impl<F: Fn(&Position) -> ChessMove> CallbackPlayer<F> {
+    pub fn new(name: &str, ask: F) -> CallbackPlayer<F> {
+        CallbackPlayer {
+            name: String::from(name),
+            ask,
+            rules: &StandardRules,
+        }
+    }
+
+    // The rules moves are checked against, standard chess by default.
+    pub fn with_rules(mut self, rules: &'static dyn RuleSet) -> CallbackPlayer<F> {
+        self.rules = rules;
+        self
+    }
+}
+
+// where the moves of `CallbackPlayer::from_channel` come from
+pub type AskMove = Box<dyn Fn(&Position) -> ChessMove + Send + Sync>;
+
+impl CallbackPlayer<AskMove> {
+    // Waits for each move to come down `moves`. Panics if the sending end
+    // goes away while a move is expected.
+    pub fn from_channel(name: &str, moves: Receiver<ChessMove>) -> CallbackPlayer<AskMove> {
+        let moves = Mutex::new(moves);
+        CallbackPlayer::new(
+            name,
+            Box::new(move |_: &Position| {
+                moves
+                    .lock()
+                    .expect("move channel lock poisoned")
+                    .recv()
+                    .expect("the sender hung up before the game was over")
+            }),
+        )
+    }
+}
+
+impl<F> Display for CallbackPlayer<F> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.name)
+    }
+}
+
+impl<F: Fn(&Position) -> ChessMove> Player for CallbackPlayer<F> {
+    fn offer_move(&self, position: &Position) -> ChessMove {
+        loop {
+            let chess_move = (self.ask)(position);
+            if self.rules.is_move_legal(position, &chess_move) {
+                return chess_move;
+            }
+        }
+    }
+    fn evalutate(&self, _position: &Position) -> isize {
+        0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::FirstMovePlayer;
+    use crate::HordeRules;
+
+    use super::*;
+
+    // remembers every move of the game so far
+    #[derive(Default)]
+    struct Scorekeeper {
+        moves: Vec<ChessMove>,
+        games: usize,
+    }
+
+    impl Display for Scorekeeper {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            write!(f, "Scorekeeper")
+        }
+    }
+
+    impl EnginePlayer for Scorekeeper {
+        fn offer_move(&mut self, position: &Position) -> ChessMove {
+            Player::offer_move(&FirstMovePlayer, position)
+        }
+        fn evaluate(&mut self, _position: &Position) -> isize {
+            self.moves.len() as isize
+        }
+        fn new_game(&mut self) {
+            self.moves.clear();
+            self.games += 1;
+        }
+        fn move_played(&mut self, _position: &Position, chess_move: &ChessMove) {
+            self.moves.push(chess_move.clone());
+        }
+    }
+
+    #[test]
+    fn engines_keep_what_they_saw_across_moves() {
+        let engine = StatefulPlayer::new(Scorekeeper::default());
+        let initial = Position::initial();
+        let first = engine.offer_move(&initial);
+        engine.move_played(&initial, &first);
+        assert_eq!(engine.evalutate(&initial.after_move(&first)), 1);
+        engine.new_game();
+        assert_eq!(engine.evalutate(&initial), 0);
+        assert_eq!(engine.to_string(), "Scorekeeper");
+        let engine = engine.into_inner();
+        assert_eq!(engine.games, 1);
+    }
+
+    #[test]
+    fn moves_sent_down_a_channel_are_played() {
+        let initial = Position::initial();
+        let (sender, receiver) = std::sync::mpsc::channel();
+        let player = CallbackPlayer::from_channel("Someone", receiver);
+        let e4 = ChessMove::from_uci_long("e2e4", &initial);
+        // the first one is the wrong way round and isn't played
+        sender
+            .send(ChessMove::from_uci_long("e4e2", &initial))
+            .unwrap();
+        sender.send(e4.clone()).unwrap();
+        assert_eq!(player.offer_move(&initial), e4);
+        assert_eq!(player.to_string(), "Someone");
+        let replies = CallbackPlayer::new("Mirror", |position: &Position| {
+            FirstMovePlayer.offer_move(position)
+        });
+        let after = initial.after_move(&e4);
+        assert_eq!(
+            replies.offer_move(&after),
+            FirstMovePlayer.offer_move(&after)
+        );
+    }
+    #[test]
+    fn moves_are_checked_against_the_players_rules() {
+        // only horde pawns may step two squares from the first rank
+        let position = Position::from_fen("4k3/8/8/8/8/8/8/P7 w - - 0 1");
+        let skip = ChessMove::from_uci_long("a1a3", &position);
+        let player =
+            CallbackPlayer::new("Horde", |_: &Position| skip.clone()).with_rules(&HordeRules);
+        assert_eq!(player.offer_move(&position), skip);
+    }
 }
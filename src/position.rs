@@ -1,30 +1,46 @@
 use std::fmt::Debug;
+use std::hash::Hash;
+use std::hash::Hasher;
 use std::str;
 
 use crate::all_squares;
-use crate::cards;
-use crate::eight_degrees;
-use crate::inter_cards;
+use crate::attack_map::AttackMaps;
+use crate::bitboard::{pawn_attacks, PieceBitboards};
+use crate::castling::CastlingRights;
+use crate::move_gen::{pins, KingSafety, Pin};
+use crate::move_list::MoveList;
 use crate::move_piece;
 use crate::piece_at;
 use crate::put_piece_at;
 use crate::take_piece_at;
+use crate::Bitboard;
 use crate::ChessMove;
 use crate::Coords;
 use crate::Direction;
+use crate::Mailbox;
 use crate::Move;
 use crate::Piece;
 use crate::PieceColor;
 use crate::PieceKind;
+use crate::Square;
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum FenError {
+    // a record needs all six fields, one space apart
+    FieldCount(usize),
+    // a placement field without eight squares on each of eight ranks
+    Board(String),
+    SideToMove(String),
+    EnPassant(String),
+}
 
-#[derive(Clone, PartialEq)]
+#[derive(Clone)]
 pub struct Position {
-    pub board: Vec<Vec<Option<Piece>>>,
+    board: Mailbox,
+    bitboards: PieceBitboards,
+    attack_maps: AttackMaps,
     pub to_move: PieceColor,
-    white_can_castle_queen_side: bool,
-    white_can_castle_king_side: bool,
-    black_can_castle_queen_side: bool,
-    black_can_castle_king_side: bool,
+    castling: CastlingRights,
     pub en_passant_on: Option<Coords>,
 }
 
@@ -34,152 +50,143 @@ impl Debug for Position {
     }
 }
 
+// Two positions are the same, for repetitions as FIDE counts them, when
+// the same pieces stand on the same squares with the same side to move and
+// the same castling rights, and en passant is possible in both or neither.
+// An en passant square no pawn could legally take on doesn't count.
+impl PartialEq for Position {
+    fn eq(&self, other: &Position) -> bool {
+        self.board == other.board
+            && self.to_move == other.to_move
+            && self.castling == other.castling
+            && self.en_passant_target() == other.en_passant_target()
+    }
+}
+
+impl Eq for Position {}
+
+impl Hash for Position {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        state.write_u64(self.zobrist_key());
+    }
+}
+
 impl Position {
     pub fn initial() -> Position {
-        let mut board = Vec::new();
-        for i in 0..8 {
-            let mut row = Vec::new();
-            for j in 0..8 {
-                row.push(Piece::from_initial_position(j, i));
+        let mut board: Mailbox = [[None; 8]; 8];
+        for (i, rank) in board.iter_mut().enumerate() {
+            for (j, square) in rank.iter_mut().enumerate() {
+                *square = Piece::from_initial_position(j as isize, i as isize);
             }
-            board.push(row);
         }
+        let bitboards = PieceBitboards::from_board(&board);
         Position {
             board,
+            attack_maps: AttackMaps::new(&bitboards),
+            bitboards,
             to_move: PieceColor::White,
-            white_can_castle_king_side: true,
-            white_can_castle_queen_side: true,
-            black_can_castle_king_side: true,
-            black_can_castle_queen_side: true,
+            castling: CastlingRights::standard(),
             en_passant_on: None,
         }
     }
     pub fn empty_board() -> Position {
-        let mut board = Vec::new();
-        for _ in 0..8 {
-            let mut row = Vec::new();
-            for _ in 0..8 {
-                row.push(None);
-            }
-            board.push(row);
-        }
+        let board: Mailbox = [[None; 8]; 8];
+        let bitboards = PieceBitboards::from_board(&board);
         Position {
             board,
+            attack_maps: AttackMaps::new(&bitboards),
+            bitboards,
             to_move: PieceColor::White,
-            white_can_castle_king_side: true,
-            white_can_castle_queen_side: true,
-            black_can_castle_king_side: true,
-            black_can_castle_queen_side: true,
+            castling: CastlingRights::standard(),
             en_passant_on: None,
         }
     }
+    // For FENs written into the code, which are known to be good. Anything
+    // read from outside goes through `try_from_fen`.
     pub fn from_fen(fen_record: &str) -> Position {
-        let fields: Vec<&str> = fen_record.split(" ").collect();
+        Position::try_from_fen(fen_record)
+            .unwrap_or_else(|error| panic!("invalid FEN {fen_record:?}: {error:?}"))
+    }
 
-        assert!(fields.len() == 6);
+    pub fn try_from_fen(fen_record: &str) -> Result<Position, FenError> {
+        let fields: Vec<&str> = fen_record.split(" ").collect();
+        if fields.len() != 6 {
+            return Err(FenError::FieldCount(fields.len()));
+        }
 
-        let mut board = vec![vec![]; 8];
+        let bad_board = || FenError::Board(String::from(fields[0]));
+        let mut board: Mailbox = [[None; 8]; 8];
         let mut rank = 0;
-        fields[0].chars().for_each(|character| match character {
-            '1'..='8' => {
-                for _ in 0..character.to_digit(10).expect("matched digits 1 through 8") {
-                    board[rank].push(None);
+        let mut file = 0;
+        for character in fields[0].chars() {
+            match character {
+                '1'..='8' => {
+                    file += character.to_digit(10).expect("matched digits 1 through 8") as usize;
+                    if file > 8 {
+                        return Err(bad_board());
+                    }
+                }
+                '/' => {
+                    if file != 8 || rank == 7 {
+                        return Err(bad_board());
+                    }
+                    rank += 1;
+                    file = 0;
+                }
+                _ => {
+                    let piece = Piece::from_fen_char(character).ok_or_else(bad_board)?;
+                    if file == 8 {
+                        return Err(bad_board());
+                    }
+                    board[rank][file] = Some(piece);
+                    file += 1;
                 }
             }
-            '/' => {
-                rank += 1;
-            }
-            'r' => board[rank].push(Some(Piece {
-                kind: PieceKind::Rook,
-                color: PieceColor::Black,
-            })),
-            'n' => board[rank].push(Some(Piece {
-                kind: PieceKind::Knight,
-                color: PieceColor::Black,
-            })),
-            'b' => board[rank].push(Some(Piece {
-                kind: PieceKind::Bishop,
-                color: PieceColor::Black,
-            })),
-            'q' => board[rank].push(Some(Piece {
-                kind: PieceKind::Queen,
-                color: PieceColor::Black,
-            })),
-            'k' => board[rank].push(Some(Piece {
-                kind: PieceKind::King,
-                color: PieceColor::Black,
-            })),
-            'p' => board[rank].push(Some(Piece {
-                kind: PieceKind::Pawn,
-                color: PieceColor::Black,
-            })),
-            'R' => board[rank].push(Some(Piece {
-                kind: PieceKind::Rook,
-                color: PieceColor::White,
-            })),
-            'N' => board[rank].push(Some(Piece {
-                kind: PieceKind::Knight,
-                color: PieceColor::White,
-            })),
-            'B' => board[rank].push(Some(Piece {
-                kind: PieceKind::Bishop,
-                color: PieceColor::White,
-            })),
-            'Q' => board[rank].push(Some(Piece {
-                kind: PieceKind::Queen,
-                color: PieceColor::White,
-            })),
-            'K' => board[rank].push(Some(Piece {
-                kind: PieceKind::King,
-                color: PieceColor::White,
-            })),
-            'P' => board[rank].push(Some(Piece {
-                kind: PieceKind::Pawn,
-                color: PieceColor::White,
-            })),
-            _ => panic!("{} is not a valid board character in FEN", character),
-        });
-
-        assert_eq!(board.len(), 8);
-        for rank in &board {
-            assert_eq!(rank.len(), 8);
+        }
+        if rank != 7 || file != 8 {
+            return Err(bad_board());
         }
 
-        assert!(fields[1].len() == 1);
-
-        let to_move = match fields[1]
-            .chars()
-            .nth(0)
-            .expect("Second FEN field should be 'w' or 'b'")
-        {
-            'w' => PieceColor::White,
-            'b' => PieceColor::Black,
-            _ => panic!("Second FEN field should be 'w' or 'b'"),
+        let to_move = match fields[1] {
+            "w" => PieceColor::White,
+            "b" => PieceColor::Black,
+            _ => return Err(FenError::SideToMove(String::from(fields[1]))),
         };
 
-        let white_can_castle_left = fields[2].contains("Q");
-        let white_can_castle_right = fields[2].contains("K");
-        let black_can_castle_left = fields[2].contains("q");
-        let black_can_castle_right = fields[2].contains("k");
+        let castling = CastlingRights::from_fen_field(fields[2], &board);
 
-        let en_passant_on = if fields[3] == "-" {
-            None
-        } else {
-            Some(Coords::from_algebraic(fields[3]))
+        let en_passant_on = match fields[3] {
+            "-" => None,
+            square => Some(
+                Square::from_algebraic(square)
+                    .ok_or_else(|| FenError::EnPassant(String::from(square)))?
+                    .coords(),
+            ),
         };
 
-        Position {
+        let bitboards = PieceBitboards::from_board(&board);
+        Ok(Position {
             board,
+            attack_maps: AttackMaps::new(&bitboards),
+            bitboards,
             to_move,
             en_passant_on,
-            white_can_castle_queen_side: white_can_castle_left,
-            white_can_castle_king_side: white_can_castle_right,
-            black_can_castle_queen_side: black_can_castle_left,
-            black_can_castle_king_side: black_can_castle_right,
-        }
+            castling,
+        })
     }
 
+    // Castling rights are written the X-FEN way, which for standard chess is
+    // plain FEN.
     pub fn to_fen(&self) -> String {
+        self.fen_with_castling(false)
+    }
+
+    // Castling rights are written as rook files, "HAha".
+    pub fn to_shredder_fen(&self) -> String {
+        self.fen_with_castling(true)
+    }
+
+    fn fen_with_castling(&self, shredder: bool) -> String {
         let mut fen = String::new();
         self.board.iter().for_each(|rank| {
             rank.iter()
@@ -213,26 +220,7 @@ impl Position {
         }
         fen.push(' ');
 
-        if !self.white_can_castle_king_side
-            && !self.white_can_castle_queen_side
-            && !self.black_can_castle_king_side
-            && !self.black_can_castle_queen_side
-        {
-            fen.push('-');
-        } else {
-            if self.white_can_castle_king_side {
-                fen.push('K');
-            }
-            if self.white_can_castle_queen_side {
-                fen.push('Q');
-            }
-            if self.black_can_castle_king_side {
-                fen.push('k');
-            }
-            if self.black_can_castle_queen_side {
-                fen.push('q');
-            }
-        }
+        fen.push_str(&self.castling.to_fen_field(&self.board, shredder));
         fen.push(' ');
 
         match self.en_passant_on {
@@ -253,6 +241,19 @@ impl Position {
         new_position
     }
 
+    // Passes the turn, which also gives up any en passant capture, as
+    // null-move pruning and showing what the other side threatens need.
+    pub fn after_null_move(&self) -> Position {
+        let mut passed = self.clone();
+        passed.make_null_move();
+        passed
+    }
+
+    pub fn make_null_move(&mut self) {
+        self.to_move = self.to_move.opposite();
+        self.en_passant_on = None;
+    }
+
     pub fn color_to_move(&self, color: PieceColor) -> Position {
         Position {
             to_move: color,
@@ -260,8 +261,150 @@ impl Position {
         }
     }
 
+    pub fn mirror(&self) -> Position {
+        let mut board: Mailbox = [[None; 8]; 8];
+        for (y, rank) in self.board.iter().enumerate() {
+            for (x, square_contents) in rank.iter().enumerate() {
+                board[7 - y][x] = square_contents.map(|piece| Piece {
+                    kind: piece.kind,
+                    color: piece.color.opposite(),
+                });
+            }
+        }
+        let bitboards = PieceBitboards::from_board(&board);
+        Position {
+            board,
+            attack_maps: AttackMaps::new(&bitboards),
+            bitboards,
+            to_move: self.to_move.opposite(),
+            castling: self.castling.swap_colors(),
+            en_passant_on: self.en_passant_on.map(|square| Coords {
+                x: square.x,
+                y: 7 - square.y,
+            }),
+        }
+    }
+
+    // Swaps the a-file with the h-file and so on. The kings end up on the
+    // wrong side of their queens for castling, so every castling right is
+    // dropped.
+    pub fn flip_horizontal(&self) -> Position {
+        let mut board: Mailbox = [[None; 8]; 8];
+        for (y, rank) in self.board.iter().enumerate() {
+            for (x, square_contents) in rank.iter().enumerate() {
+                board[y][7 - x] = *square_contents;
+            }
+        }
+        let bitboards = PieceBitboards::from_board(&board);
+        Position {
+            board,
+            attack_maps: AttackMaps::new(&bitboards),
+            bitboards,
+            to_move: self.to_move,
+            castling: CastlingRights::none(),
+            en_passant_on: self.en_passant_on.map(|square| Coords {
+                x: 7 - square.x,
+                y: square.y,
+            }),
+        }
+    }
+
+    pub fn board(&self) -> &Mailbox {
+        &self.board
+    }
+
+    pub(crate) fn bitboards(&self) -> &PieceBitboards {
+        &self.bitboards
+    }
+
+    pub fn piece_at(&self, loc: &Coords) -> Option<Piece> {
+        piece_at(&self.board, loc)
+    }
+
+    pub fn is_empty(&self, square: &Coords) -> bool {
+        !self.bitboards.occupancy().contains(square)
+    }
+
+    pub fn occupied_squares(&self) -> Bitboard {
+        self.bitboards.occupancy()
+    }
+
+    pub fn empty_squares(&self) -> Bitboard {
+        !self.bitboards.occupancy()
+    }
+
+    pub fn put_piece_at(&mut self, piece: Piece, loc: Coords) {
+        put_piece_at(&mut self.board, piece, loc);
+        self.refresh_square(&loc);
+    }
+
+    pub fn take_piece_at(&mut self, loc: Coords) -> Option<Piece> {
+        let taken = take_piece_at(&mut self.board, loc);
+        self.refresh_square(&loc);
+        taken
+    }
+
+    fn refresh_square(&mut self, loc: &Coords) {
+        self.bitboards.set(loc, self.piece_at(loc));
+        self.attack_maps
+            .update(&self.bitboards, Bitboard::from_square(loc));
+    }
+
+    pub fn attacked_squares(&self, by: &PieceColor) -> Bitboard {
+        self.attack_maps.attacked_by(by)
+    }
+
+    pub(crate) fn touched_squares(&self, chess_move: &ChessMove) -> Bitboard {
+        let row = self.to_move.homerow();
+        let squares = match chess_move {
+            ChessMove::RegularMove(movement)
+            | ChessMove::PawnSkip(movement)
+            | ChessMove::Promotion(movement, _) => vec![movement.origin, movement.destination],
+            ChessMove::EnPassant(movement, pawn_taken) => {
+                vec![movement.origin, movement.destination, *pawn_taken]
+            }
+            ChessMove::CastleLeft => [0, 2, 3, 4]
+                .iter()
+                .map(|x| Coords { x: *x, y: row })
+                .collect(),
+            ChessMove::CastleRight => [4, 5, 6, 7]
+                .iter()
+                .map(|x| Coords { x: *x, y: row })
+                .collect(),
+        };
+        squares.iter().fold(Bitboard::EMPTY, |touched, square| {
+            touched | Bitboard::from_square(square)
+        })
+    }
+
+    // A right is lost once the king or that rook leaves its square, or when
+    // the rook is captured there.
+    fn keeps_castling_right(
+        &self,
+        chess_move: &ChessMove,
+        color: &PieceColor,
+        rook_x: isize,
+    ) -> bool {
+        let rook_square = Coords {
+            x: rook_x,
+            y: color.homerow(),
+        };
+        match chess_move.movement() {
+            None => self.to_move != *color,
+            Some(movement) => {
+                self.piece_at(&movement.origin)
+                    != Some(Piece {
+                        kind: PieceKind::King,
+                        color: *color,
+                    })
+                    && movement.origin != rook_square
+                    && movement.destination != rook_square
+            }
+        }
+    }
+
     pub fn after_move(&self, chess_move: &ChessMove) -> Position {
-        let mut new_board = self.board.clone();
+        let mut new_board = self.board;
         let mut en_passant_on = None;
         match chess_move {
             ChessMove::RegularMove(coordinates) => {
@@ -317,81 +460,34 @@ impl Position {
             }
         }
 
-        let black_can_castle_king_side = match chess_move {
-            ChessMove::CastleLeft => {
-                self.to_move == PieceColor::White && self.black_can_castle_king_side
-            }
-            ChessMove::CastleRight => {
-                self.to_move == PieceColor::White && self.black_can_castle_king_side
-            }
-            ChessMove::RegularMove(movement) => {
-                ((movement.origin != Coords { y: 7, x: 4 }
-                    && movement.origin != Coords { y: 7, x: 7 })
-                    || self.to_move == PieceColor::White)
-                    && self.black_can_castle_king_side
-            }
-            _ => self.black_can_castle_king_side,
-        };
-
-        let black_can_castle_queen_side = match chess_move {
-            ChessMove::CastleLeft => {
-                self.to_move == PieceColor::White && self.black_can_castle_queen_side
-            }
-            ChessMove::CastleRight => {
-                self.to_move == PieceColor::White && self.black_can_castle_queen_side
-            }
-            ChessMove::RegularMove(movement) => {
-                ((movement.origin != Coords { y: 7, x: 4 }
-                    && movement.origin != Coords { y: 7, x: 0 })
-                    || self.to_move == PieceColor::White)
-                    && self.black_can_castle_queen_side
-            }
-            _ => self.black_can_castle_queen_side,
-        };
-        let white_can_castle_king_side = match chess_move {
-            ChessMove::CastleLeft => {
-                self.to_move == PieceColor::Black && self.white_can_castle_king_side
-            }
-            ChessMove::CastleRight => {
-                self.to_move == PieceColor::Black && self.white_can_castle_king_side
-            }
-            ChessMove::RegularMove(movement) => {
-                ((movement.origin != Coords { y: 7, x: 4 }
-                    && movement.origin != Coords { y: 7, x: 7 })
-                    || self.to_move == PieceColor::Black)
-                    && self.white_can_castle_king_side
-            }
-            _ => self.white_can_castle_king_side,
-        };
+        let mut castling = self.castling;
+        for color in PieceColor::both() {
+            let kept = |rook_file: Option<isize>| {
+                rook_file.filter(|x| self.keeps_castling_right(chess_move, &color, *x))
+            };
+            castling.set_king_side(&color, kept(self.castling.king_side(&color)));
+            castling.set_queen_side(&color, kept(self.castling.queen_side(&color)));
+        }
 
-        let white_can_castle_queen_side = match chess_move {
-            ChessMove::CastleLeft => {
-                self.to_move == PieceColor::Black && self.white_can_castle_queen_side
-            }
-            ChessMove::CastleRight => {
-                self.to_move == PieceColor::Black && self.white_can_castle_queen_side
-            }
-            ChessMove::RegularMove(movement) => {
-                ((movement.origin != Coords { y: 7, x: 4 }
-                    && movement.origin != Coords { y: 7, x: 0 })
-                    || self.to_move == PieceColor::Black)
-                    && self.white_can_castle_queen_side
-            }
-            _ => self.white_can_castle_queen_side,
-        };
+        let touched = self.touched_squares(chess_move);
+        let mut bitboards = self.bitboards;
+        for square in touched.squares() {
+            bitboards.set(&square, piece_at(&new_board, &square));
+        }
+        let mut attack_maps = self.attack_maps.clone();
+        attack_maps.update(&bitboards, touched);
 
         Position {
             board: new_board,
+            bitboards,
+            attack_maps,
             to_move: self.to_move.opposite(),
             en_passant_on,
-            white_can_castle_queen_side,
-            white_can_castle_king_side,
-            black_can_castle_queen_side,
-            black_can_castle_king_side,
+            castling,
         }
     }
     pub fn is_checkmate(&self) -> bool {
-        self.is_in_check(&self.to_move) && self.all_legal_moves().is_empty()
+        self.is_in_check(&self.to_move) && !self.has_legal_move()
     }
     pub fn checkmated(&self) -> Option<PieceColor> {
         if self.is_checkmate() {
@@ -401,38 +497,101 @@ impl Position {
         }
     }
     pub fn all_legal_moves(&self) -> Vec<ChessMove> {
-        all_squares()
-            .iter()
-            .flat_map(|square| self.legal_moves_from_origin(square))
-            .collect()
+        self.legal_moves_iter().collect()
     }
 
     pub fn all_possible_moves(&self) -> Vec<ChessMove> {
         all_squares()
             .iter()
-            .flat_map(|square| self.possible_moves_from_origin(square))
+            .flat_map(|square| self.pseudo_legal_moves_from(square))
             .collect()
     }
     pub fn legal_moves_from_origin(&self, origin: &Coords) -> Vec<ChessMove> {
-        self.possible_moves_from_origin(origin)
-            .iter()
-            .filter(|chess_move| !self.opens_own_king(chess_move))
-            .cloned()
+        let safety = KingSafety::of(self);
+        self.pseudo_legal_moves_from(origin)
+            .into_iter()
+            .filter(|chess_move| safety.allows(self, chess_move))
             .collect()
     }
 
-    fn possible_moves_from_origin(&self, origin: &Coords) -> Vec<ChessMove> {
-        match piece_at(&self.board, origin) {
-            None => Vec::new(),
-            Some(piece) => {
-                if piece.color == self.to_move {
-                    self.movement_from_origin(origin, piece)
-                } else {
-                    Vec::new()
-                }
+    pub fn movable_pieces(&self) -> Vec<Coords> {
+        let safety = KingSafety::of(self);
+        all_squares()
+            .into_iter()
+            .filter(|square| {
+                self.pseudo_legal_moves_from(square)
+                    .iter()
+                    .any(|chess_move| safety.allows(self, chess_move))
+            })
+            .collect()
+    }
+
+    // Squares the piece on `origin` can legally move to, with castling given as
+    // the king's destination and every promotion on a square counted once.
+    pub fn legal_destinations(&self, origin: &Coords) -> Vec<Coords> {
+        let mut destinations = vec![];
+        for chess_move in self.legal_moves_from_origin(origin) {
+            let (_, destination) = chess_move.origin_and_destination(&self.to_move);
+            if !destinations.contains(&destination) {
+                destinations.push(destination);
             }
         }
+        destinations
+    }
+
+    // Legal moves ending on `destination`, castling ending where the king
+    // does.
+    pub fn legal_moves_to(&self, destination: &Coords) -> Vec<ChessMove> {
+        self.legal_moves_iter()
+            .filter(|chess_move| chess_move.origin_and_destination(&self.to_move).1 == *destination)
+            .collect()
+    }
+
+    // Legal moves of the side to move's pieces of `kind`, castling counting
+    // as a king move. Only squares holding such a piece are looked at.
+    pub fn legal_moves_of_kind(&self, kind: PieceKind) -> Vec<ChessMove> {
+        self.pieces_of(self.to_move, kind)
+            .flat_map(|(origin, _)| self.legal_moves_from_origin(&origin))
+            .collect()
     }
+
+    // Moves that follow the movement rules of the piece on `origin`, including
+    // those that would leave the mover's own king in check.
+    pub fn pseudo_legal_moves_from(&self, origin: &Coords) -> Vec<ChessMove> {
+        let mut moves = MoveList::new();
+        self.push_pseudo_legal_moves_from(origin, &mut moves);
+        moves.to_vec()
+    }
+    pub(crate) fn push_pseudo_legal_moves_from(&self, origin: &Coords, moves: &mut MoveList) {
+        if let Some(piece) = self.piece_at(origin) {
+            if piece.color == self.to_move {
+                self.movement_from_origin(origin, piece, moves);
+            }
+        }
+    }
+    // The en passant square when a pawn of the side to move can legally
+    // take there.
+    pub fn en_passant_target(&self) -> Option<Coords> {
+        let square = self.en_passant_on?;
+        let takers = pawn_attacks(&square, &self.to_move.opposite())
+            & self.bitboards.pieces(&self.to_move, &PieceKind::Pawn);
+        takers
+            .squares()
+            .any(|origin| {
+                self.is_move_legal(&ChessMove::EnPassant(
+                    Move {
+                        origin,
+                        destination: square,
+                    },
+                    Coords {
+                        x: square.x,
+                        y: origin.y,
+                    },
+                ))
+            })
+            .then_some(square)
+    }
+
     pub fn is_move_legal(&self, chess_move: &ChessMove) -> bool {
         let origin = match chess_move {
             ChessMove::RegularMove(movement) => movement.origin,
@@ -447,219 +606,182 @@ impl Position {
 
         self.legal_moves_from_origin(&origin).contains(chess_move)
     }
+    // Only looks at the squares taken on, so it is as cheap for illegal moves.
+    pub fn is_capture(&self, chess_move: &ChessMove) -> bool {
+        self.captured_piece(chess_move).is_some()
+    }
+    // The pawn beside the mover for en passant, whatever is on the
+    // destination otherwise.
+    pub fn captured_piece(&self, chess_move: &ChessMove) -> Option<Piece> {
+        match chess_move {
+            ChessMove::CastleLeft | ChessMove::CastleRight => None,
+            ChessMove::EnPassant(_, taken) => self.piece_at(taken),
+            ChessMove::RegularMove(movement)
+            | ChessMove::PawnSkip(movement)
+            | ChessMove::Promotion(movement, _) => self.piece_at(&movement.destination),
+        }
+    }
+    // Every piece of `color` pinned to its own king, whoever is to move.
+    pub fn pinned_pieces(&self, color: &PieceColor) -> Vec<Pin> {
+        pins(self, color)
+    }
     pub fn is_attacked_by(&self, by: &PieceColor, square: &Coords) -> bool {
-        let attacked_by_king: bool = self
-            .projected_movement(square, eight_degrees(), &by.opposite(), Some(1))
-            .iter()
-            .any(|chess_move| match chess_move {
-                ChessMove::RegularMove(movement) => piece_at(&self.board, &movement.destination)
-                    .is_some_and(|piece| piece.kind == PieceKind::King && &piece.color == by),
-                _ => false,
-            });
-        let attacked_by_rook_or_queen: bool =
-            self.rook_from(square, &by.opposite())
-                .iter()
-                .any(|chess_move| match chess_move {
-                    ChessMove::RegularMove(movement) => {
-                        piece_at(&self.board, &movement.destination).is_some_and(|piece| {
-                            (piece.kind == PieceKind::Rook || piece.kind == PieceKind::Queen)
-                                && &piece.color == by
-                        })
-                    }
-                    _ => false,
-                });
-
-        let attacked_by_bishop_or_queen: bool = self
-            .bishop_from(square, &by.opposite())
-            .iter()
-            .any(|chess_move| match chess_move {
-                ChessMove::RegularMove(movement) => piece_at(&self.board, &movement.destination)
-                    .is_some_and(|piece| {
-                        (piece.kind == PieceKind::Bishop || piece.kind == PieceKind::Queen)
-                            && &piece.color == by
-                    }),
-                _ => false,
-            });
-        let attacked_by_knight: bool =
-            self.knight_from(square, &by.opposite())
-                .iter()
-                .any(|chess_move| match chess_move {
-                    ChessMove::RegularMove(movement) => {
-                        piece_at(&self.board, &movement.destination).is_some_and(|piece| {
-                            piece.kind == PieceKind::Knight && &piece.color == by
-                        })
-                    }
-                    _ => false,
-                });
-
-        let attacked_by_pawn: bool = self.attacked_by_pawn(square, by);
-        let attacked_en_passant: bool = piece_at(&self.board, square)
-            .is_some_and(|piece| piece.color == by.opposite() && piece.kind == PieceKind::Pawn)
-            && self.en_passant_on.is_some_and(|en_passant_on| {
-                en_passant_on
-                    == *square
-                        + Direction {
-                            dx: 0,
-                            dy: by.pawn_orientation(),
-                        }
-                    && self.attacked_by_pawn(&en_passant_on, by)
-            });
-
-        attacked_by_knight
-            || attacked_by_pawn
-            || attacked_en_passant
-            || attacked_by_bishop_or_queen
-            || attacked_by_rook_or_queen
-            || attacked_by_king
+        // the cached maps answer nearly every query, only a pawn that could be
+        // taken en passant needs a closer look
+        self.attack_maps.attacked_by(by).contains(square)
+            || self
+                .piece_at(square)
+                .is_some_and(|piece| piece.color == by.opposite() && piece.kind == PieceKind::Pawn)
+                && self.en_passant_on.is_some_and(|en_passant_on| {
+                    en_passant_on
+                        == *square
+                            + Direction {
+                                dx: 0,
+                                dy: by.pawn_orientation(),
+                            }
+                        && Position::attacked_by_pawn(&self.bitboards, &en_passant_on, by)
+                })
     }
 
-    fn attacked_by_pawn(&self, square: &Coords, attacking_color: &PieceColor) -> bool {
-        Position::pawn_attacked_squares(square, &attacking_color.opposite())
-            .iter()
-            .any(|attacking_square| {
-                attacking_square.is_in_bounds()
-                    && piece_at(&self.board, attacking_square).is_some_and(|piece| {
-                        piece.kind == PieceKind::Pawn && &piece.color == attacking_color
-                    })
-            })
+    fn attacked_by_pawn(
+        bitboards: &PieceBitboards,
+        square: &Coords,
+        attacking_color: &PieceColor,
+    ) -> bool {
+        // a pawn attacks the square if a pawn of the other color on that square would attack it back
+        !(pawn_attacks(square, &attacking_color.opposite())
+            & bitboards.pieces(attacking_color, &PieceKind::Pawn))
+        .is_empty()
     }
 
-    fn is_in_check(&self, color: &PieceColor) -> bool {
+    pub fn is_in_check(&self, color: &PieceColor) -> bool {
         match self.king_location(color) {
             None => false,
             Some(loc) => self.is_attacked_by(&color.opposite(), &loc),
         }
     }
-    fn opens_own_king(&self, chess_move: &ChessMove) -> bool {
+    pub(crate) fn opens_own_king(&self, chess_move: &ChessMove) -> bool {
         let potential_position = self.after_move(chess_move);
         potential_position.is_in_check(&self.to_move)
     }
 
+    pub fn castling_rights(&self) -> CastlingRights {
+        self.castling
+    }
+    pub fn set_castling_rights(&mut self, castling: CastlingRights) {
+        self.castling = castling;
+    }
     pub fn can_castle_queen_side(&self, color: &PieceColor) -> bool {
-        match color {
-            PieceColor::White => self.white_can_castle_queen_side,
-            PieceColor::Black => self.black_can_castle_queen_side,
-        }
+        self.castling.queen_side(color).is_some()
     }
     pub fn can_castle_king_side(&self, color: &PieceColor) -> bool {
-        match color {
-            PieceColor::White => self.white_can_castle_king_side,
-            PieceColor::Black => self.black_can_castle_king_side,
-        }
+        self.castling.king_side(color).is_some()
+    }
+    // Allowing castling puts the rook on the a-file.
+    pub fn set_can_castle_queen_side(&mut self, color: &PieceColor, allowed: bool) {
+        self.castling.set_queen_side(color, allowed.then_some(0));
+    }
+    // Allowing castling puts the rook on the h-file.
+    pub fn set_can_castle_king_side(&mut self, color: &PieceColor, allowed: bool) {
+        self.castling.set_king_side(color, allowed.then_some(7));
     }
-    fn movement_from_origin(&self, origin: &Coords, piece: Piece) -> Vec<ChessMove> {
+    fn movement_from_origin(&self, origin: &Coords, piece: Piece, moves: &mut MoveList) {
         match piece.kind {
-            PieceKind::Pawn => self.pawn_from(origin, &piece.color),
-            PieceKind::Rook => self.rook_from(origin, &piece.color),
-            PieceKind::Knight => self.knight_from(origin, &piece.color),
-            PieceKind::Bishop => self.bishop_from(origin, &piece.color),
-            PieceKind::Queen => self.queen_movement(origin, &piece.color),
-            PieceKind::King => self.king_movement(origin, &piece.color),
+            PieceKind::Pawn => self.pawn_from(origin, &piece.color, moves),
+            PieceKind::Rook => self.rook_from(origin, &piece.color, moves),
+            PieceKind::Knight => self.knight_from(origin, &piece.color, moves),
+            PieceKind::Bishop => self.bishop_from(origin, &piece.color, moves),
+            PieceKind::Queen => self.queen_movement(origin, &piece.color, moves),
+            PieceKind::King => self.king_movement(origin, &piece.color, moves),
         }
     }
-    fn king_movement(&self, origin: &Coords, origin_color: &PieceColor) -> Vec<ChessMove> {
-        let mut moves = self.projected_movement(origin, eight_degrees(), origin_color, Some(1));
+    // The squares a piece attacks that don't hold one of its own, taken
+    // from the attack maps, which already stop sliders at the first piece
+    // in the way. Every piece but the pawn moves where it attacks.
+    fn attack_moves(&self, origin: &Coords, color: &PieceColor, moves: &mut MoveList) {
+        moves.extend(
+            (self.attack_maps.attacks_from(origin) & !self.bitboards.color(color))
+                .squares()
+                .map(|destination| {
+                    ChessMove::RegularMove(Move {
+                        origin: *origin,
+                        destination,
+                    })
+                }),
+        );
+    }
+    fn king_movement(&self, origin: &Coords, origin_color: &PieceColor, moves: &mut MoveList) {
+        self.attack_moves(origin, origin_color, moves);
+        // Chess960 rights on other files are kept but never castled with
         let row = origin_color.homerow();
-        if piece_at(&self.board, &Coords { y: row, x: 5 }).is_none()
-            && piece_at(&self.board, &Coords { y: row, x: 6 }).is_none()
-            && piece_at(&self.board, &Coords { y: row, x: 4 }).is_some_and(|piece| {
-                piece
-                    == Piece {
-                        kind: PieceKind::King,
-                        color: *origin_color,
-                    }
-            })
-            && piece_at(&self.board, &Coords { y: row, x: 7 }).is_some_and(|piece| {
-                piece
-                    == Piece {
-                        kind: PieceKind::Rook,
-                        color: *origin_color,
-                    }
-            })
-            && self.can_castle_king_side(origin_color)
+        if self.is_empty(&Coords { y: row, x: 5 })
+            && self.is_empty(&Coords { y: row, x: 6 })
+            && self
+                .piece_at(&Coords { y: row, x: 4 })
+                .is_some_and(|piece| {
+                    piece
+                        == Piece {
+                            kind: PieceKind::King,
+                            color: *origin_color,
+                        }
+                })
+            && self
+                .piece_at(&Coords { y: row, x: 7 })
+                .is_some_and(|piece| {
+                    piece
+                        == Piece {
+                            kind: PieceKind::Rook,
+                            color: *origin_color,
+                        }
+                })
+            && self.castling.king_side(origin_color) == Some(7)
             && !self.is_in_check(origin_color)
+            && !self.is_attacked_by(&origin_color.opposite(), &Coords { y: row, x: 5 })
         {
             moves.push(ChessMove::CastleRight);
         }
-        if piece_at(&self.board, &Coords { y: row, x: 3 }).is_none()
-            && piece_at(&self.board, &Coords { y: row, x: 2 }).is_none()
-            && piece_at(&self.board, &Coords { y: row, x: 1 }).is_none()
-            && piece_at(&self.board, &Coords { y: row, x: 4 }).is_some_and(|piece| {
-                piece
-                    == Piece {
-                        kind: PieceKind::King,
-                        color: *origin_color,
-                    }
-            })
-            && piece_at(&self.board, &Coords { y: row, x: 0 }).is_some_and(|piece| {
-                piece
-                    == Piece {
-                        kind: PieceKind::Rook,
-                        color: *origin_color,
-                    }
-            })
-            && self.can_castle_queen_side(origin_color)
+        if self.is_empty(&Coords { y: row, x: 3 })
+            && self.is_empty(&Coords { y: row, x: 2 })
+            && self.is_empty(&Coords { y: row, x: 1 })
+            && self
+                .piece_at(&Coords { y: row, x: 4 })
+                .is_some_and(|piece| {
+                    piece
+                        == Piece {
+                            kind: PieceKind::King,
+                            color: *origin_color,
+                        }
+                })
+            && self
+                .piece_at(&Coords { y: row, x: 0 })
+                .is_some_and(|piece| {
+                    piece
+                        == Piece {
+                            kind: PieceKind::Rook,
+                            color: *origin_color,
+                        }
+                })
+            && self.castling.queen_side(origin_color) == Some(0)
             && !self.is_in_check(origin_color)
+            && !self.is_attacked_by(&origin_color.opposite(), &Coords { y: row, x: 3 })
         {
             moves.push(ChessMove::CastleLeft);
         }
-
-        moves
-    }
-    fn queen_movement(&self, origin: &Coords, color: &PieceColor) -> Vec<ChessMove> {
-        self.projected_movement(origin, eight_degrees(), color, None)
-    }
-    fn bishop_from(&self, origin: &Coords, color: &PieceColor) -> Vec<ChessMove> {
-        self.projected_movement(origin, inter_cards(), color, None)
-    }
-    fn knight_from(&self, origin: &Coords, color: &PieceColor) -> Vec<ChessMove> {
-        let directions: Vec<Direction> = vec![
-            Direction { dy: 2, dx: 1 },
-            Direction { dy: 2, dx: -1 },
-            Direction { dy: 1, dx: 2 },
-            Direction { dy: 1, dx: -2 },
-            Direction { dy: -2, dx: 1 },
-            Direction { dy: -2, dx: -1 },
-            Direction { dy: -1, dx: -2 },
-            Direction { dy: -1, dx: 2 },
-        ];
-        let potential_moves = directions.iter().map(|direction| {
-            ChessMove::RegularMove(Move {
-                origin: *origin,
-                destination: *origin + *direction,
-            })
-        });
-        potential_moves
-            .into_iter()
-            .filter(|chess_move| match chess_move {
-                ChessMove::RegularMove(coordinates) => {
-                    coordinates.destination.is_in_bounds()
-                        && piece_at(&self.board, &coordinates.destination)
-                            .is_none_or(|piece| &piece.color != color)
-                }
-                _ => false,
-            })
-            .collect()
     }
-    fn rook_from(&self, origin: &Coords, color: &PieceColor) -> Vec<ChessMove> {
-        self.projected_movement(origin, cards(), color, None)
+    fn queen_movement(&self, origin: &Coords, color: &PieceColor, moves: &mut MoveList) {
+        self.attack_moves(origin, color, moves)
     }
-
-    fn pawn_attacked_squares(origin: &Coords, color: &PieceColor) -> Vec<Coords> {
-        vec![
-            Coords {
-                x: origin.x + 1,
-                y: origin.y + color.pawn_orientation(),
-            },
-            Coords {
-                x: origin.x - 1,
-                y: origin.y + color.pawn_orientation(),
-            },
-        ]
+    fn bishop_from(&self, origin: &Coords, color: &PieceColor, moves: &mut MoveList) {
+        self.attack_moves(origin, color, moves)
+    }
+    fn knight_from(&self, origin: &Coords, color: &PieceColor, moves: &mut MoveList) {
+        self.attack_moves(origin, color, moves)
+    }
+    fn rook_from(&self, origin: &Coords, color: &PieceColor, moves: &mut MoveList) {
+        self.attack_moves(origin, color, moves)
     }
 
-    fn pawn_from(&self, origin: &Coords, color: &PieceColor) -> Vec<ChessMove> {
-        let mut legal_moves = vec![];
+    fn pawn_from(&self, origin: &Coords, color: &PieceColor, moves: &mut MoveList) {
         let forward = Direction {
             dx: 0,
             dy: color.pawn_orientation(),
@@ -668,64 +790,50 @@ impl Position {
         let ahead_two = ahead_one + forward;
 
         if !ahead_one.is_in_bounds() {
-            return legal_moves;
+            return;
         }
 
-        if piece_at(&self.board, &ahead_one).is_none() {
-            legal_moves.push(ChessMove::RegularMove(Move {
-                origin: *origin,
-                destination: ahead_one,
-            }));
+        if self.is_empty(&ahead_one) {
+            Position::push_pawn_move(origin, ahead_one, color, moves);
             if ahead_two.is_in_bounds()
                 && (origin.y == 1 || origin.y == 6)
-                && piece_at(&self.board, &ahead_two).is_none()
+                && self.is_empty(&ahead_two)
             {
-                legal_moves.push(ChessMove::PawnSkip(Move {
+                moves.push(ChessMove::PawnSkip(Move {
                     origin: *origin,
                     destination: ahead_two,
                 }));
             }
         }
 
-        Position::pawn_attacked_squares(origin, color)
-            .iter()
-            .for_each(|diagonal| {
-                if diagonal.is_in_bounds() {
-                    match piece_at(&self.board, diagonal) {
-                        None => {}
-                        Some(piece) => {
-                            if piece.color == color.opposite() {
-                                legal_moves.push(ChessMove::RegularMove(Move {
-                                    origin: *origin,
-                                    destination: *diagonal,
-                                }));
-                            }
-                        }
-                    }
-                }
-            });
+        let captures = pawn_attacks(origin, color) & self.bitboards.color(&color.opposite());
+        for diagonal in captures.squares() {
+            Position::push_pawn_move(origin, diagonal, color, moves);
+        }
         if let Some(en_passant) = self.en_passant_from(origin, color) {
-            legal_moves.push(en_passant);
+            moves.push(en_passant);
+        }
+    }
+    // A pawn reaching the far rank promotes, to any of the pieces it can.
+    fn push_pawn_move(
+        origin: &Coords,
+        destination: Coords,
+        color: &PieceColor,
+        moves: &mut MoveList,
+    ) {
+        let movement = Move {
+            origin: *origin,
+            destination,
+        };
+        if destination.y == color.opposite().homerow() {
+            moves.extend(
+                PieceKind::promoteable().map(|promotable_kind| {
+                    ChessMove::Promotion(movement.clone(), *promotable_kind)
+                }),
+            );
+        } else {
+            moves.push(ChessMove::RegularMove(movement));
         }
-        legal_moves
-            .iter()
-            .flat_map(|pawn_move| match pawn_move {
-                ChessMove::RegularMove(movement) => {
-                    if movement.destination.y == color.opposite().homerow() {
-                        PieceKind::promoteable()
-                            .map(|promotable_kind| {
-                                ChessMove::Promotion(movement.clone(), *promotable_kind)
-                            })
-                            .collect()
-                    } else {
-                        vec![pawn_move.clone()]
-                    }
-                }
-                ChessMove::PawnSkip(_) => vec![pawn_move.clone()],
-                ChessMove::EnPassant(_, _) => vec![pawn_move.clone()],
-                _ => panic!("Pawn moves should only be regular, skip or en passant"),
-            })
-            .collect()
     }
     fn en_passant_from(&self, origin: &Coords, color: &PieceColor) -> Option<ChessMove> {
         match self.en_passant_on {
@@ -761,36 +869,11 @@ impl Position {
             }
         }
     }
-    fn king_location(&self, color: &PieceColor) -> Option<Coords> {
-        for i in 0..8 {
-            for j in 0..8 {
-                let loc = Coords { y: i, x: j };
-                if piece_at(&self.board, &loc)
-                    .is_some_and(|piece| piece.kind == PieceKind::King && piece.color == *color)
-                {
-                    return Some(loc);
-                }
-            }
-        }
-        None
-    }
-    fn projected_movement(
-        &self,
-        origin: &Coords,
-        directions: Vec<Direction>,
-        origin_color: &PieceColor,
-        limit: Option<isize>,
-    ) -> Vec<ChessMove> {
-        directions
-            .iter()
-            .flat_map(|dir| self.raycast(origin, dir, origin_color, limit))
-            .map(|destination| {
-                ChessMove::RegularMove(Move {
-                    origin: *origin,
-                    destination,
-                })
-            })
-            .collect()
+    pub fn king_location(&self, color: &PieceColor) -> Option<Coords> {
+        self.bitboards
+            .pieces(color, &PieceKind::King)
+            .squares()
+            .next()
     }
     pub fn raycast(
         &self,
@@ -807,7 +890,7 @@ impl Position {
             if !next_square.is_in_bounds() {
                 break;
             }
-            if let Some(piece) = piece_at(&self.board, &next_square) {
+            if let Some(piece) = self.piece_at(&next_square) {
                 if piece.color == origin_color.opposite() {
                     squares.push(next_square);
                 }
@@ -817,17 +900,35 @@ impl Position {
         }
         squares
     }
+    // Each of `color`'s pieces with its square, in square order, read off
+    // the bitboards without building a list.
+    pub fn pieces(&self, color: PieceColor) -> impl Iterator<Item = (Coords, Piece)> + '_ {
+        self.bitboards.color(&color).squares().map(|square| {
+            let piece = self
+                .piece_at(&square)
+                .expect("the bitboards and the board agree");
+            (square, piece)
+        })
+    }
+
+    pub fn pieces_of(
+        &self,
+        color: PieceColor,
+        kind: PieceKind,
+    ) -> impl Iterator<Item = (Coords, Piece)> {
+        let piece = Piece { kind, color };
+        self.bitboards
+            .pieces(&color, &kind)
+            .squares()
+            .map(move |square| (square, piece))
+    }
+
     pub fn piece_count(&self, color: PieceColor) -> usize {
-        all_squares()
-            .iter()
-            .filter(|square| {
-                piece_at(&self.board, square).is_some_and(|piece| piece.color == color)
-            })
-            .count()
+        self.bitboards.color(&color).count() as usize
     }
 
     pub fn is_stalemate(&self) -> bool {
-        self.all_legal_moves().is_empty() && !self.is_in_check(&self.to_move)
+        !self.is_in_check(&self.to_move) && !self.has_legal_move()
     }
 }
 
@@ -844,6 +945,34 @@ mod tests {
         );
     }
 
+    #[test]
+    fn malformed_fen_is_an_error() {
+        assert_eq!(
+            Position::try_from_fen("8/8/8/8/8/8/8/8 w - -"),
+            Err(FenError::FieldCount(4))
+        );
+        for board in [
+            "9/8/8/8/8/8/8/8",
+            "8/8/8/8/8/8/8",
+            "8/8/8/8/8/8/8/8/8",
+            "7x/8/8/8/8/8/8/8",
+        ] {
+            assert_eq!(
+                Position::try_from_fen(&format!("{board} w - - 0 1")),
+                Err(FenError::Board(String::from(board)))
+            );
+        }
+        assert_eq!(
+            Position::try_from_fen("8/8/8/8/8/8/8/K6k x - - 0 1"),
+            Err(FenError::SideToMove(String::from("x")))
+        );
+        assert_eq!(
+            Position::try_from_fen("8/8/8/8/8/8/8/K6k w - e9 0 1"),
+            Err(FenError::EnPassant(String::from("e9")))
+        );
+        assert!(Position::try_from_fen("8/8/8/8/8/8/8/K6k w - - 0 1").is_ok());
+    }
+
     #[test]
     fn initial_position_from_and_to_fen() {
         let fen_string =
@@ -867,14 +996,20 @@ mod tests {
     fn execute_move_into_check() {
         let mut position = Position::empty_board();
 
-        position.board[0][0] = Some(Piece {
-            kind: PieceKind::King,
-            color: PieceColor::White,
-        });
-        position.board[2][2] = Some(Piece {
-            kind: PieceKind::Knight,
-            color: PieceColor::Black,
-        });
+        position.put_piece_at(
+            Piece {
+                kind: PieceKind::King,
+                color: PieceColor::White,
+            },
+            Coords { y: 0, x: 0 },
+        );
+        position.put_piece_at(
+            Piece {
+                kind: PieceKind::Knight,
+                color: PieceColor::Black,
+            },
+            Coords { y: 2, x: 2 },
+        );
         let king_location = Coords { y: 0, x: 0 };
         let king_destination = Coords { y: 0, x: 1 };
 
@@ -891,14 +1026,20 @@ mod tests {
     fn detects_check() {
         let mut position = Position::empty_board();
 
-        position.board[0][1] = Some(Piece {
-            kind: PieceKind::King,
-            color: PieceColor::White,
-        });
-        position.board[2][2] = Some(Piece {
-            kind: PieceKind::Knight,
-            color: PieceColor::Black,
-        });
+        position.put_piece_at(
+            Piece {
+                kind: PieceKind::King,
+                color: PieceColor::White,
+            },
+            Coords { y: 0, x: 1 },
+        );
+        position.put_piece_at(
+            Piece {
+                kind: PieceKind::Knight,
+                color: PieceColor::Black,
+            },
+            Coords { y: 2, x: 2 },
+        );
         assert!(position.is_in_check(&PieceColor::White));
     }
 
@@ -906,14 +1047,20 @@ mod tests {
     fn detects_move_into_check() {
         let mut position = Position::empty_board();
 
-        position.board[0][0] = Some(Piece {
-            kind: PieceKind::King,
-            color: PieceColor::White,
-        });
-        position.board[2][2] = Some(Piece {
-            kind: PieceKind::Knight,
-            color: PieceColor::Black,
-        });
+        position.put_piece_at(
+            Piece {
+                kind: PieceKind::King,
+                color: PieceColor::White,
+            },
+            Coords { y: 0, x: 0 },
+        );
+        position.put_piece_at(
+            Piece {
+                kind: PieceKind::Knight,
+                color: PieceColor::Black,
+            },
+            Coords { y: 2, x: 2 },
+        );
         let king_location = Coords { y: 0, x: 0 };
         assert!(position.opens_own_king(&ChessMove::RegularMove(Move {
             origin: king_location,
@@ -1009,14 +1156,20 @@ mod tests {
     #[test]
     fn no_en_passant_from_accross_the_board() {
         let mut position = Position::empty_board();
-        position.board[1][4] = Some(Piece {
-            kind: PieceKind::Pawn,
-            color: PieceColor::White,
-        });
-        position.board[7][2] = Some(Piece {
-            kind: PieceKind::Pawn,
-            color: PieceColor::Black,
-        });
+        position.put_piece_at(
+            Piece {
+                kind: PieceKind::Pawn,
+                color: PieceColor::White,
+            },
+            Coords { y: 1, x: 4 },
+        );
+        position.put_piece_at(
+            Piece {
+                kind: PieceKind::Pawn,
+                color: PieceColor::Black,
+            },
+            Coords { y: 7, x: 2 },
+        );
         let after_skip = position.after_move(&ChessMove::PawnSkip(Move {
             origin: Coords { y: 1, x: 4 },
             destination: Coords { y: 3, x: 4 },
@@ -1080,10 +1233,13 @@ mod tests {
     fn finds_king() {
         let mut position = Position::empty_board();
 
-        position.board[0][0] = Some(Piece {
-            kind: PieceKind::King,
-            color: PieceColor::White,
-        });
+        position.put_piece_at(
+            Piece {
+                kind: PieceKind::King,
+                color: PieceColor::White,
+            },
+            Coords { y: 0, x: 0 },
+        );
         assert_eq!(
             position.king_location(&PieceColor::White).unwrap(),
             Coords { x: 0, y: 0 }
@@ -1101,8 +1257,8 @@ mod tests {
             destination: one_above,
         }));
 
-        assert!(!after_move_up.white_can_castle_king_side);
-        assert!(!after_move_up.white_can_castle_queen_side);
+        assert!(!after_move_up.can_castle_king_side(&PieceColor::White));
+        assert!(!after_move_up.can_castle_queen_side(&PieceColor::White));
         assert!(!after_move_up.is_move_legal(&ChessMove::CastleLeft));
         assert!(!after_move_up.is_move_legal(&ChessMove::CastleRight));
 
@@ -1111,8 +1267,8 @@ mod tests {
             destination: king_initial_location,
         }));
 
-        assert!(!after_move_back.black_can_castle_king_side);
-        assert!(!after_move_back.black_can_castle_queen_side);
+        assert!(!after_move_back.can_castle_king_side(&PieceColor::Black));
+        assert!(!after_move_back.can_castle_queen_side(&PieceColor::Black));
         assert!(!after_move_back.is_move_legal(&ChessMove::CastleLeft));
         assert!(!after_move_back.is_move_legal(&ChessMove::CastleRight));
     }
@@ -1132,54 +1288,46 @@ mod tests {
             }))
             .color_to_move(PieceColor::White);
 
-        assert!(!moved_left_rook_up_one.white_can_castle_queen_side);
-        assert!(moved_left_rook_up_one.white_can_castle_king_side);
+        assert!(!moved_left_rook_up_one.can_castle_queen_side(&PieceColor::White));
+        assert!(moved_left_rook_up_one.can_castle_king_side(&PieceColor::White));
 
         assert!(!moved_left_rook_up_one.is_move_legal(&ChessMove::CastleLeft));
-        assert!(piece_at(
-            &moved_left_rook_up_one.board,
-            &Coords {
+        assert!(moved_left_rook_up_one
+            .piece_at(&Coords {
                 y: PieceColor::White.homerow(),
                 x: 5
-            }
-        )
-        .is_none());
-        assert!(piece_at(
-            &moved_left_rook_up_one.board,
-            &Coords {
+            })
+            .is_none());
+        assert!(moved_left_rook_up_one
+            .piece_at(&Coords {
                 y: PieceColor::White.homerow(),
                 x: 6
-            }
-        )
-        .is_none());
-        assert!(piece_at(
-            &moved_left_rook_up_one.board,
-            &Coords {
+            })
+            .is_none());
+        assert!(moved_left_rook_up_one
+            .piece_at(&Coords {
                 y: PieceColor::White.homerow(),
                 x: 4
-            }
-        )
-        .is_some_and(|piece| {
-            piece
-                == Piece {
-                    kind: PieceKind::King,
-                    color: PieceColor::White,
-                }
-        }));
-        assert!(piece_at(
-            &moved_left_rook_up_one.board,
-            &Coords {
+            })
+            .is_some_and(|piece| {
+                piece
+                    == Piece {
+                        kind: PieceKind::King,
+                        color: PieceColor::White,
+                    }
+            }));
+        assert!(moved_left_rook_up_one
+            .piece_at(&Coords {
                 y: PieceColor::White.homerow(),
                 x: 7
-            }
-        )
-        .is_some_and(|piece| {
-            piece
-                == Piece {
-                    kind: PieceKind::Rook,
-                    color: PieceColor::White,
-                }
-        }));
+            })
+            .is_some_and(|piece| {
+                piece
+                    == Piece {
+                        kind: PieceKind::Rook,
+                        color: PieceColor::White,
+                    }
+            }));
         assert!(moved_left_rook_up_one.can_castle_king_side(&PieceColor::White));
         assert!(moved_left_rook_up_one
             .all_legal_moves()
@@ -1193,7 +1341,7 @@ mod tests {
             }))
             .color_to_move(PieceColor::White);
 
-        assert!(!moved_right_rook_up_one.white_can_castle_king_side);
+        assert!(!moved_right_rook_up_one.can_castle_king_side(&PieceColor::White));
         assert!(!moved_right_rook_up_one.is_move_legal(&ChessMove::CastleRight));
 
         let moved_rooks_back = moved_right_rook_up_one
@@ -1206,27 +1354,65 @@ mod tests {
                 destination: right_rook_initial_location,
             }));
 
-        assert!(!moved_rooks_back.white_can_castle_king_side);
-        assert!(!moved_rooks_back.white_can_castle_queen_side);
+        assert!(!moved_rooks_back.can_castle_king_side(&PieceColor::White));
+        assert!(!moved_rooks_back.can_castle_queen_side(&PieceColor::White));
         assert!(!moved_rooks_back.is_move_legal(&ChessMove::CastleRight));
         assert!(!moved_rooks_back.is_move_legal(&ChessMove::CastleLeft));
     }
 
+    #[test]
+    fn mirrors_position() {
+        let position =
+            Position::from_fen("rnbqkbnr/ppp1pppp/8/3pP3/8/8/PPPP1PPP/RNBQKBNR w Kq d6 0 1");
+        assert_eq!(
+            position.mirror().to_fen(),
+            "rnbqkbnr/pppp1ppp/8/8/3Pp3/8/PPP1PPPP/RNBQKBNR b Qk d3 0 1"
+        );
+        assert_eq!(position.mirror().mirror(), position);
+    }
+
+    #[test]
+    fn flips_files_and_drops_castling() {
+        let position =
+            Position::from_fen("rnbqkbnr/ppp1pppp/8/3pP3/8/8/PPPP1PPP/RNBQKBNR w Kq d6 0 1");
+        let flipped = position.flip_horizontal();
+        assert_eq!(
+            flipped.to_fen(),
+            "rnbkqbnr/pppp1ppp/8/3Pp3/8/8/PPP1PPPP/RNBKQBNR w - e6 0 1"
+        );
+        // with the pieces still home nobody could castle anyway
+        assert_eq!(
+            flipped.all_legal_moves().len(),
+            position.all_legal_moves().len()
+        );
+        let no_castling = Position::from_fen("4k3/8/8/8/8/8/8/R3K3 b - - 0 1");
+        assert_eq!(no_castling.flip_horizontal().flip_horizontal(), no_castling);
+    }
+
     #[test]
     fn detects_stalemate() {
         let mut position = Position::empty_board();
-        position.board[0][0] = Some(Piece {
-            kind: PieceKind::King,
-            color: PieceColor::White,
-        });
-        position.board[2][1] = Some(Piece {
-            kind: PieceKind::Rook,
-            color: PieceColor::Black,
-        });
-        position.board[1][2] = Some(Piece {
-            kind: PieceKind::Rook,
-            color: PieceColor::Black,
-        });
+        position.put_piece_at(
+            Piece {
+                kind: PieceKind::King,
+                color: PieceColor::White,
+            },
+            Coords { y: 0, x: 0 },
+        );
+        position.put_piece_at(
+            Piece {
+                kind: PieceKind::Rook,
+                color: PieceColor::Black,
+            },
+            Coords { y: 2, x: 1 },
+        );
+        position.put_piece_at(
+            Piece {
+                kind: PieceKind::Rook,
+                color: PieceColor::Black,
+            },
+            Coords { y: 1, x: 2 },
+        );
 
         assert!(position.is_stalemate());
     }
@@ -1241,4 +1427,211 @@ mod tests {
         let position = Position::from_fen("8/8/8/8/8/8/2n5/4K2R w K - 0 1");
         assert!(!position.is_move_legal(&ChessMove::CastleRight));
     }
+    #[test]
+    fn pinned_piece_has_pseudo_legal_moves_only() {
+        let position = Position::from_fen("4r3/8/8/8/8/8/4N3/4K3 w - - 0 1");
+        let knight = Coords::from_algebraic("e2");
+        assert_eq!(position.pseudo_legal_moves_from(&knight).len(), 6);
+        assert!(position.legal_moves_from_origin(&knight).is_empty());
+        assert!(position
+            .pseudo_legal_moves_from(&Coords::from_algebraic("e8"))
+            .is_empty());
+    }
+
+    #[test]
+    fn legal_moves_can_be_asked_for_by_square_or_kind() {
+        let position = Position::from_fen("4k3/8/8/8/8/2N1N3/8/R3K3 w Q - 0 1");
+        let to_d5 = position.legal_moves_to(&Coords::from_algebraic("d5"));
+        assert_eq!(to_d5.len(), 2);
+        assert!(to_d5.iter().all(|chess_move| position
+            .piece_at(&chess_move.origin_and_destination(&position.to_move).0)
+            .is_some_and(|piece| piece.kind == PieceKind::Knight)));
+        assert!(position
+            .legal_moves_to(&Coords::from_algebraic("c1"))
+            .contains(&ChessMove::CastleLeft));
+        let king_moves = position.legal_moves_of_kind(PieceKind::King);
+        assert!(king_moves.contains(&ChessMove::CastleLeft));
+        assert_eq!(
+            position.legal_moves_of_kind(PieceKind::Knight).len()
+                + position.legal_moves_of_kind(PieceKind::Rook).len()
+                + king_moves.len(),
+            position.all_legal_moves().len()
+        );
+        assert!(position.legal_moves_of_kind(PieceKind::Queen).is_empty());
+    }
+
+    #[test]
+    fn legal_destinations_collapse_promotions_and_castling() {
+        let position = Position::from_fen("8/1P6/8/8/8/8/8/k3K2R w K - 0 1");
+        assert_eq!(
+            position.legal_destinations(&Coords::from_algebraic("b7")),
+            vec![Coords::from_algebraic("b8")]
+        );
+        assert!(position
+            .legal_destinations(&Coords::from_algebraic("e1"))
+            .contains(&Coords::from_algebraic("g1")));
+        assert!(position
+            .legal_destinations(&Coords::from_algebraic("a1"))
+            .is_empty());
+    }
+
+    #[test]
+    fn lists_movable_pieces() {
+        let position = Position::from_fen("4r2k/8/8/8/8/8/4N3/4K3 w - - 0 1");
+        assert_eq!(
+            position.movable_pieces(),
+            vec![Coords::from_algebraic("e1")]
+        );
+        assert_eq!(Position::initial().movable_pieces().len(), 10);
+    }
+
+    #[test]
+    fn detects_captures_from_the_destination() {
+        let position = Position::from_fen("n3k3/8/8/3pP3/8/8/8/R3K3 w Q d6 0 1");
+        let captures = position
+            .all_legal_moves()
+            .into_iter()
+            .filter(|chess_move| position.is_capture(chess_move))
+            .collect::<Vec<_>>();
+        assert_eq!(captures.len(), 2);
+        assert!(captures
+            .iter()
+            .any(|chess_move| matches!(chess_move, ChessMove::EnPassant(..))));
+        assert!(!position.is_capture(&ChessMove::CastleLeft));
+    }
+
+    #[test]
+    fn captured_pieces_include_pawns_taken_en_passant() {
+        let position = Position::from_fen("n3k3/8/8/3pP3/8/8/8/R3K3 w Q d6 0 1");
+        let captured = |uci_long: &str| {
+            position.captured_piece(&ChessMove::from_uci_long(uci_long, &position))
+        };
+        assert_eq!(
+            captured("e5d6"),
+            Some(Piece {
+                kind: PieceKind::Pawn,
+                color: PieceColor::Black
+            })
+        );
+        assert_eq!(
+            captured("a1a8"),
+            Some(Piece {
+                kind: PieceKind::Knight,
+                color: PieceColor::Black
+            })
+        );
+        assert_eq!(captured("a1a7"), None);
+        assert_eq!(position.captured_piece(&ChessMove::CastleLeft), None);
+    }
+
+    #[test]
+    fn finds_pins_for_either_side() {
+        // the e2 bishop is pinned by the rook, the d7 knight by the bishop,
+        // and the f2 pawn is only in front of a queen that isn't lined up
+        let position = Position::from_fen("4r3/3n4/8/1B6/8/7q/4BP2/4K2k w - - 0 1");
+        assert_eq!(
+            position.pinned_pieces(&PieceColor::White),
+            vec![Pin {
+                pinned: Coords::from_algebraic("e2"),
+                pinner: Coords::from_algebraic("e8"),
+                ray: ["e2", "e3", "e4", "e5", "e6", "e7", "e8"]
+                    .into_iter()
+                    .fold(Bitboard::EMPTY, |ray, square| {
+                        ray | Bitboard::from_square(&Coords::from_algebraic(square))
+                    }),
+            }]
+        );
+        assert_eq!(position.pinned_pieces(&PieceColor::Black), vec![]);
+        let position = Position::from_fen("4k3/3n4/8/1B6/8/8/8/4K3 b - - 0 1");
+        let pins = position.pinned_pieces(&PieceColor::Black);
+        assert_eq!(pins.len(), 1);
+        assert_eq!(pins[0].pinned, Coords::from_algebraic("d7"));
+        assert_eq!(pins[0].pinner, Coords::from_algebraic("b5"));
+    }
+
+    #[test]
+    fn en_passant_only_counts_when_it_can_be_played() {
+        use std::collections::HashSet;
+
+        // no black pawn next to e4, so the square the push left doesn't
+        // matter
+        let pushed = Position::from_fen("4k3/8/8/8/4P3/8/8/4K3 b - e3 0 1");
+        let placed = Position::from_fen("4k3/8/8/8/4P3/8/8/4K3 b - - 0 1");
+        assert_eq!(pushed.en_passant_target(), None);
+        assert_eq!(pushed, placed);
+        assert_eq!(pushed.zobrist_key(), placed.zobrist_key());
+
+        let takeable = Position::from_fen("4k3/8/8/8/3pP3/8/8/4K3 b - e3 0 1");
+        assert_eq!(
+            takeable.en_passant_target(),
+            Some(Coords::from_algebraic("e3"))
+        );
+        assert_ne!(
+            takeable,
+            Position::from_fen("4k3/8/8/8/3pP3/8/8/4K3 b - - 0 1")
+        );
+        // taking would leave the black king in check from the rook
+        let pinned = Position::from_fen("8/8/8/8/k2pP2R/8/8/4K3 b - e3 0 1");
+        assert_eq!(pinned.en_passant_target(), None);
+
+        let seen: HashSet<Position> = [pushed, placed, takeable].into_iter().collect();
+        assert_eq!(seen.len(), 2);
+        assert_ne!(
+            Position::initial(),
+            Position::initial().opposite_color_to_move()
+        );
+    }
+
+    #[test]
+    fn null_moves_pass_the_turn() {
+        let position = Position::from_fen("4k3/8/8/8/3pP3/8/8/4K3 b - e3 0 1");
+        let passed = position.after_null_move();
+        assert_eq!(passed.to_fen(), "4k3/8/8/8/3pP3/8/8/4K3 w - - 0 1");
+        let mut back = passed.clone();
+        back.make_null_move();
+        assert_eq!(back.to_move, PieceColor::Black);
+        assert_eq!(back.en_passant_on, None);
+        assert!(!back
+            .all_legal_moves()
+            .iter()
+            .any(|chess_move| matches!(chess_move, ChessMove::EnPassant(..))));
+    }
+
+    #[test]
+    fn iterates_over_one_sides_pieces() {
+        let position = Position::from_fen("4k3/8/8/8/8/8/3PP3/2N1K1N1 w - - 0 1");
+        let white: Vec<String> = position
+            .pieces(PieceColor::White)
+            .map(|(square, piece)| format!("{}{}", piece.to_fen_char(), square.to_algebraic()))
+            .collect();
+        assert_eq!(white, vec!["Pd2", "Pe2", "Nc1", "Ke1", "Ng1"]);
+        let knights: Vec<Coords> = position
+            .pieces_of(PieceColor::White, PieceKind::Knight)
+            .map(|(square, _)| square)
+            .collect();
+        assert_eq!(
+            knights,
+            vec![Coords::from_algebraic("c1"), Coords::from_algebraic("g1")]
+        );
+        assert_eq!(position.pieces(PieceColor::Black).count(), 1);
+        assert_eq!(
+            position
+                .pieces_of(PieceColor::Black, PieceKind::Pawn)
+                .count(),
+            0
+        );
+    }
+
+    #[test]
+    fn occupancy_covers_every_square_once() {
+        let position = Position::initial();
+        assert_eq!(position.occupied_squares().count(), 32);
+        assert_eq!(position.empty_squares().count(), 32);
+        assert!(position.is_empty(&Coords::from_algebraic("e4")));
+        assert!(!position.is_empty(&Coords::from_algebraic("e2")));
+        assert!(position
+            .empty_squares()
+            .squares()
+            .all(|square| position.piece_at(&square).is_none()));
+    }
 }
@@ -0,0 +1,406 @@
+use crate::all_squares;
+use crate::Coords;
+use crate::Direction;
+use crate::Piece;
+use crate::PieceColor;
+use crate::PieceKind;
+use crate::Position;
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum PositionError {
+    MissingKing(PieceColor),
+    TooManyKings(PieceColor),
+    TooManyPawns(PieceColor),
+    TooManyPieces(PieceColor),
+    PawnOnBackRank(Coords),
+    SideNotToMoveInCheck(PieceColor),
+    InvalidCastlingRight { color: PieceColor, king_side: bool },
+    // a right Chess960 would have, with the king off the e-file or the rook
+    // off the a- or h-file, which moves are never generated for
+    UnplayableCastlingRight { color: PieceColor, king_side: bool },
+    InvalidEnPassant(Coords),
+}
+
+fn count(position: &Position, color: &PieceColor, kind: Option<&PieceKind>) -> usize {
+    all_squares()
+        .iter()
+        .filter(|square| {
+            position.piece_at(square).is_some_and(|piece| {
+                piece.color == *color && kind.is_none_or(|kind| piece.kind == *kind)
+            })
+        })
+        .count()
+}
+
+// Each right needs the king on its home row and the rook on its file, on
+// the side of the king the right is for. Castling is only played from the
+// standard squares, so rights on other files are reported too.
+fn castling_problems(position: &Position, color: &PieceColor) -> Vec<PositionError> {
+    let row = color.homerow();
+    let king = (0..8).find(|x| {
+        position.piece_at(&Coords { x: *x, y: row })
+            == Some(Piece {
+                kind: PieceKind::King,
+                color: *color,
+            })
+    });
+    let rook_home = |x: isize| {
+        position.piece_at(&Coords { x, y: row })
+            == Some(Piece {
+                kind: PieceKind::Rook,
+                color: *color,
+            })
+    };
+    let rights = position.castling_rights();
+    [
+        (true, rights.king_side(color)),
+        (false, rights.queen_side(color)),
+    ]
+    .into_iter()
+    .filter_map(|(king_side, rook_file)| {
+        let rook_x = rook_file?;
+        if !(rook_home(rook_x) && king.is_some_and(|king| (rook_x > king) == king_side)) {
+            Some(PositionError::InvalidCastlingRight {
+                color: *color,
+                king_side,
+            })
+        } else if king != Some(4) || rook_x != if king_side { 7 } else { 0 } {
+            Some(PositionError::UnplayableCastlingRight {
+                color: *color,
+                king_side,
+            })
+        } else {
+            None
+        }
+    })
+    .collect()
+}
+
+fn en_passant_problems(position: &Position) -> Vec<PositionError> {
+    let Some(square) = position.en_passant_on else {
+        return vec![];
+    };
+    // the pawn that just skipped moved away from the side to move
+    let mover = position.to_move.opposite();
+    let skipped_pawn = square
+        + Direction {
+            dx: 0,
+            dy: mover.pawn_orientation(),
+        };
+    let origin = square
+        + Direction {
+            dx: 0,
+            dy: -mover.pawn_orientation(),
+        };
+    let valid = square.is_in_bounds()
+        && square.y == mover.homerow() + 2 * mover.pawn_orientation()
+        && position.is_empty(&square)
+        && position.is_empty(&origin)
+        && position.piece_at(&skipped_pawn)
+            == Some(Piece {
+                kind: PieceKind::Pawn,
+                color: mover,
+            });
+    if valid {
+        vec![]
+    } else {
+        vec![PositionError::InvalidEnPassant(square)]
+    }
+}
+
+fn position_problems(position: &Position) -> Vec<PositionError> {
+    let mut problems = vec![];
+    for color in PieceColor::both() {
+        match count(position, &color, Some(&PieceKind::King)) {
+            0 => problems.push(PositionError::MissingKing(color)),
+            1 => {}
+            _ => problems.push(PositionError::TooManyKings(color)),
+        }
+        if count(position, &color, Some(&PieceKind::Pawn)) > 8 {
+            problems.push(PositionError::TooManyPawns(color));
+        }
+        if count(position, &color, None) > 16 {
+            problems.push(PositionError::TooManyPieces(color));
+        }
+    }
+    for square in all_squares() {
+        if (square.y == 0 || square.y == 7)
+            && position
+                .piece_at(&square)
+                .is_some_and(|piece| piece.kind == PieceKind::Pawn)
+        {
+            problems.push(PositionError::PawnOnBackRank(square));
+        }
+    }
+    let waiting = position.to_move.opposite();
+    if position.is_in_check(&waiting) {
+        problems.push(PositionError::SideNotToMoveInCheck(waiting));
+    }
+    for color in PieceColor::both() {
+        problems.extend(castling_problems(position, &color));
+    }
+    problems.extend(en_passant_problems(position));
+    problems
+}
+
+impl Position {
+    // Every reason the position couldn't come up in a game, for positions
+    // read from FEN or set up by hand.
+    pub fn validate(&self) -> Result<(), Vec<PositionError>> {
+        let problems = position_problems(self);
+        if problems.is_empty() {
+            Ok(())
+        } else {
+            Err(problems)
+        }
+    }
+}
+
+// Sets up positions piece by piece, either by chaining calls or by editing
+// in place, and reports at any point why the position is not yet legal.
+#[derive(Clone, PartialEq, Debug)]
+pub struct PositionBuilder {
+    position: Position,
+}
+
+impl Default for PositionBuilder {
+    fn default() -> PositionBuilder {
+        PositionBuilder::new()
+    }
+}
+
+impl PositionBuilder {
+    pub fn new() -> PositionBuilder {
+        let mut position = Position::empty_board();
+        for color in PieceColor::both() {
+            position.set_can_castle_king_side(&color, false);
+            position.set_can_castle_queen_side(&color, false);
+        }
+        PositionBuilder { position }
+    }
+
+    pub fn from_position(position: Position) -> PositionBuilder {
+        PositionBuilder { position }
+    }
+
+    pub fn piece(mut self, square: Coords, piece: Piece) -> PositionBuilder {
+        self.place(square, piece);
+        self
+    }
+
+    pub fn to_move(mut self, color: PieceColor) -> PositionBuilder {
+        self.set_to_move(color);
+        self
+    }
+
+    pub fn castling(
+        mut self,
+        color: PieceColor,
+        king_side: bool,
+        queen_side: bool,
+    ) -> PositionBuilder {
+        self.position.set_can_castle_king_side(&color, king_side);
+        self.position.set_can_castle_queen_side(&color, queen_side);
+        self
+    }
+
+    pub fn en_passant(mut self, square: Option<Coords>) -> PositionBuilder {
+        self.set_en_passant(square);
+        self
+    }
+
+    // Returns the piece that was replaced, if any.
+    pub fn place(&mut self, square: Coords, piece: Piece) -> Option<Piece> {
+        let replaced = self.position.take_piece_at(square);
+        self.position.put_piece_at(piece, square);
+        replaced
+    }
+
+    pub fn remove(&mut self, square: Coords) -> Option<Piece> {
+        self.position.take_piece_at(square)
+    }
+
+    pub fn clear(&mut self) {
+        for square in all_squares() {
+            self.position.take_piece_at(square);
+        }
+    }
+
+    pub fn set_to_move(&mut self, color: PieceColor) {
+        self.position.to_move = color;
+    }
+
+    pub fn toggle_to_move(&mut self) {
+        self.position.to_move = self.position.to_move.opposite();
+    }
+
+    pub fn toggle_castling(&mut self, color: PieceColor, king_side: bool) {
+        if king_side {
+            let allowed = self.position.can_castle_king_side(&color);
+            self.position.set_can_castle_king_side(&color, !allowed);
+        } else {
+            let allowed = self.position.can_castle_queen_side(&color);
+            self.position.set_can_castle_queen_side(&color, !allowed);
+        }
+    }
+
+    pub fn set_en_passant(&mut self, square: Option<Coords>) {
+        self.position.en_passant_on = square;
+    }
+
+    pub fn position(&self) -> &Position {
+        &self.position
+    }
+
+    pub fn problems(&self) -> Vec<PositionError> {
+        position_problems(&self.position)
+    }
+
+    pub fn is_legal(&self) -> bool {
+        self.problems().is_empty()
+    }
+
+    pub fn build(self) -> Result<Position, Vec<PositionError>> {
+        let problems = self.problems();
+        if problems.is_empty() {
+            Ok(self.position)
+        } else {
+            Err(problems)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn piece(kind: PieceKind, color: PieceColor) -> Piece {
+        Piece { kind, color }
+    }
+
+    #[test]
+    fn validates_positions_read_from_fen() {
+        assert_eq!(Position::initial().validate(), Ok(()));
+        assert_eq!(
+            Position::from_fen("4k3/8/8/8/8/8/8/4K2R w KQ - 0 1").validate(),
+            Err(vec![PositionError::InvalidCastlingRight {
+                color: PieceColor::White,
+                king_side: false
+            }])
+        );
+        assert_eq!(
+            Position::from_fen("1r2k1r1/8/8/8/8/8/8/1R2K1R1 w GBgb - 0 1").validate(),
+            Err(vec![
+                PositionError::UnplayableCastlingRight {
+                    color: PieceColor::White,
+                    king_side: true
+                },
+                PositionError::UnplayableCastlingRight {
+                    color: PieceColor::White,
+                    king_side: false
+                },
+                PositionError::UnplayableCastlingRight {
+                    color: PieceColor::Black,
+                    king_side: true
+                },
+                PositionError::UnplayableCastlingRight {
+                    color: PieceColor::Black,
+                    king_side: false
+                },
+            ])
+        );
+        assert_eq!(
+            Position::from_fen("P3k3/8/8/8/8/8/8/4R3 w - - 0 1").validate(),
+            Err(vec![
+                PositionError::MissingKing(PieceColor::White),
+                PositionError::PawnOnBackRank(Coords::from_algebraic("a8")),
+                PositionError::SideNotToMoveInCheck(PieceColor::Black),
+            ])
+        );
+    }
+
+    #[test]
+    fn builds_legal_position() {
+        let position = PositionBuilder::new()
+            .piece(
+                Coords::from_algebraic("e1"),
+                piece(PieceKind::King, PieceColor::White),
+            )
+            .piece(
+                Coords::from_algebraic("h1"),
+                piece(PieceKind::Rook, PieceColor::White),
+            )
+            .piece(
+                Coords::from_algebraic("e8"),
+                piece(PieceKind::King, PieceColor::Black),
+            )
+            .castling(PieceColor::White, true, false)
+            .to_move(PieceColor::Black)
+            .build()
+            .unwrap();
+        assert_eq!(position.to_fen(), "4k3/8/8/8/8/8/8/4K2R b K - 0 1");
+    }
+
+    #[test]
+    fn explains_what_is_wrong_while_editing() {
+        let mut editor = PositionBuilder::new();
+        assert_eq!(
+            editor.problems(),
+            vec![
+                PositionError::MissingKing(PieceColor::White),
+                PositionError::MissingKing(PieceColor::Black)
+            ]
+        );
+        editor.place(
+            Coords::from_algebraic("e1"),
+            piece(PieceKind::King, PieceColor::White),
+        );
+        editor.place(
+            Coords::from_algebraic("e8"),
+            piece(PieceKind::King, PieceColor::Black),
+        );
+        editor.place(
+            Coords::from_algebraic("a1"),
+            piece(PieceKind::Pawn, PieceColor::White),
+        );
+        editor.place(
+            Coords::from_algebraic("e2"),
+            piece(PieceKind::Rook, PieceColor::White),
+        );
+        editor.toggle_castling(PieceColor::White, false);
+        editor.set_to_move(PieceColor::Black);
+        assert_eq!(
+            editor.problems(),
+            vec![
+                PositionError::PawnOnBackRank(Coords::from_algebraic("a1")),
+                PositionError::InvalidCastlingRight {
+                    color: PieceColor::White,
+                    king_side: false
+                }
+            ]
+        );
+        editor.remove(Coords::from_algebraic("a1"));
+        editor.toggle_castling(PieceColor::White, false);
+        editor.toggle_to_move();
+        assert_eq!(
+            editor.problems(),
+            vec![PositionError::SideNotToMoveInCheck(PieceColor::Black)]
+        );
+        editor.toggle_to_move();
+        assert!(editor.is_legal());
+    }
+
+    #[test]
+    fn checks_en_passant_square() {
+        let position = Position::from_fen("4k3/8/8/3pP3/8/8/8/4K3 w - d6 0 1");
+        assert!(PositionBuilder::from_position(position.clone()).is_legal());
+        let mut editor = PositionBuilder::from_position(position);
+        editor.set_en_passant(Some(Coords::from_algebraic("e6")));
+        assert_eq!(
+            editor.problems(),
+            vec![PositionError::InvalidEnPassant(Coords::from_algebraic(
+                "e6"
+            ))]
+        );
+    }
+}
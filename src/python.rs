@@ -0,0 +1,424 @@
+use std::time::Duration;
+
+use pyo3::exceptions::PyValueError;
+use pyo3::prelude::*;
+use pyo3::types::PyDict;
+
+use crate::pgn::result_token;
+use crate::uci;
+use crate::ChessMove;
+use crate::Game;
+use crate::GameStatus;
+use crate::PieceColor;
+use crate::Planner;
+use crate::Position;
+use crate::SearchLimits;
+use crate::Square;
+
+fn read_fen(fen: &str) -> PyResult<Position> {
+    let position = Position::try_from_fen(fen)
+        .map_err(|error| PyValueError::new_err(format!("invalid FEN: {fen} ({error:?})")))?;
+    position
+        .validate()
+        .map_err(|problems| PyValueError::new_err(format!("illegal position: {problems:?}")))?;
+    Ok(position)
+}
+
+fn legal_move(position: &Position, uci: &str) -> PyResult<ChessMove> {
+    uci::legal_move(uci, position)
+        .ok_or_else(|| PyValueError::new_err(format!("illegal move: {uci}")))
+}
+
+fn san_move(position: &Position, san: &str) -> PyResult<ChessMove> {
+    ChessMove::from_san(san, position)
+        .map_err(|error| PyValueError::new_err(format!("{san}: {error:?}")))
+}
+
+fn uci_moves(position: &Position) -> Vec<String> {
+    position
+        .all_legal_moves()
+        .iter()
+        .map(|chess_move| chess_move.to_uci_long(position))
+        .collect()
+}
+
+#[pyclass(name = "Position", frozen, eq)]
+#[derive(Clone, PartialEq)]
+struct PyPosition(Position);
+
+#[pymethods]
+impl PyPosition {
+    #[new]
+    #[pyo3(signature = (fen = None))]
+    fn new(fen: Option<&str>) -> PyResult<PyPosition> {
+        match fen {
+            Some(fen) => read_fen(fen).map(PyPosition),
+            None => Ok(PyPosition(Position::initial())),
+        }
+    }
+
+    fn fen(&self) -> String {
+        self.0.to_fen()
+    }
+
+    #[getter]
+    fn turn(&self) -> bool {
+        self.0.to_move == PieceColor::White
+    }
+
+    fn legal_moves(&self) -> Vec<String> {
+        uci_moves(&self.0)
+    }
+
+    fn is_legal(&self, uci: &str) -> bool {
+        legal_move(&self.0, uci).is_ok()
+    }
+
+    // The position after the move, this one being left as it is.
+    fn push_uci(&self, uci: &str) -> PyResult<PyPosition> {
+        let chess_move = legal_move(&self.0, uci)?;
+        Ok(PyPosition(self.0.after_move(&chess_move)))
+    }
+
+    fn push_san(&self, san: &str) -> PyResult<PyPosition> {
+        let chess_move = san_move(&self.0, san)?;
+        Ok(PyPosition(self.0.after_move(&chess_move)))
+    }
+
+    fn san(&self, uci: &str) -> PyResult<String> {
+        Ok(legal_move(&self.0, uci)?.to_san(&self.0))
+    }
+
+    fn parse_san(&self, san: &str) -> PyResult<String> {
+        Ok(san_move(&self.0, san)?.to_uci_long(&self.0))
+    }
+
+    // The FEN letter of the piece on a square such as "e4".
+    fn piece_at(&self, square: &str) -> PyResult<Option<char>> {
+        let square = Square::from_algebraic(square)
+            .ok_or_else(|| PyValueError::new_err(format!("not a square: {square}")))?;
+        Ok(self
+            .0
+            .piece_at(&square.coords())
+            .map(|piece| piece.to_fen_char()))
+    }
+
+    fn is_check(&self) -> bool {
+        self.0.is_in_check(&self.0.to_move)
+    }
+
+    fn is_checkmate(&self) -> bool {
+        self.0.is_checkmate()
+    }
+
+    fn is_stalemate(&self) -> bool {
+        GameStatus::of(&self.0) == GameStatus::Stalemate
+    }
+
+    fn is_game_over(&self) -> bool {
+        GameStatus::of(&self.0).is_over()
+    }
+
+    fn zobrist_hash(&self) -> u64 {
+        self.0.zobrist_key()
+    }
+
+    fn __hash__(&self) -> u64 {
+        self.0.zobrist_key()
+    }
+
+    fn __repr__(&self) -> String {
+        format!("Position('{}')", self.0.to_fen())
+    }
+}
+
+#[pyclass(name = "Game")]
+struct PyGame(Game);
+
+impl PyGame {
+    // `Game` takes no moves once the game is over, resigned games included.
+    fn going_on(&self) -> PyResult<()> {
+        if self.0.status().is_over() {
+            return Err(PyValueError::new_err("the game is over"));
+        }
+        Ok(())
+    }
+}
+
+#[pymethods]
+impl PyGame {
+    #[new]
+    #[pyo3(signature = (fen = None))]
+    fn new(fen: Option<&str>) -> PyResult<PyGame> {
+        match fen {
+            Some(fen) => {
+                read_fen(fen).map(|position| PyGame(Game::from_starting_position(position)))
+            }
+            None => Ok(PyGame(Game::start())),
+        }
+    }
+
+    #[getter]
+    fn position(&self) -> PyPosition {
+        PyPosition(self.0.current_position.clone())
+    }
+
+    fn fen(&self) -> String {
+        self.0.current_position.to_fen()
+    }
+
+    #[getter]
+    fn turn(&self) -> bool {
+        self.0.current_position.to_move == PieceColor::White
+    }
+
+    #[getter]
+    fn move_stack(&self) -> Vec<String> {
+        self.0
+            .previous_positions
+            .iter()
+            .zip(self.0.history())
+            .map(|(position, chess_move)| chess_move.to_uci_long(position))
+            .collect()
+    }
+
+    fn legal_moves(&self) -> Vec<String> {
+        uci_moves(&self.0.current_position)
+    }
+
+    fn push_uci(&mut self, uci: &str) -> PyResult<()> {
+        self.going_on()?;
+        let chess_move = legal_move(&self.0.current_position, uci)?;
+        self.0.make_move(&chess_move);
+        Ok(())
+    }
+
+    fn push_san(&mut self, san: &str) -> PyResult<()> {
+        self.going_on()?;
+        let chess_move = san_move(&self.0.current_position, san)?;
+        self.0.make_move(&chess_move);
+        Ok(())
+    }
+
+    // Takes back the last move, returning it.
+    fn pop(&mut self) -> PyResult<String> {
+        self.0
+            .undo_move()
+            .map(|chess_move| chess_move.to_uci_long(&self.0.current_position))
+            .ok_or_else(|| PyValueError::new_err("no move to take back"))
+    }
+
+    fn is_game_over(&self) -> bool {
+        self.0.status().is_over()
+    }
+
+    // "1-0", "0-1", "1/2-1/2" or "*" while the game goes on.
+    fn result(&self) -> &'static str {
+        result_token(self.0.status().result().as_ref())
+    }
+
+    fn resign(&mut self, white: bool) {
+        self.0.resign(if white {
+            PieceColor::White
+        } else {
+            PieceColor::Black
+        });
+    }
+
+    // Claims a draw by repetition or the fifty-move rule, saying whether
+    // there was one to claim.
+    fn claim_draw(&mut self) -> bool {
+        self.0.claim_draw().is_some()
+    }
+
+    fn movetext(&self) -> String {
+        self.0.movetext()
+    }
+}
+
+// The searching engine, keeping its tables between calls as `Planner`
+// does.
+#[pyclass(name = "Engine", frozen)]
+struct PyEngine(Planner);
+
+fn limits(
+    depth: Option<usize>,
+    movetime: Option<f64>,
+    nodes: Option<u64>,
+) -> PyResult<SearchLimits> {
+    let mut limits = match movetime {
+        Some(seconds) => {
+            SearchLimits::movetime(Duration::try_from_secs_f64(seconds).map_err(|_| {
+                PyValueError::new_err(format!("movetime must be seconds to think, not {seconds}"))
+            })?)
+        }
+        None => SearchLimits::depth(depth.unwrap_or(4)),
+    };
+    if let (Some(depth), Some(_)) = (depth, movetime) {
+        limits.depth = depth;
+    }
+    if let Some(nodes) = nodes {
+        limits = limits.nodes(nodes);
+    }
+    Ok(limits)
+}
+
+fn playable(position: &Position) -> PyResult<()> {
+    if GameStatus::of(position).is_over() {
+        return Err(PyValueError::new_err("the game is over"));
+    }
+    Ok(())
+}
+
+#[pymethods]
+impl PyEngine {
+    #[new]
+    fn new() -> PyEngine {
+        PyEngine(Planner::new())
+    }
+
+    fn new_game(&self) {
+        self.0.new_game();
+    }
+
+    // Searches to `depth`, for `movetime` seconds or `nodes` nodes, four
+    // plies deep when none are given.
+    #[pyo3(signature = (position, depth = None, movetime = None, nodes = None))]
+    fn play(
+        &self,
+        py: Python<'_>,
+        position: &PyPosition,
+        depth: Option<usize>,
+        movetime: Option<f64>,
+        nodes: Option<u64>,
+    ) -> PyResult<String> {
+        playable(&position.0)?;
+        let limits = limits(depth, movetime, nodes)?;
+        let result = py.allow_threads(|| self.0.search_with_limits(&position.0, &limits));
+        Ok(result.best_move.to_uci_long(&position.0))
+    }
+
+    // As python-chess's `analyse`, a dict with the score for the side to
+    // move in centipawns, the expected line, the depth and the node count.
+    #[pyo3(signature = (position, depth = None, movetime = None, nodes = None))]
+    fn analyse<'py>(
+        &self,
+        py: Python<'py>,
+        position: &PyPosition,
+        depth: Option<usize>,
+        movetime: Option<f64>,
+        nodes: Option<u64>,
+    ) -> PyResult<Bound<'py, PyDict>> {
+        playable(&position.0)?;
+        let limits = limits(depth, movetime, nodes)?;
+        let result = py.allow_threads(|| self.0.search_with_limits(&position.0, &limits));
+        let mut line = Vec::new();
+        let mut current = position.0.clone();
+        for chess_move in &result.pv {
+            line.push(chess_move.to_uci_long(&current));
+            current = current.after_move(chess_move);
+        }
+        let info = PyDict::new(py);
+        info.set_item("score", result.score)?;
+        info.set_item("pv", line)?;
+        info.set_item("depth", result.depth)?;
+        info.set_item("nodes", result.nodes)?;
+        Ok(info)
+    }
+}
+
+// Python bindings, built as the `libchessticot` extension module with the
+// `python` feature. Names follow python-chess where the two libraries do
+// the same thing: moves are passed around as UCI strings and the side to
+// move is True for white.
+#[pymodule]
+fn libchessticot(module: &Bound<'_, PyModule>) -> PyResult<()> {
+    module.add_class::<PyPosition>()?;
+    module.add_class::<PyGame>()?;
+    module.add_class::<PyEngine>()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::ffi::CString;
+
+    use super::*;
+
+    #[test]
+    fn games_are_played_with_uci_and_san() {
+        let mut game = PyGame::new(None).unwrap();
+        for san in ["f3", "e5", "g4"] {
+            game.push_san(san).unwrap();
+        }
+        assert!(game.push_uci("e1e3").is_err());
+        game.push_uci("d8h4").unwrap();
+        assert_eq!(game.move_stack(), vec!["f2f3", "e7e5", "g2g4", "d8h4"]);
+        assert!(game.position().is_checkmate());
+        assert_eq!(game.result(), "0-1");
+        assert_eq!(game.pop().unwrap(), "d8h4");
+        assert_eq!(game.result(), "*");
+        assert!(PyPosition::new(Some("not a fen")).is_err());
+        assert!(PyPosition::new(Some("9/8/8/8/8/8/8/8 w - - 0 1")).is_err());
+    }
+
+    #[test]
+    fn finished_games_take_no_moves() {
+        let mut game = PyGame::new(Some("6k1/5ppp/8/8/8/8/8/4R1K1 w - - 0 1")).unwrap();
+        game.push_uci("e1e8").unwrap();
+        assert!(game.is_game_over());
+        assert!(game.push_uci("g8h8").is_err());
+        game.pop().unwrap();
+        game.resign(true);
+        assert!(game.push_san("Re8#").is_err());
+        assert_eq!(game.move_stack(), Vec::<String>::new());
+    }
+
+    #[test]
+    fn promotions_are_named_by_their_piece() {
+        let position = PyPosition::new(Some("8/1P4k1/8/8/8/8/8/4K3 w - - 0 1")).unwrap();
+        let promotions: Vec<String> = position
+            .legal_moves()
+            .into_iter()
+            .filter(|uci| uci.starts_with("b7b8"))
+            .collect();
+        assert_eq!(promotions.len(), 4);
+        assert!(["b7b8q", "b7b8r", "b7b8b", "b7b8n"]
+            .iter()
+            .all(|uci| promotions.contains(&String::from(*uci))));
+        assert!(position.is_legal("b7b8q"));
+        assert!(!position.is_legal("b7b8"));
+        assert_eq!(
+            position.push_uci("b7b8n").unwrap().fen(),
+            "1N6/6k1/8/8/8/8/8/4K3 b - - 0 1"
+        );
+    }
+
+    #[test]
+    fn movetime_must_be_seconds_to_think() {
+        assert!(limits(None, Some(0.5), None).is_ok());
+        for movetime in [-1.0, f64::NAN, f64::INFINITY] {
+            assert!(limits(None, Some(movetime), None).is_err());
+        }
+    }
+
+    #[test]
+    fn python_sees_the_module() {
+        pyo3::prepare_freethreaded_python();
+        Python::with_gil(|py| {
+            let module = PyModule::new(py, "libchessticot").unwrap();
+            libchessticot(&module).unwrap();
+            let globals = PyDict::new(py);
+            globals.set_item("chess", module).unwrap();
+            let script = CString::new(
+                "position = chess.Position().push_san('e4')\n\
+                 assert not position.turn\n\
+                 assert position.piece_at('e4') == 'P'\n\
+                 info = chess.Engine().analyse(position, depth=2)\n\
+                 assert info['depth'] == 2 and position.is_legal(info['pv'][0])\n",
+            )
+            .unwrap();
+            py.run(&script, Some(&globals), None).unwrap();
+        });
+    }
+}
@@ -0,0 +1,102 @@
+use crate::ChessMove;
+use crate::DrawReason;
+use crate::GameResult;
+use crate::PieceColor;
+use crate::Position;
+use crate::RuleSet;
+use crate::WinReason;
+
+// The legal moves that don't give check.
+fn racing_moves(position: &Position) -> Vec<ChessMove> {
+    let other = position.to_move.opposite();
+    position
+        .all_legal_moves()
+        .into_iter()
+        .filter(|chess_move| !position.after_move(chess_move).is_in_check(&other))
+        .collect()
+}
+
+// Racing Kings: both sides start on the first two ranks and race their king
+// to the eighth. Nobody may give check, so no king can ever be mated. When
+// white gets there first, black has one move left to draw by getting there
+// too.
+pub struct RacingKingsRules;
+
+impl RuleSet for RacingKingsRules {
+    fn name(&self) -> &str {
+        "Racing Kings"
+    }
+    fn starting_position(&self) -> Position {
+        Position::from_fen("8/8/8/8/8/8/krbnNBRK/qrbnNBRQ w - - 0 1")
+    }
+    fn legal_moves(&self, position: &Position) -> Vec<ChessMove> {
+        if self.result(position).is_some() {
+            return Vec::new();
+        }
+        racing_moves(position)
+    }
+    fn result(&self, position: &Position) -> Option<GameResult> {
+        let home = |color: PieceColor| {
+            position
+                .king_location(&color)
+                .is_some_and(|king| king.y == PieceColor::Black.homerow())
+        };
+        match (home(PieceColor::White), home(PieceColor::Black)) {
+            (true, true) => return Some(GameResult::Draw(DrawReason::Variant)),
+            (false, true) => return Some(GameResult::BlackWin(WinReason::Variant)),
+            (true, false) => {
+                let black_catches_up = position.to_move == PieceColor::Black
+                    && racing_moves(position).iter().any(|chess_move| {
+                        position
+                            .after_move(chess_move)
+                            .king_location(&PieceColor::Black)
+                            .is_some_and(|king| king.y == PieceColor::Black.homerow())
+                    });
+                if !black_catches_up {
+                    return Some(GameResult::WhiteWin(WinReason::Variant));
+                }
+            }
+            (false, false) => {}
+        }
+        if racing_moves(position).is_empty() {
+            Some(GameResult::Draw(DrawReason::Stalemate))
+        } else {
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn checks_are_not_allowed() {
+        let start = RacingKingsRules.starting_position();
+        assert_eq!(RacingKingsRules.result(&start), None);
+        assert!(!RacingKingsRules.legal_moves(&start).is_empty());
+        let position = Position::from_fen("7k/8/8/8/8/8/8/K5R1 w - - 0 1");
+        let check = ChessMove::from_uci_long("g1h1", &position);
+        assert!(position.all_legal_moves().contains(&check));
+        assert!(!RacingKingsRules.legal_moves(&position).contains(&check));
+    }
+
+    #[test]
+    fn black_gets_one_move_to_catch_up() {
+        assert_eq!(
+            RacingKingsRules.result(&Position::from_fen("K7/8/8/8/8/8/8/7k b - - 0 1")),
+            Some(GameResult::WhiteWin(WinReason::Variant))
+        );
+        let catching_up = Position::from_fen("K7/6k1/8/8/8/8/8/8 b - - 0 1");
+        assert_eq!(RacingKingsRules.result(&catching_up), None);
+        let caught_up = catching_up.after_move(&ChessMove::from_uci_long("g7g8", &catching_up));
+        assert_eq!(
+            RacingKingsRules.result(&caught_up),
+            Some(GameResult::Draw(DrawReason::Variant))
+        );
+        assert_eq!(
+            RacingKingsRules.result(&Position::from_fen("6k1/8/8/8/8/8/8/K7 w - - 0 1")),
+            Some(GameResult::BlackWin(WinReason::Variant))
+        );
+    }
+}
@@ -0,0 +1,442 @@
+use std::collections::HashSet;
+
+use crate::all_squares;
+use crate::ChessMove;
+use crate::Coords;
+use crate::Move;
+use crate::Piece;
+use crate::PieceColor;
+use crate::PieceKind;
+use crate::Position;
+
+// A deliberately naive move generator: every piece is tried against every
+// destination square, attacks are found by scanning the whole board and
+// legality is checked by playing the move out. It shares nothing with the
+// generator in `Position` beyond the types, so the two can be compared.
+#[derive(Clone, PartialEq, Debug)]
+pub struct ReferencePosition {
+    squares: [[Option<Piece>; 8]; 8],
+    to_move: PieceColor,
+    // indexed by color, then queen side 0 and king side 1
+    castling: [[bool; 2]; 2],
+    en_passant_on: Option<Coords>,
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct MovegenMismatch {
+    pub origin: Coords,
+    pub missing: Vec<ChessMove>,
+    pub unexpected: Vec<ChessMove>,
+}
+
+impl ReferencePosition {
+    pub fn from_position(position: &Position) -> ReferencePosition {
+        let mut squares = [[None; 8]; 8];
+        for square in all_squares() {
+            squares[square.y as usize][square.x as usize] = position.piece_at(&square);
+        }
+        let mut castling = [[false; 2]; 2];
+        for color in PieceColor::both() {
+            castling[color.index()] = [
+                position.can_castle_queen_side(&color),
+                position.can_castle_king_side(&color),
+            ];
+        }
+        ReferencePosition {
+            squares,
+            to_move: position.to_move,
+            castling,
+            en_passant_on: position.en_passant_on,
+        }
+    }
+
+    fn at(&self, square: &Coords) -> Option<Piece> {
+        self.squares[square.y as usize][square.x as usize]
+    }
+
+    fn set(&mut self, square: &Coords, piece: Option<Piece>) {
+        self.squares[square.y as usize][square.x as usize] = piece;
+    }
+
+    fn path_is_clear(&self, from: &Coords, to: &Coords) -> bool {
+        let step_x = (to.x - from.x).signum();
+        let step_y = (to.y - from.y).signum();
+        let mut current = Coords {
+            x: from.x + step_x,
+            y: from.y + step_y,
+        };
+        while current != *to {
+            if self.at(&current).is_some() {
+                return false;
+            }
+            current = Coords {
+                x: current.x + step_x,
+                y: current.y + step_y,
+            };
+        }
+        true
+    }
+
+    fn attacks(&self, piece: &Piece, from: &Coords, target: &Coords) -> bool {
+        let dx = target.x - from.x;
+        let dy = target.y - from.y;
+        if dx == 0 && dy == 0 {
+            return false;
+        }
+        match piece.kind {
+            PieceKind::Pawn => dx.abs() == 1 && dy == piece.color.pawn_orientation(),
+            PieceKind::Knight => dx.abs() * dy.abs() == 2,
+            PieceKind::King => dx.abs() <= 1 && dy.abs() <= 1,
+            PieceKind::Rook => (dx == 0 || dy == 0) && self.path_is_clear(from, target),
+            PieceKind::Bishop => dx.abs() == dy.abs() && self.path_is_clear(from, target),
+            PieceKind::Queen => {
+                (dx == 0 || dy == 0 || dx.abs() == dy.abs()) && self.path_is_clear(from, target)
+            }
+        }
+    }
+
+    fn is_attacked_by(&self, color: &PieceColor, target: &Coords) -> bool {
+        all_squares().iter().any(|square| {
+            self.at(square)
+                .is_some_and(|piece| piece.color == *color && self.attacks(&piece, square, target))
+        })
+    }
+
+    fn is_in_check(&self, color: &PieceColor) -> bool {
+        all_squares().iter().any(|square| {
+            self.at(square)
+                == Some(Piece {
+                    kind: PieceKind::King,
+                    color: *color,
+                })
+                && self.is_attacked_by(&color.opposite(), square)
+        })
+    }
+
+    fn pawn_moves(&self, origin: &Coords, destination: &Coords, moves: &mut Vec<ChessMove>) {
+        let color = self.to_move;
+        let dx = destination.x - origin.x;
+        let dy = destination.y - origin.y;
+        let forward = color.pawn_orientation();
+        let start_row = color.opposite().homerow() - forward * 6;
+        let movement = Move {
+            origin: *origin,
+            destination: *destination,
+        };
+        let target = self.at(destination);
+        if dx == 0 && dy == 2 * forward {
+            if origin.y == start_row && target.is_none() && self.path_is_clear(origin, destination)
+            {
+                moves.push(ChessMove::PawnSkip(movement));
+            }
+            return;
+        }
+        let single_step = dx == 0 && dy == forward && target.is_none();
+        let capture =
+            dx.abs() == 1 && dy == forward && target.is_some_and(|piece| piece.color != color);
+        if single_step || capture {
+            if destination.y == color.opposite().homerow() {
+                for kind in PieceKind::promoteable() {
+                    moves.push(ChessMove::Promotion(movement.clone(), *kind));
+                }
+            } else {
+                moves.push(ChessMove::RegularMove(movement));
+            }
+        } else if dx.abs() == 1 && dy == forward && self.en_passant_on == Some(*destination) {
+            let taken = Coords {
+                x: destination.x,
+                y: origin.y,
+            };
+            if self.at(&taken).is_some_and(|piece| {
+                piece.kind == PieceKind::Pawn && piece.color == color.opposite()
+            }) {
+                moves.push(ChessMove::EnPassant(movement, taken));
+            }
+        }
+    }
+
+    fn castling_moves(&self, moves: &mut Vec<ChessMove>) {
+        let color = self.to_move;
+        let row = color.homerow();
+        let king = Piece {
+            kind: PieceKind::King,
+            color,
+        };
+        let rook = Piece {
+            kind: PieceKind::Rook,
+            color,
+        };
+        if self.at(&Coords { x: 4, y: row }) != Some(king) || self.is_in_check(&color) {
+            return;
+        }
+        for (side, rook_x, king_path, chess_move) in [
+            (0, 0, [3, 2], ChessMove::CastleLeft),
+            (1, 7, [5, 6], ChessMove::CastleRight),
+        ] {
+            let king_square = Coords { x: 4, y: row };
+            let rook_square = Coords { x: rook_x, y: row };
+            if self.castling[color.index()][side]
+                && self.at(&rook_square) == Some(rook)
+                && self.path_is_clear(&king_square, &rook_square)
+                && king_path
+                    .iter()
+                    .all(|x| !self.is_attacked_by(&color.opposite(), &Coords { x: *x, y: row }))
+            {
+                moves.push(chess_move);
+            }
+        }
+    }
+
+    pub fn pseudo_legal_moves(&self) -> Vec<ChessMove> {
+        let mut moves = vec![];
+        for origin in all_squares() {
+            let Some(piece) = self.at(&origin) else {
+                continue;
+            };
+            if piece.color != self.to_move {
+                continue;
+            }
+            for destination in all_squares() {
+                if self
+                    .at(&destination)
+                    .is_some_and(|target| target.color == piece.color)
+                {
+                    continue;
+                }
+                if piece.kind == PieceKind::Pawn {
+                    self.pawn_moves(&origin, &destination, &mut moves);
+                } else if self.attacks(&piece, &origin, &destination) {
+                    moves.push(ChessMove::RegularMove(Move {
+                        origin,
+                        destination,
+                    }));
+                }
+            }
+        }
+        self.castling_moves(&mut moves);
+        moves
+    }
+
+    pub fn legal_moves(&self) -> Vec<ChessMove> {
+        self.pseudo_legal_moves()
+            .into_iter()
+            .filter(|chess_move| !self.after_move(chess_move).is_in_check(&self.to_move))
+            .collect()
+    }
+
+    pub fn after_move(&self, chess_move: &ChessMove) -> ReferencePosition {
+        let mut after = self.clone();
+        let color = self.to_move;
+        let row = color.homerow();
+        after.en_passant_on = None;
+        after.to_move = color.opposite();
+        let (king_to, rook_from, rook_to) = match chess_move {
+            ChessMove::CastleLeft => (2, 0, 3),
+            ChessMove::CastleRight => (6, 7, 5),
+            _ => {
+                let movement = chess_move
+                    .movement()
+                    .expect("only castling has no movement");
+                let moved = self.at(&movement.origin);
+                after.set(&movement.origin, None);
+                after.set(
+                    &movement.destination,
+                    match chess_move {
+                        ChessMove::Promotion(_, kind) => Some(Piece { kind: *kind, color }),
+                        _ => moved,
+                    },
+                );
+                match chess_move {
+                    ChessMove::EnPassant(_, taken) => after.set(taken, None),
+                    ChessMove::PawnSkip(_) => {
+                        after.en_passant_on = Some(Coords {
+                            x: movement.origin.x,
+                            y: (movement.origin.y + movement.destination.y) / 2,
+                        })
+                    }
+                    _ => {}
+                }
+                for corner_color in PieceColor::both() {
+                    let corner_row = corner_color.homerow();
+                    for (side, corner_x) in [(0, 0), (1, 7)] {
+                        let corner = Coords {
+                            x: corner_x,
+                            y: corner_row,
+                        };
+                        if movement.origin == corner || movement.destination == corner {
+                            after.castling[corner_color.index()][side] = false;
+                        }
+                    }
+                }
+                if moved.is_some_and(|piece| piece.kind == PieceKind::King) {
+                    after.castling[color.index()] = [false, false];
+                }
+                return after;
+            }
+        };
+        after.set(&Coords { x: 4, y: row }, None);
+        after.set(
+            &Coords {
+                x: rook_from,
+                y: row,
+            },
+            None,
+        );
+        after.set(
+            &Coords { x: king_to, y: row },
+            Some(Piece {
+                kind: PieceKind::King,
+                color,
+            }),
+        );
+        after.set(
+            &Coords { x: rook_to, y: row },
+            Some(Piece {
+                kind: PieceKind::Rook,
+                color,
+            }),
+        );
+        after.castling[color.index()] = [false, false];
+        after
+    }
+
+    pub fn perft(&self, depth: usize) -> u64 {
+        if depth == 0 {
+            return 1;
+        }
+        self.legal_moves()
+            .iter()
+            .map(|chess_move| self.after_move(chess_move).perft(depth - 1))
+            .sum()
+    }
+}
+
+fn move_origin(position: &Position, chess_move: &ChessMove) -> Coords {
+    match chess_move.movement() {
+        Some(movement) => movement.origin,
+        None => Coords {
+            x: 4,
+            y: position.to_move.homerow(),
+        },
+    }
+}
+
+// Compares the legal moves of `position` with the reference generator, square
+// by square, and reports every origin where the two disagree.
+pub fn movegen_mismatches(position: &Position) -> Vec<MovegenMismatch> {
+    let reference = ReferencePosition::from_position(position).legal_moves();
+    let optimized = position.all_legal_moves();
+    all_squares()
+        .into_iter()
+        .filter_map(|origin| {
+            let from_origin = |moves: &[ChessMove]| -> HashSet<ChessMove> {
+                moves
+                    .iter()
+                    .filter(|chess_move| move_origin(position, chess_move) == origin)
+                    .cloned()
+                    .collect()
+            };
+            let expected = from_origin(&reference);
+            let actual = from_origin(&optimized);
+            if expected == actual {
+                return None;
+            }
+            Some(MovegenMismatch {
+                origin,
+                missing: expected.difference(&actual).cloned().collect(),
+                unexpected: actual.difference(&expected).cloned().collect(),
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::perft;
+
+    use super::*;
+
+    const PERFT_POSITIONS: [(&str, usize, u64); 4] = [
+        (
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+            3,
+            8902,
+        ),
+        (
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+            2,
+            2039,
+        ),
+        ("8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1", 3, 2812),
+        (
+            "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1",
+            2,
+            264,
+        ),
+    ];
+
+    fn assert_generators_agree(position: &Position) {
+        let mismatches = movegen_mismatches(position);
+        assert!(mismatches.is_empty(), "{:?}: {:?}", position, mismatches);
+    }
+
+    #[test]
+    fn reference_matches_known_perft_counts() {
+        for (fen, depth, nodes) in PERFT_POSITIONS {
+            let position = Position::from_fen(fen);
+            assert_eq!(
+                ReferencePosition::from_position(&position).perft(depth),
+                nodes,
+                "{}",
+                fen
+            );
+            assert_eq!(perft(&position, depth), nodes, "{}", fen);
+        }
+    }
+
+    #[test]
+    fn generators_agree_over_perft_positions() {
+        for (fen, _, _) in PERFT_POSITIONS {
+            let position = Position::from_fen(fen);
+            assert_generators_agree(&position);
+            for chess_move in position.all_legal_moves() {
+                assert_generators_agree(&position.after_move(&chess_move));
+            }
+        }
+    }
+
+    #[test]
+    fn generators_agree_over_random_games() {
+        let mut state: u64 = 0x2545_f491_4f6c_dd1d;
+        for (fen, _, _) in PERFT_POSITIONS {
+            let mut position = Position::from_fen(fen);
+            let mut reference = ReferencePosition::from_position(&position);
+            for _ in 0..60 {
+                assert_generators_agree(&position);
+                let moves = position.all_legal_moves();
+                if moves.is_empty() {
+                    break;
+                }
+                state ^= state << 13;
+                state ^= state >> 7;
+                state ^= state << 17;
+                let chess_move = &moves[(state % moves.len() as u64) as usize];
+                position = position.after_move(chess_move);
+                reference = reference.after_move(chess_move);
+                assert_eq!(
+                    ReferencePosition::from_position(&position),
+                    reference,
+                    "{:?}",
+                    chess_move
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn castling_through_check_is_not_generated() {
+        let position = Position::from_fen("4k3/8/8/8/8/8/5r2/4K2R w K - 0 1");
+        assert_generators_agree(&position);
+        assert!(!position.all_legal_moves().contains(&ChessMove::CastleRight));
+    }
+}
@@ -0,0 +1,133 @@
+use std::fmt::Debug;
+
+use crate::ChessMove;
+use crate::DrawReason;
+use crate::FenError;
+use crate::GameResult;
+use crate::GameStatus;
+use crate::PieceColor;
+use crate::Position;
+use crate::WinReason;
+
+// The rules of a variant: where it starts, which moves it allows, when it
+// is over and how its positions are written down. Standard chess is the
+// default for everything but its name, so each variant only says where it
+// differs.
+pub trait RuleSet: Send + Sync {
+    fn name(&self) -> &str;
+    fn starting_position(&self) -> Position {
+        Position::initial()
+    }
+    fn legal_moves(&self, position: &Position) -> Vec<ChessMove> {
+        position.all_legal_moves()
+    }
+    fn is_move_legal(&self, position: &Position, chess_move: &ChessMove) -> bool {
+        self.legal_moves(position).contains(chess_move)
+    }
+    // None while the game goes on.
+    fn result(&self, position: &Position) -> Option<GameResult> {
+        GameStatus::of(position).result()
+    }
+    // The result as a `Game` keeps it, goals other than mate being variant
+    // wins.
+    fn status(&self, position: &Position) -> GameStatus {
+        match self.result(position) {
+            None => GameStatus::Ongoing {
+                check: position.is_in_check(&position.to_move),
+            },
+            Some(GameResult::Draw(DrawReason::Stalemate)) => GameStatus::Stalemate,
+            Some(GameResult::Draw(reason)) => GameStatus::Draw(reason),
+            Some(GameResult::WhiteWin(WinReason::Checkmate)) => {
+                GameStatus::Checkmate(PieceColor::Black)
+            }
+            Some(GameResult::BlackWin(WinReason::Checkmate)) => {
+                GameStatus::Checkmate(PieceColor::White)
+            }
+            Some(result) => {
+                GameStatus::VariantWin(result.winner().expect("only draws have no winner"))
+            }
+        }
+    }
+    fn read_fen(&self, fen: &str) -> Result<Position, FenError> {
+        Position::try_from_fen(fen)
+    }
+    fn write_fen(&self, position: &Position) -> String {
+        position.to_fen()
+    }
+}
+
+impl Debug for dyn RuleSet {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} rules", self.name())
+    }
+}
+
+pub struct StandardRules;
+
+impl RuleSet for StandardRules {
+    fn name(&self) -> &str {
+        "Standard"
+    }
+    fn is_move_legal(&self, position: &Position, chess_move: &ChessMove) -> bool {
+        position.is_move_legal(chess_move)
+    }
+    fn status(&self, position: &Position) -> GameStatus {
+        GameStatus::of(position)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::DarkChessRules;
+    use crate::DrawReason;
+    use crate::HordeRules;
+    use crate::RacingKingsRules;
+
+    use super::*;
+
+    fn all_rules() -> Vec<Box<dyn RuleSet>> {
+        vec![
+            Box::new(StandardRules),
+            Box::new(HordeRules),
+            Box::new(RacingKingsRules),
+            Box::new(DarkChessRules),
+        ]
+    }
+
+    #[test]
+    fn every_variant_starts_with_moves_to_play() {
+        for rules in all_rules() {
+            let start = rules.starting_position();
+            assert_eq!(rules.result(&start), None, "{}", rules.name());
+            let moves = rules.legal_moves(&start);
+            assert!(!moves.is_empty(), "{}", rules.name());
+            assert!(rules.is_move_legal(&start, &moves[0]));
+            assert_eq!(rules.read_fen(&rules.write_fen(&start)), Ok(start));
+            assert!(rules.read_fen("9/8/8/8/8/8/8/8 w - - 0 1").is_err());
+        }
+    }
+
+    #[test]
+    fn variants_decide_their_own_results() {
+        // the black king has been taken, which only dark chess allows
+        let position = Position::from_fen("8/8/8/8/8/8/8/4K3 b - - 0 1");
+        assert_eq!(
+            DarkChessRules.result(&position),
+            Some(GameResult::WhiteWin(WinReason::Variant))
+        );
+        let stalemate = Position::from_fen("7k/5Q2/6K1/8/8/8/8/8 b - - 0 1");
+        assert_eq!(
+            StandardRules.result(&stalemate),
+            Some(GameResult::Draw(DrawReason::Stalemate))
+        );
+        let raced_home = Position::from_fen("6k1/8/8/8/8/8/8/K7 w - - 0 1");
+        assert_eq!(
+            RacingKingsRules.result(&raced_home),
+            Some(GameResult::win_for(PieceColor::Black, WinReason::Variant))
+        );
+        assert_eq!(
+            StandardRules.result(&raced_home),
+            Some(GameResult::Draw(DrawReason::InsufficientMaterial))
+        );
+    }
+}
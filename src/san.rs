@@ -0,0 +1,366 @@
+use crate::ChessMove;
+use crate::Coords;
+use crate::PieceKind;
+use crate::Position;
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum SanError {
+    Malformed(String),
+    Illegal(String),
+    Ambiguous(String),
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum SanLanguage {
+    #[default]
+    English,
+    German,
+    French,
+    Spanish,
+    Italian,
+    Dutch,
+}
+
+// How pieces are written in SAN: as letters in some language, or as the
+// unicode figurines used by figurine algebraic notation.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum SanNotation {
+    Letters(SanLanguage),
+    Figurine,
+}
+
+impl Default for SanNotation {
+    fn default() -> SanNotation {
+        SanNotation::Letters(SanLanguage::English)
+    }
+}
+
+const PIECE_KINDS: [PieceKind; 5] = [
+    PieceKind::King,
+    PieceKind::Queen,
+    PieceKind::Rook,
+    PieceKind::Bishop,
+    PieceKind::Knight,
+];
+
+// symbols for king, queen, rook, bishop and knight
+fn piece_symbols(notation: &SanNotation) -> [&'static str; 5] {
+    match notation {
+        SanNotation::Letters(SanLanguage::English) => ["K", "Q", "R", "B", "N"],
+        SanNotation::Letters(SanLanguage::German) => ["K", "D", "T", "L", "S"],
+        SanNotation::Letters(SanLanguage::French) => ["R", "D", "T", "F", "C"],
+        SanNotation::Letters(SanLanguage::Spanish) => ["R", "D", "T", "A", "C"],
+        SanNotation::Letters(SanLanguage::Italian) => ["R", "D", "T", "A", "C"],
+        SanNotation::Letters(SanLanguage::Dutch) => ["K", "D", "T", "L", "P"],
+        SanNotation::Figurine => ["\u{2654}", "\u{2655}", "\u{2656}", "\u{2657}", "\u{2658}"],
+    }
+}
+
+pub(crate) fn piece_letter(kind: &PieceKind, notation: &SanNotation) -> &'static str {
+    PIECE_KINDS
+        .iter()
+        .position(|candidate| candidate == kind)
+        .map_or("", |index| piece_symbols(notation)[index])
+}
+
+pub(crate) fn kind_from_letter(letter: char, notation: &SanNotation) -> Option<PieceKind> {
+    piece_symbols(notation)
+        .iter()
+        .position(|symbol| symbol.starts_with(letter))
+        .map(|index| PIECE_KINDS[index])
+}
+
+pub(crate) fn check_suffix(chess_move: &ChessMove, position: &Position) -> &'static str {
+    let after_move = position.after_move(chess_move);
+    if after_move.is_checkmate() {
+        "#"
+    } else if after_move.is_in_check(&after_move.to_move) {
+        "+"
+    } else {
+        ""
+    }
+}
+
+fn promotion_target(chess_move: &ChessMove) -> Option<PieceKind> {
+    match chess_move {
+        ChessMove::Promotion(_, kind) => Some(*kind),
+        _ => None,
+    }
+}
+
+impl ChessMove {
+    pub fn to_san(&self, position: &Position) -> String {
+        self.to_san_with(position, SanNotation::default())
+    }
+
+    pub fn to_san_with(&self, position: &Position, notation: SanNotation) -> String {
+        let mut san = match self {
+            ChessMove::CastleRight => String::from("O-O"),
+            ChessMove::CastleLeft => String::from("O-O-O"),
+            _ => self.san_body(position, &notation),
+        };
+        san.push_str(check_suffix(self, position));
+        san
+    }
+
+    fn san_body(&self, position: &Position, notation: &SanNotation) -> String {
+        let movement = self
+            .movement()
+            .expect("castling is handled before building the SAN body");
+        let kind = position
+            .piece_at(&movement.origin)
+            .map_or(PieceKind::Pawn, |piece| piece.kind);
+        let is_capture = position.is_capture(self);
+        let origin_algebraic = movement.origin.to_algebraic();
+
+        let mut san = String::from(piece_letter(&kind, notation));
+        if kind == PieceKind::Pawn {
+            if is_capture {
+                san.push_str(&origin_algebraic[..1]);
+            }
+        } else {
+            let rivals: Vec<Coords> = position
+                .legal_moves_of_kind(kind)
+                .iter()
+                .filter_map(|other| other.movement())
+                .filter(|other| {
+                    other.destination == movement.destination && other.origin != movement.origin
+                })
+                .map(|other| other.origin)
+                .collect();
+            if !rivals.is_empty() {
+                if rivals.iter().all(|rival| rival.x != movement.origin.x) {
+                    san.push_str(&origin_algebraic[..1]);
+                } else if rivals.iter().all(|rival| rival.y != movement.origin.y) {
+                    san.push_str(&origin_algebraic[1..]);
+                } else {
+                    san.push_str(&origin_algebraic);
+                }
+            }
+        }
+        if is_capture {
+            san.push('x');
+        }
+        san.push_str(&movement.destination.to_algebraic());
+        if let Some(promoted_to) = promotion_target(self) {
+            san.push('=');
+            san.push_str(piece_letter(&promoted_to, notation));
+        }
+        san
+    }
+
+    pub fn from_san(san: &str, position: &Position) -> Result<ChessMove, SanError> {
+        ChessMove::from_san_with(san, position, SanNotation::default())
+    }
+
+    pub fn from_san_with(
+        san: &str,
+        position: &Position,
+        notation: SanNotation,
+    ) -> Result<ChessMove, SanError> {
+        let trimmed = san.trim_end_matches(['+', '#', '!', '?']);
+
+        let castle = match trimmed {
+            "O-O" | "0-0" => Some(ChessMove::CastleRight),
+            "O-O-O" | "0-0-0" => Some(ChessMove::CastleLeft),
+            _ => None,
+        };
+        if let Some(castle) = castle {
+            return if position.is_move_legal(&castle) {
+                Ok(castle)
+            } else {
+                Err(SanError::Illegal(san.to_string()))
+            };
+        }
+
+        let malformed = || SanError::Malformed(san.to_string());
+        let mut characters: Vec<char> = trimmed.chars().filter(|c| *c != 'x').collect();
+
+        let kind = match characters
+            .first()
+            .and_then(|letter| kind_from_letter(*letter, &notation))
+        {
+            Some(kind) => {
+                characters.remove(0);
+                kind
+            }
+            None => PieceKind::Pawn,
+        };
+
+        let promotion = match characters
+            .last()
+            .and_then(|letter| kind_from_letter(*letter, &notation))
+        {
+            Some(target) if kind == PieceKind::Pawn => {
+                characters.pop();
+                if characters.last() == Some(&'=') {
+                    characters.pop();
+                }
+                Some(target)
+            }
+            _ => None,
+        };
+
+        if characters.len() < 2 || characters.len() > 4 {
+            return Err(malformed());
+        }
+        let destination: String = characters[characters.len() - 2..].iter().collect();
+        if !is_algebraic_square(&destination) {
+            return Err(malformed());
+        }
+        let destination = Coords::from_algebraic(&destination);
+
+        let mut from_file = None;
+        let mut from_rank = None;
+        for character in &characters[..characters.len() - 2] {
+            match character {
+                'a'..='h' => from_file = Some(*character as isize - 'a' as isize),
+                '1'..='8' => from_rank = Some(8 - (*character as isize - '0' as isize)),
+                _ => return Err(malformed()),
+            }
+        }
+
+        let legal_moves = position.legal_moves_of_kind(kind);
+        let candidates: Vec<&ChessMove> = legal_moves
+            .iter()
+            .filter(|candidate| {
+                candidate.movement().is_some_and(|movement| {
+                    movement.destination == destination
+                        && from_file.is_none_or(|x| movement.origin.x == x)
+                        && from_rank.is_none_or(|y| movement.origin.y == y)
+                }) && promotion_target(candidate) == promotion
+            })
+            .collect();
+
+        match candidates.len() {
+            0 => Err(SanError::Illegal(san.to_string())),
+            1 => Ok(candidates[0].clone()),
+            _ => Err(SanError::Ambiguous(san.to_string())),
+        }
+    }
+}
+
+pub fn is_algebraic_square(square: &str) -> bool {
+    let mut characters = square.chars();
+    matches!(
+        (characters.next(), characters.next(), characters.next()),
+        (Some('a'..='h'), Some('1'..='8'), None)
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::Move;
+
+    use super::*;
+
+    #[test]
+    fn pawn_push_and_knight_move() {
+        let position = Position::initial();
+        assert_eq!(
+            ChessMove::from_san("e4", &position)
+                .unwrap()
+                .to_san(&position),
+            "e4"
+        );
+        assert_eq!(
+            ChessMove::from_san("Nf3", &position).unwrap(),
+            ChessMove::RegularMove(Move {
+                origin: Coords::from_algebraic("g1"),
+                destination: Coords::from_algebraic("f3")
+            })
+        );
+    }
+
+    #[test]
+    fn disambiguates_by_file_then_rank() {
+        let position = Position::from_fen("k7/8/8/8/8/8/4K3/R6R w - - 0 1");
+        let rook_to_d1 = ChessMove::from_san("Rad1", &position).unwrap();
+        assert_eq!(rook_to_d1.to_san(&position), "Rad1");
+
+        let position = Position::from_fen("7k/8/8/8/R7/8/8/R3K3 w - - 0 1");
+        assert_eq!(
+            ChessMove::from_san("R1a2", &position)
+                .unwrap()
+                .to_san(&position),
+            "R1a2"
+        );
+        assert_eq!(
+            ChessMove::from_san("Ra2", &position),
+            Err(SanError::Ambiguous(String::from("Ra2")))
+        );
+    }
+
+    #[test]
+    fn disambiguates_by_square_when_file_and_rank_are_shared() {
+        // queens on a1, a3 and c1 all reach b2
+        let position = Position::from_fen("6k1/8/8/8/8/Q7/8/Q1Q1K3 w - - 0 1");
+        let from_a1 = ChessMove::RegularMove(Move {
+            origin: Coords::from_algebraic("a1"),
+            destination: Coords::from_algebraic("b2"),
+        });
+        assert_eq!(from_a1.to_san(&position), "Qa1b2");
+        assert_eq!(ChessMove::from_san("Qa1b2", &position), Ok(from_a1));
+    }
+
+    #[test]
+    fn castles_captures_and_checks() {
+        let position = Position::from_fen("4k3/8/8/3p4/4P3/8/8/4K2R w K - 0 1");
+        assert_eq!(ChessMove::CastleRight.to_san(&position), "O-O");
+        assert_eq!(
+            ChessMove::from_san("exd5", &position)
+                .unwrap()
+                .to_san(&position),
+            "exd5"
+        );
+        assert_eq!(
+            ChessMove::from_san("Rh8", &position)
+                .unwrap()
+                .to_san(&position),
+            "Rh8+"
+        );
+    }
+
+    #[test]
+    fn promotion_with_mate() {
+        let position = Position::from_fen("k7/7P/1K6/8/8/8/8/8 w - - 0 1");
+        let promotion = ChessMove::from_san("h8=Q", &position).unwrap();
+        assert_eq!(promotion.to_san(&position), "h8=Q#");
+        assert_eq!(ChessMove::from_san("h8Q#", &position), Ok(promotion));
+    }
+
+    #[test]
+    fn rejects_illegal_and_malformed() {
+        let position = Position::initial();
+        assert_eq!(
+            ChessMove::from_san("e5", &position),
+            Err(SanError::Illegal(String::from("e5")))
+        );
+        assert_eq!(
+            ChessMove::from_san("Zz9", &position),
+            Err(SanError::Malformed(String::from("Zz9")))
+        );
+    }
+
+    #[test]
+    fn localized_and_figurine_notation() {
+        let position = Position::from_fen("4k3/1P6/8/8/8/8/8/4K1N1 w - - 0 1");
+        let knight_move = ChessMove::from_san("Nf3", &position).unwrap();
+        let german = SanNotation::Letters(SanLanguage::German);
+        assert_eq!(knight_move.to_san_with(&position, german), "Sf3");
+        assert_eq!(
+            knight_move.to_san_with(&position, SanNotation::Letters(SanLanguage::French)),
+            "Cf3"
+        );
+        assert_eq!(
+            knight_move.to_san_with(&position, SanNotation::Figurine),
+            "\u{2658}f3"
+        );
+        let promotion = ChessMove::from_san_with("b8=D+", &position, german).unwrap();
+        assert_eq!(promotion.to_san(&position), "b8=Q+");
+        assert_eq!(
+            ChessMove::from_san_with("\u{2658}f3", &position, SanNotation::Figurine),
+            Ok(knight_move)
+        );
+    }
+}
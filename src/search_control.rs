@@ -0,0 +1,275 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+
+use crate::ClockState;
+use crate::PieceColor;
+use crate::SearchLimits;
+use crate::TimeBudget;
+use crate::TimeManager;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+struct Deadlines {
+    soft: Instant,
+    hard: Instant,
+}
+
+// Shared between the search and whoever controls it, so that a running
+// search can be handed a deadline it didn't have when it started.
+#[derive(Clone, Debug, Default)]
+pub struct SearchDeadline(Arc<Mutex<Option<Deadlines>>>);
+
+impl SearchDeadline {
+    pub fn new() -> SearchDeadline {
+        SearchDeadline::default()
+    }
+
+    // No new iteration is started past `started + budget.optimum`, and the
+    // search is aborted at `started + budget.maximum`.
+    pub fn set(&self, started: Instant, budget: &TimeBudget) {
+        *self.0.lock().expect("deadline lock is never poisoned") = Some(Deadlines {
+            soft: started + budget.optimum,
+            hard: started + budget.maximum,
+        });
+    }
+
+    pub fn clear(&self) {
+        *self.0.lock().expect("deadline lock is never poisoned") = None;
+    }
+
+    pub fn soft_deadline(&self) -> Option<Instant> {
+        self.deadlines().map(|deadlines| deadlines.soft)
+    }
+
+    pub fn hard_deadline(&self) -> Option<Instant> {
+        self.deadlines().map(|deadlines| deadlines.hard)
+    }
+
+    pub(crate) fn soft_passed(&self) -> bool {
+        self.soft_deadline()
+            .is_some_and(|deadline| Instant::now() >= deadline)
+    }
+
+    pub(crate) fn hard_passed(&self) -> bool {
+        self.hard_deadline()
+            .is_some_and(|deadline| Instant::now() >= deadline)
+    }
+
+    fn deadlines(&self) -> Option<Deadlines> {
+        *self.0.lock().expect("deadline lock is never poisoned")
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum EngineState {
+    Idle,
+    Searching,
+    Pondering,
+    // the ponder search ran out of depth before the opponent moved, its
+    // result is held back until ponderhit or stop
+    PonderFinished,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum ControlError {
+    AlreadySearching,
+    NotPondering,
+}
+
+// Tracks go / go ponder / ponderhit / stop for one engine. Every search is
+// started through this so that it shares the stop flag and the deadline.
+#[derive(Debug)]
+pub struct SearchControl {
+    time_manager: TimeManager,
+    state: EngineState,
+    stop: Arc<AtomicBool>,
+    deadline: SearchDeadline,
+    ponder_clock: Option<(ClockState, PieceColor)>,
+}
+
+impl Default for SearchControl {
+    fn default() -> SearchControl {
+        SearchControl::new(TimeManager::default())
+    }
+}
+
+impl SearchControl {
+    pub fn new(time_manager: TimeManager) -> SearchControl {
+        SearchControl {
+            time_manager,
+            state: EngineState::Idle,
+            stop: Arc::new(AtomicBool::new(false)),
+            deadline: SearchDeadline::new(),
+            ponder_clock: None,
+        }
+    }
+
+    pub fn state(&self) -> EngineState {
+        self.state
+    }
+
+    // A search on our own clock, which starts running now.
+    pub fn go(
+        &mut self,
+        depth: usize,
+        clock: &ClockState,
+        color: &PieceColor,
+    ) -> Result<SearchLimits, ControlError> {
+        let limits = self.start(depth, EngineState::Searching)?;
+        self.deadline
+            .set(Instant::now(), &self.time_manager.allocate(clock, color));
+        Ok(limits)
+    }
+
+    // A search with no deadline, only ended by `stop` or by reaching `depth`.
+    pub fn go_infinite(&mut self, depth: usize) -> Result<SearchLimits, ControlError> {
+        self.start(depth, EngineState::Searching)
+    }
+
+    // Searches the position after the predicted move while the opponent
+    // thinks. `clock` is our clock as it will be if the prediction is right.
+    pub fn ponder(
+        &mut self,
+        depth: usize,
+        clock: &ClockState,
+        color: &PieceColor,
+    ) -> Result<SearchLimits, ControlError> {
+        let limits = self.start(depth, EngineState::Pondering)?;
+        self.ponder_clock = Some((*clock, *color));
+        Ok(limits)
+    }
+
+    // The opponent played the predicted move: the ponder search carries on
+    // as a normal timed search. Our clock only started running now, so the
+    // time spent pondering is not taken out of the budget.
+    pub fn ponderhit(&mut self) -> Result<TimeBudget, ControlError> {
+        let (clock, color) = match self.state {
+            EngineState::Pondering | EngineState::PonderFinished => self
+                .ponder_clock
+                .take()
+                .expect("ponder clock is set while pondering"),
+            _ => return Err(ControlError::NotPondering),
+        };
+        let budget = self.time_manager.allocate(&clock, &color);
+        self.deadline.set(Instant::now(), &budget);
+        self.state = match self.state {
+            EngineState::PonderFinished => EngineState::Idle,
+            _ => EngineState::Searching,
+        };
+        Ok(budget)
+    }
+
+    // Ends whatever is running. Returns whether a best move is now owed,
+    // which is the case unless the engine was already idle.
+    pub fn stop(&mut self) -> bool {
+        self.stop.store(true, Ordering::Relaxed);
+        self.ponder_clock = None;
+        let was_busy = self.state != EngineState::Idle;
+        self.state = EngineState::Idle;
+        was_busy
+    }
+
+    // Called by the search once it returns. Returns whether its best move
+    // may be reported now; a finished ponder search has to wait.
+    pub fn search_finished(&mut self) -> bool {
+        match self.state {
+            EngineState::Searching => {
+                self.state = EngineState::Idle;
+                true
+            }
+            EngineState::Pondering => {
+                self.state = EngineState::PonderFinished;
+                false
+            }
+            EngineState::PonderFinished | EngineState::Idle => false,
+        }
+    }
+
+    fn start(&mut self, depth: usize, state: EngineState) -> Result<SearchLimits, ControlError> {
+        if self.state != EngineState::Idle {
+            return Err(ControlError::AlreadySearching);
+        }
+        self.state = state;
+        // a fresh flag, so that a late stop can't reach the new search
+        self.stop = Arc::new(AtomicBool::new(false));
+        self.deadline = SearchDeadline::new();
+        Ok(SearchLimits::depth(depth)
+            .stop_flag(self.stop.clone())
+            .deadline(self.deadline.clone()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::thread;
+    use std::time::Duration;
+
+    use crate::{Planner, Position};
+
+    use super::*;
+
+    fn clock() -> ClockState {
+        ClockState {
+            white_remaining: Duration::from_millis(2_030),
+            black_remaining: Duration::from_millis(2_030),
+            ..ClockState::default()
+        }
+    }
+
+    #[test]
+    fn pondering_waits_for_ponderhit_before_reporting() {
+        let mut control = SearchControl::default();
+        control.ponder(1, &clock(), &PieceColor::White).unwrap();
+        assert_eq!(control.state(), EngineState::Pondering);
+        assert!(!control.search_finished());
+        assert_eq!(control.state(), EngineState::PonderFinished);
+        control.ponderhit().unwrap();
+        assert_eq!(control.state(), EngineState::Idle);
+        assert_eq!(control.ponderhit(), Err(ControlError::NotPondering));
+    }
+
+    #[test]
+    fn ponderhit_budget_starts_at_ponderhit() {
+        let mut control = SearchControl::default();
+        let limits = control.ponder(50, &clock(), &PieceColor::White).unwrap();
+        let deadline = limits.deadline.clone().unwrap();
+        assert_eq!(deadline.hard_deadline(), None);
+        thread::sleep(Duration::from_millis(20));
+        let hit = Instant::now();
+        let budget = control.ponderhit().unwrap();
+        assert_eq!(control.state(), EngineState::Searching);
+        assert_eq!(
+            budget,
+            TimeManager::default().allocate(&clock(), &PieceColor::White)
+        );
+        assert!(deadline.hard_deadline().unwrap() >= hit + budget.maximum);
+    }
+
+    #[test]
+    fn ponder_search_turns_into_timed_search() {
+        let mut control = SearchControl::default();
+        let limits = control.ponder(50, &clock(), &PieceColor::White).unwrap();
+        let search =
+            thread::spawn(move || Planner::new().search_with_limits(&Position::initial(), &limits));
+        thread::sleep(Duration::from_millis(10));
+        let budget = control.ponderhit().unwrap();
+        let result = search.join().unwrap();
+        assert!(result.depth < 50);
+        assert!(control.search_finished());
+        assert!(budget.maximum < Duration::from_secs(1));
+    }
+
+    #[test]
+    fn stop_ends_any_search() {
+        let mut control = SearchControl::default();
+        let limits = control.go_infinite(50).unwrap();
+        assert_eq!(
+            control.go_infinite(1).err(),
+            Some(ControlError::AlreadySearching)
+        );
+        assert!(control.stop());
+        assert!(limits.stop.unwrap().load(Ordering::Relaxed));
+        assert!(!control.stop());
+        assert!(control.go(1, &clock(), &PieceColor::Black).is_ok());
+    }
+}
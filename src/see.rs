@@ -0,0 +1,125 @@
+use crate::Bitboard;
+use crate::ChessMove;
+use crate::PieceKind;
+use crate::Position;
+
+// The kings can't be taken, but a value this large keeps them the last
+// piece either side recaptures with.
+pub fn exchange_value(kind: &PieceKind) -> isize {
+    match kind {
+        PieceKind::Pawn => 100,
+        PieceKind::Knight | PieceKind::Bishop => 300,
+        PieceKind::Rook => 500,
+        PieceKind::Queen => 900,
+        PieceKind::King => 20_000,
+    }
+}
+
+// Cheapest first, the order pieces join an exchange in.
+const EXCHANGE_ORDER: [PieceKind; 6] = [
+    PieceKind::Pawn,
+    PieceKind::Knight,
+    PieceKind::Bishop,
+    PieceKind::Rook,
+    PieceKind::Queen,
+    PieceKind::King,
+];
+
+// The material the side to move comes out of `chess_move` with, once both
+// sides have made every capture on the destination square that pays for
+// them, always taking with their cheapest piece. Quiet moves count too, so
+// a piece moved where it can be taken for nothing scores minus its value.
+// Pins are ignored, and castling is always worth nothing.
+pub fn static_exchange(position: &Position, chess_move: &ChessMove) -> isize {
+    let Some(movement) = chess_move.movement() else {
+        return 0;
+    };
+    let captured = position.captured_piece(chess_move).map(|piece| piece.kind);
+    let Some(mover) = position.piece_at(&movement.origin) else {
+        return 0;
+    };
+    let bitboards = position.bitboards();
+    let target = movement.destination;
+    let mut occupancy = bitboards.occupancy() ^ Bitboard::from_square(&movement.origin);
+    if let ChessMove::EnPassant(_, taken) = chess_move {
+        occupancy ^= Bitboard::from_square(taken);
+    }
+    let mut on_target = mover.kind;
+    let mut gains = vec![captured.as_ref().map_or(0, exchange_value)];
+    if let ChessMove::Promotion(_, kind) = chess_move {
+        gains[0] += exchange_value(kind) - exchange_value(&PieceKind::Pawn);
+        on_target = *kind;
+    }
+    let mut side = mover.color.opposite();
+    loop {
+        let attackers = bitboards.attackers(&target, &side, occupancy) & occupancy;
+        let Some((attacker, kind)) = cheapest(position, attackers) else {
+            break;
+        };
+        // a king only takes when nothing can take it back
+        if kind == PieceKind::King
+            && !(bitboards.attackers(&target, &side.opposite(), occupancy) & occupancy).is_empty()
+        {
+            break;
+        }
+        let previous = gains[gains.len() - 1];
+        gains.push(exchange_value(&on_target) - previous);
+        on_target = kind;
+        occupancy ^= attacker;
+        side = side.opposite();
+    }
+    // each side stops capturing once going on would lose it material
+    while gains.len() > 1 {
+        let last = gains.pop().expect("there is more than one gain");
+        let previous = gains.len() - 1;
+        gains[previous] = -(-gains[previous]).max(last);
+    }
+    gains[0]
+}
+
+fn cheapest(position: &Position, attackers: Bitboard) -> Option<(Bitboard, PieceKind)> {
+    let bitboards = position.bitboards();
+    EXCHANGE_ORDER.iter().find_map(|kind| {
+        let of_kind = attackers & bitboards.kind(kind);
+        of_kind
+            .squares()
+            .next()
+            .map(|square| (Bitboard::from_square(&square), *kind))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn see(fen: &str, uci_long: &str) -> isize {
+        let position = Position::from_fen(fen);
+        static_exchange(&position, &ChessMove::from_uci_long(uci_long, &position))
+    }
+
+    #[test]
+    fn counts_the_recaptures() {
+        // an undefended pawn
+        assert_eq!(
+            see("1k1r4/1pp4p/p7/4p3/8/P5P1/1PP4P/2K1R3 w - - 0 1", "e1e5"),
+            100
+        );
+        // a defended pawn taken by a knight, the rooks behind joining in
+        assert_eq!(
+            see(
+                "1k1r3q/1ppn3p/p4b2/4p3/8/P2N2P1/1PP1R1BP/2K1Q3 w - - 0 1",
+                "d3e5"
+            ),
+            -200
+        );
+        // a queen left where a pawn takes it
+        assert_eq!(see("4k3/8/3p4/8/4Q3/8/8/4K3 w - - 0 1", "e4e5"), -900);
+        assert_eq!(see("4k3/8/8/8/8/8/8/R3K2R w KQ - 0 1", "e1g1"), 0);
+    }
+
+    #[test]
+    fn kings_only_take_what_is_left_undefended() {
+        assert_eq!(see("4k3/3p4/8/8/8/8/3Q4/4K3 w - - 0 1", "d2d7"), -800);
+        assert_eq!(see("4k3/3p4/8/1B6/8/8/3Q4/4K3 w - - 0 1", "d2d7"), 100);
+    }
+}
@@ -0,0 +1,344 @@
+use std::sync::atomic::AtomicUsize;
+use std::sync::atomic::Ordering;
+use std::sync::Mutex;
+use std::thread;
+
+use crate::play_game;
+use crate::AdjudicationPolicy;
+use crate::ChessMove;
+use crate::ClockState;
+use crate::EngineGame;
+use crate::EngineOption;
+use crate::PieceColor;
+use crate::Player;
+use crate::Position;
+use crate::StandardRules;
+
+// What a player says about itself: its name and the settings it declares.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct PlayerConfig {
+    pub name: String,
+    pub options: Vec<EngineOption>,
+}
+
+impl PlayerConfig {
+    pub fn of(player: &dyn Player) -> PlayerConfig {
+        PlayerConfig {
+            name: player.to_string(),
+            options: player.options(),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum ReplayError {
+    // the player built for that side isn't the one that played the game
+    PlayerChanged(PieceColor),
+    // the first ply the two games differ at, None where one of them had
+    // already ended
+    Diverged {
+        ply: usize,
+        recorded: Option<ChessMove>,
+        replayed: Option<ChessMove>,
+    },
+}
+
+// One game of a self-play run, with everything it was played from. The
+// seed is the one both players were built with.
+pub struct SelfPlayGame {
+    pub index: usize,
+    pub seed: u64,
+    pub opening: Position,
+    pub white: PlayerConfig,
+    pub black: PlayerConfig,
+    // the clock at the start, None when the game was played without one
+    pub clock: Option<ClockState>,
+    pub adjudication: AdjudicationPolicy,
+    pub game: EngineGame,
+}
+
+impl SelfPlayGame {
+    fn play(
+        index: usize,
+        seed: u64,
+        opening: Position,
+        white: &dyn Player,
+        black: &dyn Player,
+        clock: Option<ClockState>,
+        adjudication: AdjudicationPolicy,
+    ) -> SelfPlayGame {
+        let game = play_game(
+            white,
+            black,
+            &StandardRules,
+            opening.clone(),
+            clock,
+            &adjudication,
+        );
+        SelfPlayGame {
+            index,
+            seed,
+            opening,
+            white: PlayerConfig::of(white),
+            black: PlayerConfig::of(black),
+            clock,
+            adjudication,
+            game,
+        }
+    }
+
+    pub fn moves(&self) -> Vec<ChessMove> {
+        match self.game.moves.mainline().last() {
+            Some(last) => self.game.moves.moves_to(*last),
+            None => Vec::new(),
+        }
+    }
+
+    // Plays the game again with players built from its seed by the same
+    // factories it was played with, and checks every move comes out the
+    // same. Players searching to a depth or node count replay exactly;
+    // those thinking on the clock can only be expected to when they had
+    // time to spare.
+    pub fn replay(
+        &self,
+        white: impl Fn(u64) -> Box<dyn Player>,
+        black: impl Fn(u64) -> Box<dyn Player>,
+    ) -> Result<SelfPlayGame, ReplayError> {
+        let (white, black) = (white(self.seed), black(self.seed));
+        if PlayerConfig::of(white.as_ref()) != self.white {
+            return Err(ReplayError::PlayerChanged(PieceColor::White));
+        }
+        if PlayerConfig::of(black.as_ref()) != self.black {
+            return Err(ReplayError::PlayerChanged(PieceColor::Black));
+        }
+        let replayed = SelfPlayGame::play(
+            self.index,
+            self.seed,
+            self.opening.clone(),
+            white.as_ref(),
+            black.as_ref(),
+            self.clock,
+            self.adjudication,
+        );
+        let (recorded_moves, replayed_moves) = (self.moves(), replayed.moves());
+        for ply in 0..recorded_moves.len().max(replayed_moves.len()) {
+            let (recorded, replayed) = (recorded_moves.get(ply), replayed_moves.get(ply));
+            if recorded != replayed {
+                return Err(ReplayError::Diverged {
+                    ply,
+                    recorded: recorded.cloned(),
+                    replayed: replayed.cloned(),
+                });
+            }
+        }
+        Ok(replayed)
+    }
+}
+
+// Plays many games between two kinds of player at once, one game per thread
+// at a time. Players are built afresh for every game from the game's seed,
+// inside the thread that plays it, so they don't need to be `Send` and
+// seeded players vary from game to game. Games go through `openings` in
+// turn, from the usual start when there are none. A game's seed and opening
+// only depend on its index, so any of them can be replayed on its own.
+pub struct SelfPlay {
+    games: usize,
+    threads: usize,
+    seed: u64,
+    openings: Vec<Position>,
+    clock: Option<ClockState>,
+    adjudication: AdjudicationPolicy,
+}
+
+impl SelfPlay {
+    pub fn new(games: usize) -> SelfPlay {
+        SelfPlay {
+            games,
+            threads: thread::available_parallelism().map_or(1, |threads| threads.get()),
+            seed: 0,
+            openings: vec![Position::initial()],
+            clock: None,
+            adjudication: AdjudicationPolicy::default(),
+        }
+    }
+
+    // As many as the machine runs at once by default.
+    pub fn threads(mut self, threads: usize) -> SelfPlay {
+        self.threads = threads.max(1);
+        self
+    }
+
+    // Each game's seed is worked out from this one and the game's index.
+    pub fn seed(mut self, seed: u64) -> SelfPlay {
+        self.seed = seed;
+        self
+    }
+
+    pub fn openings(mut self, openings: Vec<Position>) -> SelfPlay {
+        if !openings.is_empty() {
+            self.openings = openings;
+        }
+        self
+    }
+
+    pub fn clock(mut self, clock: ClockState) -> SelfPlay {
+        self.clock = Some(clock);
+        self
+    }
+
+    pub fn adjudication(mut self, policy: AdjudicationPolicy) -> SelfPlay {
+        self.adjudication = policy;
+        self
+    }
+
+    // splitmix64 over the run's seed, so that neighbouring games get
+    // unrelated seeds
+    pub fn game_seed(&self, index: usize) -> u64 {
+        let mut z = self
+            .seed
+            .wrapping_add((index as u64 + 1).wrapping_mul(0x9e37_79b9_7f4a_7c15));
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    pub fn opening(&self, index: usize) -> &Position {
+        &self.openings[index % self.openings.len()]
+    }
+
+    // The games in order, whichever thread finished them first.
+    pub fn run(
+        &self,
+        white: impl Fn(u64) -> Box<dyn Player> + Sync,
+        black: impl Fn(u64) -> Box<dyn Player> + Sync,
+    ) -> Vec<SelfPlayGame> {
+        let next = AtomicUsize::new(0);
+        let finished = Mutex::new(Vec::with_capacity(self.games));
+        thread::scope(|scope| {
+            for _ in 0..self.threads.min(self.games) {
+                scope.spawn(|| loop {
+                    let index = next.fetch_add(1, Ordering::Relaxed);
+                    if index >= self.games {
+                        break;
+                    }
+                    let seed = self.game_seed(index);
+                    let played = SelfPlayGame::play(
+                        index,
+                        seed,
+                        self.opening(index).clone(),
+                        white(seed).as_ref(),
+                        black(seed).as_ref(),
+                        self.clock,
+                        self.adjudication,
+                    );
+                    finished
+                        .lock()
+                        .unwrap_or_else(|poisoned| poisoned.into_inner())
+                        .push(played);
+                });
+            }
+        });
+        let mut games = finished
+            .into_inner()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        games.sort_by_key(|game| game.index);
+        games
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::FirstMovePlayer;
+    use crate::GreedyMaterialPlayer;
+
+    use super::*;
+
+    #[test]
+    fn games_come_back_in_order_from_every_opening() {
+        let openings = vec![
+            Position::initial(),
+            Position::from_fen("rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq - 0 1"),
+        ];
+        let policy = AdjudicationPolicy {
+            max_plies: Some(20),
+            ..AdjudicationPolicy::default()
+        };
+        let self_play = SelfPlay::new(5)
+            .threads(3)
+            .seed(7)
+            .openings(openings.clone())
+            .adjudication(policy);
+        let seeds = Mutex::new(Vec::new());
+        let games = self_play.run(
+            |seed| {
+                seeds.lock().unwrap().push(seed);
+                Box::new(GreedyMaterialPlayer)
+            },
+            |_| Box::new(FirstMovePlayer),
+        );
+        assert_eq!(
+            games.iter().map(|game| game.index).collect::<Vec<_>>(),
+            (0..5).collect::<Vec<_>>()
+        );
+        let mut seeds = seeds.into_inner().unwrap();
+        seeds.sort();
+        seeds.dedup();
+        assert_eq!(seeds.len(), 5);
+        for played in &games {
+            assert_eq!(played.seed, self_play.game_seed(played.index));
+            assert_eq!(played.opening, openings[played.index % 2]);
+            let alone = play_game(
+                &GreedyMaterialPlayer,
+                &FirstMovePlayer,
+                &StandardRules,
+                played.opening.clone(),
+                None,
+                &policy,
+            );
+            assert_eq!(played.game.to_pgn(), alone.to_pgn());
+            assert!(played
+                .replay(
+                    |_| Box::new(GreedyMaterialPlayer),
+                    |_| Box::new(FirstMovePlayer)
+                )
+                .is_ok());
+        }
+    }
+
+    #[cfg(feature = "rng")]
+    #[test]
+    fn seeded_games_replay_move_for_move() {
+        use crate::RandomCapturePrioPlayer;
+        use crate::RandomPlayer;
+
+        let white = |seed| Box::new(RandomPlayer::seeded(seed)) as Box<dyn Player>;
+        let black = |seed| Box::new(RandomCapturePrioPlayer::seeded(seed)) as Box<dyn Player>;
+        let games = SelfPlay::new(4)
+            .threads(2)
+            .seed(11)
+            .adjudication(AdjudicationPolicy {
+                max_plies: Some(40),
+                ..AdjudicationPolicy::default()
+            })
+            .run(white, black);
+        assert_ne!(games[0].moves(), games[1].moves());
+        for played in &games {
+            assert_eq!(played.white, PlayerConfig::of(white(0).as_ref()));
+            let replayed = played.replay(white, black).unwrap();
+            assert_eq!(replayed.game.to_pgn(), played.game.to_pgn());
+        }
+        assert_eq!(
+            games[0].replay(|_| Box::new(FirstMovePlayer), black).err(),
+            Some(ReplayError::PlayerChanged(PieceColor::White))
+        );
+        let reseeded = |seed: u64| white(seed.wrapping_add(1));
+        assert!(matches!(
+            games[0].replay(reseeded, black),
+            Err(ReplayError::Diverged {
+                ply: _,
+                recorded: Some(_),
+                ..
+            })
+        ));
+    }
+}
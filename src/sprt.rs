@@ -0,0 +1,278 @@
+use crate::play_game;
+use crate::AdjudicationPolicy;
+use crate::GameResult;
+use crate::PieceColor;
+use crate::Player;
+use crate::Position;
+use crate::StandardRules;
+
+// Whether a candidate is `elo1` or more stronger than a baseline, rather than
+// `elo0` or less, with `alpha` the chance of accepting it when it isn't and
+// `beta` the chance of rejecting it when it is. Testing stops after
+// `max_games` if neither has been shown by then.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct SprtConfig {
+    pub elo0: f64,
+    pub elo1: f64,
+    pub alpha: f64,
+    pub beta: f64,
+    pub max_games: usize,
+}
+
+impl Default for SprtConfig {
+    fn default() -> SprtConfig {
+        SprtConfig {
+            elo0: 0.0,
+            elo1: 5.0,
+            alpha: 0.05,
+            beta: 0.05,
+            max_games: 20_000,
+        }
+    }
+}
+
+impl SprtConfig {
+    // Below this the baseline is kept.
+    pub fn lower_bound(&self) -> f64 {
+        (self.beta / (1.0 - self.alpha)).ln()
+    }
+
+    // Above this the candidate is accepted.
+    pub fn upper_bound(&self) -> f64 {
+        ((1.0 - self.beta) / self.alpha).ln()
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum SprtVerdict {
+    // the candidate is at least `elo1` stronger
+    Accepted,
+    // the candidate is no more than `elo0` stronger
+    Rejected,
+    Undecided,
+}
+
+// The games so far, from the candidate's side.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Sprt {
+    pub config: SprtConfig,
+    pub wins: usize,
+    pub draws: usize,
+    pub losses: usize,
+}
+
+fn expected_score(elo: f64) -> f64 {
+    1.0 / (1.0 + 10f64.powf(-elo / 400.0))
+}
+
+impl Sprt {
+    pub fn new(config: SprtConfig) -> Sprt {
+        Sprt {
+            config,
+            wins: 0,
+            draws: 0,
+            losses: 0,
+        }
+    }
+
+    pub fn games(&self) -> usize {
+        self.wins + self.draws + self.losses
+    }
+
+    // Games cut off by the move limit count as draws.
+    pub fn record(&mut self, result: &GameResult, candidate_was_white: bool) {
+        match result.winner() {
+            Some(winner) if (winner == PieceColor::White) == candidate_was_white => self.wins += 1,
+            Some(_) => self.losses += 1,
+            None => self.draws += 1,
+        }
+    }
+
+    // The log-likelihood ratio of the two hypotheses, approximated from the
+    // mean and variance of the scores. Zero until the results vary at all.
+    pub fn llr(&self) -> f64 {
+        let games = self.games() as f64;
+        if games == 0.0 {
+            return 0.0;
+        }
+        let (wins, draws) = (self.wins as f64 / games, self.draws as f64 / games);
+        let score = wins + draws / 2.0;
+        let variance = (wins + draws / 4.0 - score * score) / games;
+        if variance <= 0.0 {
+            return 0.0;
+        }
+        let (s0, s1) = (
+            expected_score(self.config.elo0),
+            expected_score(self.config.elo1),
+        );
+        (s1 - s0) * (2.0 * score - s0 - s1) / (2.0 * variance)
+    }
+
+    pub fn verdict(&self) -> SprtVerdict {
+        let llr = self.llr();
+        if llr >= self.config.upper_bound() {
+            SprtVerdict::Accepted
+        } else if llr <= self.config.lower_bound() {
+            SprtVerdict::Rejected
+        } else {
+            SprtVerdict::Undecided
+        }
+    }
+}
+
+// Plays `candidate` against `baseline` until the test reaches a verdict or
+// runs out of games. As in a `Tournament`, every opening is played once with
+// each color before the next one is used, and games start from the initial
+// position when there are none. Deterministic engines play the same game
+// from the same opening, so they need openings for the games to tell them
+// anything.
+pub fn run_sprt(
+    candidate: impl Fn() -> Box<dyn Player>,
+    baseline: impl Fn() -> Box<dyn Player>,
+    openings: &[Position],
+    config: SprtConfig,
+) -> Sprt {
+    let mut sprt = Sprt::new(config);
+    while sprt.games() < config.max_games && sprt.verdict() == SprtVerdict::Undecided {
+        let game = sprt.games();
+        let candidate_is_white = game.is_multiple_of(2);
+        let opening = match openings.len() {
+            0 => Position::initial(),
+            count => openings[(game / 2) % count].clone(),
+        };
+        let (candidate, baseline) = (candidate(), baseline());
+        let (white, black) = if candidate_is_white {
+            (&candidate, &baseline)
+        } else {
+            (&baseline, &candidate)
+        };
+        let result = play_game(
+            white.as_ref(),
+            black.as_ref(),
+            &StandardRules,
+            opening,
+            None,
+            &AdjudicationPolicy::default(),
+        )
+        .result;
+        sprt.record(&result, candidate_is_white);
+    }
+    sprt
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fmt::Display;
+    use std::sync::{Arc, Mutex};
+
+    use crate::{ChessMove, FirstMovePlayer};
+
+    use super::*;
+
+    fn with_results(wins: usize, draws: usize, losses: usize) -> Sprt {
+        Sprt {
+            wins,
+            draws,
+            losses,
+            ..Sprt::new(SprtConfig::default())
+        }
+    }
+
+    #[test]
+    fn decides_once_the_evidence_is_strong_enough() {
+        let config = SprtConfig::default();
+        assert!(config.lower_bound() < 0.0 && config.upper_bound() > 0.0);
+        assert_eq!(with_results(0, 0, 0).verdict(), SprtVerdict::Undecided);
+        assert_eq!(with_results(12, 10, 8).verdict(), SprtVerdict::Undecided);
+        assert_eq!(
+            with_results(1200, 1000, 800).verdict(),
+            SprtVerdict::Accepted
+        );
+        assert_eq!(
+            with_results(800, 1000, 1200).verdict(),
+            SprtVerdict::Rejected
+        );
+        assert!(with_results(510, 0, 490).llr() > with_results(490, 0, 510).llr());
+    }
+
+    #[test]
+    fn stops_after_the_last_game_allowed() {
+        let config = SprtConfig {
+            max_games: 4,
+            ..SprtConfig::default()
+        };
+        let sprt = run_sprt(
+            || Box::new(FirstMovePlayer),
+            || Box::new(FirstMovePlayer),
+            &[],
+            config,
+        );
+        assert_eq!(sprt.games(), 4);
+        assert_eq!(sprt.wins, sprt.losses);
+        assert_eq!(sprt.verdict(), SprtVerdict::Undecided);
+    }
+
+    // Plays as `FirstMovePlayer`, noting the position each game starts from.
+    struct OpeningRecorder {
+        openings: Arc<Mutex<Vec<String>>>,
+        starting: Mutex<bool>,
+    }
+
+    impl Display for OpeningRecorder {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            write!(f, "OpeningRecorder")
+        }
+    }
+
+    impl Player for OpeningRecorder {
+        fn offer_move(&self, position: &Position) -> ChessMove {
+            FirstMovePlayer.offer_move(position)
+        }
+        fn evalutate(&self, _position: &Position) -> isize {
+            0
+        }
+        fn new_game(&self) {
+            *self.starting.lock().unwrap() = true;
+        }
+        fn move_played(&self, position: &Position, _chess_move: &ChessMove) {
+            let mut starting = self.starting.lock().unwrap();
+            if *starting {
+                self.openings.lock().unwrap().push(position.to_fen());
+                *starting = false;
+            }
+        }
+    }
+
+    #[test]
+    fn plays_each_opening_with_both_colors() {
+        let after_e4 = "rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq - 0 1";
+        let openings = Arc::new(Mutex::new(Vec::new()));
+        let config = SprtConfig {
+            max_games: 6,
+            ..SprtConfig::default()
+        };
+        run_sprt(
+            || Box::new(FirstMovePlayer),
+            || {
+                Box::new(OpeningRecorder {
+                    openings: openings.clone(),
+                    starting: Mutex::new(false),
+                })
+            },
+            &[Position::initial(), Position::from_fen(after_e4)],
+            config,
+        );
+        let initial = Position::initial().to_fen();
+        assert_eq!(
+            *openings.lock().unwrap(),
+            [
+                initial.as_str(),
+                initial.as_str(),
+                after_e4,
+                after_e4,
+                initial.as_str(),
+                initial.as_str()
+            ]
+        );
+    }
+}
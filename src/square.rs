@@ -0,0 +1,245 @@
+use crate::Coords;
+
+// A square of the board, named the way it is written down. The squares are
+// numbered like `Coords::to_square_number`, from a8 along each rank to h1,
+// so `Square::A8 as usize` is 0 and `Square::H1 as usize` is 63.
+#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Debug)]
+pub enum Square {
+    A8 = 0,
+    B8,
+    C8,
+    D8,
+    E8,
+    F8,
+    G8,
+    H8,
+    A7,
+    B7,
+    C7,
+    D7,
+    E7,
+    F7,
+    G7,
+    H7,
+    A6,
+    B6,
+    C6,
+    D6,
+    E6,
+    F6,
+    G6,
+    H6,
+    A5,
+    B5,
+    C5,
+    D5,
+    E5,
+    F5,
+    G5,
+    H5,
+    A4,
+    B4,
+    C4,
+    D4,
+    E4,
+    F4,
+    G4,
+    H4,
+    A3,
+    B3,
+    C3,
+    D3,
+    E3,
+    F3,
+    G3,
+    H3,
+    A2,
+    B2,
+    C2,
+    D2,
+    E2,
+    F2,
+    G2,
+    H2,
+    A1,
+    B1,
+    C1,
+    D1,
+    E1,
+    F1,
+    G1,
+    H1,
+}
+
+impl Square {
+    pub const ALL: [Square; 64] = [
+        Square::A8,
+        Square::B8,
+        Square::C8,
+        Square::D8,
+        Square::E8,
+        Square::F8,
+        Square::G8,
+        Square::H8,
+        Square::A7,
+        Square::B7,
+        Square::C7,
+        Square::D7,
+        Square::E7,
+        Square::F7,
+        Square::G7,
+        Square::H7,
+        Square::A6,
+        Square::B6,
+        Square::C6,
+        Square::D6,
+        Square::E6,
+        Square::F6,
+        Square::G6,
+        Square::H6,
+        Square::A5,
+        Square::B5,
+        Square::C5,
+        Square::D5,
+        Square::E5,
+        Square::F5,
+        Square::G5,
+        Square::H5,
+        Square::A4,
+        Square::B4,
+        Square::C4,
+        Square::D4,
+        Square::E4,
+        Square::F4,
+        Square::G4,
+        Square::H4,
+        Square::A3,
+        Square::B3,
+        Square::C3,
+        Square::D3,
+        Square::E3,
+        Square::F3,
+        Square::G3,
+        Square::H3,
+        Square::A2,
+        Square::B2,
+        Square::C2,
+        Square::D2,
+        Square::E2,
+        Square::F2,
+        Square::G2,
+        Square::H2,
+        Square::A1,
+        Square::B1,
+        Square::C1,
+        Square::D1,
+        Square::E1,
+        Square::F1,
+        Square::G1,
+        Square::H1,
+    ];
+
+    pub const fn from_index(index: usize) -> Option<Square> {
+        if index < 64 {
+            Some(Square::ALL[index])
+        } else {
+            None
+        }
+    }
+
+    // `file` from 0 for the a-file, `rank` from 1 to 8 as it is written.
+    pub const fn from_file_rank(file: usize, rank: usize) -> Option<Square> {
+        if file < 8 && rank >= 1 && rank <= 8 {
+            Square::from_index((8 - rank) * 8 + file)
+        } else {
+            None
+        }
+    }
+
+    pub const fn from_coords(coords: Coords) -> Option<Square> {
+        if coords.x >= 0 && coords.x < 8 && coords.y >= 0 && coords.y < 8 {
+            Square::from_index((coords.y * 8 + coords.x) as usize)
+        } else {
+            None
+        }
+    }
+
+    pub fn from_algebraic(square: &str) -> Option<Square> {
+        let mut chars = square.chars();
+        let file = chars.next()?;
+        let rank = chars.next()?.to_digit(10)? as usize;
+        if chars.next().is_some() || !('a'..='h').contains(&file) {
+            return None;
+        }
+        Square::from_file_rank(file as usize - 'a' as usize, rank)
+    }
+
+    pub const fn index(self) -> usize {
+        self as usize
+    }
+
+    pub const fn file(self) -> usize {
+        self.index() % 8
+    }
+
+    pub const fn rank(self) -> usize {
+        8 - self.index() / 8
+    }
+
+    pub const fn coords(self) -> Coords {
+        Coords {
+            x: self.file() as isize,
+            y: (self.index() / 8) as isize,
+        }
+    }
+
+    pub fn to_algebraic(self) -> String {
+        self.coords().to_algebraic()
+    }
+}
+
+impl From<Square> for Coords {
+    fn from(square: Square) -> Coords {
+        square.coords()
+    }
+}
+
+// Coords off the board are given back.
+impl TryFrom<Coords> for Square {
+    type Error = Coords;
+    fn try_from(coords: Coords) -> Result<Square, Coords> {
+        Square::from_coords(coords).ok_or(coords)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn squares_agree_with_coords() {
+        assert_eq!(Square::A8.coords(), Coords { x: 0, y: 0 });
+        assert_eq!(Square::H1.coords(), Coords { x: 7, y: 7 });
+        assert_eq!(Coords::from(Square::E4), Coords::from_algebraic("e4"));
+        for (index, square) in Square::ALL.into_iter().enumerate() {
+            assert_eq!(square.index(), index);
+            assert_eq!(square.coords().to_square_number(), index);
+            assert_eq!(Square::try_from(square.coords()), Ok(square));
+            assert_eq!(Square::from_algebraic(&square.to_algebraic()), Some(square));
+        }
+        assert_eq!((Square::E4.file(), Square::E4.rank()), (4, 4));
+        assert_eq!(Square::from_file_rank(0, 1), Some(Square::A1));
+    }
+
+    #[test]
+    fn squares_off_the_board_are_refused() {
+        assert_eq!(Square::from_index(64), None);
+        assert_eq!(Square::from_file_rank(8, 1), None);
+        assert_eq!(Square::from_file_rank(0, 0), None);
+        let off = Coords { x: 3, y: -1 };
+        assert_eq!(Square::try_from(off), Err(off));
+        for text in ["i1", "a9", "a0", "e44", "e", ""] {
+            assert_eq!(Square::from_algebraic(text), None);
+        }
+    }
+}
@@ -0,0 +1,89 @@
+use crate::Position;
+
+// Evaluations in this crate are relative to the side to move. Mirroring swaps
+// the colors and the side to move, so from white's point of view a symmetric
+// evaluation satisfies eval(position) == -eval(position.mirror()), which for a
+// side-to-move relative score means both evaluations are equal.
+pub fn eval_asymmetry(
+    evaluator: impl Fn(&Position) -> isize,
+    position: &Position,
+) -> Option<(isize, isize)> {
+    let original = evaluator(position);
+    let mirrored = evaluator(&position.mirror());
+    if original == mirrored {
+        None
+    } else {
+        Some((original, mirrored))
+    }
+}
+
+pub fn assert_eval_symmetric(evaluator: impl Fn(&Position) -> isize, position: &Position) {
+    if let Some((original, mirrored)) = eval_asymmetry(evaluator, position) {
+        panic!(
+            "evaluation is not symmetric: {} for {:?} but {} for its mirror {:?}",
+            original,
+            position,
+            mirrored,
+            position.mirror()
+        );
+    }
+}
+
+pub fn assert_evals_symmetric(evaluator: impl Fn(&Position) -> isize, positions: &[Position]) {
+    let asymmetric: Vec<String> = positions
+        .iter()
+        .filter_map(|position| {
+            eval_asymmetry(&evaluator, position).map(|(original, mirrored)| {
+                format!("{:?}: {} vs {} mirrored", position, original, mirrored)
+            })
+        })
+        .collect();
+    if !asymmetric.is_empty() {
+        panic!(
+            "evaluation is not symmetric in {} of {} positions:\n{}",
+            asymmetric.len(),
+            positions.len(),
+            asymmetric.join("\n")
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{BasicEvaluationPlayer, BetterEvaluationPlayer, PieceColor, Player};
+
+    use super::*;
+
+    fn sample_positions() -> Vec<Position> {
+        [
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+            "r1bqkbnr/pppp1ppp/2n5/1B2p3/4P3/5N2/PPPP1PPP/RNBQK2R b KQkq - 0 1",
+            "rnb1kbnr/pppppppp/8/1N6/8/8/PPPPPPPP/R1BQKBNR w KQkq - 0 1",
+            "8/8/8/8/4Pp2/8/8/8 b - e3 0 1",
+        ]
+        .iter()
+        .map(|fen| Position::from_fen(fen))
+        .collect()
+    }
+
+    #[test]
+    fn built_in_evaluations_are_symmetric() {
+        assert_evals_symmetric(
+            |position| BasicEvaluationPlayer.evalutate(position),
+            &sample_positions(),
+        );
+        assert_evals_symmetric(
+            |position| BetterEvaluationPlayer::default().evalutate(position),
+            &sample_positions(),
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "evaluation is not symmetric")]
+    fn catches_color_biased_evaluation() {
+        assert_eval_symmetric(
+            |position| position.piece_count(PieceColor::White) as isize,
+            &Position::from_fen("8/8/8/8/8/8/PP6/8 w - - 0 1"),
+        );
+    }
+}
@@ -0,0 +1,227 @@
+use std::fmt::Display;
+
+use crate::ChessMove;
+use crate::ClockState;
+use crate::Player;
+use crate::Position;
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum TacticalSuiteError {
+    // a line that isn't an EPD record with a bm or am operation
+    Malformed(String),
+    // a bm or am move that can't be played in its position
+    IllegalMove(String),
+}
+
+// One EPD record of a suite such as WAC or STS: the position, the moves
+// that solve it (bm) and the moves that fail it (am). A record may give
+// either or both.
+#[derive(Clone, PartialEq, Debug)]
+pub struct TacticalCase {
+    pub id: Option<String>,
+    pub position: Position,
+    pub best_moves: Vec<ChessMove>,
+    pub avoid_moves: Vec<ChessMove>,
+}
+
+impl TacticalCase {
+    // Blank lines and lines starting with '#' are skipped.
+    pub fn parse_suite(text: &str) -> Result<Vec<TacticalCase>, TacticalSuiteError> {
+        text.lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+            .map(TacticalCase::parse)
+            .collect()
+    }
+
+    // Reads `<board> <side> <castling> <en passant> bm Qg6; id "WAC.001";`.
+    // Operations other than bm, am and id are skipped.
+    pub fn parse(line: &str) -> Result<TacticalCase, TacticalSuiteError> {
+        let malformed = || TacticalSuiteError::Malformed(String::from(line));
+        let mut fields = line.splitn(5, char::is_whitespace);
+        let fen: Vec<&str> = fields.by_ref().take(4).collect();
+        if fen.len() < 4 {
+            return Err(malformed());
+        }
+        let fen = format!("{} 0 1", fen.join(" "));
+        let position = Position::try_from_fen(&fen).map_err(|_| malformed())?;
+        let mut case = TacticalCase {
+            id: None,
+            position,
+            best_moves: Vec::new(),
+            avoid_moves: Vec::new(),
+        };
+        for operation in fields.next().unwrap_or_default().split(';') {
+            let (opcode, operands) = operation
+                .trim()
+                .split_once(' ')
+                .unwrap_or((operation.trim(), ""));
+            let moves = || {
+                operands
+                    .split_whitespace()
+                    .map(|san| {
+                        ChessMove::from_san(san, &case.position)
+                            .map_err(|_| TacticalSuiteError::IllegalMove(String::from(san)))
+                    })
+                    .collect::<Result<Vec<ChessMove>, TacticalSuiteError>>()
+            };
+            match opcode {
+                "bm" => case.best_moves = moves()?,
+                "am" => case.avoid_moves = moves()?,
+                "id" => case.id = Some(operands.trim().trim_matches('"').to_string()),
+                _ => {}
+            }
+        }
+        if case.best_moves.is_empty() && case.avoid_moves.is_empty() {
+            return Err(malformed());
+        }
+        Ok(case)
+    }
+
+    // One of the best moves when there are any, none of the moves to avoid.
+    pub fn is_solved_by(&self, chess_move: &ChessMove) -> bool {
+        (self.best_moves.is_empty() || self.best_moves.contains(chess_move))
+            && !self.avoid_moves.contains(chess_move)
+    }
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct TacticalOutcome {
+    pub case: TacticalCase,
+    pub played: ChessMove,
+}
+
+impl TacticalOutcome {
+    pub fn solved(&self) -> bool {
+        self.case.is_solved_by(&self.played)
+    }
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct TacticalReport {
+    pub outcomes: Vec<TacticalOutcome>,
+}
+
+impl TacticalReport {
+    pub fn solved(&self) -> usize {
+        self.outcomes
+            .iter()
+            .filter(|outcome| outcome.solved())
+            .count()
+    }
+
+    pub fn failures(&self) -> Vec<&TacticalOutcome> {
+        self.outcomes
+            .iter()
+            .filter(|outcome| !outcome.solved())
+            .collect()
+    }
+}
+
+fn sans(moves: &[ChessMove], position: &Position) -> String {
+    moves
+        .iter()
+        .map(|chess_move| chess_move.to_san(position))
+        .collect::<Vec<String>>()
+        .join(" ")
+}
+
+// One line per position, then the score.
+impl Display for TacticalReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for outcome in &self.outcomes {
+            let case = &outcome.case;
+            let name = case.id.clone().unwrap_or_else(|| case.position.to_fen());
+            let played = outcome.played.to_san(&case.position);
+            if outcome.solved() {
+                writeln!(f, "ok   {name} {played}")?;
+                continue;
+            }
+            write!(f, "FAIL {name} played {played}")?;
+            if !case.best_moves.is_empty() {
+                write!(f, ", bm {}", sans(&case.best_moves, &case.position))?;
+            }
+            if !case.avoid_moves.is_empty() {
+                write!(f, ", am {}", sans(&case.avoid_moves, &case.position))?;
+            }
+            writeln!(f)?;
+        }
+        write!(f, "{} of {} solved", self.solved(), self.outcomes.len())
+    }
+}
+
+// Asks `player` for its move in every position, each as the start of a new
+// game. The player searches as deep as it is set up to, or, given a clock,
+// for as long as it would on that clock.
+pub fn run_tactical_suite(
+    cases: &[TacticalCase],
+    player: &dyn Player,
+    clock: Option<&ClockState>,
+) -> TacticalReport {
+    let outcomes = cases
+        .iter()
+        .map(|case| {
+            player.new_game();
+            let played = match clock {
+                Some(clock) => player.offer_move_timed(&case.position, clock),
+                None => player.offer_move(&case.position),
+            };
+            TacticalOutcome {
+                case: case.clone(),
+                played,
+            }
+        })
+        .collect();
+    TacticalReport { outcomes }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::FirstMovePlayer;
+    use crate::Planner;
+
+    use super::*;
+
+    const FORKS: &str = "\
+# the knight takes on c7 with check and wins the rook
+rnb1kbnr/pppppppp/8/1N6/8/8/PPPPPPPP/R1BQKBNR w KQkq - bm Nxc7+; id \"fork\";
+# the cornered knight is lost if it goes back to c7
+Nnbk1bnr/pp1p1ppp/8/4p3/8/8/PPPPPPPP/R1BQKBNR w KQ - am Nc7; id \"trapped\";
+";
+
+    #[test]
+    fn reads_bm_am_and_id() {
+        let cases = TacticalCase::parse_suite(FORKS).unwrap();
+        assert_eq!(cases.len(), 2);
+        assert_eq!(cases[0].id.as_deref(), Some("fork"));
+        assert_eq!(cases[0].best_moves.len(), 1);
+        assert!(cases[0].avoid_moves.is_empty());
+        assert_eq!(cases[1].avoid_moves.len(), 1);
+        assert_eq!(
+            TacticalCase::parse("8/8/8/8/8/8/8/K6k w - - bm Kb3;"),
+            Err(TacticalSuiteError::IllegalMove(String::from("Kb3")))
+        );
+        assert_eq!(
+            TacticalCase::parse("8/8/8/8/8/8/8/K6x w - - bm Kb2;"),
+            Err(TacticalSuiteError::Malformed(String::from(
+                "8/8/8/8/8/8/8/K6x w - - bm Kb2;"
+            )))
+        );
+        assert_eq!(
+            TacticalCase::parse("8/8/8/8/8/8/8/K6k w - - id \"nothing\";"),
+            Err(TacticalSuiteError::Malformed(String::from(
+                "8/8/8/8/8/8/8/K6k w - - id \"nothing\";"
+            )))
+        );
+    }
+
+    #[test]
+    fn planner_solves_the_forks() {
+        let cases = TacticalCase::parse_suite(FORKS).unwrap();
+        let report = run_tactical_suite(&cases, &Planner::new(), None);
+        assert_eq!(report.solved(), 2, "{report}");
+        let report = run_tactical_suite(&cases, &FirstMovePlayer, None);
+        assert!(report.solved() < 2);
+        assert!(report.to_string().contains("FAIL fork played"));
+    }
+}
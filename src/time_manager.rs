@@ -0,0 +1,190 @@
+use std::time::Duration;
+
+use crate::PieceColor;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub struct ClockState {
+    pub white_remaining: Duration,
+    pub black_remaining: Duration,
+    pub white_increment: Duration,
+    pub black_increment: Duration,
+    pub moves_to_go: Option<u32>,
+}
+
+impl ClockState {
+    pub fn remaining(&self, color: &PieceColor) -> Duration {
+        match color {
+            PieceColor::White => self.white_remaining,
+            PieceColor::Black => self.black_remaining,
+        }
+    }
+
+    pub fn increment(&self, color: &PieceColor) -> Duration {
+        match color {
+            PieceColor::White => self.white_increment,
+            PieceColor::Black => self.black_increment,
+        }
+    }
+
+    // Takes `spent` off `color`'s clock and adds the increment, or returns
+    // false and leaves the clock alone when `spent` is more than was left.
+    pub fn spend(&mut self, color: &PieceColor, spent: Duration) -> bool {
+        let Some(left) = self.remaining(color).checked_sub(spent) else {
+            return false;
+        };
+        let left = left + self.increment(color);
+        match color {
+            PieceColor::White => self.white_remaining = left,
+            PieceColor::Black => self.black_remaining = left,
+        }
+        true
+    }
+}
+
+// How long to think about one move: the search should wrap up once it passes
+// `optimum` between iterations and must stop at `maximum` no matter what.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct TimeBudget {
+    pub optimum: Duration,
+    pub maximum: Duration,
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct TimeManager {
+    // kept in reserve for communication and move application lag
+    pub move_overhead: Duration,
+    // assumed number of moves left when the time control doesn't say
+    pub default_moves_to_go: u32,
+    // never plan to spend more than this share of the remaining clock on a move
+    pub max_share: f64,
+    // below this much time, only the increment and a sliver of the clock are used
+    pub panic_threshold: Duration,
+}
+
+impl Default for TimeManager {
+    fn default() -> TimeManager {
+        TimeManager {
+            move_overhead: Duration::from_millis(30),
+            default_moves_to_go: 30,
+            max_share: 0.25,
+            panic_threshold: Duration::from_secs(1),
+        }
+    }
+}
+
+impl TimeManager {
+    pub fn allocate(&self, clock: &ClockState, color: &PieceColor) -> TimeBudget {
+        let remaining = clock.remaining(color).saturating_sub(self.move_overhead);
+        let increment = clock.increment(color);
+        if remaining < self.panic_threshold {
+            let panic_time = (remaining / 20).max(increment.min(remaining / 2));
+            return TimeBudget {
+                optimum: panic_time,
+                maximum: panic_time,
+            };
+        }
+        let moves_to_go = clock.moves_to_go.unwrap_or(self.default_moves_to_go).max(1);
+        let maximum = remaining.mul_f64(self.max_share);
+        let optimum = (remaining / moves_to_go + increment.mul_f64(0.75)).min(maximum);
+        TimeBudget {
+            optimum,
+            maximum: maximum.max(optimum),
+        }
+    }
+
+    // When the best score dropped between iterations the position is more
+    // critical than it looked, so the search gets to spend part of its
+    // reserve. `score_drop` is in centipawns.
+    pub fn extended_optimum(&self, budget: &TimeBudget, score_drop: isize) -> Duration {
+        let factor = match score_drop {
+            drop if drop >= 150 => 2.0,
+            drop if drop >= 50 => 1.5,
+            _ => 1.0,
+        };
+        budget.optimum.mul_f64(factor).min(budget.maximum)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn clock(remaining_ms: u64, increment_ms: u64) -> ClockState {
+        ClockState {
+            white_remaining: Duration::from_millis(remaining_ms),
+            black_remaining: Duration::from_millis(remaining_ms / 2),
+            white_increment: Duration::from_millis(increment_ms),
+            black_increment: Duration::from_millis(increment_ms),
+            moves_to_go: None,
+        }
+    }
+
+    #[test]
+    fn spreads_clock_over_remaining_moves() {
+        let manager = TimeManager::default();
+        let budget = manager.allocate(&clock(60_030, 1_000), &PieceColor::White);
+        assert_eq!(budget.optimum, Duration::from_millis(2_000 + 750));
+        assert_eq!(budget.maximum, Duration::from_millis(15_000));
+
+        let with_moves_to_go = ClockState {
+            moves_to_go: Some(10),
+            ..clock(60_030, 0)
+        };
+        assert_eq!(
+            manager
+                .allocate(&with_moves_to_go, &PieceColor::White)
+                .optimum,
+            Duration::from_millis(6_000)
+        );
+        assert!(
+            manager
+                .allocate(&clock(60_030, 0), &PieceColor::Black)
+                .optimum
+                < manager
+                    .allocate(&clock(60_030, 0), &PieceColor::White)
+                    .optimum
+        );
+    }
+
+    #[test]
+    fn spending_adds_the_increment_until_the_flag_falls() {
+        let mut clock = clock(1_000, 100);
+        assert!(clock.spend(&PieceColor::White, Duration::from_millis(300)));
+        assert_eq!(clock.white_remaining, Duration::from_millis(800));
+        assert!(!clock.spend(&PieceColor::Black, Duration::from_millis(501)));
+        assert_eq!(clock.black_remaining, Duration::from_millis(500));
+    }
+
+    #[test]
+    fn panics_near_the_flag() {
+        let manager = TimeManager::default();
+        let budget = manager.allocate(&clock(530, 100), &PieceColor::White);
+        assert_eq!(budget.optimum, Duration::from_millis(100));
+        assert_eq!(budget.maximum, budget.optimum);
+        assert_eq!(
+            manager.allocate(&clock(20, 0), &PieceColor::White).maximum,
+            Duration::ZERO
+        );
+    }
+
+    #[test]
+    fn extends_for_unstable_scores() {
+        let manager = TimeManager::default();
+        let budget = TimeBudget {
+            optimum: Duration::from_millis(1_000),
+            maximum: Duration::from_millis(1_800),
+        };
+        assert_eq!(
+            manager.extended_optimum(&budget, 10),
+            Duration::from_millis(1_000)
+        );
+        assert_eq!(
+            manager.extended_optimum(&budget, 60),
+            Duration::from_millis(1_500)
+        );
+        assert_eq!(
+            manager.extended_optimum(&budget, 400),
+            Duration::from_millis(1_800)
+        );
+    }
+}
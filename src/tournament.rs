@@ -0,0 +1,264 @@
+use crate::play_game;
+use crate::AdjudicationPolicy;
+use crate::GameResult;
+use crate::PieceColor;
+use crate::Player;
+use crate::Position;
+use crate::RuleSet;
+use crate::StandardRules;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum TournamentFormat {
+    // everyone plays everyone else
+    RoundRobin,
+    // the first entrant plays each of the others, who don't play each other
+    Gauntlet,
+}
+
+// One game of a tournament, the players given by their place in the list
+// of entrants.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct TournamentGame {
+    pub white: usize,
+    pub black: usize,
+    pub result: GameResult,
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct Standing {
+    pub entrant: usize,
+    pub name: String,
+    pub wins: usize,
+    pub draws: usize,
+    pub losses: usize,
+}
+
+impl Standing {
+    pub fn games(&self) -> usize {
+        self.wins + self.draws + self.losses
+    }
+
+    // draws and games cut off by the move limit count half a point
+    pub fn points(&self) -> f64 {
+        self.wins as f64 + self.draws as f64 / 2.0
+    }
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct Standings {
+    // best first, ties kept in the order the entrants were given in
+    pub table: Vec<Standing>,
+    pub games: Vec<TournamentGame>,
+}
+
+impl Standings {
+    pub fn to_table(&self) -> String {
+        let width = self
+            .table
+            .iter()
+            .map(|standing| standing.name.len())
+            .max()
+            .unwrap_or(0)
+            .max("Player".len());
+        let mut table = format!(
+            "{:>3}  {:<width$}  {:>5}  {:>4}  {:>4}  {:>4}  {:>6}\n",
+            "#",
+            "Player",
+            "Games",
+            "W",
+            "D",
+            "L",
+            "Points",
+            width = width
+        );
+        for (rank, standing) in self.table.iter().enumerate() {
+            table += &format!(
+                "{:>3}  {:<width$}  {:>5}  {:>4}  {:>4}  {:>4}  {:>6.1}\n",
+                rank + 1,
+                standing.name,
+                standing.games(),
+                standing.wins,
+                standing.draws,
+                standing.losses,
+                standing.points(),
+                width = width
+            );
+        }
+        table
+    }
+}
+
+// Matches between engines, each pairing playing `games_per_pairing` games
+// with colors alternating. Every opening is played once with each color
+// before the next one is used, the rules' own starting position when there
+// are none.
+pub struct Tournament {
+    entrants: Vec<Box<dyn Player>>,
+    format: TournamentFormat,
+    games_per_pairing: usize,
+    openings: Vec<Position>,
+    adjudication: AdjudicationPolicy,
+    rules: &'static dyn RuleSet,
+}
+
+impl Tournament {
+    pub fn new(entrants: Vec<Box<dyn Player>>, format: TournamentFormat) -> Tournament {
+        Tournament {
+            entrants,
+            format,
+            games_per_pairing: 2,
+            openings: Vec::new(),
+            adjudication: AdjudicationPolicy::default(),
+            rules: &StandardRules,
+        }
+    }
+
+    pub fn round_robin(entrants: Vec<Box<dyn Player>>) -> Tournament {
+        Tournament::new(entrants, TournamentFormat::RoundRobin)
+    }
+
+    pub fn gauntlet(entrants: Vec<Box<dyn Player>>) -> Tournament {
+        Tournament::new(entrants, TournamentFormat::Gauntlet)
+    }
+
+    pub fn games_per_pairing(mut self, games: usize) -> Tournament {
+        self.games_per_pairing = games;
+        self
+    }
+
+    // An empty list keeps the starting position.
+    pub fn openings(mut self, openings: Vec<Position>) -> Tournament {
+        self.openings = openings;
+        self
+    }
+
+    pub fn adjudication(mut self, policy: AdjudicationPolicy) -> Tournament {
+        self.adjudication = policy;
+        self
+    }
+
+    // Standard chess by default.
+    pub fn rules(mut self, rules: &'static dyn RuleSet) -> Tournament {
+        self.rules = rules;
+        self
+    }
+
+    pub fn pairings(&self) -> Vec<(usize, usize)> {
+        let count = self.entrants.len();
+        match self.format {
+            TournamentFormat::RoundRobin => (0..count)
+                .flat_map(|first| (first + 1..count).map(move |second| (first, second)))
+                .collect(),
+            TournamentFormat::Gauntlet => (1..count).map(|second| (0, second)).collect(),
+        }
+    }
+
+    pub fn run(&self) -> Standings {
+        let mut table: Vec<Standing> = self
+            .entrants
+            .iter()
+            .enumerate()
+            .map(|(entrant, player)| Standing {
+                entrant,
+                name: player.to_string(),
+                wins: 0,
+                draws: 0,
+                losses: 0,
+            })
+            .collect();
+        let mut games = Vec::new();
+        for (first, second) in self.pairings() {
+            for game in 0..self.games_per_pairing {
+                let (white, black) = if game % 2 == 0 {
+                    (first, second)
+                } else {
+                    (second, first)
+                };
+                let opening = match self.openings.len() {
+                    0 => self.rules.starting_position(),
+                    openings => self.openings[(game / 2) % openings].clone(),
+                };
+                let result = play_game(
+                    self.entrants[white].as_ref(),
+                    self.entrants[black].as_ref(),
+                    self.rules,
+                    opening,
+                    None,
+                    &self.adjudication,
+                )
+                .result;
+                match result.winner() {
+                    Some(PieceColor::White) => {
+                        table[white].wins += 1;
+                        table[black].losses += 1;
+                    }
+                    Some(PieceColor::Black) => {
+                        table[black].wins += 1;
+                        table[white].losses += 1;
+                    }
+                    None => {
+                        table[white].draws += 1;
+                        table[black].draws += 1;
+                    }
+                }
+                games.push(TournamentGame {
+                    white,
+                    black,
+                    result,
+                });
+            }
+        }
+        table.sort_by(|a, b| b.points().total_cmp(&a.points()));
+        Standings { table, games }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::FirstMovePlayer;
+    use crate::GreedyMaterialPlayer;
+
+    use super::*;
+
+    fn entrants() -> Vec<Box<dyn Player>> {
+        vec![
+            Box::new(FirstMovePlayer),
+            Box::new(GreedyMaterialPlayer),
+            Box::new(FirstMovePlayer),
+        ]
+    }
+
+    #[test]
+    fn round_robins_pair_everyone_with_colors_alternating() {
+        let standings = Tournament::round_robin(entrants()).run();
+        assert_eq!(standings.games.len(), 6);
+        assert!(standings.table.iter().all(|standing| standing.games() == 4));
+        for pairing in standings.games.chunks(2) {
+            assert_eq!(
+                (pairing[0].white, pairing[0].black),
+                (pairing[1].black, pairing[1].white)
+            );
+        }
+        let points: f64 = standings.table.iter().map(Standing::points).sum();
+        assert_eq!(points, 6.0);
+        assert!(standings.table[0].points() >= standings.table[1].points());
+        assert_eq!(standings.to_table().lines().count(), 4);
+    }
+
+    #[test]
+    fn gauntlets_only_pair_the_first_entrant() {
+        let tournament = Tournament::gauntlet(entrants())
+            .games_per_pairing(4)
+            .openings(vec![
+                Position::initial(),
+                Position::from_fen("rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq - 0 1"),
+            ]);
+        assert_eq!(tournament.pairings(), vec![(0, 1), (0, 2)]);
+        let standings = tournament.run();
+        assert_eq!(standings.games.len(), 8);
+        assert!(standings
+            .games
+            .iter()
+            .all(|game| game.white == 0 || game.black == 0));
+    }
+}
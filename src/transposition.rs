@@ -0,0 +1,378 @@
+use crate::ChessMove;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Bound {
+    Exact,
+    // the score is at least this much, the search failed high
+    Lower,
+    // the score is at most this much, no move raised alpha
+    Upper,
+}
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct TableEntry {
+    pub key: u64,
+    pub depth: isize,
+    pub score: isize,
+    pub bound: Bound,
+    pub best_move: Option<ChessMove>,
+}
+
+const BUCKET_SIZE: usize = 4;
+
+// Which entry of a full bucket a new result takes the place of. An entry
+// for the same key is always replaced first, then an empty one.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum ReplacementPolicy {
+    // the entry stored longest ago
+    Always,
+    // the shallowest entry, the first of them on ties
+    DepthPreferred,
+    // the first half of each bucket keeps the deepest results, and only takes
+    // results at least as deep as the shallowest of them; everything else
+    // goes to the second half, where it replaces the entry stored longest ago
+    TwoTier,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct TableConfig {
+    pub megabytes: usize,
+    pub bucket_size: usize,
+    pub replacement: ReplacementPolicy,
+    // entries left over from earlier searches are replaced before any stored
+    // by the current one, see `TranspositionTable::new_search`
+    pub aging: bool,
+}
+
+impl Default for TableConfig {
+    fn default() -> TableConfig {
+        TableConfig {
+            megabytes: 16,
+            bucket_size: BUCKET_SIZE,
+            replacement: ReplacementPolicy::DepthPreferred,
+            aging: true,
+        }
+    }
+}
+
+// How full the table is. `current` only counts the entries stored since
+// the last `new_search`.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct TableStats {
+    pub capacity: usize,
+    pub used: usize,
+    pub current: usize,
+}
+
+impl TableStats {
+    // Per mille, as UCI's hashfull.
+    pub fn permille(&self) -> usize {
+        self.used * 1000 / self.capacity.max(1)
+    }
+}
+
+#[derive(Clone, Debug)]
+struct Slot {
+    entry: TableEntry,
+    generation: u8,
+    // when it was stored, for the policies that replace the oldest entry
+    stored_at: u64,
+}
+
+// Results of earlier searches keyed by Zobrist key. Keys map to a bucket of
+// a few entries, and a full bucket makes room as the `ReplacementPolicy`
+// says.
+#[derive(Clone, Debug)]
+pub struct TranspositionTable {
+    slots: Vec<Option<Slot>>,
+    bucket_size: usize,
+    replacement: ReplacementPolicy,
+    aging: bool,
+    generation: u8,
+    stores: u64,
+}
+
+impl TranspositionTable {
+    // Rounded up to whole buckets, so any capacity works.
+    pub fn new(capacity: usize) -> TranspositionTable {
+        TranspositionTable::with_capacity(capacity, &TableConfig::default())
+    }
+
+    // As many entries as fit in `megabytes`, but never less than one bucket.
+    pub fn with_megabytes(megabytes: usize) -> TranspositionTable {
+        TranspositionTable::with_config(&TableConfig {
+            megabytes,
+            ..TableConfig::default()
+        })
+    }
+
+    pub fn with_config(config: &TableConfig) -> TranspositionTable {
+        let bucket_size = config.bucket_size.max(1);
+        let bytes = config.megabytes.saturating_mul(1024 * 1024);
+        let capacity = bytes / (TranspositionTable::entry_size() * bucket_size) * bucket_size;
+        TranspositionTable::with_capacity(capacity, config)
+    }
+
+    fn with_capacity(capacity: usize, config: &TableConfig) -> TranspositionTable {
+        let bucket_size = config.bucket_size.max(1);
+        let buckets = capacity.div_ceil(bucket_size).max(1);
+        TranspositionTable {
+            slots: vec![None; buckets * bucket_size],
+            bucket_size,
+            replacement: config.replacement,
+            aging: config.aging,
+            generation: 0,
+            stores: 0,
+        }
+    }
+
+    // The bytes each entry takes up.
+    pub fn entry_size() -> usize {
+        std::mem::size_of::<Option<Slot>>()
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.slots.len()
+    }
+
+    pub fn bucket_size(&self) -> usize {
+        self.bucket_size
+    }
+
+    pub fn replacement(&self) -> ReplacementPolicy {
+        self.replacement
+    }
+
+    pub fn aging(&self) -> bool {
+        self.aging
+    }
+
+    // Called before each search, so that with aging on, what earlier
+    // searches stored gives way to what this one finds.
+    pub fn new_search(&mut self) {
+        self.generation = self.generation.wrapping_add(1);
+    }
+
+    pub fn probe(&self, key: u64) -> Option<&TableEntry> {
+        self.bucket(key)
+            .iter()
+            .flatten()
+            .map(|slot| &slot.entry)
+            .find(|entry| entry.key == key)
+    }
+
+    pub fn store(&mut self, entry: TableEntry) {
+        let generation = self.generation;
+        let stored_at = self.stores;
+        self.stores += 1;
+        let slot = self.victim(&entry);
+        let start = self.bucket_start(entry.key);
+        self.slots[start + slot] = Some(Slot {
+            entry,
+            generation,
+            stored_at,
+        });
+    }
+
+    // The index in its bucket `entry` goes to.
+    fn victim(&self, entry: &TableEntry) -> usize {
+        let bucket = self.bucket(entry.key);
+        if let Some(slot) = bucket
+            .iter()
+            .position(|slot| {
+                slot.as_ref()
+                    .is_some_and(|slot| slot.entry.key == entry.key)
+            })
+            .or_else(|| bucket.iter().position(Option::is_none))
+        {
+            return slot;
+        }
+        let slots = || bucket.iter().flatten().enumerate();
+        let oldest = |range: std::ops::Range<usize>| {
+            range
+                .min_by_key(|slot| bucket[*slot].as_ref().map_or(0, |slot| slot.stored_at))
+                .expect("buckets are never empty")
+        };
+        // first of the shallowest, so ties evict the same entry every time
+        let shallowest = |range: std::ops::Range<usize>| {
+            range
+                .min_by_key(|slot| {
+                    bucket[*slot]
+                        .as_ref()
+                        .map_or(isize::MIN, |slot| slot.entry.depth)
+                })
+                .expect("buckets are never empty")
+        };
+        if self.aging {
+            if let Some((slot, _)) = slots()
+                .filter(|(_, slot)| slot.generation != self.generation)
+                .min_by_key(|(_, slot)| (slot.entry.depth, slot.stored_at))
+            {
+                return slot;
+            }
+        }
+        match self.replacement {
+            ReplacementPolicy::Always => oldest(0..self.bucket_size),
+            ReplacementPolicy::DepthPreferred => shallowest(0..self.bucket_size),
+            ReplacementPolicy::TwoTier => {
+                let deep = self.bucket_size.div_ceil(2);
+                let candidate = shallowest(0..deep);
+                let candidate_depth = bucket[candidate]
+                    .as_ref()
+                    .map_or(isize::MIN, |slot| slot.entry.depth);
+                if entry.depth >= candidate_depth || deep == self.bucket_size {
+                    candidate
+                } else {
+                    oldest(deep..self.bucket_size)
+                }
+            }
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.slots.iter_mut().for_each(|slot| *slot = None);
+        self.generation = 0;
+        self.stores = 0;
+    }
+
+    pub fn len(&self) -> usize {
+        self.slots.iter().flatten().count()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn stats(&self) -> TableStats {
+        TableStats {
+            capacity: self.capacity(),
+            used: self.len(),
+            current: self
+                .slots
+                .iter()
+                .flatten()
+                .filter(|slot| slot.generation == self.generation)
+                .count(),
+        }
+    }
+
+    fn bucket_start(&self, key: u64) -> usize {
+        let buckets = (self.slots.len() / self.bucket_size) as u64;
+        (key % buckets) as usize * self.bucket_size
+    }
+
+    fn bucket(&self, key: u64) -> &[Option<Slot>] {
+        let start = self.bucket_start(key);
+        &self.slots[start..start + self.bucket_size]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(key: u64, depth: isize) -> TableEntry {
+        TableEntry {
+            key,
+            depth,
+            score: 10,
+            bound: Bound::Exact,
+            best_move: Some(ChessMove::CastleRight),
+        }
+    }
+
+    #[test]
+    fn probes_only_match_the_stored_key() {
+        let mut table = TranspositionTable::new(16);
+        table.store(entry(3, 2));
+        assert_eq!(table.probe(3), Some(&entry(3, 2)));
+        assert_eq!(table.probe(7), None);
+        table.store(entry(3, 4));
+        assert_eq!(table.probe(3), Some(&entry(3, 4)));
+        assert_eq!(table.len(), 1);
+        table.clear();
+        assert!(table.is_empty());
+    }
+
+    #[test]
+    fn full_bucket_evicts_the_shallowest_entry() {
+        // a single bucket, so every key competes for the same entries
+        let mut table = TranspositionTable::new(3);
+        assert_eq!(table.capacity(), 4);
+        for (key, depth) in [(1, 5), (2, 1), (3, 4), (4, 2)] {
+            table.store(entry(key, depth));
+        }
+        table.store(entry(5, 3));
+        assert_eq!(table.probe(2), None);
+        assert!([1, 3, 4, 5].iter().all(|key| table.probe(*key).is_some()));
+    }
+
+    #[test]
+    fn sized_in_megabytes() {
+        let entry_size = TranspositionTable::entry_size();
+        let table = TranspositionTable::with_megabytes(1);
+        assert_eq!(table.capacity() % 4, 0);
+        assert!(table.capacity() * entry_size <= 1024 * 1024);
+        assert!((table.capacity() + 4) * entry_size > 1024 * 1024);
+        assert_eq!(TranspositionTable::with_megabytes(0).capacity(), 4);
+        let mut odd = TranspositionTable::with_megabytes(3);
+        odd.store(entry(u64::MAX, 1));
+        assert!(odd.probe(u64::MAX).is_some());
+    }
+
+    #[test]
+    fn policies_pick_different_victims() {
+        let config = |replacement| TableConfig {
+            megabytes: 0,
+            bucket_size: 4,
+            replacement,
+            aging: false,
+        };
+        let filled = |replacement| {
+            let mut table = TranspositionTable::with_config(&config(replacement));
+            for (key, depth) in [(1, 1), (2, 5), (3, 4), (4, 2)] {
+                table.store(entry(key, depth));
+            }
+            table.store(entry(5, 0));
+            table
+        };
+        // the first stored goes, whatever its depth
+        let always = filled(ReplacementPolicy::Always);
+        assert_eq!(always.probe(1), None);
+        let depth_preferred = filled(ReplacementPolicy::DepthPreferred);
+        assert_eq!(depth_preferred.probe(1), None);
+        assert!(depth_preferred.probe(5).is_some());
+        // too shallow for the deep half, so the older of the other half goes
+        let two_tier = filled(ReplacementPolicy::TwoTier);
+        assert!([1, 2, 4, 5]
+            .iter()
+            .all(|key| two_tier.probe(*key).is_some()));
+        assert_eq!(two_tier.probe(3), None);
+    }
+
+    #[test]
+    fn older_searches_make_room_first() {
+        let mut table = TranspositionTable::new(4);
+        for (key, depth) in [(1, 1), (2, 9), (3, 8)] {
+            table.store(entry(key, depth));
+        }
+        table.new_search();
+        table.store(entry(4, 1));
+        assert_eq!(
+            table.stats(),
+            TableStats {
+                capacity: 4,
+                used: 4,
+                current: 1
+            }
+        );
+        assert_eq!(table.stats().permille(), 1000);
+        // the deep entries from before go ahead of this search's shallow one
+        table.store(entry(5, 0));
+        table.store(entry(6, 0));
+        assert_eq!(table.probe(1), None);
+        assert_eq!(table.probe(3), None);
+        assert!(table.probe(4).is_some());
+        assert!(table.probe(2).is_some());
+    }
+}
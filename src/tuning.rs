@@ -0,0 +1,265 @@
+use crate::play_game;
+use crate::AdjudicationPolicy;
+use crate::BetterEvaluationPlayer;
+use crate::EvalParams;
+use crate::PieceColor;
+use crate::Planner;
+use crate::Player;
+use crate::Position;
+use crate::PruningParams;
+use crate::StandardRules;
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct SpsaConfig {
+    pub iterations: usize,
+    // step sizes are relative to the magnitude of each starting value
+    pub a: f64,
+    pub c: f64,
+    pub stability: f64,
+    pub alpha: f64,
+    pub gamma: f64,
+    pub seed: u64,
+}
+
+impl Default for SpsaConfig {
+    fn default() -> SpsaConfig {
+        SpsaConfig {
+            iterations: 100,
+            a: 0.1,
+            c: 0.05,
+            stability: 10.0,
+            alpha: 0.602,
+            gamma: 0.101,
+            seed: 1,
+        }
+    }
+}
+
+// xorshift64, so tuning runs are reproducible without the rng feature
+struct PerturbationSource(u64);
+
+impl PerturbationSource {
+    fn next_sign(&mut self) -> f64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        if self.0 & 1 == 0 {
+            1.0
+        } else {
+            -1.0
+        }
+    }
+}
+
+// `compare(plus, minus)` should be positive when `plus` is the stronger set of
+// values, ideally within -1.0..=1.0 like a match score.
+pub fn spsa(
+    initial: &[isize],
+    config: &SpsaConfig,
+    mut compare: impl FnMut(&[isize], &[isize]) -> f64,
+) -> Vec<isize> {
+    let scales: Vec<f64> = initial
+        .iter()
+        .map(|value| value.unsigned_abs().max(1) as f64)
+        .collect();
+    let mut theta: Vec<f64> = initial.iter().map(|value| *value as f64).collect();
+    let mut source = PerturbationSource(config.seed.max(1));
+
+    for k in 0..config.iterations {
+        let a_k = config.a / (k as f64 + 1.0 + config.stability).powf(config.alpha);
+        let c_k = config.c / (k as f64 + 1.0).powf(config.gamma);
+        let deltas: Vec<f64> = theta.iter().map(|_| source.next_sign()).collect();
+        // perturb by at least one so that small integer values still move
+        let offsets: Vec<f64> = scales
+            .iter()
+            .zip(&deltas)
+            .map(|(scale, delta)| (c_k * scale).max(1.0) * delta)
+            .collect();
+        let plus: Vec<isize> = theta
+            .iter()
+            .zip(&offsets)
+            .map(|(value, offset)| (value + offset).round() as isize)
+            .collect();
+        let minus: Vec<isize> = theta
+            .iter()
+            .zip(&offsets)
+            .map(|(value, offset)| (value - offset).round() as isize)
+            .collect();
+        let difference = compare(&plus, &minus);
+        for ((value, scale), delta) in theta.iter_mut().zip(&scales).zip(&deltas) {
+            *value += a_k * scale * difference / (2.0 * c_k * delta);
+        }
+    }
+    theta.iter().map(|value| value.round() as isize).collect()
+}
+
+// Score of `first` against `second` between 0.0 and 1.0, draws counting as
+// half a point. As in `run_sprt`, every opening is played once with each
+// color before the next one is used, and games start from the initial
+// position when there are none; deterministic players need openings for
+// more than two games to tell them anything.
+pub fn play_match(
+    first: impl Fn() -> Box<dyn Player>,
+    second: impl Fn() -> Box<dyn Player>,
+    openings: &[Position],
+    games: usize,
+) -> f64 {
+    if games == 0 {
+        return 0.5;
+    }
+    let points: f64 = (0..games)
+        .map(|game| {
+            let first_is_white = game % 2 == 0;
+            let opening = match openings.len() {
+                0 => Position::initial(),
+                count => openings[(game / 2) % count].clone(),
+            };
+            let (first_player, second_player) = (first(), second());
+            let (white, black) = if first_is_white {
+                (&first_player, &second_player)
+            } else {
+                (&second_player, &first_player)
+            };
+            let result = play_game(
+                white.as_ref(),
+                black.as_ref(),
+                &StandardRules,
+                opening,
+                None,
+                &AdjudicationPolicy::default(),
+            )
+            .result;
+            let first = if first_is_white {
+                PieceColor::White
+            } else {
+                PieceColor::Black
+            };
+            result.points_for(&first)
+        })
+        .sum();
+    points / games as f64
+}
+
+pub fn tune_eval_params(
+    initial: EvalParams,
+    config: &SpsaConfig,
+    openings: &[Position],
+    games_per_iteration: usize,
+) -> EvalParams {
+    let tuned = spsa(&initial.tunable_values(), config, |plus, minus| {
+        let plus = initial.with_tunable_values(plus);
+        let minus = initial.with_tunable_values(minus);
+        let score = play_match(
+            || Box::new(BetterEvaluationPlayer::with_params(plus)),
+            || Box::new(BetterEvaluationPlayer::with_params(minus)),
+            openings,
+            games_per_iteration,
+        );
+        2.0 * score - 1.0
+    });
+    initial.with_tunable_values(&tuned)
+}
+
+// Tunes the Planner's pruning constants in matches searched to `depth`.
+pub fn tune_pruning_params(
+    initial: PruningParams,
+    config: &SpsaConfig,
+    depth: usize,
+    openings: &[Position],
+    games_per_iteration: usize,
+) -> PruningParams {
+    let tuned = spsa(&initial.tunable_values(), config, |plus, minus| {
+        let plus = initial.with_tunable_values(plus);
+        let minus = initial.with_tunable_values(minus);
+        let score = play_match(
+            || Box::new(Planner::new().with_depth(depth).with_pruning(plus)),
+            || Box::new(Planner::new().with_depth(depth).with_pruning(minus)),
+            openings,
+            games_per_iteration,
+        );
+        2.0 * score - 1.0
+    });
+    initial.with_tunable_values(&tuned)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn quadratic_comparison(target: &[isize]) -> impl Fn(&[isize], &[isize]) -> f64 + '_ {
+        let loss = move |values: &[isize]| -> f64 {
+            values
+                .iter()
+                .zip(target)
+                .map(|(value, target)| {
+                    let error = (value - target) as f64 / *target as f64;
+                    error * error
+                })
+                .sum()
+        };
+        move |plus, minus| loss(minus) - loss(plus)
+    }
+
+    #[test]
+    fn converges_on_quadratic_optimum() {
+        let target = [150, 350, 20];
+        let config = SpsaConfig {
+            iterations: 2000,
+            a: 0.5,
+            ..SpsaConfig::default()
+        };
+        let tuned = spsa(&[100, 300, 10], &config, quadratic_comparison(&target));
+        for (tuned, target) in tuned.iter().zip(target) {
+            assert!(
+                (tuned - target).abs() * 10 <= target,
+                "{:?} should be close to {:?}",
+                tuned,
+                target
+            );
+        }
+    }
+
+    #[test]
+    fn same_seed_gives_same_result() {
+        let target = [150, 350];
+        let config = SpsaConfig::default();
+        assert_eq!(
+            spsa(&[100, 300], &config, quadratic_comparison(&target)),
+            spsa(&[100, 300], &config, quadratic_comparison(&target))
+        );
+    }
+
+    #[test]
+    fn eval_params_round_trip_through_tunable_values() {
+        let params = EvalParams::default();
+        assert_eq!(params.with_tunable_values(&params.tunable_values()), params);
+    }
+
+    #[test]
+    fn pruning_params_round_trip_through_tunable_values() {
+        let params = PruningParams::default();
+        assert_eq!(params.with_tunable_values(&params.tunable_values()), params);
+        assert_eq!(
+            params.with_tunable_values(&[-1, 150, 300]).futility_depth,
+            0
+        );
+    }
+
+    #[test]
+    fn matches_swap_colors_on_each_opening() {
+        // white is a queen up in the first opening and a queen down in the
+        // second, so a player given white in the first and black in the
+        // second would win both
+        let openings = [
+            Position::from_fen("3qk3/8/8/8/8/8/8/3QK2Q w - - 0 1"),
+            Position::from_fen("3qk2q/8/8/8/8/8/8/3QK3 w - - 0 1"),
+        ];
+        let score = play_match(
+            || Box::new(Planner::new().with_depth(1)),
+            || Box::new(Planner::new().with_depth(1)),
+            &openings,
+            4,
+        );
+        assert_eq!(score, 0.5);
+    }
+}
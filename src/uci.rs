@@ -0,0 +1,704 @@
+use std::io::{BufRead, Write};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+use crate::engine::{MATE_BOUND, MATE_SCORE, MAX_SEARCH_DEPTH};
+use crate::ChessMove;
+use crate::ClockState;
+use crate::ControlError;
+use crate::EngineOption;
+use crate::EngineOptions;
+use crate::EngineState;
+use crate::OptionError;
+use crate::OptionValue;
+use crate::Planner;
+use crate::Player;
+use crate::Position;
+use crate::SearchControl;
+use crate::SearchLimits;
+
+const MAX_MULTI_PV: i64 = 16;
+// Every ply of a search keeps its position and move list on the stack,
+// captures followed at the leaves included, so the search thread gets more
+// room than a spawned thread's default.
+const SEARCH_STACK_SIZE: usize = 16 * 1024 * 1024;
+
+#[derive(Clone, PartialEq, Debug, Default)]
+pub struct GoOptions {
+    pub depth: Option<usize>,
+    pub nodes: Option<u64>,
+    pub movetime: Option<Duration>,
+    // set when the GUI sent wtime or btime
+    pub clock: Option<ClockState>,
+    pub infinite: bool,
+    pub ponder: bool,
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub enum UciCommand {
+    Uci,
+    IsReady,
+    UciNewGame,
+    Position(Box<Position>),
+    Go(GoOptions),
+    Stop,
+    PonderHit,
+    SetOption { name: String, value: Option<String> },
+    Quit,
+}
+
+impl UciCommand {
+    // None for blank lines, commands this front-end doesn't know, and
+    // positions with a malformed FEN or a move that isn't legal.
+    pub fn parse(line: &str) -> Option<UciCommand> {
+        let tokens: Vec<&str> = line.split_whitespace().collect();
+        let (command, arguments) = tokens.split_first()?;
+        match *command {
+            "uci" => Some(UciCommand::Uci),
+            "isready" => Some(UciCommand::IsReady),
+            "ucinewgame" => Some(UciCommand::UciNewGame),
+            "position" => {
+                parse_position(arguments).map(|position| UciCommand::Position(Box::new(position)))
+            }
+            "go" => Some(UciCommand::Go(parse_go(arguments))),
+            "stop" => Some(UciCommand::Stop),
+            "ponderhit" => Some(UciCommand::PonderHit),
+            "setoption" => parse_setoption(arguments),
+            "quit" => Some(UciCommand::Quit),
+            _ => None,
+        }
+    }
+}
+
+fn parse_position(arguments: &[&str]) -> Option<Position> {
+    let moves_at = arguments
+        .iter()
+        .position(|token| *token == "moves")
+        .unwrap_or(arguments.len());
+    let (setup, moves) = arguments.split_at(moves_at);
+    let mut position = match setup {
+        ["startpos"] => Position::initial(),
+        ["fen", fields @ ..] if fields.len() == 6 => {
+            Position::try_from_fen(&fields.join(" ")).ok()?
+        }
+        _ => return None,
+    };
+    for uci_long in moves.iter().skip(1) {
+        let chess_move = legal_move(uci_long, &position)?;
+        position = position.after_move(&chess_move);
+    }
+    Some(position)
+}
+
+pub(crate) fn legal_move(uci_long: &str, position: &Position) -> Option<ChessMove> {
+    position
+        .all_legal_moves()
+        .into_iter()
+        .find(|chess_move| chess_move.to_uci_long(position) == uci_long)
+}
+
+// Option names and values may both have spaces in them.
+fn parse_setoption(arguments: &[&str]) -> Option<UciCommand> {
+    let ["name", rest @ ..] = arguments else {
+        return None;
+    };
+    let value_at = rest.iter().position(|token| *token == "value");
+    let (name, value) = match value_at {
+        Some(value_at) => (&rest[..value_at], Some(rest[value_at + 1..].join(" "))),
+        None => (rest, None),
+    };
+    if name.is_empty() {
+        return None;
+    }
+    Some(UciCommand::SetOption {
+        name: name.join(" "),
+        value,
+    })
+}
+
+fn parse_go(arguments: &[&str]) -> GoOptions {
+    let mut options = GoOptions::default();
+    let mut clock = ClockState::default();
+    let mut timed = false;
+    let mut tokens = arguments.iter();
+    while let Some(token) = tokens.next() {
+        // some GUIs send negative times once a clock runs out
+        let mut value = || {
+            tokens
+                .next()
+                .and_then(|value| value.parse::<i64>().ok())
+                .map(|value| value.max(0) as u64)
+        };
+        match *token {
+            "infinite" => options.infinite = true,
+            "ponder" => options.ponder = true,
+            "depth" => options.depth = value().map(|depth| depth as usize),
+            "nodes" => options.nodes = value(),
+            "movetime" => options.movetime = value().map(Duration::from_millis),
+            "movestogo" => clock.moves_to_go = value().map(|moves| moves as u32),
+            "wtime" | "btime" | "winc" | "binc" => {
+                let time = Duration::from_millis(value().unwrap_or(0));
+                match *token {
+                    "wtime" => clock.white_remaining = time,
+                    "btime" => clock.black_remaining = time,
+                    "winc" => clock.white_increment = time,
+                    _ => clock.black_increment = time,
+                }
+                timed |= matches!(*token, "wtime" | "btime");
+            }
+            _ => {}
+        }
+    }
+    if timed {
+        options.clock = Some(clock);
+    }
+    options
+}
+
+// Mates are given in moves, negative when the engine is the one mated.
+fn uci_score(score: isize) -> String {
+    if score > MATE_BOUND {
+        format!("mate {}", (MATE_SCORE - score + 1) / 2)
+    } else if score < -MATE_BOUND {
+        format!("mate -{}", (MATE_SCORE + score) / 2)
+    } else {
+        format!("cp {}", score)
+    }
+}
+
+// Each move is written out in the position it is played from.
+fn uci_line(position: &Position, line: &[ChessMove]) -> String {
+    let mut position = position.clone();
+    let mut moves = Vec::new();
+    for chess_move in line {
+        moves.push(chess_move.to_uci_long(&position));
+        position = position.after_move(chess_move);
+    }
+    moves.join(" ")
+}
+
+#[derive(Clone)]
+enum Engine {
+    // searched with limits, so it can be stopped and put on the clock
+    Planner(Arc<Planner>),
+    // any other player just offers its move
+    Player(Arc<dyn Player + Send + Sync>),
+}
+
+impl Engine {
+    fn player(&self) -> &dyn Player {
+        match self {
+            Engine::Planner(planner) => planner.as_ref(),
+            Engine::Player(player) => player.as_ref(),
+        }
+    }
+
+    // The lines to send once the search is over. Each line past the first
+    // is searched without the moves of the lines before it.
+    // Players other than the planner only see the clock, when there is one.
+    fn search(
+        &self,
+        position: &Position,
+        limits: &SearchLimits,
+        clock: Option<&ClockState>,
+        multi_pv: usize,
+    ) -> Vec<String> {
+        match self {
+            Engine::Planner(planner) => {
+                let mut remaining = position.all_legal_moves();
+                let mut lines = Vec::new();
+                let mut best_move = None;
+                for index in 1..=multi_pv.min(remaining.len()) {
+                    let result = if index == 1 {
+                        planner.search_with_limits(position, limits)
+                    } else {
+                        planner.search_with_limits(
+                            position,
+                            &limits.clone().root_moves(remaining.clone()),
+                        )
+                    };
+                    // stopped before it got anywhere
+                    if index > 1 && result.depth == 0 {
+                        break;
+                    }
+                    remaining.retain(|chess_move| *chess_move != result.best_move);
+                    let pv = uci_line(position, &result.pv);
+                    let multipv = match multi_pv {
+                        1 => String::new(),
+                        _ => format!(" multipv {}", index),
+                    };
+                    lines.push(format!(
+                        "info{} depth {} score {} nodes {} pv {}",
+                        multipv,
+                        result.depth,
+                        uci_score(result.score),
+                        result.nodes,
+                        pv
+                    ));
+                    best_move.get_or_insert(result.best_move.to_uci_long(position));
+                }
+                lines.push(format!(
+                    "bestmove {}",
+                    best_move.expect("the position has a legal move")
+                ));
+                lines
+            }
+            Engine::Player(player) => {
+                let chess_move = match clock {
+                    Some(clock) => player.offer_move_timed(position, clock),
+                    None => player.offer_move(position),
+                };
+                vec![format!("bestmove {}", chess_move.to_uci_long(position))]
+            }
+        }
+    }
+}
+
+// Speaks UCI for one engine, so that GUIs such as Arena or Cute Chess can
+// play it. Searches run on their own thread, which reports the best move
+// itself unless it was pondering or got stopped; in those cases the lines
+// it hands back are sent once the GUI says ponderhit or stop.
+pub struct UciEngine<W: Write + Send + 'static> {
+    engine: Engine,
+    position: Position,
+    control: Arc<Mutex<SearchControl>>,
+    search: Option<JoinHandle<Vec<String>>>,
+    output: Arc<Mutex<W>>,
+    // the front-end's own options and then the engine's
+    options: EngineOptions,
+}
+
+impl<W: Write + Send + 'static> UciEngine<W> {
+    pub fn new(planner: Planner, output: W) -> UciEngine<W> {
+        UciEngine::with_engine(Engine::Planner(Arc::new(planner)), output)
+    }
+
+    pub fn with_player(player: Box<dyn Player + Send + Sync>, output: W) -> UciEngine<W> {
+        UciEngine::with_engine(Engine::Player(Arc::from(player)), output)
+    }
+
+    fn with_engine(engine: Engine, output: W) -> UciEngine<W> {
+        let mut options = EngineOptions::new();
+        if let Engine::Planner(_) = engine {
+            options.declare(EngineOption::spin("MultiPV", 1, 1, MAX_MULTI_PV));
+        }
+        for option in engine.player().options() {
+            options.declare(option);
+        }
+        UciEngine {
+            engine,
+            position: Position::initial(),
+            control: Arc::new(Mutex::new(SearchControl::default())),
+            search: None,
+            output: Arc::new(Mutex::new(output)),
+            options,
+        }
+    }
+
+    // Handles commands until quit or the end of the input, which is taken
+    // as quit too.
+    pub fn run(&mut self, input: impl BufRead) {
+        for line in input.lines() {
+            let Ok(line) = line else {
+                break;
+            };
+            if !self.handle(&line) {
+                return;
+            }
+        }
+        self.stop();
+    }
+
+    // Returns false once the GUI has asked to quit.
+    pub fn handle(&mut self, line: &str) -> bool {
+        let Some(command) = UciCommand::parse(line) else {
+            return true;
+        };
+        match command {
+            UciCommand::Uci => {
+                let mut lines = vec![
+                    format!("id name libchessticot {}", self.engine.player()),
+                    String::from("id author Simisticot"),
+                ];
+                lines.extend(self.options.declarations().map(EngineOption::to_uci));
+                lines.push(String::from("uciok"));
+                self.send(&lines);
+            }
+            UciCommand::IsReady => self.send(&[String::from("readyok")]),
+            UciCommand::UciNewGame => {
+                self.abandon();
+                self.engine.player().new_game();
+            }
+            UciCommand::Position(position) => {
+                self.abandon();
+                self.position = *position;
+            }
+            UciCommand::Go(options) => self.go(&options),
+            UciCommand::Stop => self.stop(),
+            UciCommand::PonderHit => self.ponderhit(),
+            UciCommand::SetOption { name, value } => self.set_option(&name, value.as_deref()),
+            UciCommand::Quit => {
+                self.abandon();
+                return false;
+            }
+        }
+        true
+    }
+
+    // Waits for the running search to end by itself and sends its best
+    // move if it hasn't been sent yet.
+    pub fn wait(&mut self) {
+        if let Some(search) = self.search.take() {
+            let lines = search.join().expect("search thread doesn't panic");
+            self.send(&lines);
+        }
+    }
+
+    pub fn into_output(mut self) -> W {
+        self.abandon();
+        let output = Arc::try_unwrap(self.output)
+            .ok()
+            .expect("every search has been joined");
+        output
+            .into_inner()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    fn go(&mut self, options: &GoOptions) {
+        self.abandon();
+        let position = self.position.clone();
+        if !position.has_legal_move() {
+            self.send(&[String::from("bestmove 0000")]);
+            return;
+        }
+        let Ok(limits) = self.start(options, &position) else {
+            return;
+        };
+        let engine = self.engine.clone();
+        let multi_pv = match self.options.value("MultiPV") {
+            Some(OptionValue::Spin(lines)) => *lines as usize,
+            _ => 1,
+        };
+        let clock = options.clock.filter(|_| !options.infinite);
+        let control = self.control.clone();
+        let output = self.output.clone();
+        let search = thread::Builder::new()
+            .name(String::from("search"))
+            .stack_size(SEARCH_STACK_SIZE)
+            .spawn(move || {
+                let lines = engine.search(&position, &limits, clock.as_ref(), multi_pv);
+                if lock(&control).search_finished() {
+                    send(&output, &lines);
+                    Vec::new()
+                } else {
+                    lines
+                }
+            })
+            .expect("the search thread should start");
+        self.search = Some(search);
+    }
+
+    fn start(
+        &self,
+        options: &GoOptions,
+        position: &Position,
+    ) -> Result<SearchLimits, ControlError> {
+        let open_ended = options.infinite
+            || options.ponder
+            || options.clock.is_some()
+            || options.movetime.is_some()
+            || options.nodes.is_some();
+        let depth = options.depth.unwrap_or(match &self.engine {
+            Engine::Planner(planner) if !open_ended => planner.limits().depth,
+            _ => MAX_SEARCH_DEPTH,
+        });
+        let mut control = lock(&self.control);
+        let limits = match &options.clock {
+            Some(clock) if options.ponder => control.ponder(depth, clock, &position.to_move),
+            Some(clock) if !options.infinite => control.go(depth, clock, &position.to_move),
+            _ => control.go_infinite(depth),
+        }?;
+        let limits = match options.movetime {
+            Some(movetime) => limits.time(movetime),
+            None => limits,
+        };
+        Ok(match options.nodes {
+            Some(nodes) => limits.nodes(nodes),
+            None => limits,
+        })
+    }
+
+    // The engine's options are passed on to it, the front-end only keeps
+    // track of its own. Either way there is no search running once the GUI
+    // changes settings.
+    fn set_option(&mut self, name: &str, value: Option<&str>) {
+        self.abandon();
+        match self.options.set(name, value) {
+            Ok((option, value)) => self.engine.player().set_option(&option.name, value),
+            Err(OptionError::Unknown(name)) => {
+                self.send(&[format!("info string no option named {}", name)])
+            }
+            Err(OptionError::InvalidValue { name, value }) => {
+                self.send(&[format!("info string {} can't be set to {}", name, value)])
+            }
+        }
+    }
+
+    fn stop(&mut self) {
+        lock(&self.control).stop();
+        self.wait();
+    }
+
+    fn ponderhit(&mut self) {
+        let finished = {
+            let mut control = lock(&self.control);
+            control.ponderhit().is_ok() && control.state() == EngineState::Idle
+        };
+        // the ponder search ended before the opponent moved, so its move
+        // is owed now
+        if finished {
+            self.wait();
+        }
+    }
+
+    // Stops the running search without reporting it, for when the GUI has
+    // moved on.
+    fn abandon(&mut self) {
+        lock(&self.control).stop();
+        if let Some(search) = self.search.take() {
+            search.join().expect("search thread doesn't panic");
+        }
+    }
+
+    fn send(&self, lines: &[String]) {
+        send(&self.output, lines);
+    }
+}
+
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    mutex
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+// A GUI that stopped reading has gone away, so write errors are ignored.
+fn send<W: Write>(output: &Mutex<W>, lines: &[String]) {
+    let mut output = lock(output);
+    for line in lines {
+        let _ = writeln!(output, "{}", line);
+    }
+    let _ = output.flush();
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use crate::FirstMovePlayer;
+
+    use super::*;
+
+    fn output_lines(engine: UciEngine<Vec<u8>>) -> Vec<String> {
+        String::from_utf8(engine.into_output())
+            .unwrap()
+            .lines()
+            .map(String::from)
+            .collect()
+    }
+
+    #[test]
+    fn parses_positions_and_search_limits() {
+        let Some(UciCommand::Position(position)) =
+            UciCommand::parse("position startpos moves e2e4 e7e5 g1f3")
+        else {
+            panic!("expected a position");
+        };
+        assert_eq!(
+            *position,
+            Position::from_fen("rnbqkbnr/pppp1ppp/8/4p3/4P3/5N2/PPPP1PPP/RNBQKB1R b KQkq - 1 2")
+        );
+        assert_eq!(UciCommand::parse("position startpos moves e2e5"), None);
+        assert_eq!(
+            UciCommand::parse("position fen 9/8/8/8/8/8/8/8 w - - 0 1"),
+            None
+        );
+        assert_eq!(
+            UciCommand::parse("go wtime 1000 btime -20 winc 10 movestogo 5 depth 4"),
+            Some(UciCommand::Go(GoOptions {
+                depth: Some(4),
+                clock: Some(ClockState {
+                    white_remaining: Duration::from_millis(1000),
+                    white_increment: Duration::from_millis(10),
+                    moves_to_go: Some(5),
+                    ..ClockState::default()
+                }),
+                ..GoOptions::default()
+            }))
+        );
+        assert_eq!(UciCommand::parse("   "), None);
+    }
+
+    #[test]
+    fn answers_the_handshake_and_reports_mates() {
+        let mut engine = UciEngine::new(Planner::new(), Vec::new());
+        for line in [
+            "uci",
+            "isready",
+            "ucinewgame",
+            "position fen 6k1/5ppp/8/8/8/8/8/4R1K1 w - - 0 1",
+            "go depth 2",
+        ] {
+            assert!(engine.handle(line));
+        }
+        engine.wait();
+        let lines = output_lines(engine);
+        assert_eq!(lines[0], "id name libchessticot Planner");
+        assert_eq!(
+            lines[2..5],
+            [
+                "option name MultiPV type spin default 1 min 1 max 16",
+                "option name Hash type spin default 16 min 1 max 1024",
+                "option name Threads type spin default 1 min 1 max 1",
+            ]
+        );
+        assert_eq!(lines[5..7], ["uciok", "readyok"]);
+        assert!(lines[7].contains("score mate 1"));
+        assert_eq!(lines[8], "bestmove e1e8");
+    }
+
+    #[test]
+    fn multi_pv_reports_a_line_per_root_move() {
+        let mut engine = UciEngine::new(Planner::new(), Vec::new());
+        engine.handle("setoption name multipv value 3");
+        engine.handle("position startpos");
+        engine.handle("go depth 2");
+        engine.wait();
+        let lines = output_lines(engine);
+        assert_eq!(lines.len(), 4);
+        let moves: HashSet<&str> = lines[..3]
+            .iter()
+            .enumerate()
+            .map(|(index, line)| {
+                assert!(line.starts_with(&format!("info multipv {} depth 2", index + 1)));
+                // each line's first move
+                line.split(" pv ")
+                    .nth(1)
+                    .unwrap()
+                    .split(' ')
+                    .next()
+                    .unwrap()
+            })
+            .collect();
+        assert_eq!(moves.len(), 3);
+        assert!(lines[0].contains(&format!(" pv {}", lines[3].trim_start_matches("bestmove "))));
+    }
+
+    #[test]
+    fn promotions_carry_their_piece_both_ways() {
+        let Some(UciCommand::Position(position)) =
+            UciCommand::parse("position fen 8/1P4k1/8/8/8/8/8/4K3 w - - 0 1 moves b7b8q")
+        else {
+            panic!("expected a position");
+        };
+        assert_eq!(
+            *position,
+            Position::from_fen("1Q6/6k1/8/8/8/8/8/4K3 b - - 0 1")
+        );
+        assert_eq!(
+            UciCommand::parse("position fen 8/1P4k1/8/8/8/8/8/4K3 w - - 0 1 moves b7b8"),
+            None
+        );
+
+        let mut engine = UciEngine::new(Planner::new(), Vec::new());
+        engine.handle("position fen 8/1P4k1/8/8/8/8/8/4K3 w - - 0 1");
+        engine.handle("go depth 3");
+        engine.wait();
+        let lines = output_lines(engine);
+        assert_eq!(lines.last().unwrap(), "bestmove b7b8q");
+    }
+
+    struct Configurable {
+        settings: Arc<Mutex<Vec<(String, OptionValue)>>>,
+    }
+
+    impl std::fmt::Display for Configurable {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            write!(f, "Configurable")
+        }
+    }
+
+    impl Player for Configurable {
+        fn offer_move(&self, position: &Position) -> ChessMove {
+            FirstMovePlayer.offer_move(position)
+        }
+        fn evalutate(&self, _position: &Position) -> isize {
+            0
+        }
+        fn options(&self) -> Vec<EngineOption> {
+            vec![EngineOption::combo("Style", "Normal", &["Solid", "Normal"])]
+        }
+        fn set_option(&self, name: &str, value: &OptionValue) {
+            self.settings
+                .lock()
+                .unwrap()
+                .push((String::from(name), value.clone()));
+        }
+    }
+
+    #[test]
+    fn setoption_reaches_the_options_players_declare() {
+        assert_eq!(
+            UciCommand::parse("setoption name Clear Hash"),
+            Some(UciCommand::SetOption {
+                name: String::from("Clear Hash"),
+                value: None
+            })
+        );
+        let settings = Arc::new(Mutex::new(Vec::new()));
+        let player = Configurable {
+            settings: settings.clone(),
+        };
+        let mut engine = UciEngine::with_player(Box::new(player), Vec::new());
+        engine.run(
+            "uci\nsetoption name style value solid\nsetoption name Style value Wild\nsetoption name MultiPV value 2\n"
+                .as_bytes(),
+        );
+        let lines = output_lines(engine);
+        assert_eq!(
+            lines[2],
+            "option name Style type combo default Normal var Solid var Normal"
+        );
+        assert_eq!(lines[3], "uciok");
+        assert_eq!(lines[4], "info string Style can't be set to Wild");
+        assert_eq!(lines[5], "info string no option named MultiPV");
+        assert_eq!(
+            *settings.lock().unwrap(),
+            [(
+                String::from("Style"),
+                OptionValue::Combo(String::from("Solid"))
+            )]
+        );
+    }
+
+    #[test]
+    fn stop_ends_an_infinite_search_with_a_best_move() {
+        let mut engine = UciEngine::new(Planner::new(), Vec::new());
+        engine.run("position startpos\ngo infinite\nstop\nquit\ngo depth 1\n".as_bytes());
+        let lines = output_lines(engine);
+        assert_eq!(lines.len(), 2);
+        assert!(lines[1].starts_with("bestmove "));
+    }
+
+    #[test]
+    fn other_players_and_finished_games() {
+        let mut engine = UciEngine::with_player(Box::new(FirstMovePlayer), Vec::new());
+        engine.handle("go movetime 10");
+        engine.wait();
+        engine.run("position fen 6Rk/5Q2/8/8/8/8/8/6K1 b - - 0 1\ngo\n".as_bytes());
+        let lines = output_lines(engine);
+        assert_eq!(lines.len(), 2);
+        assert!(lines[0].starts_with("bestmove "));
+        assert_eq!(lines[1], "bestmove 0000");
+    }
+}
@@ -0,0 +1,386 @@
+use std::ffi::OsStr;
+use std::fmt::Display;
+use std::io::{self, BufRead, BufReader, ErrorKind, Write};
+use std::process::{Child, ChildStdin, ChildStdout, Command, Stdio};
+use std::sync::{Mutex, MutexGuard};
+use std::time::Duration;
+
+use crate::engine::{MATE_SCORE, MAX_SEARCH_DEPTH};
+use crate::uci::legal_move;
+use crate::ChessMove;
+use crate::ClockState;
+use crate::Player;
+use crate::Position;
+
+// How long the engine thinks about a move when it isn't on a clock.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+enum GoLimit {
+    Depth(usize),
+    MoveTime(Duration),
+}
+
+impl GoLimit {
+    fn to_uci(self) -> String {
+        match self {
+            GoLimit::Depth(depth) => format!("go depth {}", depth),
+            GoLimit::MoveTime(time) => format!("go movetime {}", time.as_millis()),
+        }
+    }
+}
+
+// The game so far, so that the engine is sent the moves that led to the
+// position and can tell repetitions apart.
+struct History {
+    start: Position,
+    moves: Vec<ChessMove>,
+    current: Position,
+}
+
+struct Process {
+    child: Child,
+    input: ChildStdin,
+    output: BufReader<ChildStdout>,
+    history: Option<History>,
+}
+
+impl Process {
+    fn send(&mut self, line: &str) -> io::Result<()> {
+        writeln!(self.input, "{}", line)?;
+        self.input.flush()
+    }
+
+    // Reads lines up to the first that starts with `prefix`, passing the
+    // ones before it to `seen`.
+    fn wait_for(&mut self, prefix: &str, mut seen: impl FnMut(&str)) -> io::Result<String> {
+        let mut line = String::new();
+        loop {
+            line.clear();
+            if self.output.read_line(&mut line)? == 0 {
+                return Err(io::Error::new(
+                    ErrorKind::UnexpectedEof,
+                    format!("engine exited before sending {}", prefix),
+                ));
+            }
+            let trimmed = line.trim();
+            if trimmed.split_whitespace().next() == Some(prefix) {
+                return Ok(String::from(trimmed));
+            }
+            seen(trimmed);
+        }
+    }
+
+    fn position_command(&self, position: &Position) -> String {
+        match &self.history {
+            Some(history) if history.current == *position && !history.moves.is_empty() => {
+                let mut moves = Vec::new();
+                let mut replayed = history.start.clone();
+                for chess_move in &history.moves {
+                    moves.push(chess_move.to_uci_long(&replayed));
+                    replayed = replayed.after_move(chess_move);
+                }
+                format!(
+                    "position fen {} moves {}",
+                    history.start.to_fen(),
+                    moves.join(" ")
+                )
+            }
+            _ => format!("position fen {}", position.to_fen()),
+        }
+    }
+
+    // The best move along with the last score the engine gave for it.
+    fn think(&mut self, position: &Position, go: &str) -> io::Result<(ChessMove, Option<isize>)> {
+        let command = self.position_command(position);
+        self.send(&command)?;
+        self.send(go)?;
+        let mut score = None;
+        let best = self.wait_for("bestmove", |line| {
+            if let Some(reported) = parse_score(line) {
+                score = Some(reported);
+            }
+        })?;
+        let uci_long = best.split_whitespace().nth(1).unwrap_or("");
+        let chess_move = legal_move(uci_long, position).ok_or_else(|| {
+            io::Error::new(
+                ErrorKind::InvalidData,
+                format!("engine played {}, which isn't legal", uci_long),
+            )
+        })?;
+        Ok((chess_move, score))
+    }
+}
+
+// The score of an info line, from the side to move's point of view. Mates
+// are scored the way the planner scores them.
+fn parse_score(line: &str) -> Option<isize> {
+    let words: Vec<&str> = line.split_whitespace().collect();
+    if words.first() != Some(&"info") {
+        return None;
+    }
+    let at = words.iter().position(|word| *word == "score")?;
+    let value = words.get(at + 2)?.parse::<isize>().ok()?;
+    match *words.get(at + 1)? {
+        "cp" => Some(value),
+        "mate" if value > 0 => Some(MATE_SCORE - 2 * value + 1),
+        "mate" => Some(-(MATE_SCORE + 2 * value)),
+        _ => None,
+    }
+}
+
+// An engine in a process of its own, Stockfish say, spoken to over UCI so
+// that it can play against the engines of this crate. The process is asked
+// to quit when the player is dropped.
+pub struct UciEnginePlayer {
+    name: String,
+    limit: GoLimit,
+    process: Mutex<Process>,
+}
+
+impl UciEnginePlayer {
+    // Starts `program` and waits until it is ready to play.
+    pub fn spawn(program: impl AsRef<OsStr>) -> io::Result<UciEnginePlayer> {
+        UciEnginePlayer::spawn_with_args(program, &[] as &[&str])
+    }
+
+    pub fn spawn_with_args(
+        program: impl AsRef<OsStr>,
+        args: &[impl AsRef<OsStr>],
+    ) -> io::Result<UciEnginePlayer> {
+        let mut child = Command::new(program)
+            .args(args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()?;
+        let input = child.stdin.take().expect("stdin is piped");
+        let output = BufReader::new(child.stdout.take().expect("stdout is piped"));
+        let mut process = Process {
+            child,
+            input,
+            output,
+            history: None,
+        };
+        let mut name = String::from("UCI engine");
+        // an engine that exits straight away can close its input before
+        // uci is sent, which reads better as it never answering
+        let sent = process.send("uci");
+        process.wait_for("uciok", |line| {
+            if let Some(id) = line.strip_prefix("id name ") {
+                name = String::from(id.trim());
+            }
+        })?;
+        sent?;
+        process.send("isready")?;
+        process.wait_for("readyok", |_| {})?;
+        Ok(UciEnginePlayer {
+            name,
+            limit: GoLimit::MoveTime(Duration::from_millis(100)),
+            process: Mutex::new(process),
+        })
+    }
+
+    pub fn with_depth(mut self, depth: usize) -> UciEnginePlayer {
+        self.limit = GoLimit::Depth(depth.clamp(1, MAX_SEARCH_DEPTH));
+        self
+    }
+
+    pub fn with_movetime(mut self, time: Duration) -> UciEnginePlayer {
+        self.limit = GoLimit::MoveTime(time);
+        self
+    }
+
+    // The name the engine gave itself.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn send_option(&self, name: &str, value: &str) -> io::Result<()> {
+        let mut process = self.process();
+        process.send(&format!("setoption name {} value {}", name, value))?;
+        process.send("isready")?;
+        process.wait_for("readyok", |_| {}).map(|_| ())
+    }
+
+    fn process(&self) -> MutexGuard<'_, Process> {
+        self.process
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    fn think(&self, position: &Position, go: &str) -> (ChessMove, Option<isize>) {
+        self.process()
+            .think(position, go)
+            .unwrap_or_else(|error| panic!("{} stopped playing: {}", self.name, error))
+    }
+}
+
+impl Player for UciEnginePlayer {
+    fn offer_move(&self, position: &Position) -> ChessMove {
+        self.think(position, &self.limit.to_uci()).0
+    }
+    fn evalutate(&self, position: &Position) -> isize {
+        if !position.has_legal_move() {
+            return 0;
+        }
+        self.think(position, &self.limit.to_uci()).1.unwrap_or(0)
+    }
+    fn offer_move_timed(&self, position: &Position, clock: &ClockState) -> ChessMove {
+        let mut go = format!(
+            "go wtime {} btime {} winc {} binc {}",
+            clock.white_remaining.as_millis(),
+            clock.black_remaining.as_millis(),
+            clock.white_increment.as_millis(),
+            clock.black_increment.as_millis()
+        );
+        if let Some(moves_to_go) = clock.moves_to_go {
+            go += &format!(" movestogo {}", moves_to_go);
+        }
+        self.think(position, &go).0
+    }
+    fn new_game(&self) {
+        let mut process = self.process();
+        process.history = None;
+        let ready = process
+            .send("ucinewgame")
+            .and_then(|_| process.send("isready"))
+            .and_then(|_| process.wait_for("readyok", |_| {}));
+        if let Err(error) = ready {
+            panic!("{} stopped playing: {}", self.name, error);
+        }
+    }
+    fn move_played(&self, position: &Position, chess_move: &ChessMove) {
+        let mut process = self.process();
+        let current = position.after_move(chess_move);
+        match &mut process.history {
+            Some(history) if history.current == *position => {
+                history.moves.push(chess_move.clone());
+                history.current = current;
+            }
+            history => {
+                *history = Some(History {
+                    start: position.clone(),
+                    moves: vec![chess_move.clone()],
+                    current,
+                })
+            }
+        }
+    }
+}
+
+impl Display for UciEnginePlayer {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.name)
+    }
+}
+
+impl Drop for UciEnginePlayer {
+    fn drop(&mut self) {
+        let process = self
+            .process
+            .get_mut()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        if process.send("quit").is_err() || process.child.wait().is_err() {
+            let _ = process.child.kill();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::engine::MATE_BOUND;
+    use crate::{Coords, FirstMovePlayer, Move, PieceKind};
+
+    use super::*;
+
+    #[test]
+    fn reads_scores_from_info_lines() {
+        assert_eq!(
+            parse_score("info depth 3 score cp -25 nodes 100 pv e2e4"),
+            Some(-25)
+        );
+        assert_eq!(
+            parse_score("info depth 9 score mate 1 pv d8h4"),
+            Some(MATE_SCORE - 1)
+        );
+        assert!(parse_score("info score mate -2 pv a2a3").unwrap() < -MATE_BOUND);
+        assert_eq!(parse_score("info string score cp?"), None);
+        assert_eq!(parse_score("bestmove e2e4"), None);
+    }
+
+    // A stand-in engine, played by a shell script, that answers each go with
+    // the next of `moves`.
+    #[cfg(unix)]
+    fn scripted_engine(moves: &[&str]) -> io::Result<UciEnginePlayer> {
+        let script = format!(
+            r#"
+            n=0
+            while read -r line; do
+                case "$line" in
+                    uci) echo "id name Scripted"; echo "uciok" ;;
+                    isready) echo "readyok" ;;
+                    go*)
+                        set -- {}
+                        shift $n
+                        n=$((n + 1))
+                        echo "info depth 1 score cp 12 pv $1"
+                        echo "bestmove $1" ;;
+                    quit) exit 0 ;;
+                esac
+            done
+            "#,
+            moves.join(" ")
+        );
+        UciEnginePlayer::spawn_with_args("sh", &["-c", script.as_str()])
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn plays_the_moves_the_engine_sends() {
+        let engine = scripted_engine(&["f2f3", "g2g4"]).unwrap();
+        assert_eq!(engine.to_string(), "Scripted");
+        engine.new_game();
+        let initial = Position::initial();
+        let first = engine.offer_move(&initial);
+        assert_eq!(first.to_uci_long(&initial), "f2f3");
+        engine.move_played(&initial, &first);
+        let after_first = initial.after_move(&first);
+        let reply = FirstMovePlayer.offer_move(&after_first);
+        engine.move_played(&after_first, &reply);
+        let position = after_first.after_move(&reply);
+        assert_eq!(engine.evalutate(&position), 12);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn promotions_go_both_ways_with_their_piece() {
+        let engine = scripted_engine(&["b7b8q"]).unwrap();
+        let position = Position::from_fen("8/1P4k1/8/8/8/8/8/4K3 w - - 0 1");
+        let promotion = engine.offer_move(&position);
+        assert_eq!(
+            promotion,
+            ChessMove::Promotion(
+                Move {
+                    origin: Coords::from_algebraic("b7"),
+                    destination: Coords::from_algebraic("b8"),
+                },
+                PieceKind::Queen
+            )
+        );
+        engine.move_played(&position, &promotion);
+        assert_eq!(
+            engine
+                .process()
+                .position_command(&position.after_move(&promotion)),
+            "position fen 8/1P4k1/8/8/8/8/8/4K3 w - - 0 1 moves b7b8q"
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn engines_that_go_away_cannot_be_spawned() {
+        let error = UciEnginePlayer::spawn_with_args("sh", &["-c", "exit 0"])
+            .err()
+            .expect("the engine never answered uci");
+        assert_eq!(error.kind(), ErrorKind::UnexpectedEof);
+    }
+}
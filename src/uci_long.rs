@@ -1,8 +1,17 @@
 use crate::{
     board_manip::{king_at, pawn_at},
-    ChessMove, Coords, Direction, Move, PieceColor, PieceKind, Position,
+    chess_move::promotion_letter,
+    ChessMove, Coords, Move, PieceColor, PieceKind, Position,
 };
 
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum InferMoveError {
+    OffBoard(Coords),
+    EmptyOrigin(Coords),
+    MissingPromotion,
+    InvalidPromotion(PieceKind),
+}
+
 impl ChessMove {
     pub fn to_uci_long(&self, current_position: &Position) -> String {
         let origin: Coords = match self {
@@ -29,7 +38,11 @@ impl ChessMove {
                 PieceColor::White => Coords::from_algebraic("g1"),
             },
         };
-        origin.to_algebraic() + &destination.to_algebraic()
+        let mut uci_long = origin.to_algebraic() + &destination.to_algebraic();
+        if let ChessMove::Promotion(_, kind) = self {
+            uci_long.push(promotion_letter(kind));
+        }
+        uci_long
     }
     pub fn from_uci_long(uci_long: &str, current_position: &Position) -> ChessMove {
         assert!(uci_long.len() >= 4);
@@ -49,36 +62,77 @@ impl ChessMove {
 
         let origin: Coords = Coords::from_algebraic(&uci_long[..2]);
         let destination: Coords = Coords::from_algebraic(&uci_long[2..4]);
+        ChessMove::infer(origin, destination, promotion_target, current_position).unwrap_or_else(
+            |_| {
+                let movement = Move {
+                    origin,
+                    destination,
+                };
+                match promotion_target {
+                    Some(target) => ChessMove::Promotion(movement, target),
+                    None => ChessMove::RegularMove(movement),
+                }
+            },
+        )
+    }
+
+    // Works out which kind of move takes the piece on `origin` to `destination`.
+    // The move is classified, not checked for legality.
+    pub fn infer(
+        origin: Coords,
+        destination: Coords,
+        promotion: Option<PieceKind>,
+        current_position: &Position,
+    ) -> Result<ChessMove, InferMoveError> {
+        for square in [origin, destination] {
+            if !square.is_in_bounds() {
+                return Err(InferMoveError::OffBoard(square));
+            }
+        }
+        if current_position.piece_at(&origin).is_none() {
+            return Err(InferMoveError::EmptyOrigin(origin));
+        }
         let movement = Move {
             origin,
             destination,
         };
-        if let Some(target) = promotion_target {
-            ChessMove::Promotion(movement, target)
-        } else if pawn_at(&current_position.board, &movement.origin)
-            && movement.y_abs_distance() > 1
-        {
-            ChessMove::PawnSkip(movement)
-        } else if pawn_at(&current_position.board, &movement.origin)
+        let is_pawn = pawn_at(current_position.board(), &origin);
+        let reaches_last_rank = is_pawn
+            && current_position
+                .piece_at(&origin)
+                .is_some_and(|pawn| destination.y == pawn.color.opposite().homerow());
+        match promotion {
+            Some(target)
+                if !reaches_last_rank || matches!(target, PieceKind::Pawn | PieceKind::King) =>
+            {
+                return Err(InferMoveError::InvalidPromotion(target))
+            }
+            Some(target) => return Ok(ChessMove::Promotion(movement, target)),
+            None if reaches_last_rank => return Err(InferMoveError::MissingPromotion),
+            None => {}
+        }
+
+        if is_pawn && movement.y_abs_distance() > 1 {
+            Ok(ChessMove::PawnSkip(movement))
+        } else if is_pawn
+            && movement.x_abs_distance() == 1
             && current_position
                 .en_passant_on
-                .is_some_and(|square| square == movement.destination)
-        {
-            ChessMove::EnPassant(
-                movement.clone(),
-                movement.destination
-                    + Direction {
-                        dx: 0,
-                        dy: current_position.to_move.pawn_orientation(),
-                    },
-            )
-        } else if king_at(&current_position.board, &movement.origin) && movement.x_distance() == -2
+                .is_some_and(|square| square == destination)
         {
-            ChessMove::CastleLeft
-        } else if king_at(&current_position.board, &movement.origin) && movement.x_distance() == 2 {
-            ChessMove::CastleRight
+            Ok(ChessMove::EnPassant(
+                movement,
+                Coords {
+                    x: destination.x,
+                    y: origin.y,
+                },
+            ))
+        } else if king_at(current_position.board(), &origin) && movement.x_distance() == 2 {
+            Ok(ChessMove::CastleLeft)
+        } else if king_at(current_position.board(), &origin) && movement.x_distance() == -2 {
+            Ok(ChessMove::CastleRight)
         } else {
-            ChessMove::RegularMove(movement)
+            Ok(ChessMove::RegularMove(movement))
         }
     }
 }
@@ -129,27 +183,111 @@ mod tests {
     }
 
     #[test]
-    fn deserializes_castle_right() {
+    fn deserializes_castle_left() {
         assert_eq!(
             ChessMove::from_uci_long(
                 "e1c1",
                 &Position::from_fen("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/R3KBNR w KQkq - 0 1")
             ),
-            ChessMove::CastleRight
+            ChessMove::CastleLeft
         )
     }
 
     #[test]
-    fn deserializes_castle_left() {
+    fn deserializes_castle_right() {
         assert_eq!(
             ChessMove::from_uci_long(
                 "e1g1",
                 &Position::from_fen("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQK2R w KQkq - 0 1")
             ),
-            ChessMove::CastleLeft
+            ChessMove::CastleRight
         )
     }
 
+    #[test]
+    fn infers_moves_from_coordinates() {
+        let position = Position::from_fen("4k3/1P6/8/3pP3/8/8/8/4K2R w K d6 0 1");
+        assert_eq!(
+            ChessMove::infer(
+                Coords::from_algebraic("e5"),
+                Coords::from_algebraic("d6"),
+                None,
+                &position
+            ),
+            Ok(ChessMove::EnPassant(
+                Move {
+                    origin: Coords::from_algebraic("e5"),
+                    destination: Coords::from_algebraic("d6")
+                },
+                Coords::from_algebraic("d5")
+            ))
+        );
+        assert_eq!(
+            ChessMove::infer(
+                Coords::from_algebraic("e1"),
+                Coords::from_algebraic("g1"),
+                None,
+                &position
+            ),
+            Ok(ChessMove::CastleRight)
+        );
+        assert_eq!(
+            ChessMove::infer(
+                Coords::from_algebraic("b7"),
+                Coords::from_algebraic("b8"),
+                None,
+                &position
+            ),
+            Err(InferMoveError::MissingPromotion)
+        );
+        assert_eq!(
+            ChessMove::infer(
+                Coords::from_algebraic("h1"),
+                Coords::from_algebraic("h8"),
+                Some(PieceKind::Queen),
+                &position
+            ),
+            Err(InferMoveError::InvalidPromotion(PieceKind::Queen))
+        );
+        assert_eq!(
+            ChessMove::infer(
+                Coords::from_algebraic("a1"),
+                Coords::from_algebraic("a2"),
+                None,
+                &position
+            ),
+            Err(InferMoveError::EmptyOrigin(Coords::from_algebraic("a1")))
+        );
+    }
+
+    #[test]
+    fn castling_round_trips_through_uci_long() {
+        let position = Position::from_fen("r3k2r/8/8/8/8/8/8/R3K2R b KQkq - 0 1");
+        for castle in [ChessMove::CastleLeft, ChessMove::CastleRight] {
+            assert_eq!(
+                ChessMove::from_uci_long(&castle.to_uci_long(&position), &position),
+                castle
+            );
+        }
+    }
+
+    #[test]
+    fn promotion_round_trips_through_uci_long() {
+        let position = Position::from_fen("8/1P4k1/8/8/8/8/8/4K3 w - - 0 1");
+        let promotion = ChessMove::Promotion(
+            Move {
+                origin: Coords::from_algebraic("b7"),
+                destination: Coords::from_algebraic("b8"),
+            },
+            PieceKind::Knight,
+        );
+        assert_eq!(promotion.to_uci_long(&position), "b7b8n");
+        assert_eq!(
+            ChessMove::from_uci_long(&promotion.to_uci_long(&position), &position),
+            promotion
+        );
+    }
+
     #[test]
     fn deserializes_knight_to_c3() {
         assert_eq!(
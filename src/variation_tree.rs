@@ -0,0 +1,677 @@
+use crate::pgn::{
+    join_movetext, result_from_tokens, result_token, termination_tag, tokenize, write_tag,
+    PgnError, PgnTags, PgnToken, SevenTagRoster,
+};
+use crate::ChessMove;
+use crate::GameResult;
+use crate::PieceColor;
+use crate::Position;
+
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug)]
+pub struct NodeId(usize);
+
+#[derive(Clone, Debug)]
+struct Node {
+    position: Position,
+    chess_move: Option<ChessMove>,
+    parent: Option<NodeId>,
+    children: Vec<NodeId>,
+    // the comment after the move, or before the first move at the root
+    comment: Option<String>,
+    // a comment before the move, as variations can start with
+    starting_comment: Option<String>,
+    nags: Vec<u8>,
+    // centipawns from white's point of view, as an engine scored the
+    // position
+    eval: Option<isize>,
+}
+
+// Moves played from a root position, where the first child of every node is
+// the main continuation and the other children are alternatives to it.
+#[derive(Clone, Debug)]
+pub struct VariationTree {
+    nodes: Vec<Option<Node>>,
+    result: Option<GameResult>,
+}
+
+impl VariationTree {
+    pub fn new(root: Position) -> VariationTree {
+        VariationTree {
+            nodes: vec![Some(Node {
+                position: root,
+                chess_move: None,
+                parent: None,
+                children: vec![],
+                comment: None,
+                starting_comment: None,
+                nags: vec![],
+                eval: None,
+            })],
+            result: None,
+        }
+    }
+
+    pub fn result(&self) -> Option<&GameResult> {
+        self.result.as_ref()
+    }
+
+    pub fn set_result(&mut self, result: Option<GameResult>) {
+        self.result = result;
+    }
+
+    pub fn root(&self) -> NodeId {
+        NodeId(0)
+    }
+
+    fn node(&self, node: NodeId) -> Option<&Node> {
+        self.nodes.get(node.0).and_then(|node| node.as_ref())
+    }
+
+    fn node_mut(&mut self, node: NodeId) -> Option<&mut Node> {
+        self.nodes.get_mut(node.0).and_then(|node| node.as_mut())
+    }
+
+    pub fn position(&self, node: NodeId) -> Option<&Position> {
+        self.node(node).map(|node| &node.position)
+    }
+
+    pub fn chess_move(&self, node: NodeId) -> Option<&ChessMove> {
+        self.node(node).and_then(|node| node.chess_move.as_ref())
+    }
+
+    pub fn parent(&self, node: NodeId) -> Option<NodeId> {
+        self.node(node).and_then(|node| node.parent)
+    }
+
+    pub fn children(&self, node: NodeId) -> &[NodeId] {
+        self.node(node).map_or(&[], |node| &node.children)
+    }
+
+    pub fn comment(&self, node: NodeId) -> Option<&str> {
+        self.node(node).and_then(|node| node.comment.as_deref())
+    }
+
+    pub fn set_comment(&mut self, node: NodeId, comment: Option<String>) {
+        if let Some(node) = self.node_mut(node) {
+            node.comment = comment;
+        }
+    }
+
+    pub fn starting_comment(&self, node: NodeId) -> Option<&str> {
+        self.node(node)
+            .and_then(|node| node.starting_comment.as_deref())
+    }
+
+    pub fn set_starting_comment(&mut self, node: NodeId, comment: Option<String>) {
+        if let Some(node) = self.node_mut(node) {
+            node.starting_comment = comment;
+        }
+    }
+
+    // Numeric annotation glyphs, 1 for "!", 2 for "?" and so on.
+    pub fn nags(&self, node: NodeId) -> &[u8] {
+        self.node(node).map_or(&[], |node| &node.nags)
+    }
+
+    pub fn add_nag(&mut self, node: NodeId, nag: u8) {
+        if let Some(node) = self.node_mut(node) {
+            if !node.nags.contains(&nag) {
+                node.nags.push(nag);
+            }
+        }
+    }
+
+    pub fn clear_nags(&mut self, node: NodeId) {
+        if let Some(node) = self.node_mut(node) {
+            node.nags.clear();
+        }
+    }
+
+    pub fn eval(&self, node: NodeId) -> Option<isize> {
+        self.node(node).and_then(|node| node.eval)
+    }
+
+    pub fn set_eval(&mut self, node: NodeId, eval: Option<isize>) {
+        if let Some(node) = self.node_mut(node) {
+            node.eval = eval;
+        }
+    }
+
+    // Returns the existing child when the move was already played from `parent`,
+    // and None when the move is illegal there.
+    pub fn add_move(&mut self, parent: NodeId, chess_move: &ChessMove) -> Option<NodeId> {
+        if !self.position(parent)?.is_move_legal(chess_move) {
+            return None;
+        }
+        self.add_checked_move(parent, chess_move)
+    }
+
+    // Like `add_move`, for moves the caller has already checked against the
+    // rules it plays by, which may be a variant's.
+    pub(crate) fn add_checked_move(
+        &mut self,
+        parent: NodeId,
+        chess_move: &ChessMove,
+    ) -> Option<NodeId> {
+        if let Some(existing) = self
+            .children(parent)
+            .iter()
+            .find(|child| self.chess_move(**child) == Some(chess_move))
+        {
+            return Some(*existing);
+        }
+        let position = self.position(parent)?;
+        let child = NodeId(self.nodes.len());
+        self.nodes.push(Some(Node {
+            position: position.after_move(chess_move),
+            chess_move: Some(chess_move.clone()),
+            parent: Some(parent),
+            children: vec![],
+            comment: None,
+            starting_comment: None,
+            nags: vec![],
+            eval: None,
+        }));
+        self.node_mut(parent)?.children.push(child);
+        Some(child)
+    }
+
+    // Makes `node` the main continuation of its parent.
+    pub fn promote_variation(&mut self, node: NodeId) -> bool {
+        let Some(parent) = self.parent(node) else {
+            return false;
+        };
+        let siblings = &mut self
+            .node_mut(parent)
+            .expect("parent of an existing node exists")
+            .children;
+        siblings.retain(|sibling| *sibling != node);
+        siblings.insert(0, node);
+        true
+    }
+
+    // Moves `node` one place down among its siblings, so that the main
+    // continuation becomes the first alternative.
+    pub fn demote_variation(&mut self, node: NodeId) -> bool {
+        let Some(parent) = self.parent(node) else {
+            return false;
+        };
+        let siblings = &mut self
+            .node_mut(parent)
+            .expect("parent of an existing node exists")
+            .children;
+        let index = siblings
+            .iter()
+            .position(|sibling| *sibling == node)
+            .expect("a node is among its parent's children");
+        if index + 1 == siblings.len() {
+            return false;
+        }
+        siblings.swap(index, index + 1);
+        true
+    }
+
+    // Promotes every variation on the path to `node` so that it lies on the main line.
+    pub fn make_mainline(&mut self, node: NodeId) -> bool {
+        if self.node(node).is_none() {
+            return false;
+        }
+        let mut current = node;
+        while self.promote_variation(current) {
+            current = self.parent(current).expect("promoted nodes have a parent");
+        }
+        true
+    }
+
+    // Removes `node` and everything played after it. The root cannot be deleted.
+    pub fn delete(&mut self, node: NodeId) -> bool {
+        let Some(parent) = self.parent(node) else {
+            return false;
+        };
+        if let Some(parent) = self.node_mut(parent) {
+            parent.children.retain(|child| *child != node);
+        }
+        let mut to_remove = vec![node];
+        while let Some(removed) = to_remove.pop() {
+            if let Some(removed) = self.nodes[removed.0].take() {
+                to_remove.extend(removed.children);
+            }
+        }
+        true
+    }
+
+    pub fn mainline(&self) -> Vec<NodeId> {
+        let mut line = vec![];
+        let mut current = self.root();
+        while let Some(next) = self.children(current).first() {
+            line.push(*next);
+            current = *next;
+        }
+        line
+    }
+
+    pub fn moves_to(&self, node: NodeId) -> Vec<ChessMove> {
+        let mut moves = vec![];
+        let mut current = Some(node);
+        while let Some(chess_move) = current.and_then(|current| self.chess_move(current)) {
+            moves.push(chess_move.clone());
+            current = current.and_then(|current| self.parent(current));
+        }
+        moves.reverse();
+        moves
+    }
+
+    // Pre-order traversal where main continuations come before alternatives.
+    pub fn depth_first(&self) -> Vec<NodeId> {
+        let mut order = vec![];
+        let mut stack = vec![self.root()];
+        while let Some(node) = stack.pop() {
+            order.push(node);
+            stack.extend(self.children(node).iter().rev());
+        }
+        order
+    }
+
+    fn ply(&self, node: NodeId) -> usize {
+        let mut ply = 0;
+        let mut current = node;
+        while let Some(parent) = self.parent(current) {
+            ply += 1;
+            current = parent;
+        }
+        ply
+    }
+
+    fn move_token(&self, node: NodeId, force_number: bool) -> String {
+        let parent = self.parent(node).expect("only the root has no parent");
+        let before = self.position(parent).expect("parent exists");
+        let black_starts =
+            self.position(self.root()).expect("root exists").to_move == PieceColor::Black;
+        let number = (self.ply(parent) + usize::from(black_starts)) / 2 + 1;
+        let san = self
+            .chess_move(node)
+            .expect("only the root has no move")
+            .to_san(before);
+        match before.to_move {
+            PieceColor::White => format!("{}. {}", number, san),
+            PieceColor::Black if force_number => format!("{}... {}", number, san),
+            PieceColor::Black => san,
+        }
+    }
+
+    // Returns whether the move was left with a comment after it, which
+    // the next move has to give its number after.
+    fn write_move(&self, node: NodeId, tokens: &mut Vec<String>, force_number: bool) -> bool {
+        let starting_comment = self.starting_comment(node);
+        if let Some(comment) = starting_comment {
+            tokens.push(comment_token(comment));
+        }
+        tokens.push(self.move_token(node, force_number || starting_comment.is_some()));
+        for nag in self.nags(node) {
+            tokens.push(format!("${}", nag));
+        }
+        match self.annotation(node) {
+            Some(annotation) => {
+                tokens.push(annotation);
+                true
+            }
+            None => false,
+        }
+    }
+
+    // The comment after the node's move, with its eval in front the way
+    // analysis exports write it.
+    fn annotation(&self, node: NodeId) -> Option<String> {
+        let eval = self
+            .eval(node)
+            .map(|eval| format!("[%eval {:.2}]", eval as f64 / 100.0));
+        match (eval, self.comment(node)) {
+            (None, None) => None,
+            (Some(eval), None) => Some(comment_token(&eval)),
+            (None, Some(comment)) => Some(comment_token(comment)),
+            (Some(eval), Some(comment)) => Some(comment_token(&format!("{} {}", eval, comment))),
+        }
+    }
+
+    fn write_continuation(&self, node: NodeId, tokens: &mut Vec<String>, force_number: bool) {
+        let Some((main, alternatives)) = self.children(node).split_first() else {
+            return;
+        };
+        let commented = self.write_move(*main, tokens, force_number);
+        for alternative in alternatives {
+            tokens.push(String::from("("));
+            let commented = self.write_move(*alternative, tokens, true);
+            self.write_continuation(*alternative, tokens, commented);
+            tokens.push(String::from(")"));
+        }
+        self.write_continuation(*main, tokens, commented || !alternatives.is_empty());
+    }
+
+    pub fn to_pgn(&self) -> String {
+        let mut tags = String::new();
+        if self.result.is_some() {
+            tags.push_str(&write_tag("Result", result_token(self.result())));
+        }
+        self.pgn_with_tags(tags)
+    }
+
+    pub fn to_pgn_with_roster(&self, roster: &SevenTagRoster) -> String {
+        self.pgn_with_tags(roster.to_tags(self.result()))
+    }
+
+    pub fn to_pgn_with_tags(&self, tags: &PgnTags) -> String {
+        self.pgn_with_tags(tags.to_tags(self.result()))
+    }
+
+    fn pgn_with_tags(&self, mut pgn: String) -> String {
+        let root = self.position(self.root()).expect("root exists");
+        if self.result.is_some() {
+            pgn.push_str(&write_tag("Termination", termination_tag(self.result())));
+        }
+        if *root != Position::initial() {
+            pgn.push_str(&write_tag("SetUp", "1"));
+            pgn.push_str(&write_tag("FEN", &root.to_fen()));
+        }
+        if !pgn.is_empty() {
+            pgn.push('\n');
+        }
+        let mut tokens = self.movetext_tokens();
+        tokens.push(String::from(result_token(self.result())));
+        pgn.push_str(&join_movetext(&tokens));
+        pgn
+    }
+
+    fn movetext_tokens(&self) -> Vec<String> {
+        let mut tokens = vec![];
+        tokens.extend(self.annotation(self.root()));
+        self.write_continuation(self.root(), &mut tokens, true);
+        tokens
+    }
+
+    // The moves and variations alone, with no tags or result.
+    pub(crate) fn movetext(&self) -> String {
+        join_movetext(&self.movetext_tokens())
+    }
+
+    pub fn from_pgn(pgn: &str) -> Result<VariationTree, PgnError> {
+        let tokens = tokenize(pgn)?;
+        let root = match tokens.iter().find_map(|token| match token {
+            PgnToken::Tag(name, value) if name == "FEN" => Some(value),
+            _ => None,
+        }) {
+            Some(fen) => {
+                Position::try_from_fen(fen).map_err(|_| PgnError::Malformed(fen.clone()))?
+            }
+            None => Position::initial(),
+        };
+
+        let mut tree = VariationTree::new(root);
+        let mut current = tree.root();
+        let mut variation_starts = vec![];
+        let mut result = None;
+        let mut termination = None;
+        let mut variation_opened = false;
+        let mut starting_comment = None;
+        for token in tokens {
+            match token {
+                PgnToken::Tag(name, value) if name == "Result" && result.is_none() => {
+                    result = Some(value)
+                }
+                PgnToken::Tag(name, value) if name == "Termination" => termination = Some(value),
+                PgnToken::Result(token) => result = Some(token),
+                PgnToken::Move(san) => {
+                    let position = tree.position(current).expect("current node exists");
+                    let chess_move = ChessMove::from_san(&san, position)?;
+                    current = tree
+                        .add_move(current, &chess_move)
+                        .expect("moves parsed from SAN are legal");
+                    if let Some(comment) = starting_comment.take() {
+                        tree.set_starting_comment(current, Some(comment));
+                    }
+                }
+                PgnToken::VariationStart => {
+                    variation_starts.push(current);
+                    current = tree
+                        .parent(current)
+                        .ok_or_else(|| PgnError::Malformed(String::from("(")))?;
+                    variation_opened = true;
+                    continue;
+                }
+                PgnToken::VariationEnd => {
+                    current = variation_starts
+                        .pop()
+                        .ok_or(PgnError::UnbalancedVariation)?;
+                }
+                // before a variation's first move a comment belongs to the
+                // move, not to the one the variation is an alternative to
+                PgnToken::Comment(comment) if variation_opened => {
+                    starting_comment = Some(joined(starting_comment, comment));
+                    continue;
+                }
+                PgnToken::Comment(comment) => {
+                    let (eval, comment) = take_eval(&comment);
+                    if eval.is_some() {
+                        tree.set_eval(current, eval);
+                    }
+                    if !comment.is_empty() {
+                        let existing = tree.comment(current).map(String::from);
+                        tree.set_comment(current, Some(joined(existing, comment)));
+                    }
+                }
+                PgnToken::Nag(nag) => tree.add_nag(current, nag),
+                _ => {}
+            }
+            variation_opened = false;
+        }
+        if !variation_starts.is_empty() {
+            return Err(PgnError::UnbalancedVariation);
+        }
+        let final_position = tree
+            .position(*tree.mainline().last().unwrap_or(&tree.root()))
+            .expect("mainline nodes exist")
+            .clone();
+        tree.result = result.and_then(|result| {
+            result_from_tokens(&result, termination.as_deref(), &final_position)
+        });
+        Ok(tree)
+    }
+}
+
+// PGN has no way to escape a } inside a comment, so it is written as a ).
+fn comment_token(comment: &str) -> String {
+    format!("{{{}}}", comment.replace('}', ")"))
+}
+
+// Splits a centipawn "[%eval 0.35]" off the rest of the comment. Mate
+// scores, "[%eval #3]", stay in the comment.
+fn take_eval(comment: &str) -> (Option<isize>, String) {
+    let Some(start) = comment.find("[%eval ") else {
+        return (None, comment.to_string());
+    };
+    let Some(length) = comment[start..].find(']') else {
+        return (None, comment.to_string());
+    };
+    let Ok(pawns) = comment[start + "[%eval ".len()..start + length].parse::<f64>() else {
+        return (None, comment.to_string());
+    };
+    let rest = format!("{}{}", &comment[..start], &comment[start + length + 1..]);
+    (
+        Some((pawns * 100.0).round() as isize),
+        rest.trim().to_string(),
+    )
+}
+
+// Comments that follow each other are kept as one.
+fn joined(existing: Option<String>, comment: String) -> String {
+    match existing {
+        Some(existing) => format!("{} {}", existing, comment),
+        None => comment,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::DrawReason;
+    use crate::WinReason;
+
+    use super::*;
+
+    fn san_line(tree: &VariationTree, line: &[NodeId]) -> Vec<String> {
+        line.iter()
+            .map(|node| {
+                tree.chess_move(*node)
+                    .unwrap()
+                    .to_san(tree.position(tree.parent(*node).unwrap()).unwrap())
+            })
+            .collect()
+    }
+
+    #[test]
+    fn round_trips_variations_through_pgn() {
+        let pgn = "1. e4 e5 (1... c5 2. Nf3 (2. Nc3) 2... d6) 2. Nf3 Nc6 *";
+        let tree = VariationTree::from_pgn(pgn).unwrap();
+        assert_eq!(
+            san_line(&tree, &tree.mainline()),
+            vec!["e4", "e5", "Nf3", "Nc6"]
+        );
+        assert_eq!(tree.to_pgn(), pgn);
+    }
+
+    #[test]
+    fn round_trips_comments_and_nags() {
+        let pgn = "{Played online} 1. e4 $1 {the best} 1... e5 \
+                   ({a starting comment} 1... c5 $2 $18 2. Nf3) 2. Nf3 {develops} *";
+        let tree = VariationTree::from_pgn(pgn).unwrap();
+        let e4 = tree.mainline()[0];
+        assert_eq!(tree.comment(tree.root()), Some("Played online"));
+        assert_eq!(tree.comment(e4), Some("the best"));
+        assert_eq!(tree.nags(e4), &[1]);
+        let sicilian = tree.children(e4)[1];
+        assert_eq!(tree.starting_comment(sicilian), Some("a starting comment"));
+        assert_eq!(tree.nags(sicilian), &[2, 18]);
+        assert_eq!(tree.comment(sicilian), None);
+        assert_eq!(tree.to_pgn(), pgn);
+
+        let mut tree = VariationTree::from_pgn("1. e4 {one} {two} e5 *").unwrap();
+        let e5 = tree.mainline()[1];
+        tree.add_nag(e5, 6);
+        tree.set_comment(e5, Some(String::from("a } brace, not (a line )")));
+        assert_eq!(
+            tree.to_pgn(),
+            "1. e4 {one two} 1... e5 $6 {a ) brace, not (a line )} *"
+        );
+        tree.clear_nags(e5);
+        assert_eq!(tree.nags(e5), &[] as &[u8]);
+    }
+
+    #[test]
+    fn keeps_evals_and_reorders_variations() {
+        let mut tree =
+            VariationTree::from_pgn("1. e4 {[%eval 0.35] central} 1... e5 (1... c5) (1... e6) *")
+                .unwrap();
+        let e4 = tree.mainline()[0];
+        assert_eq!(tree.eval(e4), Some(35));
+        assert_eq!(tree.comment(e4), Some("central"));
+        let e5 = tree.mainline()[1];
+        assert!(tree.demote_variation(e5));
+        assert_eq!(san_line(&tree, &tree.mainline()), vec!["e4", "c5"]);
+        assert!(tree.demote_variation(e5));
+        assert!(!tree.demote_variation(e5));
+        assert!(!tree.demote_variation(tree.root()));
+        tree.set_eval(tree.mainline()[1], Some(-120));
+        assert_eq!(
+            tree.to_pgn(),
+            "1. e4 {[%eval 0.35] central} 1... c5 {[%eval -1.20]} (1... e6) (1... e5) *"
+        );
+        let mate = VariationTree::from_pgn("1. e4 {[%eval #3]} *").unwrap();
+        assert_eq!(mate.eval(mate.mainline()[0]), None);
+        assert_eq!(mate.comment(mate.mainline()[0]), Some("[%eval #3]"));
+    }
+
+    #[test]
+    fn promotes_and_deletes_variations() {
+        let mut tree = VariationTree::from_pgn("1. e4 e5 (1... c5 2. Nf3) 2. Nf3 *").unwrap();
+        let sicilian = tree.children(tree.mainline()[0])[1];
+        assert!(tree.make_mainline(tree.children(sicilian)[0]));
+        assert_eq!(san_line(&tree, &tree.mainline()), vec!["e4", "c5", "Nf3"]);
+        assert_eq!(tree.to_pgn(), "1. e4 c5 (1... e5 2. Nf3) 2. Nf3 *");
+
+        assert!(tree.delete(sicilian));
+        assert_eq!(tree.to_pgn(), "1. e4 e5 2. Nf3 *");
+        assert!(tree.position(sicilian).is_none());
+        assert!(!tree.delete(tree.root()));
+    }
+
+    #[test]
+    fn rejects_illegal_moves() {
+        let mut tree = VariationTree::new(Position::initial());
+        let e5 = ChessMove::from_uci_long("e7e5", &Position::initial().opposite_color_to_move());
+        assert_eq!(tree.add_move(tree.root(), &e5), None);
+        assert_eq!(
+            VariationTree::from_pgn("1. e4 (e5) *").unwrap_err(),
+            PgnError::InvalidMove(crate::SanError::Illegal(String::from("e5")))
+        );
+        assert_eq!(
+            VariationTree::from_pgn("1. e4 (1. d4 *").unwrap_err(),
+            PgnError::UnbalancedVariation
+        );
+    }
+
+    #[test]
+    fn starts_from_fen_tag() {
+        let pgn = "[SetUp \"1\"]\n[FEN \"4k3/8/8/8/8/8/8/4K2R b K - 0 1\"]\n\n1... Kd7 2. O-O *";
+        let tree = VariationTree::from_pgn(pgn).unwrap();
+        assert_eq!(
+            tree.moves_to(tree.mainline()[1]),
+            vec![
+                ChessMove::from_san("Kd7", tree.position(tree.root()).unwrap()).unwrap(),
+                ChessMove::CastleRight
+            ]
+        );
+        assert_eq!(tree.to_pgn(), pgn);
+        assert_eq!(
+            VariationTree::from_pgn("[FEN \"garbage\"] 1. e4 *").unwrap_err(),
+            PgnError::Malformed(String::from("garbage"))
+        );
+    }
+
+    #[test]
+    fn writes_and_reads_results() {
+        let mut tree = VariationTree::from_pgn("1. f3 e5 2. g4 Qh4# *").unwrap();
+        assert_eq!(tree.result(), None);
+        tree.set_result(Some(GameResult::BlackWin(WinReason::Checkmate)));
+        let pgn = tree.to_pgn();
+        assert_eq!(
+            pgn,
+            "[Result \"0-1\"]\n[Termination \"normal\"]\n\n1. f3 e5 2. g4 Qh4# 0-1"
+        );
+        assert_eq!(
+            VariationTree::from_pgn(&pgn).unwrap().result(),
+            Some(&GameResult::BlackWin(WinReason::Checkmate))
+        );
+
+        let stalemate = "[SetUp \"1\"]\n[FEN \"k7/8/1Q6/8/8/8/8/7K w - - 0 1\"]\n\n1. Kg2 1/2-1/2";
+        assert_eq!(
+            VariationTree::from_pgn(stalemate).unwrap().result(),
+            Some(&GameResult::Draw(DrawReason::Stalemate))
+        );
+        assert_eq!(
+            VariationTree::from_pgn("1. e4 1/2-1/2").unwrap().result(),
+            Some(&GameResult::Draw(DrawReason::Agreement))
+        );
+        let flagged = "[Termination \"time forfeit\"]\n1. e4 1-0";
+        assert_eq!(
+            VariationTree::from_pgn(flagged).unwrap().result(),
+            Some(&GameResult::WhiteWin(WinReason::TimeForfeit))
+        );
+        assert_eq!(
+            VariationTree::from_pgn("1. e4 e5 0-1").unwrap().result(),
+            Some(&GameResult::BlackWin(WinReason::Resignation))
+        );
+        let adjudicated = "[Termination \"adjudication\"]\n1. e4 1/2-1/2";
+        assert_eq!(
+            VariationTree::from_pgn(adjudicated).unwrap().result(),
+            Some(&GameResult::Draw(DrawReason::Adjudicated))
+        );
+    }
+}
@@ -0,0 +1,143 @@
+use crate::ChessMove;
+use crate::Coords;
+use crate::Piece;
+use crate::PieceColor;
+use crate::Position;
+
+const PIECE_KEYS: usize = 12 * 64;
+const CASTLING_KEYS: usize = 4;
+const EN_PASSANT_KEYS: usize = 8;
+const KEY_COUNT: usize = PIECE_KEYS + CASTLING_KEYS + EN_PASSANT_KEYS + 1;
+
+// splitmix64 run at compile time, so keys are the same in every build
+const fn generate_keys() -> [u64; KEY_COUNT] {
+    let mut keys = [0; KEY_COUNT];
+    let mut state: u64 = 0x9e37_79b9_7f4a_7c15;
+    let mut i = 0;
+    while i < KEY_COUNT {
+        state = state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        keys[i] = z ^ (z >> 31);
+        i += 1;
+    }
+    keys
+}
+
+static KEYS: [u64; KEY_COUNT] = generate_keys();
+
+fn piece_key(piece: &Piece, square: &Coords) -> u64 {
+    let piece_index = piece.color.index() * 6 + piece.kind.index();
+    KEYS[piece_index * 64 + square.to_square_number()]
+}
+
+impl Position {
+    // Identifies the position for the transposition table and repetitions.
+    // Positions equal as `Position`s share a key.
+    pub fn zobrist_key(&self) -> u64 {
+        let mut key = self.state_key();
+        for (square, piece) in self
+            .pieces(PieceColor::White)
+            .chain(self.pieces(PieceColor::Black))
+        {
+            key ^= piece_key(&piece, &square);
+        }
+        key
+    }
+
+    // The key of `after`, the position `chess_move` leads to, from this
+    // position's `key`: only the squares the move touched are looked at
+    // again, rather than every piece on the board.
+    pub fn zobrist_key_after(&self, chess_move: &ChessMove, after: &Position, key: u64) -> u64 {
+        let mut key = key ^ self.state_key() ^ after.state_key();
+        for square in self.touched_squares(chess_move).squares() {
+            if let Some(piece) = self.piece_at(&square) {
+                key ^= piece_key(&piece, &square);
+            }
+            if let Some(piece) = after.piece_at(&square) {
+                key ^= piece_key(&piece, &square);
+            }
+        }
+        key
+    }
+
+    // Everything but the pieces: castling, en passant and the side to move.
+    fn state_key(&self) -> u64 {
+        let mut key = 0;
+        for (offset, color) in [PieceColor::White, PieceColor::Black].iter().enumerate() {
+            if self.can_castle_king_side(color) {
+                key ^= KEYS[PIECE_KEYS + offset * 2];
+            }
+            if self.can_castle_queen_side(color) {
+                key ^= KEYS[PIECE_KEYS + offset * 2 + 1];
+            }
+        }
+        if let Some(square) = self.en_passant_target() {
+            key ^= KEYS[PIECE_KEYS + CASTLING_KEYS + square.x as usize];
+        }
+        if self.to_move == PieceColor::Black {
+            key ^= KEYS[KEY_COUNT - 1];
+        }
+        key
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn after_san(moves: &[&str]) -> Position {
+        moves.iter().fold(Position::initial(), |position, san| {
+            position.after_move(&ChessMove::from_san(san, &position).unwrap())
+        })
+    }
+
+    #[test]
+    fn transpositions_share_a_key() {
+        assert_eq!(
+            after_san(&["Nf3", "Nf6", "Nc3"]).zobrist_key(),
+            after_san(&["Nc3", "Nf6", "Nf3"]).zobrist_key()
+        );
+        assert_ne!(
+            after_san(&["Nf3", "Nf6"]).zobrist_key(),
+            after_san(&["Nf3", "Nc6"]).zobrist_key()
+        );
+    }
+
+    #[test]
+    fn side_to_move_and_rights_change_the_key() {
+        let position = Position::initial();
+        assert_ne!(
+            position.zobrist_key(),
+            position.opposite_color_to_move().zobrist_key()
+        );
+        let mut without_castling = position.clone();
+        without_castling.set_can_castle_king_side(&PieceColor::White, false);
+        assert_ne!(position.zobrist_key(), without_castling.zobrist_key());
+    }
+
+    #[test]
+    fn keys_follow_moves_without_counting_again() {
+        for fen in [
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+            "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1",
+            "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1",
+        ] {
+            let position = Position::from_fen(fen);
+            let key = position.zobrist_key();
+            for chess_move in position.all_legal_moves() {
+                let after = position.after_move(&chess_move);
+                let after_key = position.zobrist_key_after(&chess_move, &after, key);
+                assert_eq!(after_key, after.zobrist_key(), "{fen} {chess_move:?}");
+                for reply in after.all_legal_moves() {
+                    let replied = after.after_move(&reply);
+                    assert_eq!(
+                        after.zobrist_key_after(&reply, &replied, after_key),
+                        replied.zobrist_key()
+                    );
+                }
+            }
+        }
+    }
+}